@@ -0,0 +1,71 @@
+use actix_web::{web, HttpResponse};
+use chrono::Local;
+use serde::Deserialize;
+
+use crate::db::{
+    db::Database,
+    types::{Absence, AbsenceFilter},
+};
+use crate::errors::{not_found, ApiError};
+use crate::types::ApiResponse;
+
+// Absence Types
+#[derive(Deserialize)]
+pub struct AbsenceId {
+    id: String,
+}
+
+// Endpoints
+
+// The upcoming absences, which are the ones that haven't ended before `from`, today by default
+pub async fn read_absences(
+    database: web::Data<Database>,
+    filter: web::Query<AbsenceFilter>,
+) -> Result<HttpResponse, ApiError> {
+    let mut filter = filter.into_inner();
+    filter.from = filter.from.or(Some(Local::now().date_naive()));
+
+    let absences = database.read_absences(&filter).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: absences }))
+}
+
+// Appointments during the absence stay where they are, they can be moved with a mass reschedule
+pub async fn create_absence(
+    database: web::Data<Database>,
+    absence: web::Json<Absence>,
+) -> Result<HttpResponse, ApiError> {
+    absence.validate().map_err(ApiError::InvalidRequest)?;
+    database
+        .read_doctor(&absence.doctor.get_unique_id())
+        .await
+        .map_err(not_found("Doctor"))?;
+
+    let result = database.create_absence(absence.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+pub async fn read_absence(
+    database: web::Data<Database>,
+    path: web::Path<AbsenceId>,
+) -> Result<HttpResponse, ApiError> {
+    let absence = database
+        .read_absence(&path.id)
+        .await
+        .map_err(not_found("Absence"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: absence }))
+}
+
+pub async fn delete_absence(
+    database: web::Data<Database>,
+    path: web::Path<AbsenceId>,
+) -> Result<HttpResponse, ApiError> {
+    let absence = database
+        .delete_absence(&path.id)
+        .await
+        .map_err(not_found("Absence"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: absence }))
+}
@@ -1,17 +1,28 @@
-use std::sync::{Arc, Mutex};
-
-use actix_web::{web, HttpResponse, Responder};
-use chrono::{Duration, NaiveDate, NaiveDateTime, Timelike};
-use serde::Deserialize;
-
-use crate::db::types::{AppointmentFilter, AppointmentRecordWithPatient};
-use crate::types::ApiResponse;
-use crate::util::is_valid_timeframe;
+use actix_web::{web, HttpResponse};
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, SubsecRound};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+
+use crate::availability::{AvailableDoctor, MAX_SEARCH_DAYS};
+use crate::calendar::ClinicCalendar;
+use crate::db::types::{
+    AppointmentFilter, AppointmentRecordWithPatient, AppointmentSort, AppointmentTypeRecord,
+    Buffers, DateRange, DeviceRecordId, DoctorFilter, DoctorRecordId, DoctorSort, Pagination,
+    RoomFilter, RoomRecord, RoomRecordId, RoomSort, Schedule, SeriesRecordId,
+};
+use crate::errors::{not_found, ApiError};
+use crate::rescheduling::{CandidateDoctor, Rescheduler};
+use crate::types::{ApiResponse, PageRequest, PaginatedResponse};
+use crate::util::{is_valid_timeframe, Booking};
+use crate::waitlist::{offer_to_waitlist, FreedSlot};
 use crate::{
     config::AppConfig,
     db::{
         db::Database,
-        types::{Appointment, AppointmentRecord, AppointmentType, DatabaseError},
+        types::{
+            Appointment, AppointmentRecord, AppointmentStatus, AppointmentType, DatabaseError,
+            StatusChange,
+        },
     },
 };
 
@@ -24,430 +35,918 @@ pub struct AppointmentId {
 pub struct UpdateAppointment {
     start_time: Option<NaiveDateTime>,
     appointment_type: Option<AppointmentType>,
-    doctor: Option<u32>,
-    room_nr: Option<u32>,
+    doctor: Option<DoctorRecordId>,
+    room: Option<RoomRecordId>,
+    staff: Option<Vec<DoctorRecordId>>,
+    devices: Option<Vec<DeviceRecordId>>,
 }
-#[derive(Deserialize, Debug)]
-pub struct FilterRequest {
-    filter: Option<String>,
-    value: Option<String>,
+#[derive(Deserialize)]
+pub struct StatusChangeRequest {
+    reason: Option<String>,
+}
+// Which appointments of a series a change applies to
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SeriesScope {
+    // Only the appointment itself
+    #[default]
+    This,
+    // The appointment and the ones of its series after it
+    Following,
+    // Every appointment of its series
+    All,
+}
+#[derive(Deserialize)]
+pub struct ScopeQuery {
+    #[serde(default)]
+    scope: SeriesScope,
 }
 #[derive(Debug, Deserialize)]
 pub struct MassRescheduleRequest {
-    pub doctor_id: u32,
+    pub doctor_id: DoctorRecordId,
     pub start_date: NaiveDate,
     pub end_date: NaiveDate,
+    // Whether other active doctors of the same specialty can take over the appointments
+    #[serde(default)]
+    pub allow_substitutes: bool,
+    // How many days after the leave are searched for free slots
+    pub max_days: Option<u32>,
+    // Only returns the plan, without moving any appointment
+    #[serde(default)]
+    pub dry_run: bool,
+}
+#[derive(Serialize)]
+pub struct ScheduledTime {
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    doctor: DoctorRecordId,
+    room: RoomRecordId,
+    staff: Vec<DoctorRecordId>,
+}
+impl From<&AppointmentRecordWithPatient> for ScheduledTime {
+    fn from(appointment: &AppointmentRecordWithPatient) -> Self {
+        ScheduledTime {
+            start_time: appointment.start_time,
+            end_time: appointment.end_time,
+            doctor: DoctorRecordId::from(appointment.doctor.id.clone()),
+            room: RoomRecordId::from(appointment.room.id.clone()),
+            staff: appointment
+                .staff
+                .iter()
+                .map(|member| DoctorRecordId::from(member.id.clone()))
+                .collect(),
+        }
+    }
+}
+#[derive(Serialize)]
+pub struct RescheduledAppointment {
+    appointment: Thing,
+    from: ScheduledTime,
+    to: ScheduledTime,
+}
+#[derive(Serialize)]
+pub struct MassRescheduleResponse {
+    // Whether the moves were only planned
+    dry_run: bool,
+    moved: Vec<RescheduledAppointment>,
+    // Appointments no free slot was found for, they're left as they are
+    unplaced: Vec<AppointmentRecordWithPatient>,
 }
 
-// Endpoints
-pub async fn read_all_appointments_handler(
-    database: web::Data<Arc<Mutex<Database>>>,
-    filter_request: web::Query<FilterRequest>,
-) -> impl Responder {
-    if let (Some(filter), Some(value)) = (&filter_request.filter, &filter_request.value) {
-        let appointment_filter: AppointmentFilter =
-            match AppointmentFilter::from_filter_request(filter, value) {
-                Ok(filter) => filter,
-                Err(e) => return HttpResponse::BadRequest().body(format!("Error: {:?}", e)),
-            };
-        read_all_appointments_by_filter(database, &appointment_filter).await
-    } else {
-        read_all_appointments(database).await
+const DEFAULT_RESCHEDULE_DAYS: u32 = 30;
+
+fn day_range(day: NaiveDate) -> DateRange {
+    DateRange {
+        from: Some(day),
+        to: Some(day),
     }
 }
 
-pub async fn read_all_appointments(database: web::Data<Arc<Mutex<Database>>>) -> HttpResponse {
-    let db = match database.lock() {
-        Ok(guard) => guard,
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Lock error: {:?}", err))
-        }
-    };
+// Appointments starting on any of the dates
+pub(crate) fn date_range_filter(range: DateRange) -> AppointmentFilter {
+    AppointmentFilter {
+        from: range.from.map(NaiveDateTime::from),
+        to: range
+            .to
+            .and_then(|to| to.succ_opt())
+            .map(NaiveDateTime::from),
+        ..Default::default()
+    }
+}
 
-    match db.read_all_appointments().await {
-        Ok(appointments) => HttpResponse::Ok().json(ApiResponse { data: appointments }),
-        Err(err) => HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
+// The series of the appointment, for changes that apply to more than the appointment itself
+fn series_of(appointment: &AppointmentRecord) -> Result<&SeriesRecordId, String> {
+    appointment
+        .series
+        .as_ref()
+        .ok_or("The appointment isn't part of a series".to_string())
+}
+
+// Only active doctors can be given new appointments
+pub(crate) async fn validate_doctor(
+    database: &Database,
+    doctor: &DoctorRecordId,
+) -> Result<(), ApiError> {
+    let doctor = database
+        .read_doctor(&doctor.get_unique_id())
+        .await
+        .map_err(not_found("Doctor"))?;
+
+    if !doctor.active {
+        return Err(ApiError::InvalidRequest(format!(
+            "{} is inactive and can't be booked",
+            doctor.name
+        )));
     }
+
+    Ok(())
 }
 
-pub async fn read_all_appointments_by_filter(
-    database: web::Data<Arc<Mutex<Database>>>,
-    filter_request: &AppointmentFilter,
-) -> HttpResponse {
-    let db = match database.lock() {
-        Ok(guard) => guard,
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Lock error: {:?}", err))
+// Staff members have to be active doctors as well, and the devices have to exist. Nobody and nothing can take part in
+// the same appointment twice, so the doctor can't be on its staff either.
+pub(crate) async fn validate_resources(
+    database: &Database,
+    doctor: &DoctorRecordId,
+    staff: &[DoctorRecordId],
+    devices: &[DeviceRecordId],
+) -> Result<(), ApiError> {
+    for (index, member) in staff.iter().enumerate() {
+        if member == doctor || staff[..index].contains(member) {
+            return Err(ApiError::InvalidRequest(format!(
+                "doctor:{} takes part in the appointment more than once",
+                member.get_unique_id()
+            )));
         }
-    };
+        validate_doctor(database, member).await?;
+    }
 
-    let filtered_appointments: Vec<AppointmentRecordWithPatient> = match &filter_request {
-        AppointmentFilter::Day(day) => {
-            if let Err(err) =
-                NaiveDateTime::parse_from_str(&format!("{} 00:00:00", day), "%Y-%m-%d %H:%M:%S")
-            {
-                return HttpResponse::BadRequest().body(format!(
-                    "Invalid day format: {:?}\nCorrect format is Y-m-d",
-                    err
-                ));
-            }
-            match db.read_all_appointments_by_day(day).await {
-                Ok(appointments) => appointments,
-                Err(err) => {
-                    return HttpResponse::InternalServerError().body(format!("Error: {:?}", err))
-                }
-            }
-        }
-        AppointmentFilter::Month(month) => {
-            if let Err(err) = NaiveDateTime::parse_from_str(
-                &format!("{}-01 00:00:00", month),
-                "%Y-%m-%d %H:%M:%S",
-            ) {
-                return HttpResponse::BadRequest().body(format!(
-                    "Invalid month format: {:?}\nCorrect format is Y-m",
-                    err
-                ));
-            }
-            match db.read_all_appointments_by_month(month).await {
-                Ok(appointments) => appointments,
-                Err(err) => {
-                    return HttpResponse::InternalServerError().body(format!("Error: {:?}", err))
-                }
-            }
-        }
-        AppointmentFilter::PatientId(patient) => {
-            match db.read_all_appointments_by_patient(patient).await {
-                Ok(appointments) => appointments,
-                Err(err) => {
-                    return HttpResponse::InternalServerError().body(format!("Error: {:?}", err))
-                }
-            }
-        }
-        AppointmentFilter::Doctor(doctor) => {
-            match db.read_all_appointments_by_doctor(doctor).await {
-                Ok(appointments) => appointments,
-                Err(err) => {
-                    return HttpResponse::InternalServerError().body(format!("Error: {:?}", err))
-                }
-            }
+    for (index, device) in devices.iter().enumerate() {
+        if devices[..index].contains(device) {
+            return Err(ApiError::InvalidRequest(format!(
+                "device:{} is booked for the appointment more than once",
+                device.get_unique_id()
+            )));
         }
-        AppointmentFilter::RoomNr(room) => match db.read_all_appointments_by_room(room).await {
-            Ok(appointments) => appointments,
-            Err(err) => {
-                return HttpResponse::InternalServerError().body(format!("Error: {:?}", err))
-            }
-        },
-    };
+        database
+            .read_device(&device.get_unique_id())
+            .await
+            .map_err(not_found("Device"))?;
+    }
 
-    HttpResponse::Ok().json(ApiResponse {
-        data: filtered_appointments,
-    })
+    Ok(())
 }
 
-pub async fn create_appointment(
-    database: web::Data<Arc<Mutex<Database>>>,
-    config: web::Data<AppConfig>,
-    appointment: web::Json<Appointment>,
-) -> impl Responder {
-    let db = match database.lock() {
-        Ok(guard) => guard,
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Lock error: {:?}", err))
-        }
-    };
+// The schedules of the doctor and the staff of an appointment, each of them has to be working
+pub(crate) async fn read_schedules(
+    database: &Database,
+    doctor: &DoctorRecordId,
+    staff: &[DoctorRecordId],
+) -> Result<Vec<Schedule>, DatabaseError> {
+    let mut schedules = Vec::new();
+    for participant in std::iter::once(doctor).chain(staff) {
+        schedules.push(database.read_schedule(participant).await?);
+    }
+    Ok(schedules)
+}
+
+pub(crate) async fn read_appointment_type(
+    database: &Database,
+    appointment_type: &AppointmentType,
+) -> Result<AppointmentTypeRecord, ApiError> {
+    database
+        .read_appointment_type(appointment_type.as_str())
+        .await
+        .map_err(not_found("Appointment type"))
+}
 
-    if !config.is_valid_doctor(appointment.doctor) || !config.is_valid_room(appointment.room_nr) {
-        return HttpResponse::BadRequest().body(format!("Doctor or room not found. The configured maximum doctor is {}, and the configured maximum room is {}.\nCount starts at 0", config.doctor_amount - 1, config.room_amount - 1));
+// The room has to be of a type the appointment can take place in. It's returned, since its buffers apply to the
+// appointment.
+pub(crate) async fn validate_room(
+    database: &Database,
+    room: &RoomRecordId,
+    appointment_type: &AppointmentTypeRecord,
+) -> Result<RoomRecord, ApiError> {
+    let room = database
+        .read_room(&room.get_unique_id())
+        .await
+        .map_err(not_found("Room"))?;
+
+    let room_types = &appointment_type.room_types;
+    if !room_types.contains(&room.room_type) {
+        let room_types: Vec<&str> = room_types
+            .iter()
+            .map(|room_type| room_type.as_str())
+            .collect();
+        return Err(ApiError::IncompatibleRoom(format!(
+            "{} is a room of type {}, but {} needs one of type {}",
+            room.name,
+            room.room_type.as_str(),
+            appointment_type.name,
+            room_types.join(" or ")
+        )));
     }
 
-    let appointment_with_calculated_time =
-        match appointment.into_inner().into_appointment_with_time() {
-            Ok(appointment) => appointment,
-            Err(e) => {
-                return HttpResponse::InternalServerError()
-                    .body(format!("Error: {:?}", DatabaseError::from(e)))
-            }
-        };
+    Ok(room)
+}
+
+// Endpoints
+pub async fn read_all_appointments_handler(
+    database: web::Data<Database>,
+    filter: web::Query<AppointmentFilter>,
+    page_request: web::Query<PageRequest<AppointmentSort>>,
+) -> Result<HttpResponse, ApiError> {
+    let pagination = page_request
+        .into_inner()
+        .into_pagination("appointment")
+        .map_err(ApiError::InvalidRequest)?;
+
+    let page = database.read_appointment_page(&filter, &pagination).await?;
+
+    Ok(HttpResponse::Ok().json(PaginatedResponse::from(page)))
+}
 
-    let all_appointments: Vec<AppointmentRecordWithPatient> = match db
-        .read_all_appointments_by_day(
-            &appointment_with_calculated_time
-                .start_time
-                .date()
-                .to_string(),
+pub async fn create_appointment(
+    database: web::Data<Database>,
+    config: web::Data<AppConfig>,
+    appointment: web::Json<Appointment>,
+) -> Result<HttpResponse, ApiError> {
+    let appointment_type = read_appointment_type(&database, &appointment.appointment_type).await?;
+    validate_doctor(&database, &appointment.doctor).await?;
+    validate_resources(
+        &database,
+        &appointment.doctor,
+        &appointment.staff,
+        &appointment.devices,
+    )
+    .await?;
+    let room = validate_room(&database, &appointment.room, &appointment_type).await?;
+
+    let appointment_with_calculated_time = appointment
+        .into_inner()
+        .into_appointment_with_time(
+            appointment_type.duration(),
+            &Buffers::new(&appointment_type, &room),
         )
-        .await
-    {
-        Ok(appointments) => appointments,
-        Err(err) => return HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
-    };
+        .map_err(DatabaseError::from)?;
+
+    let all_appointments: Vec<AppointmentRecordWithPatient> = database
+        .read_appointments(&AppointmentFilter {
+            day: Some(appointment_with_calculated_time.start_time.date()),
+            ..Default::default()
+        })
+        .await?;
+    let schedules = read_schedules(
+        &database,
+        &appointment_with_calculated_time.doctor,
+        &appointment_with_calculated_time.staff,
+    )
+    .await?;
+    let calendar = ClinicCalendar::load(
+        &database,
+        &config,
+        day_range(appointment_with_calculated_time.start_time.date()),
+    )
+    .await?;
 
-    match is_valid_timeframe(
-        appointment_with_calculated_time.start_time,
-        appointment_with_calculated_time.end_time,
-        appointment_with_calculated_time.doctor,
-        appointment_with_calculated_time.room_nr,
+    is_valid_timeframe(
+        &Booking::from(&appointment_with_calculated_time),
+        &schedules,
         &all_appointments,
-        &config,
+        &calendar,
     )
-    .await
-    {
-        Ok(_) => {
-            match db
-                .create_appointment(appointment_with_calculated_time)
-                .await
-            {
-                Ok(result) => HttpResponse::Ok().json(ApiResponse { data: result }),
-                Err(err) => HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
-            }
-        }
-        Err(e) => HttpResponse::BadRequest().body(format!("Error: {:?}", e)),
-    }
+    .await?;
+
+    let result = database
+        .create_appointment(appointment_with_calculated_time)
+        .await
+        .map_err(not_found("Patient"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
 }
 
+// The freed time is offered to the waitlist, unless the appointment didn't block it anymore
 pub async fn delete_appointment(
-    database: web::Data<Arc<Mutex<Database>>>,
+    database: web::Data<Database>,
+    config: web::Data<AppConfig>,
     appointment_id: web::Path<AppointmentId>,
-) -> impl Responder {
-    let db = match database.lock() {
-        Ok(guard) => guard,
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Lock error: {:?}", err))
-        }
-    };
-    match db.delete_appointment(&appointment_id.id).await {
-        Ok(appointments) => HttpResponse::Ok().json(ApiResponse { data: appointments }),
-        Err(err) => match err {
-            DatabaseError::NothingFound => HttpResponse::NotFound().body("Appointment not found"),
-            _ => HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
-        },
+) -> Result<HttpResponse, ApiError> {
+    let appointment = database
+        .delete_appointment(&appointment_id.id)
+        .await
+        .map_err(not_found("Appointment"))?;
+
+    if appointment.status.blocks_slot() {
+        offer_to_waitlist(&database, &config, vec![FreedSlot::from(&appointment)]).await;
     }
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: appointment }))
 }
 
 pub async fn update_appointment(
-    database: web::Data<Arc<Mutex<Database>>>,
+    database: web::Data<Database>,
     config: web::Data<AppConfig>,
     appointment_id: web::Path<AppointmentId>,
+    scope: web::Query<ScopeQuery>,
     update: web::Json<UpdateAppointment>,
-) -> impl Responder {
-    let db = match database.lock() {
-        Ok(guard) => guard,
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Lock error: {:?}", err))
-        }
-    };
-
-    let mut appointment: AppointmentRecord = match db.read_appointment(&appointment_id.id).await {
-        Ok(Some(appointment)) => appointment.into_appointment_record(),
-        Ok(None) => return HttpResponse::NotFound().body("Appointment not found"),
-        Err(err) => return HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
-    };
+) -> Result<HttpResponse, ApiError> {
+    let appointment = database
+        .read_appointment(&appointment_id.id)
+        .await
+        .map_err(not_found("Appointment"))?
+        .ok_or(ApiError::NotFound("Appointment"))?;
+    let mut room = appointment.room.clone();
+    let mut appointment = appointment.into_appointment_record();
+    if appointment.status != AppointmentStatus::Scheduled {
+        return Err(ApiError::InvalidStatusChange(format!(
+            "Only scheduled appointments can be changed, this one is {}",
+            appointment.status.as_str().replace('_', " ")
+        )));
+    }
+    let original = appointment.clone();
 
     if let Some(start_time) = &update.start_time {
         appointment.start_time = *start_time;
-        appointment.end_time = appointment.calculate_end_time();
     }
     if let Some(appointment_type) = &update.appointment_type {
         appointment.appointment_type = appointment_type.clone();
-        appointment.end_time = appointment.calculate_end_time();
+    }
+    // Appointments that aren't moved keep their length, even if the duration of their type changed since
+    let appointment_type = read_appointment_type(&database, &appointment.appointment_type).await?;
+    if update.start_time.is_some() || update.appointment_type.is_some() {
+        appointment.end_time = appointment.start_time + appointment_type.duration();
     }
     if let Some(doctor) = &update.doctor {
-        if !config.is_valid_doctor(*doctor) {
-            return HttpResponse::BadRequest().body(format!(
-                "Doctor not found. The configured maximum doctor is {}.\nCount starts at 0",
-                config.doctor_amount - 1
-            ));
+        if *doctor != appointment.doctor {
+            validate_doctor(&database, doctor).await?;
         }
-        appointment.doctor = *doctor;
-    }
-    if let Some(room_nr) = &update.room_nr {
-        if !config.is_valid_room(*room_nr) {
-            return HttpResponse::BadRequest().body(format!(
-                "Room not found. The configured maximum room is {}.\nCount starts at 0",
-                config.room_amount - 1
-            ));
+        appointment.doctor = doctor.clone();
+    }
+    if let Some(staff) = &update.staff {
+        appointment.staff = staff.clone();
+    }
+    if let Some(devices) = &update.devices {
+        appointment.devices = devices.clone();
+    }
+    if update.doctor.is_some() || update.staff.is_some() || update.devices.is_some() {
+        validate_resources(
+            &database,
+            &appointment.doctor,
+            &appointment.staff,
+            &appointment.devices,
+        )
+        .await?;
+    }
+    if update.room.is_some() || update.appointment_type.is_some() {
+        if let Some(room) = &update.room {
+            appointment.room = room.clone();
         }
-        appointment.room_nr = *room_nr;
+        room = validate_room(&database, &appointment.room, &appointment_type).await?;
+    }
+    // Like their length, appointments keep their buffers unless they're moved or get another type or room
+    if update.start_time.is_some() || update.appointment_type.is_some() || update.room.is_some() {
+        appointment.blocked = Buffers::new(&appointment_type, &room)
+            .blocked_time(appointment.start_time, appointment.end_time);
     }
 
-    let all_appointments = match db
-        .read_all_appointments_by_day(&appointment.start_time.date().to_string())
-        .await
-    {
-        Ok(appointments) => appointments,
-        Err(err) => return HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
-    };
+    if scope.scope != SeriesScope::This {
+        return update_series(
+            &database,
+            &config,
+            &original,
+            &appointment,
+            &update,
+            scope.scope,
+        )
+        .await;
+    }
+
+    let all_appointments = database
+        .read_appointments(&AppointmentFilter {
+            day: Some(appointment.start_time.date()),
+            ..Default::default()
+        })
+        .await?;
     let all_appointments: Vec<AppointmentRecordWithPatient> = all_appointments
         .as_slice()
         .iter()
         .filter(|a| a.id.id.to_raw() != appointment_id.id)
         .cloned()
         .collect();
+    let schedules = read_schedules(&database, &appointment.doctor, &appointment.staff).await?;
+    let calendar =
+        ClinicCalendar::load(&database, &config, day_range(appointment.start_time.date())).await?;
 
-    match is_valid_timeframe(
-        appointment.start_time,
-        appointment.end_time,
-        appointment.doctor,
-        appointment.room_nr,
+    is_valid_timeframe(
+        &Booking::from(&appointment),
+        &schedules,
         &all_appointments,
-        &config,
+        &calendar,
     )
-    .await
-    {
-        Ok(_) => match db.update_appointment(&appointment_id.id, appointment).await {
-            Ok(result) => HttpResponse::Ok().json(result),
-            Err(err) => HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
-        },
-        Err(e) => HttpResponse::BadRequest().body(format!("Error: {:?}", e)),
-    }
+    .await?;
+
+    let result = database
+        .update_appointment(&appointment_id.id, appointment)
+        .await
+        .map_err(not_found("Appointment"))?;
+
+    offer_to_waitlist(&database, &config, vec![FreedSlot::from(&original)]).await;
+
+    Ok(HttpResponse::Ok().json(result))
 }
 
-pub async fn read_appointment(
-    database: web::Data<Arc<Mutex<Database>>>,
-    appointment_id: web::Path<AppointmentId>,
-) -> impl Responder {
-    let db = match database.lock() {
-        Ok(guard) => guard,
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Lock error: {:?}", err))
+// Changes the scheduled appointments of the series in the scope like the occurrence was changed: they're moved by as
+// much as it was moved, and get the type, doctor, room, staff or devices it got. Either all of them are changed or none.
+async fn update_series(
+    database: &Database,
+    config: &AppConfig,
+    original: &AppointmentRecord,
+    changed: &AppointmentRecord,
+    update: &UpdateAppointment,
+    scope: SeriesScope,
+) -> Result<HttpResponse, ApiError> {
+    let series = series_of(original).map_err(ApiError::InvalidRequest)?;
+    let shift = changed.start_time - original.start_time;
+    let appointment_type = read_appointment_type(database, &changed.appointment_type).await?;
+    if update.doctor.is_some() {
+        validate_doctor(database, &changed.doctor).await?;
+    }
+    if update.doctor.is_some() || update.staff.is_some() || update.devices.is_some() {
+        validate_resources(database, &changed.doctor, &changed.staff, &changed.devices).await?;
+    }
+
+    let affected = database
+        .read_appointments(&AppointmentFilter {
+            series: Some(series.clone()),
+            status: Some(AppointmentStatus::Scheduled),
+            from: (scope == SeriesScope::Following).then_some(original.start_time),
+            ..Default::default()
+        })
+        .await?;
+    let freed: Vec<FreedSlot> = affected.iter().map(FreedSlot::from).collect();
+    let mut moved: Vec<AppointmentRecord> = Vec::new();
+    let mut validated_rooms: Vec<RoomRecord> = Vec::new();
+    for appointment in affected {
+        let mut room = appointment.room.clone();
+        let mut appointment = appointment.into_appointment_record();
+        // Like a single update, appointments keep their length unless their time or type changes
+        let length = match update.start_time.is_some() || update.appointment_type.is_some() {
+            true => changed.end_time - changed.start_time,
+            false => appointment.end_time - appointment.start_time,
+        };
+        appointment.start_time += shift;
+        appointment.end_time = appointment.start_time + length;
+        if update.appointment_type.is_some() {
+            appointment.appointment_type = changed.appointment_type.clone();
         }
+        if update.doctor.is_some() {
+            appointment.doctor = changed.doctor.clone();
+        }
+        if update.room.is_some() {
+            appointment.room = changed.room.clone();
+        }
+        if update.staff.is_some() {
+            appointment.staff = changed.staff.clone();
+        }
+        if update.devices.is_some() {
+            appointment.devices = changed.devices.clone();
+        }
+        if update.room.is_some() || update.appointment_type.is_some() {
+            room = match validated_rooms
+                .iter()
+                .find(|room| room.id == *appointment.room.as_thing())
+            {
+                Some(room) => room.clone(),
+                None => {
+                    let room =
+                        validate_room(database, &appointment.room, &appointment_type).await?;
+                    validated_rooms.push(room.clone());
+                    room
+                }
+            };
+        }
+        if update.start_time.is_some() || update.appointment_type.is_some() || update.room.is_some()
+        {
+            appointment.blocked = Buffers::new(&appointment_type, &room)
+                .blocked_time(appointment.start_time, appointment.end_time);
+        }
+        moved.push(appointment);
+    }
+
+    let range = DateRange {
+        from: moved
+            .iter()
+            .map(|appointment| appointment.start_time.date())
+            .min(),
+        to: moved
+            .iter()
+            .map(|appointment| appointment.start_time.date())
+            .max(),
     };
-    match db.read_appointment(&appointment_id.id).await {
-        Ok(appointments) => HttpResponse::Ok().json(ApiResponse { data: appointments }),
-        Err(err) => match err {
-            DatabaseError::NothingFound => HttpResponse::NotFound().body("Appointment not found"),
-            _ => HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
-        },
+    let others: Vec<AppointmentRecordWithPatient> = database
+        .read_appointments(&date_range_filter(range))
+        .await?
+        .into_iter()
+        .filter(|other| !moved.iter().any(|appointment| appointment.id == other.id))
+        .collect();
+    let calendar = ClinicCalendar::load(database, config, range).await?;
+
+    let mut schedules: Vec<(DoctorRecordId, Schedule)> = Vec::new();
+    let mut conflicts = Vec::new();
+    for appointment in &moved {
+        let booking = Booking::from(appointment);
+        let mut participant_schedules = Vec::new();
+        for participant in booking.participants() {
+            let schedule = match schedules.iter().find(|(doctor, _)| doctor == participant) {
+                Some((_, schedule)) => schedule.clone(),
+                None => {
+                    let schedule = database.read_schedule(participant).await?;
+                    schedules.push((participant.clone(), schedule.clone()));
+                    schedule
+                }
+            };
+            participant_schedules.push(schedule);
+        }
+
+        if let Err(err) =
+            is_valid_timeframe(&booking, &participant_schedules, &others, &calendar).await
+        {
+            conflicts.push(format!("{} ({})", appointment.start_time, err.code()));
+        }
+    }
+    if !conflicts.is_empty() {
+        return Err(ApiError::SeriesConflict(format!(
+            "{} of the appointments can't be changed: {}",
+            conflicts.len(),
+            conflicts.join(", ")
+        )));
+    }
+
+    // Moving the later appointments first keeps them from running into the old time of the one after them
+    if shift > Duration::zero() {
+        moved.reverse();
     }
+    let mut result = database
+        .update_appointments(moved)
+        .await
+        .map_err(not_found("Appointment"))?;
+    result.sort_by_key(|appointment| appointment.start_time);
+
+    offer_to_waitlist(database, config, freed).await;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
 }
 
-pub async fn mass_reschedule_doctor(
-    database: web::Data<Arc<Mutex<Database>>>,
-    config: web::Data<AppConfig>,
-    request: web::Json<MassRescheduleRequest>,
-) -> impl Responder {
-    let db = match database.lock() {
-        Ok(guard) => guard,
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Lock error: {:?}", err))
-        }
-    };
+// Moves the appointment on in its lifecycle, if its current status allows it
+async fn change_status(
+    database: &Database,
+    id: &str,
+    status: AppointmentStatus,
+    reason: Option<String>,
+) -> Result<AppointmentRecord, ApiError> {
+    let appointment = database
+        .read_appointment(id)
+        .await
+        .map_err(not_found("Appointment"))?
+        .ok_or(ApiError::NotFound("Appointment"))?;
+    if !appointment.status.can_change_to(status) {
+        return Err(ApiError::InvalidStatusChange(format!(
+            "The appointment is {} and can't become {}",
+            appointment.status.as_str().replace('_', " "),
+            status.as_str().replace('_', " ")
+        )));
+    }
 
-    let leave_start_date = request.start_date;
-    let leave_end_date = request.end_date;
+    database
+        .update_appointment_status(
+            id,
+            StatusChange {
+                status,
+                changed_at: Local::now().naive_local().trunc_subsecs(0),
+                reason,
+            },
+        )
+        .await
+        .map_err(not_found("Appointment"))
+}
 
-    let affected_doctor = request.doctor_id;
+pub async fn check_in_appointment(
+    database: web::Data<Database>,
+    appointment_id: web::Path<AppointmentId>,
+) -> Result<HttpResponse, ApiError> {
+    let result = change_status(
+        &database,
+        &appointment_id.id,
+        AppointmentStatus::CheckedIn,
+        None,
+    )
+    .await?;
 
-    let mut appointments_that_day = Vec::new();
-    let mut iterated_date = leave_start_date;
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
 
-    while iterated_date <= request.end_date {
-        let daily_appointments = match db
-            .read_all_appointments_by_day(&iterated_date.to_string())
-            .await
-        {
-            Ok(appointments) => appointments,
-            Err(err) => {
-                return HttpResponse::InternalServerError().body(format!("Error: {:?}", err))
-            }
-        };
-        appointments_that_day.extend(daily_appointments);
-        iterated_date = match iterated_date.succ_opt() {
-            Some(date) => date,
-            None => {
-                return HttpResponse::InternalServerError().body("Error: Couldn't increment date")
-            }
-        };
+pub async fn start_appointment(
+    database: web::Data<Database>,
+    appointment_id: web::Path<AppointmentId>,
+) -> Result<HttpResponse, ApiError> {
+    let result = change_status(
+        &database,
+        &appointment_id.id,
+        AppointmentStatus::InProgress,
+        None,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+pub async fn complete_appointment(
+    database: web::Data<Database>,
+    appointment_id: web::Path<AppointmentId>,
+) -> Result<HttpResponse, ApiError> {
+    let result = change_status(
+        &database,
+        &appointment_id.id,
+        AppointmentStatus::Completed,
+        None,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+pub async fn mark_appointment_no_show(
+    database: web::Data<Database>,
+    appointment_id: web::Path<AppointmentId>,
+    request: web::Json<StatusChangeRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let result = change_status(
+        &database,
+        &appointment_id.id,
+        AppointmentStatus::NoShow,
+        request.into_inner().reason,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+// Unlike deleting, cancelling keeps the appointment in the history, but frees its time, which is offered to the
+// waitlist. With a scope, the appointments of its series that can still be cancelled are cancelled along with it.
+pub async fn cancel_appointment(
+    database: web::Data<Database>,
+    config: web::Data<AppConfig>,
+    appointment_id: web::Path<AppointmentId>,
+    scope: web::Query<ScopeQuery>,
+    request: web::Json<StatusChangeRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let reason = request
+        .into_inner()
+        .reason
+        .filter(|reason| !reason.trim().is_empty())
+        .ok_or(ApiError::InvalidRequest(
+            "Cancelling an appointment needs a reason".to_string(),
+        ))?;
+
+    if scope.scope == SeriesScope::This {
+        let result = change_status(
+            &database,
+            &appointment_id.id,
+            AppointmentStatus::Cancelled,
+            Some(reason),
+        )
+        .await?;
+        offer_to_waitlist(&database, &config, vec![FreedSlot::from(&result)]).await;
+
+        return Ok(HttpResponse::Ok().json(ApiResponse { data: result }));
     }
 
-    let mut affected_appointments = Vec::new();
+    let appointment = database
+        .read_appointment(&appointment_id.id)
+        .await
+        .map_err(not_found("Appointment"))?
+        .ok_or(ApiError::NotFound("Appointment"))?
+        .into_appointment_record();
+    // The whole series is cancelled from now on, the appointments that have started stay in the history as they are
+    let now = Local::now().naive_local().trunc_subsecs(0);
+    let from = match scope.scope {
+        SeriesScope::Following => appointment.start_time,
+        _ => now,
+    };
 
-    for appointment in appointments_that_day {
-        if appointment.doctor == affected_doctor {
-            affected_appointments.push(appointment);
-        }
+    let cancelled = database
+        .update_series_status(
+            series_of(&appointment).map_err(ApiError::InvalidRequest)?,
+            Some(from),
+            StatusChange {
+                status: AppointmentStatus::Cancelled,
+                changed_at: now,
+                reason: Some(reason),
+            },
+        )
+        .await?;
+    if cancelled.is_empty() {
+        return Err(ApiError::InvalidStatusChange(
+            "None of the appointments can be cancelled anymore".to_string(),
+        ));
     }
 
-    let mut updated_appointments = Vec::new();
-
-    for mut appointment in affected_appointments {
-        let mut new_start_time: NaiveDateTime = match (leave_end_date + Duration::days(1))
-            .and_hms_opt(
-                appointment.start_time.time().hour(),
-                appointment.start_time.time().minute(),
-                appointment.start_time.time().second(),
-            ) {
-            Some(time) => time,
-            None => {
-                return HttpResponse::InternalServerError()
-                    .body("Error: Couldn't create NaiveDateTime")
-            }
-        };
-        let mut new_end_time: NaiveDateTime = match (leave_end_date + Duration::days(1))
-            .and_hms_opt(
-                appointment.end_time.time().hour(),
-                appointment.end_time.time().minute(),
-                appointment.end_time.time().second(),
-            ) {
-            Some(time) => time,
-            None => {
-                return HttpResponse::InternalServerError()
-                    .body("Error: Couldn't create NaiveDateTime")
-            }
-        };
+    offer_to_waitlist(
+        &database,
+        &config,
+        cancelled.iter().map(FreedSlot::from).collect(),
+    )
+    .await;
 
-        let mut appointments_of_new_day = match db
-            .read_all_appointments_by_day(&iterated_date.to_string())
-            .await
-        {
-            Ok(appointments) => appointments,
-            Err(err) => {
-                return HttpResponse::InternalServerError().body(format!("Error: {:?}", err))
-            }
-        };
+    Ok(HttpResponse::Ok().json(ApiResponse { data: cancelled }))
+}
 
-        while let Err(_) = is_valid_timeframe(
-            new_start_time,
-            new_end_time,
-            appointment.doctor,
-            appointment.room_nr,
-            &appointments_of_new_day,
-            &config,
-        )
+pub async fn read_appointment(
+    database: web::Data<Database>,
+    appointment_id: web::Path<AppointmentId>,
+) -> Result<HttpResponse, ApiError> {
+    let appointment = database
+        .read_appointment(&appointment_id.id)
+        .await
+        .map_err(not_found("Appointment"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: appointment }))
+}
+
+pub async fn mass_reschedule_doctor(
+    database: web::Data<Database>,
+    config: web::Data<AppConfig>,
+    request: web::Json<MassRescheduleRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let leave_start_date = request.start_date;
+    let leave_end_date = request.end_date;
+    if leave_end_date < leave_start_date {
+        return Err(ApiError::InvalidRequest(format!(
+            "The leave can't end on {} before it starts on {}",
+            leave_end_date, leave_start_date
+        )));
+    }
+
+    let max_days = request.max_days.unwrap_or(DEFAULT_RESCHEDULE_DAYS);
+    if max_days == 0 || i64::from(max_days) > MAX_SEARCH_DAYS {
+        return Err(ApiError::InvalidRequest(format!(
+            "max_days must be between 1 and {}, got {}",
+            MAX_SEARCH_DAYS, max_days
+        )));
+    }
+    let last_day = leave_end_date + Duration::days(max_days.into());
+
+    let affected_doctor = &request.doctor_id;
+    let doctor = database
+        .read_doctor(&affected_doctor.get_unique_id())
         .await
+        .map_err(not_found("Doctor"))?;
+
+    // Appointments that already took place or were cancelled stay where they are. The ones the doctor only takes part
+    // in are affected as well.
+    let leave = AppointmentFilter {
+        participant: Some(affected_doctor.clone()),
+        status: Some(AppointmentStatus::Scheduled),
+        from: leave_start_date.and_hms_opt(0, 0, 0),
+        to: (leave_end_date + Duration::days(1)).and_hms_opt(0, 0, 0),
+        ..Default::default()
+    };
+    let affected_appointments = database.read_appointments(&leave).await?;
+
+    let mut substitutes = Vec::new();
+    if request.allow_substitutes {
+        let colleagues = DoctorFilter {
+            specialty: Some(doctor.specialty.clone()),
+            active: Some(true),
+        };
+        for record in database
+            .read_doctor_page(&colleagues, &Pagination::all(DoctorSort::Name))
+            .await?
+            .items
+            .into_iter()
+            .filter(|record| record.id != *affected_doctor.as_thing())
         {
-            new_start_time += Duration::days(1);
-            new_end_time += Duration::days(1);
+            let schedule = database
+                .read_schedule(&DoctorRecordId::from(record.id.clone()))
+                .await?;
+            substitutes.push(CandidateDoctor { record, schedule });
+        }
+    }
 
-            appointments_of_new_day = match db
-                .read_all_appointments_by_day(&new_start_time.date().to_string())
-                .await
-            {
-                Ok(appointments) => appointments,
-                Err(err) => {
-                    return HttpResponse::InternalServerError().body(format!("Error: {:?}", err))
-                }
-            };
+    // Inactive doctors don't take their appointments back, so only substitutes can
+    let doctor = if doctor.active {
+        Some(CandidateDoctor {
+            schedule: database.read_schedule(affected_doctor).await?,
+            record: doctor,
+        })
+    } else {
+        None
+    };
+
+    // The staff of the appointments come along wherever they're moved
+    let mut staff: Vec<AvailableDoctor> = Vec::new();
+    for member in affected_appointments
+        .iter()
+        .flat_map(|appointment| &appointment.staff)
+    {
+        let member = DoctorRecordId::from(member.id.clone());
+        if staff.iter().all(|available| available.doctor != member) {
+            staff.push(AvailableDoctor {
+                schedule: database.read_schedule(&member).await?,
+                doctor: member,
+            });
         }
+    }
 
-        appointment.start_time = new_start_time;
-        appointment.end_time = new_end_time;
+    let searched_days = DateRange {
+        from: Some(leave_start_date),
+        to: Some(last_day),
+    };
+    let calendar = ClinicCalendar::load(&database, &config, searched_days).await?;
+    // The leave has to be stored as an absence, so nothing is booked into it, or offered from the waitlist, while the
+    // appointments are moved out of it
+    if !calendar.absent_throughout(
+        affected_doctor,
+        leave_start_date.and_time(NaiveTime::MIN),
+        (leave_end_date + Duration::days(1)).and_time(NaiveTime::MIN),
+    ) {
+        return Err(ApiError::InvalidRequest(format!(
+            "The doctor has no absence from {} to {}, create one before rescheduling",
+            leave_start_date, leave_end_date
+        )));
+    }
+    let rescheduler = Rescheduler {
+        absent_doctor: affected_doctor.clone(),
+        absent_until: leave_end_date,
+        last_day,
+        doctor,
+        substitutes,
+        staff,
+        rooms: database
+            .read_room_page(&RoomFilter::default(), &Pagination::all(RoomSort::Name))
+            .await?
+            .items,
+        appointment_types: database.read_appointment_types().await?,
+        calendar,
+    };
+    let booked = database
+        .read_appointments(&AppointmentFilter {
+            from: leave_start_date.and_hms_opt(0, 0, 0),
+            to: (last_day + Duration::days(1)).and_hms_opt(0, 0, 0),
+            ..Default::default()
+        })
+        .await?;
+
+    let plan = rescheduler
+        .plan(affected_appointments.clone(), booked)
+        .await;
+
+    let moved_ids: Vec<Thing> = plan
+        .moved
+        .iter()
+        .map(|appointment| appointment.id.clone())
+        .collect();
+    let moved = plan
+        .moved
+        .iter()
+        .filter_map(|appointment| {
+            let original = affected_appointments
+                .iter()
+                .find(|original| original.id == appointment.id)?;
+            Some(RescheduledAppointment {
+                appointment: appointment.id.clone(),
+                from: ScheduledTime::from(original),
+                to: ScheduledTime::from(appointment),
+            })
+        })
+        .collect();
 
-        match db
-            .update_appointment(
-                &appointment.id.id.to_raw(),
-                appointment.into_appointment_record(),
+    // The plan takes the appointments one after the other, so the moves are applied in that order as well. If any
+    // of the new timeframes was booked in the meantime, nothing is moved.
+    if !request.dry_run {
+        database
+            .update_appointments(
+                plan.moved
+                    .into_iter()
+                    .map(AppointmentRecordWithPatient::into_appointment_record)
+                    .collect(),
             )
-            .await
-        {
-            Ok(updated_appointment) => {
-                updated_appointments.push(updated_appointment);
-            }
-            Err(err) => {
-                return HttpResponse::InternalServerError().body(format!("Error: {:?}", err))
-            }
-        }
+            .await?;
+
+        // Appointments that only got another staff member keep their slot
+        let freed = affected_appointments
+            .iter()
+            .filter(|original| {
+                moved_ids.contains(&original.id)
+                    && original.doctor.id == *affected_doctor.as_thing()
+            })
+            .map(FreedSlot::from)
+            .collect();
+        offer_to_waitlist(&database, &config, freed).await;
     }
 
-    HttpResponse::Ok().json(updated_appointments)
+    Ok(HttpResponse::Ok().json(MassRescheduleResponse {
+        dry_run: request.dry_run,
+        moved,
+        unplaced: plan.unplaced,
+    }))
 }
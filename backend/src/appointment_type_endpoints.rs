@@ -0,0 +1,130 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+use crate::db::{
+    db::Database,
+    types::{validate_appointment_type_id, AppointmentTypeDefinition, RoomType},
+};
+use crate::errors::{not_found, ApiError};
+use crate::types::ApiResponse;
+
+// Appointment Type Types
+#[derive(Deserialize)]
+pub struct AppointmentTypeId {
+    id: String,
+}
+#[derive(Deserialize)]
+pub struct NewAppointmentType {
+    id: String,
+    #[serde(flatten)]
+    definition: AppointmentTypeDefinition,
+}
+#[derive(Deserialize)]
+pub struct UpdateAppointmentType {
+    name: Option<String>,
+    duration_minutes: Option<u32>,
+    buffer_before_minutes: Option<u32>,
+    buffer_after_minutes: Option<u32>,
+    colour: Option<String>,
+    room_types: Option<Vec<RoomType>>,
+}
+
+// Endpoints
+pub async fn read_all_appointment_types(
+    database: web::Data<Database>,
+) -> Result<HttpResponse, ApiError> {
+    let appointment_types = database.read_appointment_types().await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        data: appointment_types,
+    }))
+}
+
+pub async fn create_appointment_type(
+    database: web::Data<Database>,
+    appointment_type: web::Json<NewAppointmentType>,
+) -> Result<HttpResponse, ApiError> {
+    let NewAppointmentType { id, definition } = appointment_type.into_inner();
+    validate_appointment_type_id(&id).map_err(ApiError::InvalidRequest)?;
+    definition.validate().map_err(ApiError::InvalidRequest)?;
+
+    if database.read_appointment_type(&id).await.is_ok() {
+        return Err(ApiError::InvalidRequest(format!(
+            "An appointment type with the ID {} already exists",
+            id
+        )));
+    }
+
+    let result = database.create_appointment_type(&id, definition).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+pub async fn read_appointment_type(
+    database: web::Data<Database>,
+    appointment_type_id: web::Path<AppointmentTypeId>,
+) -> Result<HttpResponse, ApiError> {
+    let appointment_type = database
+        .read_appointment_type(&appointment_type_id.id)
+        .await
+        .map_err(not_found("Appointment type"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        data: appointment_type,
+    }))
+}
+
+// Changing the duration or the buffers only applies to appointments booked or moved afterwards
+pub async fn update_appointment_type(
+    database: web::Data<Database>,
+    appointment_type_id: web::Path<AppointmentTypeId>,
+    update: web::Json<UpdateAppointmentType>,
+) -> Result<HttpResponse, ApiError> {
+    let mut appointment_type = database
+        .read_appointment_type(&appointment_type_id.id)
+        .await
+        .map_err(not_found("Appointment type"))?;
+
+    if let Some(name) = &update.name {
+        appointment_type.name = name.clone();
+    }
+    if let Some(duration_minutes) = update.duration_minutes {
+        appointment_type.duration_minutes = duration_minutes;
+    }
+    if let Some(buffer_before_minutes) = update.buffer_before_minutes {
+        appointment_type.buffer_before_minutes = buffer_before_minutes;
+    }
+    if let Some(buffer_after_minutes) = update.buffer_after_minutes {
+        appointment_type.buffer_after_minutes = buffer_after_minutes;
+    }
+    if let Some(colour) = &update.colour {
+        appointment_type.colour = colour.clone();
+    }
+    if let Some(room_types) = &update.room_types {
+        appointment_type.room_types = room_types.clone();
+    }
+    AppointmentTypeDefinition::from(appointment_type.clone())
+        .validate()
+        .map_err(ApiError::InvalidRequest)?;
+
+    let result = database
+        .update_appointment_type(&appointment_type_id.id, appointment_type)
+        .await
+        .map_err(not_found("Appointment type"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+pub async fn delete_appointment_type(
+    database: web::Data<Database>,
+    appointment_type_id: web::Path<AppointmentTypeId>,
+) -> Result<HttpResponse, ApiError> {
+    let appointment_type = database
+        .delete_appointment_type(&appointment_type_id.id)
+        .await
+        .map_err(not_found("Appointment type"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        data: appointment_type,
+    }))
+}
@@ -0,0 +1,318 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::Serialize;
+
+use crate::{
+    calendar::ClinicCalendar,
+    db::types::{
+        AppointmentRecordWithPatient, Buffers, DeviceRecordId, DoctorRecordId, RoomRecordId,
+        Schedule,
+    },
+    util::{is_valid_timeframe, Booking},
+};
+
+// How far apart the start times of free slots are, counted from the opening time
+const SLOT_INTERVAL_MINUTES: i64 = 15;
+// Searching is done day by day, so the number of days searched at once is limited to keep requests fast
+pub const MAX_SEARCH_DAYS: i64 = 92;
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FreeSlot {
+    pub start_time: NaiveDateTime,
+    pub end_time: NaiveDateTime,
+    // A doctor and room the appointment can be booked with at that time
+    pub doctor: DoctorRecordId,
+    pub room: RoomRecordId,
+}
+
+// A doctor that can take the appointment, together with their weekly schedule
+#[derive(Debug, Clone)]
+pub struct AvailableDoctor {
+    pub doctor: DoctorRecordId,
+    pub schedule: Schedule,
+}
+
+// A room the appointment can take place in, together with the buffers it gets there
+#[derive(Debug, Clone)]
+pub struct AvailableRoom {
+    pub room: RoomRecordId,
+    pub buffers: Buffers,
+}
+
+// What an appointment needs in every slot besides a doctor and a room: its length, and the staff and devices that take
+// part in it
+#[derive(Debug, Clone)]
+pub struct SlotRequirements {
+    pub duration: Duration,
+    pub staff: Vec<AvailableDoctor>,
+    pub devices: Vec<DeviceRecordId>,
+    // Slots have to start after this time, like now for slots that are offered to be booked
+    pub starts_after: Option<NaiveDateTime>,
+}
+
+impl SlotRequirements {
+    // An appointment with just a doctor and a room
+    pub fn new(duration: Duration) -> Self {
+        SlotRequirements {
+            duration,
+            staff: vec![],
+            devices: vec![],
+            starts_after: None,
+        }
+    }
+}
+
+// The part of the day free slots have to lie within. Either end can be left open.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimeOfDay {
+    pub after: Option<NaiveTime>,
+    pub before: Option<NaiveTime>,
+}
+
+impl TimeOfDay {
+    fn contains(&self, start_time: NaiveTime, end_time: NaiveTime) -> bool {
+        self.after.is_none_or(|after| start_time >= after)
+            && self.before.is_none_or(|before| end_time <= before)
+    }
+}
+
+// The start times on the date an appointment with the requirements passes `is_valid_timeframe` with any of the
+// doctors in any of the rooms. The first doctor and room that are free are picked, so they should be passed in the
+// order they're preferred in. Doctors that are part of the required staff can't be the doctor as well.
+pub async fn find_free_slots(
+    date: NaiveDate,
+    requirements: &SlotRequirements,
+    doctors: &[AvailableDoctor],
+    rooms: &[AvailableRoom],
+    appointments: &[AppointmentRecordWithPatient],
+    calendar: &ClinicCalendar,
+    time_of_day: TimeOfDay,
+) -> Vec<FreeSlot> {
+    let mut slots = Vec::new();
+    let Some((opening_time, closing_time)) = calendar.opening_hours(date) else {
+        return slots;
+    };
+    let duration = requirements.duration;
+    let staff: Vec<DoctorRecordId> = requirements
+        .staff
+        .iter()
+        .map(|member| member.doctor.clone())
+        .collect();
+    // Each doctor with the schedules of everyone who has to be there with them
+    let candidates: Vec<(&AvailableDoctor, Vec<Schedule>)> = doctors
+        .iter()
+        .filter(|doctor| !staff.contains(&doctor.doctor))
+        .map(|doctor| {
+            let schedules = std::iter::once(doctor)
+                .chain(&requirements.staff)
+                .map(|participant| participant.schedule.clone())
+                .collect();
+            (doctor, schedules)
+        })
+        .collect();
+
+    let mut start_time = date.and_time(opening_time);
+    while start_time + duration <= date.and_time(closing_time) {
+        let end_time = start_time + duration;
+
+        let started = requirements
+            .starts_after
+            .is_some_and(|starts_after| start_time <= starts_after);
+        if !started && time_of_day.contains(start_time.time(), end_time.time()) {
+            'candidates: for (doctor, schedules) in &candidates {
+                for room in rooms {
+                    let booking = Booking {
+                        start_time,
+                        end_time,
+                        blocked: room.buffers.blocked_time(start_time, end_time),
+                        doctor: &doctor.doctor,
+                        room: &room.room,
+                        staff: &staff,
+                        devices: &requirements.devices,
+                    };
+                    if is_valid_timeframe(&booking, schedules, appointments, calendar)
+                        .await
+                        .is_ok()
+                    {
+                        slots.push(FreeSlot {
+                            start_time,
+                            end_time,
+                            doctor: doctor.doctor.clone(),
+                            room: room.room.clone(),
+                        });
+                        break 'candidates;
+                    }
+                }
+            }
+        }
+
+        start_time += Duration::minutes(SLOT_INTERVAL_MINUTES);
+    }
+
+    slots
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::config_tests::get_test_config,
+        db::types::{Shift, Weekday},
+        util::util_tests::{appointment, time},
+    };
+
+    use super::*;
+
+    fn doctor(id: &str, schedule: Schedule) -> AvailableDoctor {
+        AvailableDoctor {
+            doctor: DoctorRecordId::new(id),
+            schedule,
+        }
+    }
+
+    fn start_times(slots: &[FreeSlot]) -> Vec<String> {
+        slots
+            .iter()
+            .map(|slot| slot.start_time.time().format("%H:%M").to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_find_free_slots() {
+        let calendar = ClinicCalendar::new(&get_test_config(), vec![], vec![], vec![]);
+        let monday = time("2023-10-02T00:00:00").date();
+        let rooms = [AvailableRoom {
+            room: RoomRecordId::new("1"),
+            buffers: Buffers::default(),
+        }];
+
+        // Doctor 1 works in the morning and has an appointment from 9 to 10:30
+        let morning = Schedule {
+            shifts: vec![Shift {
+                weekday: Weekday::Monday,
+                start_time: "08:00:00".parse().unwrap(),
+                end_time: "12:00:00".parse().unwrap(),
+            }],
+        };
+        let appointments = vec![appointment(
+            "1",
+            "2",
+            "2023-10-02T09:00:00",
+            "2023-10-02T10:30:00",
+        )];
+
+        let slots = find_free_slots(
+            monday,
+            &SlotRequirements::new(Duration::hours(1)),
+            &[doctor("1", morning.clone())],
+            &rooms,
+            &appointments,
+            &calendar,
+            TimeOfDay::default(),
+        )
+        .await;
+        assert_eq!(start_times(&slots), ["08:00", "10:30", "10:45", "11:00"]);
+        assert_eq!(slots[0].end_time, time("2023-10-02T09:00:00"));
+
+        // With 15 minutes to prepare, the doctor can't start right after the appointment
+        let prepared = [AvailableRoom {
+            room: RoomRecordId::new("1"),
+            buffers: Buffers {
+                doctor_before: Duration::minutes(15),
+                room_before: Duration::minutes(15),
+                ..Default::default()
+            },
+        }];
+        let slots = find_free_slots(
+            monday,
+            &SlotRequirements::new(Duration::hours(1)),
+            &[doctor("1", morning.clone())],
+            &prepared,
+            &appointments,
+            &calendar,
+            TimeOfDay::default(),
+        )
+        .await;
+        assert_eq!(start_times(&slots), ["08:00", "10:45", "11:00"]);
+        assert_eq!(slots[1].end_time, time("2023-10-02T11:45:00"));
+
+        // Late in the morning, the slots that have started already are gone
+        let requirements = SlotRequirements {
+            starts_after: Some(time("2023-10-02T10:45:00")),
+            ..SlotRequirements::new(Duration::hours(1))
+        };
+        let slots = find_free_slots(
+            monday,
+            &requirements,
+            &[doctor("1", morning.clone())],
+            &rooms,
+            &appointments,
+            &calendar,
+            TimeOfDay::default(),
+        )
+        .await;
+        assert_eq!(start_times(&slots), ["11:00"]);
+
+        // Doctor 2 takes over where doctor 1 is busy, but both share the room
+        let slots = find_free_slots(
+            monday,
+            &SlotRequirements::new(Duration::hours(1)),
+            &[
+                doctor("1", morning.clone()),
+                doctor("2", Schedule::default()),
+            ],
+            &rooms,
+            &appointments,
+            &calendar,
+            TimeOfDay {
+                after: Some("08:30:00".parse().unwrap()),
+                before: Some("14:30:00".parse().unwrap()),
+            },
+        )
+        .await;
+        assert_eq!(
+            start_times(&slots),
+            [
+                "08:30", "08:45", "09:00", "09:15", "09:30", "09:45", "10:00", "10:15", "10:30",
+                "10:45", "11:00", "13:00", "13:15", "13:30"
+            ]
+        );
+        assert_eq!(slots[2].doctor, DoctorRecordId::new("2"));
+        assert_eq!(slots[8].doctor, DoctorRecordId::new("1"));
+
+        // With doctor 1 as part of the staff, doctor 2 can only take the slots both of them are free in
+        let requirements = SlotRequirements {
+            staff: vec![doctor("1", morning.clone())],
+            ..SlotRequirements::new(Duration::hours(1))
+        };
+        let slots = find_free_slots(
+            monday,
+            &requirements,
+            &[
+                doctor("1", morning.clone()),
+                doctor("2", Schedule::default()),
+            ],
+            &rooms,
+            &appointments,
+            &calendar,
+            TimeOfDay::default(),
+        )
+        .await;
+        assert_eq!(start_times(&slots), ["08:00", "10:30", "10:45", "11:00"]);
+        assert!(slots
+            .iter()
+            .all(|slot| slot.doctor == DoctorRecordId::new("2")));
+
+        // Nothing is free on a Sunday
+        let sunday = time("2023-10-01T00:00:00").date();
+        let slots = find_free_slots(
+            sunday,
+            &SlotRequirements::new(Duration::hours(1)),
+            &[doctor("2", Schedule::default())],
+            &rooms,
+            &[],
+            &calendar,
+            TimeOfDay::default(),
+        )
+        .await;
+        assert!(slots.is_empty());
+    }
+}
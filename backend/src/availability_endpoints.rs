@@ -0,0 +1,186 @@
+use actix_web::{web, HttpResponse};
+use chrono::{Duration, Local, NaiveDate, NaiveTime, SubsecRound};
+use serde::Deserialize;
+
+use crate::appointment_endpoints::{read_appointment_type, validate_doctor, validate_room};
+use crate::availability::{
+    find_free_slots, AvailableDoctor, AvailableRoom, SlotRequirements, TimeOfDay, MAX_SEARCH_DAYS,
+};
+use crate::calendar::ClinicCalendar;
+use crate::config::AppConfig;
+use crate::db::{
+    db::Database,
+    types::{
+        AppointmentFilter, AppointmentType, Buffers, DateRange, DoctorFilter, DoctorRecordId,
+        DoctorSort, Pagination, RoomFilter, RoomRecordId, RoomSort,
+    },
+};
+use crate::errors::ApiError;
+use crate::types::ApiResponse;
+
+// Days searched if no end date is given, starting with the first one
+const DEFAULT_SEARCH_DAYS: i64 = 14;
+const DEFAULT_SLOT_LIMIT: u32 = 20;
+const MAX_SLOT_LIMIT: u32 = 500;
+
+// Availability Types
+#[derive(Deserialize)]
+pub struct AvailabilityRequest {
+    appointment_type: AppointmentType,
+    // Any active doctor if not set
+    doctor: Option<DoctorRecordId>,
+    // Any room the appointment type can take place in if not set
+    room: Option<RoomRecordId>,
+    // Today if not set
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    after: Option<NaiveTime>,
+    before: Option<NaiveTime>,
+    limit: Option<u32>,
+}
+
+// Endpoints
+pub async fn read_availability(
+    database: web::Data<Database>,
+    config: web::Data<AppConfig>,
+    request: web::Query<AvailabilityRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let now = Local::now().naive_local().trunc_subsecs(0);
+    let from = request.from.unwrap_or(now.date());
+    let to = match request.to {
+        Some(to) => to,
+        None => from
+            .checked_add_signed(Duration::days(DEFAULT_SEARCH_DAYS - 1))
+            .ok_or(ApiError::InvalidRequest(format!(
+                "The search can't start as late as {}",
+                from
+            )))?,
+    };
+    if to < from {
+        return Err(ApiError::InvalidRequest(format!(
+            "The search can't end on {} before it starts on {}",
+            to, from
+        )));
+    }
+    if (to - from).num_days() >= MAX_SEARCH_DAYS {
+        return Err(ApiError::InvalidRequest(format!(
+            "At most {} days can be searched at once",
+            MAX_SEARCH_DAYS
+        )));
+    }
+
+    let time_of_day = TimeOfDay {
+        after: request.after,
+        before: request.before,
+    };
+    if let (Some(after), Some(before)) = (time_of_day.after, time_of_day.before) {
+        if after >= before {
+            return Err(ApiError::InvalidRequest(format!(
+                "after ({}) has to be earlier than before ({})",
+                after, before
+            )));
+        }
+    }
+
+    let limit = request.limit.unwrap_or(DEFAULT_SLOT_LIMIT);
+    if limit == 0 || limit > MAX_SLOT_LIMIT {
+        return Err(ApiError::InvalidRequest(format!(
+            "limit must be between 1 and {}, got {}",
+            MAX_SLOT_LIMIT, limit
+        )));
+    }
+
+    let appointment_type = read_appointment_type(&database, &request.appointment_type).await?;
+
+    let doctor_ids = match &request.doctor {
+        Some(doctor) => {
+            validate_doctor(&database, doctor).await?;
+            vec![doctor.clone()]
+        }
+        None => {
+            let active = DoctorFilter {
+                active: Some(true),
+                ..Default::default()
+            };
+            database
+                .read_doctor_page(&active, &Pagination::all(DoctorSort::Name))
+                .await?
+                .items
+                .into_iter()
+                .map(|doctor| DoctorRecordId::from(doctor.id))
+                .collect()
+        }
+    };
+    let mut doctors = Vec::new();
+    for doctor in doctor_ids {
+        let schedule = database.read_schedule(&doctor).await?;
+        doctors.push(AvailableDoctor { doctor, schedule });
+    }
+
+    let rooms: Vec<AvailableRoom> = match &request.room {
+        Some(room) => vec![validate_room(&database, room, &appointment_type).await?],
+        None => database
+            .read_room_page(&RoomFilter::default(), &Pagination::all(RoomSort::Name))
+            .await?
+            .items
+            .into_iter()
+            .filter(|room| appointment_type.room_types.contains(&room.room_type))
+            .collect(),
+    }
+    .into_iter()
+    .map(|room| AvailableRoom {
+        buffers: Buffers::new(&appointment_type, &room),
+        room: RoomRecordId::from(room.id),
+    })
+    .collect();
+
+    let calendar = ClinicCalendar::load(
+        &database,
+        &config,
+        DateRange {
+            from: Some(from),
+            to: Some(to),
+        },
+    )
+    .await?;
+    let appointments = database
+        .read_appointments(&AppointmentFilter {
+            from: from.and_hms_opt(0, 0, 0),
+            to: to.succ_opt().and_then(|day| day.and_hms_opt(0, 0, 0)),
+            ..Default::default()
+        })
+        .await?;
+
+    // Slots that have started already can't be booked anymore
+    let requirements = SlotRequirements {
+        starts_after: Some(now),
+        ..SlotRequirements::new(appointment_type.duration())
+    };
+    let mut slots = Vec::new();
+    for date in from.iter_days().take_while(|date| *date <= to) {
+        let appointments_of_day: Vec<_> = appointments
+            .iter()
+            .filter(|appointment| appointment.start_time.date() == date)
+            .cloned()
+            .collect();
+        slots.extend(
+            find_free_slots(
+                date,
+                &requirements,
+                &doctors,
+                &rooms,
+                &appointments_of_day,
+                &calendar,
+                time_of_day,
+            )
+            .await,
+        );
+
+        if slots.len() >= limit as usize {
+            slots.truncate(limit as usize);
+            break;
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: slots }))
+}
@@ -0,0 +1,473 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::{
+    config::{AppConfig, Break},
+    db::{
+        db::Database,
+        types::{
+            Absence, AbsenceFilter, Closure, DatabaseError, DateRange, DoctorRecordId,
+            OpeningOverride, Weekday,
+        },
+    },
+};
+
+// When the clinic is open. Combines the configured opening days and hours with the closures and the opening hours
+// of single dates that are stored in the database, along with the absences of the doctors.
+#[derive(Debug, Clone)]
+pub struct ClinicCalendar {
+    opening_days: Vec<Weekday>,
+    opening_time: NaiveTime,
+    closing_time: NaiveTime,
+    breaks: Vec<Break>,
+    closures: Vec<Closure>,
+    opening_overrides: Vec<OpeningOverride>,
+    absences: Vec<Absence>,
+}
+
+impl ClinicCalendar {
+    pub fn new(
+        config: &AppConfig,
+        closures: Vec<Closure>,
+        opening_overrides: Vec<OpeningOverride>,
+        absences: Vec<Absence>,
+    ) -> Self {
+        ClinicCalendar {
+            opening_days: config.opening_days.clone(),
+            opening_time: config.opening_time,
+            closing_time: config.closing_time,
+            breaks: config.breaks.clone(),
+            closures,
+            opening_overrides,
+            absences,
+        }
+    }
+
+    // Only the closures, overrides and absences within the range are read, so the calendar can't be asked about other
+    // dates
+    pub async fn load(
+        database: &Database,
+        config: &AppConfig,
+        range: DateRange,
+    ) -> Result<Self, DatabaseError> {
+        let closures = database.read_closures(&range).await?;
+        let opening_overrides = database.read_opening_overrides(&range).await?;
+        let absences = database
+            .read_absences(&AbsenceFilter {
+                doctor: None,
+                from: range.from,
+                to: range.to,
+            })
+            .await?;
+
+        Ok(ClinicCalendar::new(
+            config,
+            closures,
+            opening_overrides,
+            absences.into_iter().map(Absence::from).collect(),
+        ))
+    }
+
+    pub fn closure(&self, date: NaiveDate) -> Option<&Closure> {
+        self.closures.iter().find(|closure| closure.date == date)
+    }
+
+    // The opening and closing time on the date, or None if the clinic is closed. An override can open the clinic on
+    // a weekday it's usually closed on, but not on a closure.
+    pub fn opening_hours(&self, date: NaiveDate) -> Option<(NaiveTime, NaiveTime)> {
+        if self.closure(date).is_some() {
+            return None;
+        }
+
+        if let Some(opening_override) = self
+            .opening_overrides
+            .iter()
+            .find(|opening_override| opening_override.date == date)
+        {
+            return Some((opening_override.opening_time, opening_override.closing_time));
+        }
+
+        if self.opening_days.contains(&Weekday::from(date.weekday())) {
+            Some((self.opening_time, self.closing_time))
+        } else {
+            None
+        }
+    }
+
+    // The breaks the doctor takes on the date
+    pub fn breaks<'a>(
+        &'a self,
+        date: NaiveDate,
+        doctor: &'a DoctorRecordId,
+    ) -> impl Iterator<Item = &'a Break> {
+        let weekday = Weekday::from(date.weekday());
+        self.breaks
+            .iter()
+            .filter(move |clinic_break| clinic_break.applies_to(weekday, doctor))
+    }
+
+    // The absence of the doctor during the timeframe, if they have one
+    pub fn absence(
+        &self,
+        doctor: &DoctorRecordId,
+        start_time: NaiveDateTime,
+        end_time: NaiveDateTime,
+    ) -> Option<&Absence> {
+        self.absences
+            .iter()
+            .find(|absence| absence.doctor == *doctor && absence.overlaps(start_time, end_time))
+    }
+
+    // Whether the doctor is away for the whole timeframe, even if it takes several absences to cover it
+    pub fn absent_throughout(
+        &self,
+        doctor: &DoctorRecordId,
+        start_time: NaiveDateTime,
+        end_time: NaiveDateTime,
+    ) -> bool {
+        let mut absences: Vec<&Absence> = self
+            .absences
+            .iter()
+            .filter(|absence| absence.doctor == *doctor)
+            .collect();
+        absences.sort_by_key(|absence| absence.start());
+
+        let mut covered_until = start_time;
+        for absence in absences {
+            if absence.start() > covered_until {
+                break;
+            }
+            covered_until = covered_until.max(absence.end());
+        }
+        covered_until >= end_time
+    }
+}
+
+// Longer events, like a vacation that was entered for the wrong year, would close the clinic for good
+const MAX_EVENT_DAYS: i64 = 366;
+
+#[derive(Default)]
+struct Event {
+    start: Option<IcalDate>,
+    end: Option<IcalDate>,
+    summary: Option<String>,
+}
+
+enum EventOutcome {
+    // Every day the event covers, named after the event
+    Closures(Vec<Closure>),
+    // Events with a time of day, like a meeting from 08:00 to 12:00, don't close the clinic for whole days, so they're
+    // skipped with this warning
+    Skipped(String),
+}
+
+struct IcalDate {
+    date: NaiveDate,
+    // Whether it's a whole day or the very start of the day. Such end dates aren't part of the event anymore.
+    at_midnight: bool,
+}
+
+fn parse_ical_date(value: &str) -> Result<IcalDate, String> {
+    let date = value
+        .get(..8)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        .ok_or(format!("{} isn't a valid iCalendar date", value))?;
+    let time = value[8..].trim_end_matches('Z');
+
+    Ok(IcalDate {
+        date,
+        at_midnight: time.is_empty() || time == "T000000",
+    })
+}
+
+fn unescape_ical_text(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        match (char, chars.clone().next()) {
+            ('\\', Some('n' | 'N')) => {
+                text.push(' ');
+                chars.next();
+            }
+            ('\\', Some(escaped)) => {
+                text.push(escaped);
+                chars.next();
+            }
+            _ => text.push(char),
+        }
+    }
+    text
+}
+
+impl Event {
+    // Every day the event covers becomes a closure named after the event, unless it lasts only part of a day
+    fn into_closures(self) -> Result<EventOutcome, String> {
+        let start = self
+            .start
+            .ok_or("An event in the calendar has no start date".to_string())?;
+        let name = self.summary.unwrap_or("Closed".to_string());
+
+        if !start.at_midnight || self.end.as_ref().is_some_and(|end| !end.at_midnight) {
+            return Ok(EventOutcome::Skipped(format!(
+                "Skipped '{}' on {}, only events that last whole days close the clinic",
+                name, start.date
+            )));
+        }
+        let last_date = match self.end {
+            Some(end) if end.date > start.date => end.date - Duration::days(1),
+            _ => start.date,
+        };
+        if (last_date - start.date).num_days() >= MAX_EVENT_DAYS {
+            return Err(format!(
+                "'{}' lasts longer than {} days, which can't be imported as closures",
+                name, MAX_EVENT_DAYS
+            ));
+        }
+
+        Ok(EventOutcome::Closures(
+            start
+                .date
+                .iter_days()
+                .take_while(|date| *date <= last_date)
+                .map(|date| Closure {
+                    date,
+                    name: name.clone(),
+                })
+                .collect(),
+        ))
+    }
+}
+
+// The closures read from an iCalendar file, and a warning for every event that was skipped
+#[derive(Debug, Default)]
+pub struct IcalendarClosures {
+    pub closures: Vec<Closure>,
+    pub skipped: Vec<String>,
+}
+
+// Reads the closures from the events of an iCalendar file, like the public holidays most calendar apps can export.
+// Only whole-day events are used, recurring events only close the clinic on their first date.
+pub fn parse_icalendar(source: &str) -> Result<IcalendarClosures, String> {
+    // Long lines are folded by continuing them on the next line, which starts with a space or a tab
+    let mut lines: Vec<String> = Vec::new();
+    for line in source.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last_line)) => last_line.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    if !lines
+        .iter()
+        .any(|line| line.trim().eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return Err("The body isn't an iCalendar file".to_string());
+    }
+
+    let mut imported = IcalendarClosures::default();
+    let mut event: Option<Event> = None;
+    for line in &lines {
+        // Parameters like the time zone in DTSTART;TZID=Europe/Berlin:20241224T080000 don't matter, only whole days
+        // are imported
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let name = name
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        let value = value.trim();
+
+        match (name.as_str(), event.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => {
+                event = Some(Event::default())
+            }
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+                if let Some(event) = event.take() {
+                    match event.into_closures()? {
+                        EventOutcome::Closures(closures) => imported.closures.extend(closures),
+                        EventOutcome::Skipped(warning) => imported.skipped.push(warning),
+                    }
+                }
+            }
+            ("DTSTART", Some(event)) => event.start = Some(parse_ical_date(value)?),
+            ("DTEND", Some(event)) => event.end = Some(parse_ical_date(value)?),
+            ("SUMMARY", Some(event)) => event.summary = Some(unescape_ical_text(value)),
+            _ => {}
+        }
+    }
+
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::config_tests::get_test_config;
+
+    use super::*;
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    #[test]
+    fn test_opening_hours() {
+        let config = get_test_config();
+        let calendar = ClinicCalendar::new(
+            &config,
+            vec![Closure {
+                date: date("2024-12-25"),
+                name: "Christmas Day".to_string(),
+            }],
+            vec![
+                OpeningOverride {
+                    date: date("2024-12-24"),
+                    opening_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                    closing_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+                },
+                // A Saturday
+                OpeningOverride {
+                    date: date("2024-12-28"),
+                    opening_time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                    closing_time: NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+                },
+            ],
+            vec![],
+        );
+
+        let regular_hours = Some((config.opening_time, config.closing_time));
+        let cases = [
+            ("2024-12-23", regular_hours),
+            (
+                "2024-12-24",
+                Some((
+                    NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                    NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+                )),
+            ),
+            ("2024-12-25", None),
+            (
+                "2024-12-28",
+                Some((
+                    NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                    NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+                )),
+            ),
+            // Sunday
+            ("2024-12-29", None),
+        ];
+        for (day, expected) in cases {
+            assert_eq!(calendar.opening_hours(date(day)), expected, "{}", day);
+        }
+    }
+
+    #[test]
+    fn test_absent_throughout() {
+        let doctor = DoctorRecordId::new("1");
+        let absence = |start_date: &str, end_date: &str, end_time: Option<&str>| Absence {
+            doctor: doctor.clone(),
+            start_date: date(start_date),
+            end_date: date(end_date),
+            start_time: None,
+            end_time: end_time.map(|end_time| end_time.parse().unwrap()),
+            reason: "Vacation".to_string(),
+        };
+        let calendar = ClinicCalendar::new(
+            &get_test_config(),
+            vec![],
+            vec![],
+            vec![
+                absence("2024-03-06", "2024-03-08", None),
+                absence("2024-03-04", "2024-03-05", None),
+                absence("2024-03-11", "2024-03-11", Some("12:00:00")),
+            ],
+        );
+        let days = |from: &str, to: &str| {
+            (
+                date(from).and_time(NaiveTime::MIN),
+                (date(to) + Duration::days(1)).and_time(NaiveTime::MIN),
+            )
+        };
+
+        // Absences that follow each other cover the days together
+        let (start, end) = days("2024-03-04", "2024-03-08");
+        assert!(calendar.absent_throughout(&doctor, start, end));
+        let (start, end) = days("2024-03-05", "2024-03-06");
+        assert!(calendar.absent_throughout(&doctor, start, end));
+        assert!(!calendar.absent_throughout(&DoctorRecordId::new("2"), start, end));
+        // The doctor is back in between, and in the afternoon
+        let (start, end) = days("2024-03-08", "2024-03-11");
+        assert!(!calendar.absent_throughout(&doctor, start, end));
+        let (start, end) = days("2024-03-11", "2024-03-11");
+        assert!(!calendar.absent_throughout(&doctor, start, end));
+    }
+
+    #[test]
+    fn test_parse_icalendar() {
+        let source = "BEGIN:VCALENDAR\r\n\
+            VERSION:2.0\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;VALUE=DATE:20241225\r\n\
+            DTEND;VALUE=DATE:20241227\r\n\
+            SUMMARY:Christmas\\, both days\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;VALUE=DATE:20250101\r\n\
+            SUMMARY:New Year's\r\n  Day\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART:20250302T000000\r\n\
+            DTEND:20250303T000000\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;TZID=Europe/Berlin:20250303T080000\r\n\
+            DTEND;TZID=Europe/Berlin:20250303T120000\r\n\
+            SUMMARY:Team meeting\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+
+        let imported = parse_icalendar(source).unwrap();
+        let closures: Vec<(String, &str)> = imported
+            .closures
+            .iter()
+            .map(|closure| (closure.date.to_string(), closure.name.as_str()))
+            .collect();
+        assert_eq!(
+            closures,
+            [
+                ("2024-12-25".to_string(), "Christmas, both days"),
+                ("2024-12-26".to_string(), "Christmas, both days"),
+                ("2025-01-01".to_string(), "New Year's Day"),
+                ("2025-03-02".to_string(), "Closed"),
+            ]
+        );
+
+        // The meeting only takes the morning, so it doesn't close the clinic
+        assert_eq!(
+            imported.skipped,
+            ["Skipped 'Team meeting' on 2025-03-03, only events that last whole days close the clinic"]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_icalendar() {
+        assert!(parse_icalendar("").is_err());
+        assert!(parse_icalendar("not a calendar").is_err());
+
+        let missing_start =
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:Holiday\nEND:VEVENT\nEND:VCALENDAR";
+        assert!(parse_icalendar(missing_start).is_err());
+
+        let invalid_date =
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART:2024-12-25\nEND:VEVENT\nEND:VCALENDAR";
+        assert!(parse_icalendar(invalid_date).is_err());
+
+        // A year is the longest an event can close the clinic for
+        let leap_year = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART;VALUE=DATE:20240101\n\
+            DTEND;VALUE=DATE:20250101\nEND:VEVENT\nEND:VCALENDAR";
+        assert_eq!(parse_icalendar(leap_year).unwrap().closures.len(), 366);
+        let too_long = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART;VALUE=DATE:20240101\n\
+            DTEND;VALUE=DATE:20250102\nEND:VEVENT\nEND:VCALENDAR";
+        assert!(parse_icalendar(too_long).is_err());
+    }
+}
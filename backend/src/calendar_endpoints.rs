@@ -0,0 +1,121 @@
+use actix_web::{web, HttpResponse};
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::parse_icalendar;
+use crate::db::{
+    db::Database,
+    types::{Closure, DateRange, OpeningOverride},
+};
+use crate::errors::{not_found, ApiError};
+use crate::types::ApiResponse;
+
+// Calendar Types
+#[derive(Deserialize)]
+pub struct CalendarDate {
+    date: NaiveDate,
+}
+#[derive(Deserialize)]
+pub struct OpeningHours {
+    opening_time: NaiveTime,
+    closing_time: NaiveTime,
+}
+#[derive(Serialize)]
+pub struct ImportClosuresResponse {
+    data: Vec<Closure>,
+    // Why events of the file weren't imported
+    skipped: Vec<String>,
+}
+
+// Endpoints
+pub async fn read_closures(
+    database: web::Data<Database>,
+    range: web::Query<DateRange>,
+) -> Result<HttpResponse, ApiError> {
+    let closures = database.read_closures(&range).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: closures }))
+}
+
+pub async fn create_closure(
+    database: web::Data<Database>,
+    closure: web::Json<Closure>,
+) -> Result<HttpResponse, ApiError> {
+    let result = database.create_closures(&[closure.into_inner()]).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+// Takes the iCalendar file as the body
+pub async fn import_closures(
+    database: web::Data<Database>,
+    body: String,
+) -> Result<HttpResponse, ApiError> {
+    let imported = parse_icalendar(&body).map_err(ApiError::InvalidRequest)?;
+
+    let result = database.create_closures(&imported.closures).await?;
+
+    Ok(HttpResponse::Ok().json(ImportClosuresResponse {
+        data: result,
+        skipped: imported.skipped,
+    }))
+}
+
+pub async fn delete_closure(
+    database: web::Data<Database>,
+    path: web::Path<CalendarDate>,
+) -> Result<HttpResponse, ApiError> {
+    let closure = database
+        .delete_closure(path.date)
+        .await
+        .map_err(not_found("Closure"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: closure }))
+}
+
+pub async fn read_opening_overrides(
+    database: web::Data<Database>,
+    range: web::Query<DateRange>,
+) -> Result<HttpResponse, ApiError> {
+    let opening_overrides = database.read_opening_overrides(&range).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        data: opening_overrides,
+    }))
+}
+
+pub async fn update_opening_override(
+    database: web::Data<Database>,
+    path: web::Path<CalendarDate>,
+    opening_hours: web::Json<OpeningHours>,
+) -> Result<HttpResponse, ApiError> {
+    if opening_hours.opening_time >= opening_hours.closing_time {
+        return Err(ApiError::InvalidRequest(
+            "The clinic has to close after it opens".to_string(),
+        ));
+    }
+
+    let result = database
+        .update_opening_override(&OpeningOverride {
+            date: path.date,
+            opening_time: opening_hours.opening_time,
+            closing_time: opening_hours.closing_time,
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+pub async fn delete_opening_override(
+    database: web::Data<Database>,
+    path: web::Path<CalendarDate>,
+) -> Result<HttpResponse, ApiError> {
+    let opening_override = database
+        .delete_opening_override(path.date)
+        .await
+        .map_err(not_found("Opening override"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        data: opening_override,
+    }))
+}
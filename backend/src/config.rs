@@ -1,38 +1,170 @@
-use chrono::NaiveTime;
+use chrono::{Duration, NaiveTime};
 use serde::Deserialize;
 use std::convert::TryFrom;
 
+use crate::db::types::{DoctorRecordId, Weekday};
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    // Don't sign in at all, e.g. for a server started with `--unauthenticated`
+    None,
+    #[default]
+    Root,
+    Namespace,
+    Database,
+    // Authenticate with a token instead of a username and password
+    Token,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub mode: AuthMode,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Break {
+    pub name: String,
+    pub start_time: NaiveTime,
+    pub duration_minutes: u32,
+    // Every weekday if not set
+    pub weekdays: Option<Vec<Weekday>>,
+    // Every doctor if not set
+    pub doctor: Option<DoctorRecordId>,
+}
+
+impl Break {
+    pub fn end_time(&self) -> NaiveTime {
+        self.start_time + Duration::minutes(self.duration_minutes.into())
+    }
+
+    pub fn applies_to(&self, weekday: Weekday, doctor: &DoctorRecordId) -> bool {
+        self.weekdays
+            .as_ref()
+            .is_none_or(|weekdays| weekdays.contains(&weekday))
+            && self.doctor.as_ref().is_none_or(|other| other == doctor)
+    }
+
+    // Whether both breaks can apply to the same doctor on the same day
+    fn shares_days_with(&self, other: &Break) -> bool {
+        let shares_weekday = match (&self.weekdays, &other.weekdays) {
+            (Some(weekdays), Some(other_weekdays)) => weekdays
+                .iter()
+                .any(|weekday| other_weekdays.contains(weekday)),
+            _ => true,
+        };
+        let shares_doctor = match (&self.doctor, &other.doctor) {
+            (Some(doctor), Some(other_doctor)) => doctor == other_doctor,
+            _ => true,
+        };
+        shares_weekday && shares_doctor
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct AppConfig {
     pub port: u16,
-    pub doctor_amount: u32,
-    pub room_amount: u32,
     pub namespace: String,
     pub database: String,
     pub database_url: String,
+    #[serde(default)]
+    pub auth: AuthConfig,
     pub opening_time: NaiveTime,
     pub closing_time: NaiveTime,
-    pub break_time: NaiveTime,
+    #[serde(default)]
+    pub breaks: Vec<Break>,
+    // The single one-hour break of older configuration files, it's turned into a break named "Break" when loading
+    pub break_time: Option<NaiveTime>,
+    // The weekdays the clinic is open on, closures and opening hours of single dates are kept in the database
+    #[serde(default = "default_opening_days")]
+    pub opening_days: Vec<Weekday>,
+}
+
+fn default_opening_days() -> Vec<Weekday> {
+    vec![
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+    ]
 }
 
 impl AppConfig {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
+        // Environment variables like CLINIC_AUTH__PASSWORD override the values of the configuration file
         let settings = config::Config::builder()
             .add_source(config::File::with_name("server"))
+            .add_source(
+                config::Environment::with_prefix("CLINIC")
+                    .prefix_separator("_")
+                    .separator("__"),
+            )
             .build()
             .expect("Failed to read configuration file");
 
-        settings
-            .try_into()
-            .expect("Failed to deserialize configuration")
+        settings.try_into().expect("Failed to load configuration")
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.opening_time >= self.closing_time {
+            return Err("closing_time has to be after opening_time".to_string());
+        }
+        if self.opening_days.is_empty() {
+            return Err("opening_days needs at least one weekday".to_string());
+        }
+
+        for (index, clinic_break) in self.breaks.iter().enumerate() {
+            if clinic_break.name.trim().is_empty() {
+                return Err("Every break needs a name".to_string());
+            }
+            if clinic_break.duration_minutes == 0 {
+                return Err(format!("The break {} needs a duration", clinic_break.name));
+            }
+            // Also rejects breaks that would last past midnight
+            if clinic_break.start_time < self.opening_time
+                || clinic_break.end_time() > self.closing_time
+                || clinic_break.end_time() <= clinic_break.start_time
+            {
+                return Err(format!(
+                    "The break {} has to be within the opening hours",
+                    clinic_break.name
+                ));
+            }
+
+            for other in &self.breaks[index + 1..] {
+                if clinic_break.shares_days_with(other)
+                    && clinic_break.start_time < other.end_time()
+                    && other.start_time < clinic_break.end_time()
+                {
+                    return Err(format!(
+                        "The breaks {} and {} overlap",
+                        clinic_break.name, other.name
+                    ));
+                }
+            }
+        }
+
+        Ok(())
     }
 
-    pub fn is_valid_doctor(&self, doctor: u32) -> bool {
-        doctor < self.doctor_amount
+    // A plain "host:port" address is treated as a WebSocket connection to a SurrealDB server
+    pub fn database_endpoint(&self) -> String {
+        if self.database_url.contains("://") || self.database_url == "memory" {
+            self.database_url.clone()
+        } else {
+            format!("ws://{}", self.database_url)
+        }
     }
 
-    pub fn is_valid_room(&self, room: u32) -> bool {
-        room < self.room_amount
+    pub fn is_embedded_database(&self) -> bool {
+        let endpoint = self.database_endpoint();
+        endpoint.starts_with("mem://") || endpoint == "memory"
     }
 }
 
@@ -40,7 +172,27 @@ impl TryFrom<config::Config> for AppConfig {
     type Error = config::ConfigError;
 
     fn try_from(config: config::Config) -> Result<Self, Self::Error> {
-        config.try_deserialize()
+        let mut app_config: AppConfig = config.try_deserialize()?;
+
+        if let Some(break_time) = app_config.break_time.take() {
+            if !app_config.breaks.is_empty() {
+                return Err(config::ConfigError::Message(
+                    "Use either break_time or breaks, not both".to_string(),
+                ));
+            }
+            app_config.breaks.push(Break {
+                name: "Break".to_string(),
+                start_time: break_time,
+                duration_minutes: 60,
+                weekdays: None,
+                doctor: None,
+            });
+        }
+
+        app_config
+            .validate()
+            .map_err(config::ConfigError::Message)?;
+        Ok(app_config)
     }
 }
 
@@ -51,14 +203,136 @@ pub mod config_tests {
     pub fn get_test_config() -> AppConfig {
         AppConfig {
             port: 8080,
-            doctor_amount: 5,
-            room_amount: 10,
             namespace: "test".to_string(),
             database: "test".to_string(),
-            database_url: "127.0.0.1:8000".to_string(),
+            database_url: "mem://".to_string(),
+            auth: AuthConfig {
+                mode: AuthMode::None,
+                ..Default::default()
+            },
             opening_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
             closing_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
-            break_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+            breaks: vec![Break {
+                name: "Lunch".to_string(),
+                start_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+                duration_minutes: 60,
+                weekdays: None,
+                doctor: None,
+            }],
+            break_time: None,
+            opening_days: default_opening_days(),
         }
     }
+
+    fn load(toml: &str) -> Result<AppConfig, config::ConfigError> {
+        let settings = config::Config::builder()
+            .add_source(config::File::from_str(
+                &format!(
+                    "port = 8080\n\
+                    namespace = \"test\"\n\
+                    database = \"test\"\n\
+                    database_url = \"mem://\"\n\
+                    opening_time = \"08:00:00\"\n\
+                    closing_time = \"17:00:00\"\n\
+                    {}",
+                    toml
+                ),
+                config::FileFormat::Toml,
+            ))
+            .build()?;
+        AppConfig::try_from(settings)
+    }
+
+    #[test]
+    fn test_load_breaks() {
+        let config = load(
+            r#"
+            [[breaks]]
+            name = "Lunch"
+            start_time = "12:00:00"
+            duration_minutes = 45
+
+            [[breaks]]
+            name = "Team meeting"
+            start_time = "15:00:00"
+            duration_minutes = 30
+            weekdays = ["monday"]
+            doctor = "doctor:legacy_1"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.breaks.len(), 2);
+        assert_eq!(
+            config.breaks[0].end_time(),
+            NaiveTime::from_hms_opt(12, 45, 0).unwrap()
+        );
+
+        let doctor = DoctorRecordId::new("legacy_1");
+        let other_doctor = DoctorRecordId::new("legacy_2");
+        let meeting = &config.breaks[1];
+        assert!(meeting.applies_to(Weekday::Monday, &doctor));
+        assert!(!meeting.applies_to(Weekday::Tuesday, &doctor));
+        assert!(!meeting.applies_to(Weekday::Monday, &other_doctor));
+
+        // The single break of older configuration files
+        let config = load("break_time = \"13:00:00\"").unwrap();
+        assert_eq!(config.breaks.len(), 1);
+        assert_eq!(config.breaks[0].name, "Break");
+        assert_eq!(config.breaks[0].duration_minutes, 60);
+        assert_eq!(config.break_time, None);
+    }
+
+    #[test]
+    fn test_invalid_breaks_are_rejected() {
+        let break_toml = |name: &str, start_time: &str, duration_minutes: u32, extra: &str| {
+            format!(
+                "[[breaks]]\nname = \"{}\"\nstart_time = \"{}\"\nduration_minutes = {}\n{}\n",
+                name, start_time, duration_minutes, extra
+            )
+        };
+
+        let cases = [
+            break_toml("Lunch", "12:00:00", 0, ""),
+            break_toml("", "12:00:00", 30, ""),
+            break_toml("Lunch", "16:30:00", 60, ""),
+            break_toml("Lunch", "23:30:00", 60, ""),
+            break_toml("Lunch", "12:00:00", 60, "") + &break_toml("Coffee", "12:30:00", 15, ""),
+            break_toml("Lunch", "12:00:00", 60, "weekdays = [\"someday\"]"),
+            break_toml("Lunch", "12:00:00", 60, "doctor = \"room:1\""),
+            format!(
+                "break_time = \"12:00:00\"\n{}",
+                break_toml("Coffee", "15:00:00", 15, "")
+            ),
+        ];
+        for toml in cases {
+            assert!(load(&toml).is_err(), "{}", toml);
+        }
+
+        // Breaks may overlap if they never apply to the same doctor on the same day
+        let config = break_toml("Lunch", "12:00:00", 60, "weekdays = [\"monday\"]")
+            + &break_toml("Late lunch", "12:30:00", 60, "weekdays = [\"tuesday\"]")
+            + &break_toml(
+                "Meeting",
+                "12:30:00",
+                30,
+                "doctor = \"doctor:1\"\nweekdays = [\"friday\"]",
+            );
+        assert!(load(&config).is_ok());
+    }
+
+    #[test]
+    fn test_database_endpoint() {
+        let mut config = get_test_config();
+        assert_eq!(config.database_endpoint(), "mem://");
+        assert!(config.is_embedded_database());
+
+        config.database_url = "127.0.0.1:8000".to_string();
+        assert_eq!(config.database_endpoint(), "ws://127.0.0.1:8000");
+        assert!(!config.is_embedded_database());
+
+        config.database_url = "wss://db.example.com".to_string();
+        assert_eq!(config.database_endpoint(), "wss://db.example.com");
+        assert!(!config.is_embedded_database());
+    }
 }
@@ -0,0 +1,218 @@
+use surrealdb::sql::Value;
+
+use super::{
+    db::Database,
+    pagination::Bindings,
+    types::{Absence, AbsenceFilter, AbsenceRecord, DatabaseError},
+};
+
+fn filter_condition(filter: &AbsenceFilter) -> (String, Bindings) {
+    let mut conditions = Vec::new();
+    let mut bindings = Bindings::new();
+
+    if let Some(doctor) = &filter.doctor {
+        conditions.push("doctor = $doctor");
+        bindings.push(("doctor", Value::from(doctor.as_thing().clone())));
+    }
+    // Dates are stored as YYYY-MM-DD, so they can be compared as strings
+    if let Some(from) = filter.from {
+        conditions.push("end_date >= $from");
+        bindings.push(("from", Value::from(from.to_string())));
+    }
+    if let Some(to) = filter.to {
+        conditions.push("start_date <= $to");
+        bindings.push(("to", Value::from(to.to_string())));
+    }
+
+    if conditions.is_empty() {
+        return ("true".to_string(), bindings);
+    }
+    (conditions.join(" AND "), bindings)
+}
+
+impl Database {
+    pub async fn create_absence(
+        &self,
+        absence: Absence,
+    ) -> Result<Vec<AbsenceRecord>, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        conn.create("absence")
+            .content(absence)
+            .await
+            .map_err(DatabaseError::from)
+    }
+
+    pub async fn read_absences(
+        &self,
+        filter: &AbsenceFilter,
+    ) -> Result<Vec<AbsenceRecord>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let (condition, bindings) = filter_condition(filter);
+
+        let mut query = conn.query(format!(
+            "SELECT * FROM absence WHERE {condition} ORDER BY start_date, start_time"
+        ));
+        for binding in bindings {
+            query = query.bind(binding);
+        }
+
+        query
+            .await
+            .map_err(DatabaseError::from)?
+            .take(0)
+            .map_err(DatabaseError::from)
+    }
+
+    pub async fn read_absence(&self, id: &str) -> Result<AbsenceRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .select(("absence", id))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    pub async fn delete_absence(&self, id: &str) -> Result<AbsenceRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .delete(("absence", id))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+}
+
+#[cfg(test)]
+mod absence_db_tests {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::db::{
+        db::database_tests::mock_db, doctor_db::doctor_db_tests::create_dummy_doctors,
+        types::DoctorRecordId,
+    };
+
+    use super::*;
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    fn absence(doctor: &DoctorRecordId, start_date: &str, end_date: &str) -> Absence {
+        Absence {
+            doctor: doctor.clone(),
+            start_date: date(start_date),
+            end_date: date(end_date),
+            start_time: None,
+            end_time: None,
+            reason: "Sick leave".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_and_read_absences() {
+        let mock_db = mock_db().await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 2).await;
+
+        let mut half_day = absence(&doctor_ids[0], "2024-03-11", "2024-03-11");
+        half_day.start_time = NaiveTime::from_hms_opt(12, 0, 0);
+        let created = mock_db.create_absence(half_day.clone()).await.unwrap();
+        assert_eq!(Absence::from(created[0].clone()), half_day);
+        assert_eq!(
+            mock_db
+                .read_absence(&created[0].id.id.to_raw())
+                .await
+                .unwrap(),
+            created[0]
+        );
+
+        mock_db
+            .create_absence(absence(&doctor_ids[0], "2024-03-04", "2024-03-06"))
+            .await
+            .unwrap();
+        mock_db
+            .create_absence(absence(&doctor_ids[1], "2024-03-01", "2024-03-05"))
+            .await
+            .unwrap();
+
+        let filters = [
+            (AbsenceFilter::default(), 3),
+            (
+                AbsenceFilter {
+                    doctor: Some(doctor_ids[0].clone()),
+                    ..Default::default()
+                },
+                2,
+            ),
+            // Absences that started before, but last into the range, are included
+            (
+                AbsenceFilter {
+                    from: Some(date("2024-03-05")),
+                    to: Some(date("2024-03-10")),
+                    ..Default::default()
+                },
+                2,
+            ),
+            (
+                AbsenceFilter {
+                    from: Some(date("2024-03-07")),
+                    ..Default::default()
+                },
+                1,
+            ),
+        ];
+        for (filter, expected) in filters {
+            let result = mock_db.read_absences(&filter).await.unwrap();
+            assert_eq!(result.len(), expected, "{:?}", filter);
+            assert!(result
+                .windows(2)
+                .all(|pair| pair[0].start_date <= pair[1].start_date));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_absences_are_rejected() {
+        let mock_db = mock_db().await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 1).await;
+
+        let result = mock_db
+            .create_absence(absence(&doctor_ids[0], "2024-03-06", "2024-03-04"))
+            .await;
+        assert!(result.is_err());
+
+        let result = mock_db
+            .create_absence(absence(
+                &DoctorRecordId::new("doesnt_exist"),
+                "2024-03-04",
+                "2024-03-06",
+            ))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_absence() {
+        let mock_db = mock_db().await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 1).await;
+
+        let created = mock_db
+            .create_absence(absence(&doctor_ids[0], "2024-03-04", "2024-03-06"))
+            .await
+            .unwrap();
+        let id = created[0].id.id.to_raw();
+
+        assert_eq!(mock_db.delete_absence(&id).await.unwrap(), created[0]);
+        assert!(matches!(
+            mock_db.read_absence(&id).await,
+            Err(DatabaseError::NothingFound)
+        ));
+        assert!(matches!(
+            mock_db.delete_absence(&id).await,
+            Err(DatabaseError::NothingFound)
+        ));
+    }
+}
@@ -1,244 +1,516 @@
+use chrono::{Days, NaiveDate, NaiveDateTime};
+use serde::{de::DeserializeOwned, Serialize};
+use surrealdb::{
+    sql::{Thing, Value},
+    Response,
+};
+
 use super::{
     db::Database,
+    pagination::{Bindings, PageQuery},
     types::{
-        AppointmentRecord, AppointmentRecordWithPatient, AppointmentWithTime, DatabaseError,
-        PatientRecordId,
+        AppointmentFilter, AppointmentRecord, AppointmentRecordWithPatient, AppointmentSort,
+        AppointmentWithTime, BlockedTime, DatabaseError, DeviceRecordId, DoctorRecordId, Page,
+        Pagination, RoomRecordId, StatusChange,
     },
 };
 
-impl Database {
-    pub async fn create_appointment(
-        &self,
-        appointment: AppointmentWithTime,
-    ) -> Result<Vec<AppointmentRecord>, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
+// Thrown inside the booking transaction when the requested timeframe is already taken
+pub(super) const APPOINTMENT_CONFLICT: &str = "appointment_conflict";
+// On a server, the second of two transactions that touch the same lock record can fail with the storage engine's
+// write conflict instead of running into the THROW. Either way the timeframe was taken by the other one. The in-memory
+// engine lets one writing transaction in at a time, so it always runs into the THROW.
+const WRITE_CONFLICTS: [&str; 4] = [
+    // SurrealKV, and any engine on a server that retries conflicting transactions itself before giving up
+    "read or write conflict",
+    // RocksDB and SpeeDB, whose optimistic transactions fail to commit with `Status::Busy`
+    "Resource busy",
+    // TiKV, when a key was written after the transaction started
+    "WriteConflict",
+    // TiKV, when a key is still locked by a transaction that is being committed
+    "KeyIsLocked",
+];
+// Thrown inside the deletion transaction when appointments still link to the record
+const HAS_APPOINTMENTS: &str = "has_appointments";
+
+// Touching the lock record of every day the appointment blocks, for every doctor, staff member, room and device that
+// is booked, makes concurrent bookings of any of them write the same keys, so the database lets at most one of those transactions
+// commit. The overlap check then runs against a state that can't change until the transaction is done. It compares
+// the blocked times, so the buffers around the appointments count as well. A doctor is busy in appointments they're
+// the doctor of as well as in ones they're part of the staff of.
+pub(super) const RESERVE_TIMEFRAME: &str = "
+    LET $participants = array::concat([$doctor], $staff);
+    FOR $day IN $days {
+        FOR $participant IN $participants {
+            UPDATE type::thing('schedule_lock', ['doctor', $participant, $day]) SET locked_at = time::now();
+        };
+        UPDATE type::thing('schedule_lock', ['room', $room, $day]) SET locked_at = time::now();
+        FOR $device IN $devices {
+            UPDATE type::thing('schedule_lock', ['device', $device, $day]) SET locked_at = time::now();
+        };
+    };
+    LET $conflicts = (SELECT VALUE id FROM appointment
+        WHERE id != $exclude
+        AND status NOTINSIDE ['cancelled', 'no_show']
+        AND (((doctor INSIDE $participants OR staff ANYINSIDE $participants)
+                AND blocked.doctor_from < $blocked.doctor_until
+                AND blocked.doctor_until > $blocked.doctor_from)
+            OR ((room = $room OR devices ANYINSIDE $devices)
+                AND blocked.room_from < $blocked.room_until
+                AND blocked.room_until > $blocked.room_from)));
+    IF array::len($conflicts) > 0 { THROW 'appointment_conflict'; };
+";
+
+// Thrown inside the moving transaction when one of the appointments was deleted in the meantime
+const APPOINTMENT_MISSING: &str = "appointment_missing";
+// Thrown when the status of the appointment doesn't allow the change, like moving a cancelled appointment
+pub(super) const INVALID_STATUS_CHANGE: &str = "invalid_status_change";
+
+#[derive(Serialize)]
+pub(super) struct Timeframe<'a> {
+    pub blocked: &'a BlockedTime,
+    pub doctor: &'a DoctorRecordId,
+    pub room: &'a RoomRecordId,
+    pub staff: &'a [DoctorRecordId],
+    pub devices: &'a [DeviceRecordId],
+    pub exclude: Option<&'a Thing>,
+}
 
-        let patient_exists = self
-            .read_patient(appointment.patient_id.get_unique_id())
-            .await;
-        match patient_exists {
-            Ok(_) => {}
-            Err(_) => {
-                return Err(DatabaseError::NothingFound);
-            }
-        }
+// Translates every criterion of the filter into one condition. Only the placeholders end up in the query text, the
+// values are bound as parameters.
+fn filter_condition(filter: &AppointmentFilter) -> (String, Bindings) {
+    let mut conditions = Vec::new();
+    let mut bindings = Bindings::new();
+
+    if let Some(day) = filter.day {
+        conditions.push("start_time >= $day_start AND start_time < $day_end");
+        bindings.push(("day_start", Value::from(format_time(day.into()))));
+        bindings.push((
+            "day_end",
+            Value::from(format_time((day + Days::new(1)).into())),
+        ));
+    }
+    if let Some(month) = filter.month {
+        conditions.push("start_time >= $month_start AND start_time < $month_end");
+        bindings.push((
+            "month_start",
+            Value::from(format_time(month.first_day().into())),
+        ));
+        bindings.push((
+            "month_end",
+            Value::from(format_time(month.first_day_of_next_month().into())),
+        ));
+    }
+    if let Some(from) = filter.from {
+        conditions.push("start_time >= $from");
+        bindings.push(("from", Value::from(format_time(from))));
+    }
+    if let Some(to) = filter.to {
+        conditions.push("start_time < $to");
+        bindings.push(("to", Value::from(format_time(to))));
+    }
+    if let Some(patient_id) = &filter.patient_id {
+        conditions.push("patient_id = $patient_id");
+        bindings.push(("patient_id", Value::from(patient_id.as_thing().clone())));
+    }
+    if let Some(doctor) = &filter.doctor {
+        conditions.push("doctor = $doctor");
+        bindings.push(("doctor", Value::from(doctor.as_thing().clone())));
+    }
+    if let Some(participant) = &filter.participant {
+        conditions.push("(doctor = $participant OR $participant INSIDE staff)");
+        bindings.push(("participant", Value::from(participant.as_thing().clone())));
+    }
+    if let Some(room) = &filter.room {
+        conditions.push("room = $room");
+        bindings.push(("room", Value::from(room.as_thing().clone())));
+    }
+    if let Some(device) = &filter.device {
+        conditions.push("$device INSIDE devices");
+        bindings.push(("device", Value::from(device.as_thing().clone())));
+    }
+    if let Some(appointment_type) = &filter.appointment_type {
+        conditions.push("appointment_type = $appointment_type");
+        bindings.push(("appointment_type", Value::from(appointment_type.as_str())));
+    }
+    if let Some(status) = filter.status {
+        conditions.push("status = $status");
+        bindings.push(("status", Value::from(status.as_str())));
+    }
+    if let Some(series) = &filter.series {
+        conditions.push("series = $series");
+        bindings.push(("series", Value::from(series.as_thing().clone())));
+    }
 
-        conn.create("appointment")
-            .content(appointment)
-            .await
-            .map_err(DatabaseError::from)
+    if conditions.is_empty() {
+        return ("true".to_string(), bindings);
     }
+    (conditions.join(" AND "), bindings)
+}
 
-    async fn populate_appointments_with_patient(
-        &self,
-        appointments: Vec<AppointmentRecord>,
-    ) -> Result<Vec<AppointmentRecordWithPatient>, DatabaseError> {
-        let mut appointments_with_patient = Vec::new();
-
-        for appointment in appointments {
-            let patient = self
-                .read_patient(appointment.patient_id.get_unique_id())
-                .await?;
-            let appointment_with_patient =
-                AppointmentRecordWithPatient::from_appointment_record(appointment, patient);
-            appointments_with_patient.push(appointment_with_patient);
-        }
+// The days whose lock records a booking with the blocked time takes
+pub(super) fn lock_days(blocked: &BlockedTime) -> Vec<String> {
+    blocked.days().iter().map(NaiveDate::to_string).collect()
+}
 
-        Ok(appointments_with_patient)
+// Times are stored in the same format, so they can be compared as strings
+fn format_time(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+// Whether the error of a booking transaction means the timeframe is taken
+pub(super) fn is_conflict(err: &str) -> bool {
+    err.contains(APPOINTMENT_CONFLICT) || WRITE_CONFLICTS.iter().any(|marker| err.contains(marker))
+}
+
+// Errors of a booking query as a whole, like a commit that failed because of a concurrent booking
+pub(super) fn booking_error(err: surrealdb::Error) -> DatabaseError {
+    match is_conflict(&err.to_string()) {
+        true => DatabaseError::AppointmentConflict,
+        false => DatabaseError::from(err),
     }
+}
 
-    pub async fn read_all_appointments(
+impl Database {
+    // Runs `write_statement` in the same transaction as the overlap check, so the appointment is only written if the
+    // timeframe is still free for its doctor, staff, room and devices
+    async fn write_if_timeframe_free(
         &self,
-    ) -> Result<Vec<AppointmentRecordWithPatient>, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
-
-        let appointments: Vec<AppointmentRecord> = conn
-            .select("appointment")
+        write_statement: &str,
+        timeframe: Timeframe<'_>,
+        appointment: impl serde::Serialize,
+        id: Option<&str>,
+    ) -> Result<Response, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let mut response = conn
+            .query(format!(
+                "BEGIN TRANSACTION; {} {} COMMIT TRANSACTION;",
+                RESERVE_TIMEFRAME, write_statement
+            ))
+            .bind(("days", lock_days(timeframe.blocked)))
+            .bind(("blocked", timeframe.blocked))
+            .bind(("doctor", timeframe.doctor))
+            .bind(("room", timeframe.room))
+            .bind(("staff", timeframe.staff))
+            .bind(("devices", timeframe.devices))
+            .bind(("exclude", timeframe.exclude))
+            .bind(("id", id))
+            .bind(("appointment", appointment))
             .await
-            .map_err(DatabaseError::from)?;
+            .map_err(booking_error)?;
 
-        let appointments_with_patient = self
-            .populate_appointments_with_patient(appointments)
-            .await?;
+        let errors = response.take_errors();
+        for err in errors.values() {
+            let err = err.to_string();
+            if is_conflict(&err) {
+                return Err(DatabaseError::AppointmentConflict);
+            }
+            if err.contains(INVALID_STATUS_CHANGE) {
+                return Err(DatabaseError::InvalidStatusChange);
+            }
+        }
+        if let Some(err) = errors.into_values().next() {
+            return Err(DatabaseError::from(err));
+        }
 
-        Ok(appointments_with_patient)
+        Ok(response)
     }
 
-    pub async fn read_all_appointments_by_day(
+    // Deletes a record appointments link to, unless any appointment still does. `condition` matches the appointments
+    // that link to `$record`, `entity` names the record in the error.
+    pub(super) async fn delete_unless_booked<T: DeserializeOwned>(
         &self,
-        day: &str,
-    ) -> Result<Vec<AppointmentRecordWithPatient>, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
-
-        let mut result = conn
-            .query("SELECT * FROM appointment WHERE string::startsWith(start_time, $day)")
-            .bind(("day", day))
+        record: &Thing,
+        condition: &'static str,
+        entity: &'static str,
+    ) -> Result<T, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let mut response = conn
+            .query(format!(
+                "BEGIN TRANSACTION;
+                IF count((SELECT VALUE id FROM appointment WHERE {condition})) > 0 {{
+                    THROW '{HAS_APPOINTMENTS}';
+                }};
+                DELETE $record RETURN BEFORE;
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("record", record))
             .await
             .map_err(DatabaseError::from)?;
 
-        let appointments: Vec<AppointmentRecord> = result.take(0)?;
+        let errors = response.take_errors();
+        if errors
+            .values()
+            .any(|err| err.to_string().contains(HAS_APPOINTMENTS))
+        {
+            return Err(DatabaseError::HasAppointments(entity));
+        }
+        if let Some(err) = errors.into_values().next() {
+            return Err(DatabaseError::from(err));
+        }
 
-        let appointments_with_patient = self
-            .populate_appointments_with_patient(appointments)
-            .await?;
+        let last_statement = response.num_statements() - 1;
+        let result: Option<T> = response.take(last_statement)?;
 
-        Ok(appointments_with_patient)
+        result.ok_or(DatabaseError::NothingFound)
     }
 
-    pub async fn read_all_appointments_by_month(
+    pub async fn create_appointment(
         &self,
-        month: &str,
-    ) -> Result<Vec<AppointmentRecordWithPatient>, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
-
-        let mut result = conn
-            .query("SELECT * FROM appointment WHERE string::startsWith(start_time, $month)")
-            .bind(("month", month))
-            .await
-            .map_err(DatabaseError::from)?;
+        appointment: AppointmentWithTime,
+    ) -> Result<Vec<AppointmentRecord>, DatabaseError> {
+        let patient_exists = self
+            .read_patient(&appointment.patient_id.get_unique_id())
+            .await;
+        match patient_exists {
+            Ok(_) => {}
+            Err(_) => {
+                return Err(DatabaseError::NothingFound);
+            }
+        }
 
-        let appointments: Vec<AppointmentRecord> = result.take(0)?;
+        let timeframe = Timeframe {
+            blocked: &appointment.blocked,
+            doctor: &appointment.doctor,
+            room: &appointment.room,
+            staff: &appointment.staff,
+            devices: &appointment.devices,
+            exclude: None,
+        };
 
-        let appointments_with_patient = self
-            .populate_appointments_with_patient(appointments)
+        let mut response = self
+            .write_if_timeframe_free(
+                "CREATE appointment CONTENT $appointment;",
+                timeframe,
+                &appointment,
+                None,
+            )
             .await?;
 
-        Ok(appointments_with_patient)
+        let last_statement = response.num_statements() - 1;
+        Ok(response.take(last_statement)?)
     }
 
-    pub async fn read_all_appointments_by_doctor(
+    pub async fn read_appointments(
         &self,
-        doctor_id: &u32,
+        filter: &AppointmentFilter,
     ) -> Result<Vec<AppointmentRecordWithPatient>, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
-
-        let mut result = conn
-            .query("SELECT * FROM appointment WHERE doctor = $doctor_id")
-            .bind(("doctor_id", doctor_id))
-            .await
-            .map_err(DatabaseError::from)?;
-
-        let appointments: Vec<AppointmentRecord> = result.take(0)?;
+        let conn = self.get_connection().await?;
+        let (condition, bindings) = filter_condition(filter);
+
+        let mut query = conn.query(format!(
+            "SELECT * FROM appointment WHERE {} ORDER BY start_time FETCH patient_id, doctor, room, staff, devices",
+            condition
+        ));
+        for binding in bindings {
+            query = query.bind(binding);
+        }
+        let mut result = query.await.map_err(DatabaseError::from)?;
 
-        let appointments_with_patient = self
-            .populate_appointments_with_patient(appointments)
-            .await?;
+        Ok(result.take(0)?)
+    }
 
-        Ok(appointments_with_patient)
+    pub async fn read_appointment_page(
+        &self,
+        filter: &AppointmentFilter,
+        pagination: &Pagination<AppointmentSort>,
+    ) -> Result<Page<AppointmentRecordWithPatient>, DatabaseError> {
+        let (condition, bindings) = filter_condition(filter);
+
+        self.read_page(
+            PageQuery {
+                table: "appointment",
+                condition: &condition,
+                bindings,
+                fetch: Some("patient_id, doctor, room, staff, devices"),
+            },
+            pagination,
+        )
+        .await
     }
 
-    pub async fn read_all_appointments_by_room(
+    pub async fn read_appointment(
         &self,
-        room_nr: &u32,
-    ) -> Result<Vec<AppointmentRecordWithPatient>, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
+        id: &str,
+    ) -> Result<Option<AppointmentRecordWithPatient>, DatabaseError> {
+        let conn = self.get_connection().await?;
 
         let mut result = conn
-            .query("SELECT * FROM appointment WHERE room_nr = $room_nr")
-            .bind(("room_nr", room_nr))
+            .query("SELECT * FROM type::thing('appointment', $id) FETCH patient_id, doctor, room, staff, devices")
+            .bind(("id", id))
             .await
             .map_err(DatabaseError::from)?;
 
-        let appointments: Vec<AppointmentRecord> = result.take(0)?;
-
-        let appointments_with_patient = self
-            .populate_appointments_with_patient(appointments)
-            .await?;
+        let appointment: Option<AppointmentRecordWithPatient> = result.take(0)?;
 
-        Ok(appointments_with_patient)
+        appointment.ok_or(DatabaseError::NothingFound).map(Some)
     }
 
-    pub async fn read_all_appointments_by_patient(
+    pub async fn update_appointment(
         &self,
-        patient_id: &PatientRecordId,
-    ) -> Result<Vec<AppointmentRecordWithPatient>, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
-
-        let mut result = conn
-            .query("SELECT * FROM appointment WHERE patient_id = $patient_id")
-            .bind(("patient_id", patient_id.as_str()))
-            .await
-            .map_err(DatabaseError::from)?;
-
-        let appointments: Vec<AppointmentRecord> = result.take(0)?;
+        id: &str,
+        appointment: AppointmentRecord,
+    ) -> Result<AppointmentRecord, DatabaseError> {
+        let timeframe = Timeframe {
+            blocked: &appointment.blocked,
+            doctor: &appointment.doctor,
+            room: &appointment.room,
+            staff: &appointment.staff,
+            devices: &appointment.devices,
+            exclude: Some(&appointment.id),
+        };
 
-        let appointments_with_patient = self
-            .populate_appointments_with_patient(appointments)
+        // Only scheduled appointments can be moved, the check runs in the transaction so a concurrent status change
+        // isn't overwritten
+        let mut response = self
+            .write_if_timeframe_free(
+                &format!(
+                    "IF type::thing('appointment', $id).status NOTINSIDE ['scheduled', NONE] {{
+                        THROW '{INVALID_STATUS_CHANGE}';
+                    }};
+                    UPDATE type::thing('appointment', $id) MERGE $appointment;"
+                ),
+                timeframe,
+                &appointment,
+                Some(id),
+            )
             .await?;
 
-        Ok(appointments_with_patient)
+        let last_statement = response.num_statements() - 1;
+        let result: Option<AppointmentRecord> = response.take(last_statement)?;
+
+        result.ok_or(DatabaseError::NothingFound)
     }
 
-    pub async fn read_appointment(
+    // Moves all the appointments to their new time, doctor and room, or none of them if any of the new timeframes is
+    // taken. Each move reserves its timeframe like a single update does, after the moves before it, so later
+    // appointments may take the timeframes that earlier ones in the list were moved away from.
+    pub async fn update_appointments(
         &self,
-        id: &str,
-    ) -> Result<Option<AppointmentRecordWithPatient>, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
+        appointments: Vec<AppointmentRecord>,
+    ) -> Result<Vec<AppointmentRecord>, DatabaseError> {
+        #[derive(Serialize)]
+        struct Move<'a> {
+            days: Vec<String>,
+            #[serde(flatten)]
+            timeframe: Timeframe<'a>,
+            appointment: &'a AppointmentRecord,
+        }
 
-        let appointment: AppointmentRecord = conn
-            .select(("appointment", id))
+        let moves: Vec<Move> = appointments
+            .iter()
+            .map(|appointment| Move {
+                days: lock_days(&appointment.blocked),
+                timeframe: Timeframe {
+                    blocked: &appointment.blocked,
+                    doctor: &appointment.doctor,
+                    room: &appointment.room,
+                    staff: &appointment.staff,
+                    devices: &appointment.devices,
+                    exclude: Some(&appointment.id),
+                },
+                appointment,
+            })
+            .collect();
+        let ids: Vec<&Thing> = appointments
+            .iter()
+            .map(|appointment| &appointment.id)
+            .collect();
+
+        let conn = self.get_connection().await?;
+        let mut response = conn
+            .query(format!(
+                "BEGIN TRANSACTION;
+                FOR $move IN $moves {{
+                    LET $days = $move.days;
+                    LET $blocked = $move.blocked;
+                    LET $doctor = $move.doctor;
+                    LET $room = $move.room;
+                    LET $staff = $move.staff;
+                    LET $devices = $move.devices;
+                    LET $exclude = $move.exclude;
+                    IF $exclude.id = NONE {{ THROW '{APPOINTMENT_MISSING}'; }};
+                    IF $exclude.status != 'scheduled' {{ THROW '{INVALID_STATUS_CHANGE}'; }};
+                    {RESERVE_TIMEFRAME}
+                    UPDATE $exclude MERGE $move.appointment;
+                }};
+                COMMIT TRANSACTION;
+                SELECT * FROM $ids;"
+            ))
+            .bind(("moves", moves))
+            .bind(("ids", ids))
             .await
-            .map_err(DatabaseError::from)?
-            .ok_or(DatabaseError::NothingFound)?;
+            .map_err(booking_error)?;
 
-        let patient = self
-            .read_patient(appointment.patient_id.get_unique_id())
-            .await?;
+        let errors = response.take_errors();
+        for err in errors.values() {
+            let err = err.to_string();
+            if is_conflict(&err) {
+                return Err(DatabaseError::AppointmentConflict);
+            }
+            if err.contains(APPOINTMENT_MISSING) {
+                return Err(DatabaseError::NothingFound);
+            }
+            if err.contains(INVALID_STATUS_CHANGE) {
+                return Err(DatabaseError::InvalidStatusChange);
+            }
+        }
+        if let Some(err) = errors.into_values().next() {
+            return Err(DatabaseError::from(err));
+        }
 
-        Ok(Some(AppointmentRecordWithPatient::from_appointment_record(
-            appointment,
-            patient,
-        )))
+        let last_statement = response.num_statements() - 1;
+        Ok(response.take(last_statement)?)
     }
 
-    pub async fn update_appointment(
+    // Changes the status only if the appointment is in one the new status can follow, checked in the same transaction
+    // so concurrent changes can't skip a step
+    pub async fn update_appointment_status(
         &self,
         id: &str,
-        appointment: AppointmentRecord,
+        change: StatusChange,
     ) -> Result<AppointmentRecord, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
-
-        let result = conn
-            .update(("appointment", id))
-            .merge(appointment)
+        let conn = self.get_connection().await?;
+
+        let mut response = conn
+            .query(format!(
+                "BEGIN TRANSACTION;
+                LET $appointment = type::thing('appointment', $id);
+                IF $appointment.id = NONE {{ THROW '{APPOINTMENT_MISSING}'; }};
+                IF $appointment.status NOTINSIDE $previous {{ THROW '{INVALID_STATUS_CHANGE}'; }};
+                UPDATE $appointment SET status = $change.status, status_changes += $change;
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("id", id))
+            .bind(("previous", change.status.previous()))
+            .bind(("change", &change))
             .await
             .map_err(DatabaseError::from)?;
 
+        let errors = response.take_errors();
+        for err in errors.values() {
+            let err = err.to_string();
+            if err.contains(APPOINTMENT_MISSING) {
+                return Err(DatabaseError::NothingFound);
+            }
+            if err.contains(INVALID_STATUS_CHANGE) {
+                return Err(DatabaseError::InvalidStatusChange);
+            }
+        }
+        if let Some(err) = errors.into_values().next() {
+            return Err(DatabaseError::from(err));
+        }
+
+        let last_statement = response.num_statements() - 1;
+        let result: Option<AppointmentRecord> = response.take(last_statement)?;
+
         result.ok_or(DatabaseError::NothingFound)
     }
 
     pub async fn delete_appointment(&self, id: &str) -> Result<AppointmentRecord, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
+        let conn = self.get_connection().await?;
 
         let result: Option<AppointmentRecord> = conn
             .delete(("appointment", id))
@@ -250,18 +522,25 @@ impl Database {
 }
 
 #[cfg(test)]
-mod appointment_db_tests {
+pub mod appointment_db_tests {
     use crate::db::{
         db::database_tests::mock_db,
-        types::{Appointment, AppointmentType, Patient},
+        device_db::device_db_tests::create_dummy_devices,
+        doctor_db::doctor_db_tests::create_dummy_doctors,
+        room_db::room_db_tests::create_dummy_rooms,
+        types::{
+            Appointment, AppointmentStatus, AppointmentType, Buffers, Patient, PatientRecordId,
+            YearMonth,
+        },
     };
 
-    use chrono::NaiveDateTime;
+    use crate::util::util_tests::time;
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
     use rand::{thread_rng, Rng};
 
     use super::*;
 
-    async fn create_dummy_patients(db: &Database, count: u32) -> Vec<PatientRecordId> {
+    pub async fn create_dummy_patients(db: &Database, count: u32) -> Vec<PatientRecordId> {
         let mut patient_ids = Vec::new();
         for i in 0..count {
             let patient = Patient {
@@ -271,7 +550,7 @@ mod appointment_db_tests {
             };
 
             let patient = db.create_patient(patient).await.unwrap();
-            patient_ids.push(PatientRecordId::new(&patient[0].id.id.to_raw()));
+            patient_ids.push(PatientRecordId::from(patient[0].id.clone()));
         }
         patient_ids
     }
@@ -281,17 +560,26 @@ mod appointment_db_tests {
         let mock_db = mock_db().await;
 
         let patient_ids = create_dummy_patients(&mock_db, 2).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 3).await;
+        let room_ids = create_dummy_rooms(&mock_db, 3).await;
 
         let appointment = Appointment {
             start_time: "2023-10-01T10:00:00".to_string(),
-            appointment_type: AppointmentType::QuickCheckup,
+            appointment_type: AppointmentType::new("quick_checkup"),
             patient_id: patient_ids[0].clone(),
-            doctor: 1,
-            room_nr: 0,
+            doctor: doctor_ids[1].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
         };
 
         let result = &mock_db
-            .create_appointment(appointment.clone().into_appointment_with_time().unwrap())
+            .create_appointment(
+                appointment
+                    .clone()
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap(),
+            )
             .await
             .unwrap()[0];
 
@@ -301,43 +589,60 @@ mod appointment_db_tests {
             result.start_time,
             NaiveDateTime::parse_from_str(&appointment.start_time, "%Y-%m-%dT%H:%M:%S").unwrap()
         );
-        assert!(result.end_time == result.start_time + result.appointment_type.duration());
+        assert!(result.end_time == result.start_time + Duration::minutes(30));
     }
 
     #[tokio::test]
-    async fn test_populate_appointments_with_patient() {
+    async fn test_read_appointments_fetches_patient_and_doctor() {
         let mock_db = mock_db().await;
 
         let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 3).await;
+        let room_ids = create_dummy_rooms(&mock_db, 3).await;
 
         let appointment = Appointment {
             start_time: "2023-10-01T10:00:00".to_string(),
-            appointment_type: AppointmentType::QuickCheckup,
+            appointment_type: AppointmentType::new("quick_checkup"),
             patient_id: patient_ids[0].clone(),
-            doctor: 1,
-            room_nr: 0,
+            doctor: doctor_ids[1].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
         };
 
         let created_appointment = &mock_db
-            .create_appointment(appointment.clone().into_appointment_with_time().unwrap())
+            .create_appointment(
+                appointment
+                    .clone()
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap(),
+            )
             .await
             .unwrap()[0];
 
-        let appointments = vec![(*created_appointment).clone()];
+        // The patient link is stored as a record, not as a formatted string
+        let conn = mock_db.get_connection().await.unwrap();
+        let mut result = conn
+            .query("SELECT VALUE type::is::record(patient_id) FROM appointment")
+            .await
+            .unwrap();
+        let is_record: Vec<bool> = result.take(0).unwrap();
+        assert_eq!(is_record, vec![true]);
 
-        // Populate appointments with patient data
+        // Reading the appointment resolves the whole patient and doctor records along with it
         let result = mock_db
-            .populate_appointments_with_patient(appointments)
+            .read_appointments(&AppointmentFilter::default())
             .await
             .unwrap();
 
-        // Assert that the populated data matches the expected data
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].id, created_appointment.id);
         assert_eq!(
             result[0].patient.id.id.to_raw(),
             patient_ids[0].get_unique_id()
         );
+        assert_eq!(result[0].patient.name, "John Doe 0");
+        assert_eq!(result[0].doctor.name, "Dr. Jane Doe 1");
     }
 
     #[tokio::test]
@@ -345,34 +650,53 @@ mod appointment_db_tests {
         let mock_db = mock_db().await;
 
         let patient_ids = create_dummy_patients(&mock_db, 2).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 3).await;
+        let room_ids = create_dummy_rooms(&mock_db, 3).await;
 
         let appointment1 = Appointment {
             start_time: "2023-10-01T10:00:00".to_string(),
-            appointment_type: AppointmentType::QuickCheckup,
+            appointment_type: AppointmentType::new("quick_checkup"),
             patient_id: patient_ids[0].clone(),
-            doctor: 1,
-            room_nr: 0,
+            doctor: doctor_ids[1].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
         };
 
         let appointment2 = Appointment {
             start_time: "2023-10-01T13:00:00".to_string(),
-            appointment_type: AppointmentType::Surgery,
+            appointment_type: AppointmentType::new("surgery"),
             patient_id: patient_ids[1].clone(),
-            doctor: 0,
-            room_nr: 1,
+            doctor: doctor_ids[0].clone(),
+            room: room_ids[1].clone(),
+            staff: vec![],
+            devices: vec![],
         };
 
         mock_db
-            .create_appointment(appointment1.clone().into_appointment_with_time().unwrap())
+            .create_appointment(
+                appointment1
+                    .clone()
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap(),
+            )
             .await
             .unwrap();
         mock_db
-            .create_appointment(appointment2.clone().into_appointment_with_time().unwrap())
+            .create_appointment(
+                appointment2
+                    .clone()
+                    .into_appointment_with_time(Duration::minutes(120), &Buffers::default())
+                    .unwrap(),
+            )
             .await
             .unwrap();
 
         // Read all appointments from the database
-        let result = mock_db.read_all_appointments().await.unwrap();
+        let result = mock_db
+            .read_appointments(&AppointmentFilter::default())
+            .await
+            .unwrap();
 
         // Assert that the retrieved data matches the inserted data
         assert_eq!(result.len(), 2);
@@ -380,7 +704,7 @@ mod appointment_db_tests {
         let mut sorted_result = result.clone();
         sorted_result.sort_by_key(|a| a.start_time);
 
-        let mut sorted_appointments = vec![appointment1.clone(), appointment2.clone()];
+        let mut sorted_appointments = [appointment1.clone(), appointment2.clone()];
         sorted_appointments.sort_by_key(|a| {
             NaiveDateTime::parse_from_str(&a.start_time, "%Y-%m-%dT%H:%M:%S").unwrap()
         });
@@ -389,7 +713,10 @@ mod appointment_db_tests {
             sorted_result[0].patient.id.id.to_raw(),
             sorted_appointments[0].patient_id.get_unique_id()
         );
-        assert_eq!(sorted_result[0].doctor, sorted_appointments[0].doctor);
+        assert_eq!(
+            sorted_result[0].doctor.id,
+            *sorted_appointments[0].doctor.as_thing()
+        );
         assert_eq!(
             sorted_result[0].start_time,
             NaiveDateTime::parse_from_str(&sorted_appointments[0].start_time, "%Y-%m-%dT%H:%M:%S")
@@ -399,7 +726,10 @@ mod appointment_db_tests {
             sorted_result[1].patient.id.id.to_raw(),
             sorted_appointments[1].patient_id.get_unique_id()
         );
-        assert_eq!(sorted_result[1].doctor, sorted_appointments[1].doctor);
+        assert_eq!(
+            sorted_result[1].doctor.id,
+            *sorted_appointments[1].doctor.as_thing()
+        );
         assert_eq!(
             sorted_result[1].start_time,
             NaiveDateTime::parse_from_str(&sorted_appointments[1].start_time, "%Y-%m-%dT%H:%M:%S")
@@ -412,60 +742,141 @@ mod appointment_db_tests {
         let mock_db = mock_db().await;
 
         let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 3).await;
+        let room_ids = create_dummy_rooms(&mock_db, 3).await;
 
         let appointment1 = Appointment {
             start_time: "2023-10-01T10:00:00".to_string(),
-            appointment_type: AppointmentType::QuickCheckup,
+            appointment_type: AppointmentType::new("quick_checkup"),
             patient_id: patient_ids[0].clone(),
-            doctor: 1,
-            room_nr: 0,
+            doctor: doctor_ids[1].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
         };
 
         let appointment2 = Appointment {
             start_time: "2023-10-15T13:00:00".to_string(),
-            appointment_type: AppointmentType::Surgery,
+            appointment_type: AppointmentType::new("surgery"),
             patient_id: patient_ids[0].clone(),
-            doctor: 2,
-            room_nr: 1,
+            doctor: doctor_ids[2].clone(),
+            room: room_ids[1].clone(),
+            staff: vec![],
+            devices: vec![],
         };
 
         mock_db
-            .create_appointment(appointment1.clone().into_appointment_with_time().unwrap())
+            .create_appointment(
+                appointment1
+                    .clone()
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap(),
+            )
             .await
             .unwrap();
         mock_db
-            .create_appointment(appointment2.clone().into_appointment_with_time().unwrap())
-            .await
-            .unwrap();
-
-        // Test read_all_appointments_by_day
-        let result_by_day = mock_db
-            .read_all_appointments_by_day("2023-10-01")
-            .await
-            .unwrap();
-        assert_eq!(result_by_day.len(), 1);
-
-        // Test read_all_appointments_by_month
-        let result_by_month = mock_db
-            .read_all_appointments_by_month("2023-10")
+            .create_appointment(
+                appointment2
+                    .clone()
+                    .into_appointment_with_time(Duration::minutes(120), &Buffers::default())
+                    .unwrap(),
+            )
             .await
             .unwrap();
-        assert_eq!(result_by_month.len(), 2);
-
-        // Test read_all_appointments_by_doctor
-        let result_by_doctor = mock_db.read_all_appointments_by_doctor(&1).await.unwrap();
-        assert_eq!(result_by_doctor.len(), 1);
 
-        // Test read_all_appointments_by_room
-        let result_by_room = mock_db.read_all_appointments_by_room(&0).await.unwrap();
-        assert_eq!(result_by_room.len(), 1);
-
-        // Test read_all_appointments_by_patient
-        let result_by_patient = mock_db
-            .read_all_appointments_by_patient(&patient_ids[0])
-            .await
-            .unwrap();
-        assert_eq!(result_by_patient.len(), 2);
+        let day = |day: &str| NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap();
+        let time = |time: &str| NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S").unwrap();
+
+        let filters = [
+            (
+                AppointmentFilter {
+                    day: Some(day("2023-10-01")),
+                    ..Default::default()
+                },
+                1,
+            ),
+            (
+                AppointmentFilter {
+                    month: Some("2023-10".parse::<YearMonth>().unwrap()),
+                    ..Default::default()
+                },
+                2,
+            ),
+            (
+                AppointmentFilter {
+                    doctor: Some(doctor_ids[1].clone()),
+                    ..Default::default()
+                },
+                1,
+            ),
+            (
+                AppointmentFilter {
+                    room: Some(room_ids[0].clone()),
+                    ..Default::default()
+                },
+                1,
+            ),
+            (
+                AppointmentFilter {
+                    patient_id: Some(patient_ids[0].clone()),
+                    ..Default::default()
+                },
+                2,
+            ),
+            (
+                AppointmentFilter {
+                    appointment_type: Some(AppointmentType::new("surgery")),
+                    ..Default::default()
+                },
+                1,
+            ),
+            // Criteria are combined
+            (
+                AppointmentFilter {
+                    doctor: Some(doctor_ids[2].clone()),
+                    room: Some(room_ids[1].clone()),
+                    month: Some("2023-10".parse::<YearMonth>().unwrap()),
+                    ..Default::default()
+                },
+                1,
+            ),
+            (
+                AppointmentFilter {
+                    doctor: Some(doctor_ids[2].clone()),
+                    room: Some(room_ids[0].clone()),
+                    ..Default::default()
+                },
+                0,
+            ),
+            (
+                AppointmentFilter {
+                    patient_id: Some(patient_ids[0].clone()),
+                    from: Some(time("2023-10-01T10:00:01")),
+                    ..Default::default()
+                },
+                1,
+            ),
+            (
+                AppointmentFilter {
+                    from: Some(time("2023-10-01T10:00:00")),
+                    to: Some(time("2023-10-15T13:00:00")),
+                    ..Default::default()
+                },
+                1,
+            ),
+            (
+                AppointmentFilter {
+                    month: Some("2023-11".parse::<YearMonth>().unwrap()),
+                    ..Default::default()
+                },
+                0,
+            ),
+        ];
+
+        for (filter, expected) in filters {
+            let result = mock_db.read_appointments(&filter).await.unwrap();
+            assert_eq!(result.len(), expected, "{:?}", filter);
+        }
     }
 
     #[tokio::test]
@@ -473,17 +884,26 @@ mod appointment_db_tests {
         let mock_db = mock_db().await;
 
         let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 3).await;
+        let room_ids = create_dummy_rooms(&mock_db, 3).await;
 
         let appointment = Appointment {
             start_time: "2023-10-01T10:00:00".to_string(),
-            appointment_type: AppointmentType::QuickCheckup,
+            appointment_type: AppointmentType::new("quick_checkup"),
             patient_id: patient_ids[0].clone(),
-            doctor: 1,
-            room_nr: 0,
+            doctor: doctor_ids[1].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
         };
 
         let created_appointment = &mock_db
-            .create_appointment(appointment.clone().into_appointment_with_time().unwrap())
+            .create_appointment(
+                appointment
+                    .clone()
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap(),
+            )
             .await
             .unwrap()[0];
 
@@ -499,7 +919,7 @@ mod appointment_db_tests {
             result.patient.id.id.to_raw(),
             appointment.patient_id.get_unique_id()
         );
-        assert_eq!(result.doctor, appointment.doctor);
+        assert_eq!(result.doctor.id, *appointment.doctor.as_thing());
         assert_eq!(
             result.start_time,
             NaiveDateTime::parse_from_str(&appointment.start_time, "%Y-%m-%dT%H:%M:%S").unwrap()
@@ -511,17 +931,26 @@ mod appointment_db_tests {
         let mock_db = mock_db().await;
 
         let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 3).await;
+        let room_ids = create_dummy_rooms(&mock_db, 3).await;
 
         let appointment = Appointment {
             start_time: "2023-10-01T10:00:00".to_string(),
-            appointment_type: AppointmentType::QuickCheckup,
+            appointment_type: AppointmentType::new("quick_checkup"),
             patient_id: patient_ids[0].clone(),
-            doctor: 1,
-            room_nr: 0,
+            doctor: doctor_ids[1].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
         };
 
         let created_appointment = &mock_db
-            .create_appointment(appointment.clone().into_appointment_with_time().unwrap())
+            .create_appointment(
+                appointment
+                    .clone()
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap(),
+            )
             .await
             .unwrap()[0];
 
@@ -531,10 +960,17 @@ mod appointment_db_tests {
                 .unwrap(),
             end_time: NaiveDateTime::parse_from_str("2023-10-01T11:15:00", "%Y-%m-%dT%H:%M:%S")
                 .unwrap(),
-            appointment_type: AppointmentType::QuickCheckup,
+            blocked: Buffers::default()
+                .blocked_time(time("2023-10-01T11:00:00"), time("2023-10-01T11:15:00")),
+            appointment_type: AppointmentType::new("quick_checkup"),
             patient_id: patient_ids[0].clone(),
-            doctor: 2,
-            room_nr: 1,
+            doctor: doctor_ids[2].clone(),
+            room: room_ids[1].clone(),
+            staff: vec![],
+            devices: vec![],
+            status: AppointmentStatus::Scheduled,
+            status_changes: vec![],
+            series: None,
         };
 
         // Update the appointment in the database
@@ -552,22 +988,613 @@ mod appointment_db_tests {
         assert_eq!(result.start_time, updated_appointment.start_time);
     }
 
+    #[tokio::test]
+    async fn test_concurrent_create_appointment_conflict() {
+        let mock_db = mock_db().await;
+
+        let patient_ids = create_dummy_patients(&mock_db, 2).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 3).await;
+        let room_ids = create_dummy_rooms(&mock_db, 3).await;
+
+        let appointment1 = Appointment {
+            start_time: "2023-10-01T10:00:00".to_string(),
+            appointment_type: AppointmentType::new("extensive_care"),
+            patient_id: patient_ids[0].clone(),
+            doctor: doctor_ids[1].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
+        };
+
+        // Same doctor, different room, overlapping by half an hour
+        let appointment2 = Appointment {
+            start_time: "2023-10-01T10:30:00".to_string(),
+            appointment_type: AppointmentType::new("quick_checkup"),
+            patient_id: patient_ids[1].clone(),
+            doctor: doctor_ids[1].clone(),
+            room: room_ids[1].clone(),
+            staff: vec![],
+            devices: vec![],
+        };
+
+        let (result1, result2) = tokio::join!(
+            mock_db.create_appointment(
+                appointment1
+                    .into_appointment_with_time(Duration::minutes(60), &Buffers::default())
+                    .unwrap()
+            ),
+            mock_db.create_appointment(
+                appointment2
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap()
+            )
+        );
+
+        // Exactly one of the two bookings may succeed
+        assert!(result1.is_ok() != result2.is_ok());
+        let failed = if result1.is_err() { result1 } else { result2 };
+        assert!(matches!(failed, Err(DatabaseError::AppointmentConflict)));
+
+        let result = mock_db
+            .read_appointments(&AppointmentFilter {
+                day: NaiveDate::from_ymd_opt(2023, 10, 1),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_booking_locks_every_blocked_day() {
+        let mock_db = mock_db().await;
+
+        let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 1).await;
+        let room_ids = create_dummy_rooms(&mock_db, 1).await;
+
+        // The room is cleaned until after midnight
+        let buffers = Buffers {
+            room_after: Duration::minutes(30),
+            ..Default::default()
+        };
+        let appointment = Appointment {
+            start_time: "2023-10-02T23:00:00".to_string(),
+            appointment_type: AppointmentType::new("quick_checkup"),
+            patient_id: patient_ids[0].clone(),
+            doctor: doctor_ids[0].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
+        }
+        .into_appointment_with_time(Duration::minutes(45), &buffers)
+        .unwrap();
+        mock_db.create_appointment(appointment).await.unwrap();
+
+        let locked_days = |kind: &'static str| {
+            let mock_db = mock_db.clone();
+            async move {
+                let mut days: Vec<String> = mock_db
+                    .get_connection()
+                    .await
+                    .unwrap()
+                    .query("SELECT VALUE meta::id(id)[2] FROM schedule_lock WHERE meta::id(id)[0] = $kind")
+                    .bind(("kind", kind))
+                    .await
+                    .unwrap()
+                    .take(0)
+                    .unwrap();
+                days.sort();
+                days
+            }
+        };
+        // Every participant takes the locks of every blocked day, the doctor's as well as the room's
+        assert_eq!(
+            locked_days("doctor").await,
+            vec!["2023-10-02", "2023-10-03"]
+        );
+        assert_eq!(locked_days("room").await, vec!["2023-10-02", "2023-10-03"]);
+    }
+
+    // The errors each storage engine fails a conflicting commit with, as the server reports them
+    const SURREALKV_CONFLICT: &str =
+        "Failed to commit transaction due to a read or write conflict. This transaction can be retried";
+    const ROCKSDB_CONFLICT: &str =
+        "There was a problem with a datastore transaction: Resource busy: ";
+    const TIKV_WRITE_CONFLICT: &str = "There was a problem with a datastore transaction: KeyError(KeyError { \
+        locked: None, retryable: \"\", abort: \"\", conflict: Some(WriteConflict { start_ts: 1, conflict_ts: 2, \
+        key: [], primary: [], conflict_commit_ts: 2, reason: Optimistic }), already_exist: None, deadlock: None })";
+    const TIKV_LOCKED: &str = "There was a problem with a datastore transaction: KeyError(KeyError { locked: None, \
+        retryable: \"Error(Txn(Error(Mvcc(Error(KeyIsLocked(LockInfo { primary_lock: [], lock_version: 1, key: [], \
+        lock_ttl: 3000 }))))))\", abort: \"\", conflict: None, already_exist: None, deadlock: None })";
+
+    // Each marker is only there for the error of its engine
+    fn assert_only_marker(err: &str, marker: &str) {
+        assert!(is_conflict(err), "{}", err);
+        for other in WRITE_CONFLICTS.iter().filter(|other| **other != marker) {
+            assert!(!err.contains(other), "{} matches {}", err, other);
+        }
+    }
+
+    #[test]
+    fn test_surrealkv_conflict_is_conflict() {
+        assert_only_marker(SURREALKV_CONFLICT, "read or write conflict");
+    }
+
+    #[test]
+    fn test_rocksdb_conflict_is_conflict() {
+        assert_only_marker(ROCKSDB_CONFLICT, "Resource busy");
+    }
+
+    #[test]
+    fn test_tikv_conflicts_are_conflicts() {
+        assert_only_marker(TIKV_WRITE_CONFLICT, "WriteConflict");
+        assert_only_marker(TIKV_LOCKED, "KeyIsLocked");
+    }
+
+    #[test]
+    fn test_write_conflicts_are_conflicts() {
+        assert!(is_conflict("An error occurred: appointment_conflict"));
+
+        // Every marker belongs to the error of an engine above
+        let engine_errors = [
+            SURREALKV_CONFLICT,
+            ROCKSDB_CONFLICT,
+            TIKV_WRITE_CONFLICT,
+            TIKV_LOCKED,
+        ];
+        for marker in WRITE_CONFLICTS {
+            assert!(
+                engine_errors.iter().any(|err| err.contains(marker)),
+                "{}",
+                marker
+            );
+        }
+
+        assert!(!is_conflict(
+            "Found NONE for field `patient_id`, but expected a record<patient>"
+        ));
+        assert!(!is_conflict(
+            "There was a problem with a datastore transaction: Transaction is closed"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_staff_and_device_conflicts() {
+        let mock_db = mock_db().await;
+
+        let patient_ids = create_dummy_patients(&mock_db, 2).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 3).await;
+        let room_ids = create_dummy_rooms(&mock_db, 2).await;
+        let device_ids = create_dummy_devices(&mock_db, 2).await;
+
+        // Doctor 0 operates with doctor 1 assisting and the first device
+        let surgery = Appointment {
+            start_time: "2023-10-02T10:00:00".to_string(),
+            appointment_type: AppointmentType::new("surgery"),
+            patient_id: patient_ids[0].clone(),
+            doctor: doctor_ids[0].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![doctor_ids[1].clone()],
+            devices: vec![device_ids[0].clone()],
+        };
+        let surgery = mock_db
+            .create_appointment(
+                surgery
+                    .into_appointment_with_time(Duration::minutes(120), &Buffers::default())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .remove(0);
+
+        let checkup =
+            |doctor: &DoctorRecordId, staff: Vec<DoctorRecordId>, devices: Vec<DeviceRecordId>| {
+                Appointment {
+                    start_time: "2023-10-02T11:00:00".to_string(),
+                    appointment_type: AppointmentType::new("quick_checkup"),
+                    patient_id: patient_ids[1].clone(),
+                    doctor: doctor.clone(),
+                    room: room_ids[1].clone(),
+                    staff,
+                    devices,
+                }
+                .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                .unwrap()
+            };
+        let cases = [
+            // The assistant leading another appointment
+            checkup(&doctor_ids[1], vec![], vec![]),
+            // The surgeon assisting in another one
+            checkup(&doctor_ids[2], vec![doctor_ids[0].clone()], vec![]),
+            // The device used in another room
+            checkup(&doctor_ids[2], vec![], device_ids.clone()),
+        ];
+        for appointment in cases {
+            let result = mock_db.create_appointment(appointment).await;
+            assert!(matches!(result, Err(DatabaseError::AppointmentConflict)));
+        }
+        mock_db
+            .create_appointment(checkup(&doctor_ids[2], vec![], vec![device_ids[1].clone()]))
+            .await
+            .unwrap();
+
+        // The staff and devices are read along with the appointment
+        let result = mock_db
+            .read_appointments(&AppointmentFilter {
+                participant: Some(doctor_ids[1].clone()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, surgery.id);
+        assert_eq!(result[0].staff[0].name, "Dr. Jane Doe 1");
+        assert_eq!(result[0].devices[0].name, "Ultrasound 0");
+
+        for (filter, expected) in [
+            (
+                AppointmentFilter {
+                    participant: Some(doctor_ids[0].clone()),
+                    ..Default::default()
+                },
+                1,
+            ),
+            (
+                AppointmentFilter {
+                    participant: Some(doctor_ids[2].clone()),
+                    ..Default::default()
+                },
+                1,
+            ),
+            (
+                AppointmentFilter {
+                    device: Some(device_ids[0].clone()),
+                    ..Default::default()
+                },
+                1,
+            ),
+            (
+                AppointmentFilter {
+                    participant: Some(doctor_ids[1].clone()),
+                    device: Some(device_ids[1].clone()),
+                    ..Default::default()
+                },
+                0,
+            ),
+        ] {
+            let result = mock_db.read_appointments(&filter).await.unwrap();
+            assert_eq!(result.len(), expected, "{:?}", filter);
+        }
+
+        // Staff members and devices that are booked can't be deleted
+        let result = mock_db.delete_doctor(&doctor_ids[1].get_unique_id()).await;
+        assert!(matches!(result, Err(DatabaseError::HasAppointments(_))));
+        let result = mock_db.delete_device(&device_ids[0].get_unique_id()).await;
+        assert!(matches!(result, Err(DatabaseError::HasAppointments(_))));
+    }
+
+    #[tokio::test]
+    async fn test_update_appointment_conflict() {
+        let mock_db = mock_db().await;
+
+        let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 3).await;
+        let room_ids = create_dummy_rooms(&mock_db, 3).await;
+
+        let appointment1 = Appointment {
+            start_time: "2023-10-01T10:00:00".to_string(),
+            appointment_type: AppointmentType::new("quick_checkup"),
+            patient_id: patient_ids[0].clone(),
+            doctor: doctor_ids[1].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
+        };
+
+        let appointment2 = Appointment {
+            start_time: "2023-10-01T11:00:00".to_string(),
+            appointment_type: AppointmentType::new("quick_checkup"),
+            patient_id: patient_ids[0].clone(),
+            doctor: doctor_ids[2].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
+        };
+
+        mock_db
+            .create_appointment(
+                appointment1
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let created_appointment = &mock_db
+            .create_appointment(
+                appointment2
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()[0];
+
+        // Moving the second appointment into the room of the first one at the same time must fail
+        let mut moved_appointment = created_appointment.clone();
+        moved_appointment.start_time =
+            NaiveDateTime::parse_from_str("2023-10-01T10:15:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+        moved_appointment.end_time = moved_appointment.start_time + Duration::minutes(30);
+        moved_appointment.blocked = Buffers::default()
+            .blocked_time(moved_appointment.start_time, moved_appointment.end_time);
+
+        let result = mock_db
+            .update_appointment(&created_appointment.id.id.to_raw(), moved_appointment)
+            .await;
+        assert!(matches!(result, Err(DatabaseError::AppointmentConflict)));
+
+        // Updating an appointment without moving it doesn't conflict with itself
+        let result = mock_db
+            .update_appointment(
+                &created_appointment.id.id.to_raw(),
+                created_appointment.clone(),
+            )
+            .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_update_appointments() {
+        let mock_db = mock_db().await;
+
+        let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 2).await;
+        let room_ids = create_dummy_rooms(&mock_db, 2).await;
+
+        let mut created = Vec::new();
+        for (start_time, doctor, room) in [
+            ("2023-10-02T10:00:00", 0, 0),
+            ("2023-10-02T11:00:00", 1, 0),
+            ("2023-10-02T13:00:00", 1, 1),
+        ] {
+            let appointment = Appointment {
+                start_time: start_time.to_string(),
+                appointment_type: AppointmentType::new("extensive_care"),
+                patient_id: patient_ids[0].clone(),
+                doctor: doctor_ids[doctor].clone(),
+                room: room_ids[room].clone(),
+                staff: vec![],
+                devices: vec![],
+            };
+            created.push(
+                mock_db
+                    .create_appointment(
+                        appointment
+                            .into_appointment_with_time(Duration::hours(1), &Buffers::default())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap()[0]
+                    .clone(),
+            );
+        }
+        let moved = |index: usize, start_time: &str, room: usize| {
+            let mut appointment: AppointmentRecord = created[index].clone();
+            appointment.start_time = time(start_time);
+            appointment.end_time = appointment.start_time + Duration::hours(1);
+            appointment.blocked =
+                Buffers::default().blocked_time(appointment.start_time, appointment.end_time);
+            appointment.room = room_ids[room].clone();
+            appointment
+        };
+
+        // The first move is free, but the second one conflicts with the third appointment, so neither is written
+        let result = mock_db
+            .update_appointments(vec![
+                moved(1, "2023-10-02T12:00:00", 0),
+                moved(0, "2023-10-02T13:00:00", 1),
+            ])
+            .await;
+        assert!(matches!(result, Err(DatabaseError::AppointmentConflict)));
+        let unchanged = mock_db
+            .read_appointment(&created[1].id.id.to_raw())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(unchanged.start_time, created[1].start_time);
+
+        // The first appointment can take the timeframe the second one was moved away from
+        let result = mock_db
+            .update_appointments(vec![
+                moved(1, "2023-10-02T12:00:00", 0),
+                moved(0, "2023-10-02T11:00:00", 0),
+            ])
+            .await
+            .unwrap();
+        let moves: Vec<_> = result
+            .iter()
+            .map(|appointment| (appointment.id.clone(), appointment.start_time))
+            .collect();
+        assert_eq!(
+            moves,
+            [
+                (created[1].id.clone(), time("2023-10-02T12:00:00")),
+                (created[0].id.clone(), time("2023-10-02T11:00:00"))
+            ]
+        );
+
+        // Appointments that don't exist anymore aren't created again
+        let mut deleted = moved(0, "2023-10-02T15:00:00", 0);
+        deleted.id = Thing::from(("appointment", "doesnt_exist"));
+        let result = mock_db.update_appointments(vec![deleted]).await;
+        assert!(matches!(result, Err(DatabaseError::NothingFound)));
+    }
+
+    #[tokio::test]
+    async fn test_update_appointment_status() {
+        let mock_db = mock_db().await;
+
+        let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 1).await;
+        let room_ids = create_dummy_rooms(&mock_db, 1).await;
+
+        let appointment = Appointment {
+            start_time: "2023-10-02T10:00:00".to_string(),
+            appointment_type: AppointmentType::new("quick_checkup"),
+            patient_id: patient_ids[0].clone(),
+            doctor: doctor_ids[0].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
+        }
+        .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+        .unwrap();
+        let created = mock_db
+            .create_appointment(appointment.clone())
+            .await
+            .unwrap()[0]
+            .clone();
+        assert_eq!(created.status, AppointmentStatus::Scheduled);
+        let id = created.id.id.to_raw();
+
+        let change = |status, reason: Option<&str>| StatusChange {
+            status,
+            changed_at: time("2023-10-02T09:55:00"),
+            reason: reason.map(str::to_string),
+        };
+
+        // An appointment has to be checked in before it can start
+        let result = mock_db
+            .update_appointment_status(&id, change(AppointmentStatus::InProgress, None))
+            .await;
+        assert!(matches!(result, Err(DatabaseError::InvalidStatusChange)));
+
+        for status in [
+            AppointmentStatus::CheckedIn,
+            AppointmentStatus::InProgress,
+            AppointmentStatus::Completed,
+        ] {
+            let result = mock_db
+                .update_appointment_status(&id, change(status, None))
+                .await
+                .unwrap();
+            assert_eq!(result.status, status);
+        }
+        let result = mock_db
+            .update_appointment_status(&id, change(AppointmentStatus::Cancelled, Some("Ill")))
+            .await;
+        assert!(matches!(result, Err(DatabaseError::InvalidStatusChange)));
+
+        let result = mock_db
+            .read_appointment(&id)
+            .await
+            .unwrap()
+            .unwrap()
+            .status_changes;
+        let statuses: Vec<AppointmentStatus> = result.iter().map(|change| change.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                AppointmentStatus::CheckedIn,
+                AppointmentStatus::InProgress,
+                AppointmentStatus::Completed
+            ]
+        );
+
+        let result = mock_db
+            .update_appointment_status("doesnt_exist", change(AppointmentStatus::CheckedIn, None))
+            .await;
+        assert!(matches!(result, Err(DatabaseError::NothingFound)));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_appointment_frees_its_slot() {
+        let mock_db = mock_db().await;
+
+        let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 1).await;
+        let room_ids = create_dummy_rooms(&mock_db, 1).await;
+
+        let appointment = Appointment {
+            start_time: "2023-10-02T10:00:00".to_string(),
+            appointment_type: AppointmentType::new("quick_checkup"),
+            patient_id: patient_ids[0].clone(),
+            doctor: doctor_ids[0].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
+        }
+        .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+        .unwrap();
+        let cancelled = mock_db
+            .create_appointment(appointment.clone())
+            .await
+            .unwrap()[0]
+            .clone();
+        let result = mock_db
+            .update_appointment_status(
+                &cancelled.id.id.to_raw(),
+                StatusChange {
+                    status: AppointmentStatus::Cancelled,
+                    changed_at: time("2023-10-01T12:00:00"),
+                    reason: Some("Patient is ill".to_string()),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result.status_changes[0].reason.as_deref(),
+            Some("Patient is ill")
+        );
+
+        // The slot can be booked again, while the cancelled appointment stays
+        mock_db.create_appointment(appointment).await.unwrap();
+        let result = mock_db
+            .read_appointments(&AppointmentFilter {
+                status: Some(AppointmentStatus::Cancelled),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+
+        // It can't be moved anymore
+        let mut moved = cancelled.clone();
+        moved.start_time = time("2023-10-02T14:00:00");
+        moved.end_time = time("2023-10-02T14:30:00");
+        moved.blocked = Buffers::default().blocked_time(moved.start_time, moved.end_time);
+        let result = mock_db
+            .update_appointment(&cancelled.id.id.to_raw(), moved.clone())
+            .await;
+        assert!(matches!(result, Err(DatabaseError::InvalidStatusChange)));
+        let result = mock_db.update_appointments(vec![moved]).await;
+        assert!(matches!(result, Err(DatabaseError::InvalidStatusChange)));
+    }
+
     #[tokio::test]
     async fn test_delete_appointment() {
         let mock_db = mock_db().await;
 
         let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 3).await;
+        let room_ids = create_dummy_rooms(&mock_db, 3).await;
 
         let appointment = Appointment {
             start_time: "2023-10-01T10:00:00".to_string(),
-            appointment_type: AppointmentType::QuickCheckup,
+            appointment_type: AppointmentType::new("quick_checkup"),
             patient_id: patient_ids[0].clone(),
-            doctor: 1,
-            room_nr: 0,
+            doctor: doctor_ids[1].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
         };
 
         let created_appointment = &mock_db
-            .create_appointment(appointment.clone().into_appointment_with_time().unwrap())
+            .create_appointment(
+                appointment
+                    .clone()
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap(),
+            )
             .await
             .unwrap()[0];
 
@@ -0,0 +1,223 @@
+use surrealdb::sql::Thing;
+
+use super::{
+    db::Database,
+    types::{AppointmentTypeDefinition, AppointmentTypeRecord, DatabaseError},
+};
+
+impl Database {
+    pub async fn create_appointment_type(
+        &self,
+        id: &str,
+        definition: AppointmentTypeDefinition,
+    ) -> Result<AppointmentTypeRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .create(("appointment_type", id))
+            .content(definition)
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    // There are only a handful of types, so they're read all at once
+    pub async fn read_appointment_types(
+        &self,
+    ) -> Result<Vec<AppointmentTypeRecord>, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let mut result = conn
+            .query("SELECT * FROM appointment_type ORDER BY name")
+            .await
+            .map_err(DatabaseError::from)?;
+
+        Ok(result.take(0)?)
+    }
+
+    pub async fn read_appointment_type(
+        &self,
+        id: &str,
+    ) -> Result<AppointmentTypeRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .select(("appointment_type", id))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    pub async fn update_appointment_type(
+        &self,
+        id: &str,
+        appointment_type: AppointmentTypeRecord,
+    ) -> Result<AppointmentTypeRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .update(("appointment_type", id))
+            .merge(appointment_type)
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    // Types with appointments can't be deleted, since the appointments would lose their duration. Appointments
+    // store the ID of their type instead of a record link, so the link is built for the comparison.
+    pub async fn delete_appointment_type(
+        &self,
+        id: &str,
+    ) -> Result<AppointmentTypeRecord, DatabaseError> {
+        self.delete_unless_booked(
+            &Thing::from(("appointment_type", id)),
+            "type::thing('appointment_type', appointment_type) = $record",
+            "Appointment type",
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod appointment_type_db_tests {
+    use crate::db::{
+        appointment_db::appointment_db_tests::create_dummy_patients,
+        db::database_tests::mock_db,
+        doctor_db::doctor_db_tests::create_dummy_doctors,
+        room_db::room_db_tests::create_dummy_rooms,
+        types::{Appointment, AppointmentType, Buffers, RoomType},
+    };
+
+    use super::*;
+
+    fn vaccination() -> AppointmentTypeDefinition {
+        AppointmentTypeDefinition {
+            name: "Vaccination".to_string(),
+            duration_minutes: 15,
+            buffer_before_minutes: 0,
+            buffer_after_minutes: 5,
+            colour: "#fdd835".to_string(),
+            room_types: vec![RoomType::Consultation, RoomType::Examination],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_appointment_types() {
+        let mock_db = mock_db().await;
+
+        let result = mock_db.read_appointment_types().await.unwrap();
+        let types: Vec<(String, i64)> = result
+            .iter()
+            .map(|record| {
+                (
+                    record.appointment_type().as_str().to_string(),
+                    record.duration().num_minutes(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ("extensive_care".to_string(), 60),
+                ("quick_checkup".to_string(), 30),
+                ("surgery".to_string(), 120),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_update_and_read_appointment_type() {
+        let mock_db = mock_db().await;
+
+        let created = mock_db
+            .create_appointment_type("vaccination", vaccination())
+            .await
+            .unwrap();
+        assert_eq!(
+            created.appointment_type(),
+            AppointmentType::new("vaccination")
+        );
+        assert_eq!(created.buffer_after_minutes, 5);
+
+        // IDs are unique
+        let result = mock_db
+            .create_appointment_type("vaccination", vaccination())
+            .await;
+        assert!(result.is_err());
+
+        let mut updated = created.clone();
+        updated.duration_minutes = 20;
+        let result = mock_db
+            .update_appointment_type("vaccination", updated.clone())
+            .await
+            .unwrap();
+        assert_eq!(result, updated);
+        assert_eq!(
+            mock_db.read_appointment_type("vaccination").await.unwrap(),
+            updated
+        );
+
+        let result = mock_db.read_appointment_type("doesnt_exist").await;
+        assert!(matches!(result, Err(DatabaseError::NothingFound)));
+    }
+
+    #[tokio::test]
+    async fn test_delete_appointment_type() {
+        let mock_db = mock_db().await;
+
+        let booked_type = mock_db
+            .create_appointment_type("vaccination", vaccination())
+            .await
+            .unwrap();
+        mock_db
+            .create_appointment_type(
+                "flu_shot",
+                AppointmentTypeDefinition {
+                    name: "Flu Shot".to_string(),
+                    ..vaccination()
+                },
+            )
+            .await
+            .unwrap();
+
+        let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 1).await;
+        let room_ids = create_dummy_rooms(&mock_db, 1).await;
+        let appointment = Appointment {
+            start_time: "2023-10-02T10:00:00".to_string(),
+            appointment_type: booked_type.appointment_type(),
+            patient_id: patient_ids[0].clone(),
+            doctor: doctor_ids[0].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
+        };
+        mock_db
+            .create_appointment(
+                appointment
+                    .into_appointment_with_time(booked_type.duration(), &Buffers::default())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // The type with an appointment stays
+        let result = mock_db.delete_appointment_type("vaccination").await;
+        assert!(matches!(
+            result,
+            Err(DatabaseError::HasAppointments("Appointment type"))
+        ));
+        assert!(mock_db.read_appointment_type("vaccination").await.is_ok());
+
+        // The one without is deleted
+        let result = mock_db.delete_appointment_type("flu_shot").await.unwrap();
+        assert_eq!(result.name, "Flu Shot");
+        assert!(mock_db.read_appointment_type("flu_shot").await.is_err());
+
+        let result = mock_db.delete_appointment_type("doesnt_exist").await;
+        assert!(matches!(result, Err(DatabaseError::NothingFound)));
+    }
+}
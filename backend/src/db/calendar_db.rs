@@ -0,0 +1,240 @@
+use chrono::NaiveDate;
+use surrealdb::sql::Value;
+
+use super::{
+    db::Database,
+    pagination::Bindings,
+    types::{Closure, DatabaseError, DateRange, OpeningOverride},
+};
+
+fn range_condition(range: &DateRange) -> (String, Bindings) {
+    let mut conditions = Vec::new();
+    let mut bindings = Bindings::new();
+
+    if let Some(from) = range.from {
+        conditions.push("date >= $from");
+        bindings.push(("from", Value::from(from.to_string())));
+    }
+    if let Some(to) = range.to {
+        conditions.push("date <= $to");
+        bindings.push(("to", Value::from(to.to_string())));
+    }
+
+    if conditions.is_empty() {
+        return ("true".to_string(), bindings);
+    }
+    (conditions.join(" AND "), bindings)
+}
+
+impl Database {
+    pub async fn read_closures(&self, range: &DateRange) -> Result<Vec<Closure>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let (condition, bindings) = range_condition(range);
+
+        let mut query = conn.query(format!(
+            "SELECT date, name FROM closure WHERE {condition} ORDER BY date"
+        ));
+        for binding in bindings {
+            query = query.bind(binding);
+        }
+
+        query
+            .await
+            .map_err(DatabaseError::from)?
+            .take(0)
+            .map_err(DatabaseError::from)
+    }
+
+    // Closures on dates that are already closed replace the existing ones, so importing the same calendar twice
+    // doesn't change anything
+    pub async fn create_closures(
+        &self,
+        closures: &[Closure],
+    ) -> Result<Vec<Closure>, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let mut response = conn
+            .query(
+                "BEGIN TRANSACTION;
+                FOR $closure IN $closures {
+                    UPDATE type::thing('closure', $closure.date) CONTENT $closure;
+                };
+                COMMIT TRANSACTION;",
+            )
+            .bind(("closures", closures))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        if let Some(err) = response.take_errors().into_values().next() {
+            return Err(DatabaseError::from(err));
+        }
+
+        Ok(closures.to_vec())
+    }
+
+    pub async fn delete_closure(&self, date: NaiveDate) -> Result<Closure, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .delete(("closure", date.to_string()))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    pub async fn read_opening_overrides(
+        &self,
+        range: &DateRange,
+    ) -> Result<Vec<OpeningOverride>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let (condition, bindings) = range_condition(range);
+
+        let mut query = conn.query(format!(
+            "SELECT date, opening_time, closing_time FROM opening_override WHERE {condition} ORDER BY date"
+        ));
+        for binding in bindings {
+            query = query.bind(binding);
+        }
+
+        query
+            .await
+            .map_err(DatabaseError::from)?
+            .take(0)
+            .map_err(DatabaseError::from)
+    }
+
+    // Creates the override for the date or replaces the existing one
+    pub async fn update_opening_override(
+        &self,
+        opening_override: &OpeningOverride,
+    ) -> Result<OpeningOverride, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .update(("opening_override", opening_override.date.to_string()))
+            .content(opening_override)
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    pub async fn delete_opening_override(
+        &self,
+        date: NaiveDate,
+    ) -> Result<OpeningOverride, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .delete(("opening_override", date.to_string()))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+}
+
+#[cfg(test)]
+mod calendar_db_tests {
+    use chrono::NaiveTime;
+
+    use crate::db::db::database_tests::mock_db;
+
+    use super::*;
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    fn closure(date_str: &str, name: &str) -> Closure {
+        Closure {
+            date: date(date_str),
+            name: name.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_and_read_closures() {
+        let mock_db = mock_db().await;
+
+        let closures = [
+            closure("2024-12-26", "Boxing Day"),
+            closure("2024-12-25", "Christmas Day"),
+            closure("2025-01-01", "New Year's Day"),
+        ];
+        mock_db.create_closures(&closures).await.unwrap();
+
+        // Creating a closure for a date that is already closed replaces it
+        mock_db
+            .create_closures(&[closure("2024-12-26", "St. Stephen's Day")])
+            .await
+            .unwrap();
+
+        let result = mock_db.read_closures(&DateRange::default()).await.unwrap();
+        assert_eq!(
+            result,
+            [
+                closure("2024-12-25", "Christmas Day"),
+                closure("2024-12-26", "St. Stephen's Day"),
+                closure("2025-01-01", "New Year's Day"),
+            ]
+        );
+
+        let result = mock_db
+            .read_closures(&DateRange {
+                from: Some(date("2024-12-26")),
+                to: Some(date("2024-12-31")),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result, [closure("2024-12-26", "St. Stephen's Day")]);
+
+        let result = mock_db.delete_closure(date("2024-12-25")).await.unwrap();
+        assert_eq!(result, closure("2024-12-25", "Christmas Day"));
+        let result = mock_db.delete_closure(date("2024-12-25")).await;
+        assert!(matches!(result, Err(DatabaseError::NothingFound)));
+    }
+
+    #[tokio::test]
+    async fn test_update_opening_override() {
+        let mock_db = mock_db().await;
+
+        let mut opening_override = OpeningOverride {
+            date: date("2024-12-24"),
+            opening_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            closing_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+        };
+        let result = mock_db
+            .update_opening_override(&opening_override)
+            .await
+            .unwrap();
+        assert_eq!(result, opening_override);
+
+        opening_override.closing_time = NaiveTime::from_hms_opt(11, 0, 0).unwrap();
+        mock_db
+            .update_opening_override(&opening_override)
+            .await
+            .unwrap();
+
+        let result = mock_db
+            .read_opening_overrides(&DateRange::default())
+            .await
+            .unwrap();
+        assert_eq!(result, [opening_override.clone()]);
+
+        // Overrides that close before they open are rejected
+        let backwards = OpeningOverride {
+            date: date("2024-12-31"),
+            opening_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+            closing_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+        };
+        assert!(mock_db.update_opening_override(&backwards).await.is_err());
+
+        let result = mock_db
+            .delete_opening_override(date("2024-12-24"))
+            .await
+            .unwrap();
+        assert_eq!(result, opening_override);
+    }
+}
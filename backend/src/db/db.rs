@@ -1,88 +1,303 @@
-use std::sync::{Arc, Mutex};
-use surrealdb::{engine::remote::ws::Ws, opt::auth::Root, Surreal};
+use std::{
+    future::{Future, IntoFuture},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-use crate::config::AppConfig;
+use arc_swap::ArcSwapOption;
+use log::{info, warn};
+use surrealdb::{
+    engine::any::{self, Any},
+    opt::auth::{self, Root},
+    Surreal,
+};
 
+use crate::config::{AppConfig, AuthMode};
+
+use super::types::{ConnectionState, DatabaseError};
+
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+type ConnectFuture = Pin<Box<dyn Future<Output = Result<Surreal<Any>, DatabaseError>> + Send>>;
+
+// Opens a new connection for the given config, both initially and when reconnecting
+type Connector = Arc<dyn Fn(Arc<AppConfig>) -> ConnectFuture + Send + Sync>;
+
+// Cloning a Database is cheap and every clone shares the same connection. The connection is swapped in atomically,
+// so handlers never have to lock anything to use it.
 #[derive(Clone)]
 pub struct Database {
-    pub connection: Arc<Mutex<Option<Surreal<surrealdb::engine::remote::ws::Client>>>>,
+    connection: Arc<ArcSwapOption<Surreal<Any>>>,
+    config: Arc<ArcSwapOption<AppConfig>>,
+    state: Arc<AtomicU8>,
+    connector: Connector,
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Self::with_connector(|config| async move { Self::connect(&config).await })
+    }
 }
 
 impl Database {
     pub fn new() -> Self {
-        Self {
-            connection: Arc::new(Mutex::new(None)),
+        Self::default()
+    }
+
+    // A database that opens its connections with the given function instead of the configured URL, like one that
+    // fails a few times before it succeeds
+    pub fn with_connector<F, Fut>(connect: F) -> Self
+    where
+        F: Fn(Arc<AppConfig>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Surreal<Any>, DatabaseError>> + Send + 'static,
+    {
+        Database {
+            connection: Default::default(),
+            config: Default::default(),
+            state: Default::default(),
+            connector: Arc::new(move |config| Box::pin(connect(config))),
         }
     }
 
-    pub async fn initiate_db(&self, config: AppConfig) -> surrealdb::Result<()> {
-        // Connect to the server
-        let db = Surreal::new::<Ws>(config.database_url).await?;
+    pub async fn initiate_db(&self, config: AppConfig) -> Result<(), DatabaseError> {
+        let config = Arc::new(config);
+        let db = (self.connector)(config.clone()).await?;
 
-        // Signin as a namespace, database, or root user
-        db.signin(Root {
-            username: "root",
-            password: "root",
-        })
-        .await?;
+        // Store the connection in the struct
+        self.connection.store(Some(Arc::new(db)));
+        self.config.store(Some(config));
+        self.set_state(ConnectionState::Connected);
+
+        // Bring the schema up to date before anything is read or written
+        let applied = self.run_migrations().await?;
+        if !applied.is_empty() {
+            info!("Applied database migrations {:?}", applied);
+        }
+
+        Ok(())
+    }
+
+    async fn connect(config: &AppConfig) -> Result<Surreal<Any>, DatabaseError> {
+        // Connect to the server, or start an embedded database, depending on the URL scheme
+        let db = any::connect(config.database_endpoint()).await?;
+
+        // Embedded engines run without authentication
+        if !config.is_embedded_database() {
+            Self::signin(&db, config).await?;
+        }
 
         // Select a specific namespace / database
-        db.use_ns(config.namespace).use_db(config.database).await?;
+        db.use_ns(&config.namespace)
+            .use_db(&config.database)
+            .await?;
 
-        // Store the connection in the struct
-        let mut conn = self.connection.lock().unwrap();
-        *conn = Some(db);
+        Ok(db)
+    }
 
-        Ok(())
+    // Signin as a namespace, database, or root user, or with a token, as configured
+    async fn signin(db: &Surreal<Any>, config: &AppConfig) -> Result<(), DatabaseError> {
+        let auth = &config.auth;
+
+        let result = match auth.mode {
+            AuthMode::None => return Ok(()),
+            AuthMode::Token => {
+                let token = auth
+                    .token
+                    .clone()
+                    .ok_or(DatabaseError::MissingCredential("auth.token"))?;
+                db.authenticate(token).await
+            }
+            AuthMode::Root | AuthMode::Namespace | AuthMode::Database => {
+                let username = auth
+                    .username
+                    .as_deref()
+                    .ok_or(DatabaseError::MissingCredential("auth.username"))?;
+                let password = auth
+                    .password
+                    .as_deref()
+                    .ok_or(DatabaseError::MissingCredential("auth.password"))?;
+
+                let signin = match auth.mode {
+                    AuthMode::Namespace => {
+                        db.signin(auth::Namespace {
+                            namespace: &config.namespace,
+                            username,
+                            password,
+                        })
+                        .await
+                    }
+                    AuthMode::Database => {
+                        db.signin(auth::Database {
+                            namespace: &config.namespace,
+                            database: &config.database,
+                            username,
+                            password,
+                        })
+                        .await
+                    }
+                    _ => db.signin(Root { username, password }).await,
+                };
+                signin.map(|_| ())
+            }
+        };
+
+        result.map_err(|err| DatabaseError::AuthenticationFailed {
+            mode: format!("{:?}", auth.mode).to_lowercase(),
+            reason: err.to_string(),
+        })
+    }
+
+    pub async fn get_connection(&self) -> Result<Surreal<Any>, DatabaseError> {
+        match self.state() {
+            ConnectionState::Connected => {}
+            ConnectionState::Reconnecting => return Err(DatabaseError::Reconnecting),
+            ConnectionState::Disconnected => return Err(DatabaseError::ConnectionLost),
+        }
+
+        self.connection
+            .load()
+            .as_ref()
+            .map(|conn| Surreal::clone(conn))
+            .ok_or(DatabaseError::ConnectionLost)
     }
 
-    pub async fn get_connection(&self) -> Option<Surreal<surrealdb::engine::remote::ws::Client>> {
-        let conn = self.connection.lock().unwrap();
-        conn.clone()
+    pub fn state(&self) -> ConnectionState {
+        ConnectionState::from(self.state.load(Ordering::SeqCst))
     }
 
-    #[cfg(test)]
-    pub async fn delete_all_test_data(&self) -> surrealdb::Result<()> {
-        use crate::db::types::DatabaseError;
+    fn set_state(&self, state: ConnectionState) {
+        self.state.store(state as u8, Ordering::SeqCst);
+    }
 
+    // Round trip to the database, regardless of the state the connection is believed to be in
+    pub async fn ping(&self) -> Result<Duration, DatabaseError> {
         let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)
-            .unwrap();
+            .connection
+            .load_full()
+            .ok_or(DatabaseError::ConnectionLost)?;
 
-        conn.query("DELETE FROM appointment")
+        let started = Instant::now();
+        tokio::time::timeout(PING_TIMEOUT, conn.health().into_future())
             .await
-            .map_err(DatabaseError::from)
-            .unwrap();
-        conn.query("DELETE FROM patient")
-            .await
-            .map_err(DatabaseError::from)
-            .unwrap();
+            .map_err(|_| DatabaseError::Timeout)??;
 
-        Ok(())
+        Ok(started.elapsed())
+    }
+
+    // Pings the database periodically and replaces the connection once it stops answering. Embedded databases
+    // live inside this process and are never monitored, since reconnecting would start an empty one.
+    pub fn spawn_health_monitor(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let config = self.config.load_full()?;
+        if config.is_embedded_database() {
+            return None;
+        }
+
+        let db = self.clone();
+        Some(tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+                if let Err(err) = db.ping().await {
+                    warn!("Lost connection to the database: {}", err);
+                    db.reconnect().await;
+                }
+            }
+        }))
+    }
+
+    // Keeps trying to establish a new connection, doubling the wait after every failed attempt. While this runs,
+    // every query fails fast with `DatabaseError::Reconnecting`. Embedded databases are left alone, a new connection
+    // would start an empty one.
+    async fn reconnect(&self) {
+        let Some(config) = self.config.load_full() else {
+            return;
+        };
+        if config.is_embedded_database() {
+            return;
+        }
+        self.set_state(ConnectionState::Reconnecting);
+
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            match tokio::time::timeout(CONNECT_TIMEOUT, (self.connector)(config.clone())).await {
+                Ok(Ok(db)) => {
+                    self.connection.store(Some(Arc::new(db)));
+                    self.set_state(ConnectionState::Connected);
+                    info!("Reconnected to the database");
+                    return;
+                }
+                Ok(Err(err)) => warn!("Reconnecting to the database failed: {}", err),
+                Err(_) => warn!("Reconnecting to the database timed out"),
+            }
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
     }
 }
 
 #[cfg(test)]
 pub mod database_tests {
-    use crate::config::config_tests::get_test_config;
+    use crate::config::{config_tests::get_test_config, AuthConfig};
+    use surrealdb::opt::Config;
 
     use super::*;
-    use tokio::runtime::Runtime;
+    use std::sync::atomic::AtomicUsize;
+    use tokio::runtime::{Builder, Runtime};
 
+    // Every call starts its own in-memory database, so tests can run in parallel without clearing each other's data
     pub async fn mock_db() -> Database {
         let db = Database::new();
         db.initiate_db(get_test_config()).await.unwrap();
-        db.delete_all_test_data().await.unwrap();
         db
     }
 
+    // A database that is configured like a server, but gets a new in-memory database from every connection attempt
+    // after the given one. The attempts before it fail, as if the server was down.
+    fn flaky_db(attempts: Arc<AtomicUsize>, fails_from: usize, fails_until: usize) -> Database {
+        Database::with_connector(move |config| {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if (fails_from..fails_until).contains(&attempt) {
+                    return Err(DatabaseError::ConnectionLost);
+                }
+                let db = any::connect("mem://").await?;
+                db.use_ns(&config.namespace)
+                    .use_db(&config.database)
+                    .await?;
+                Ok(db)
+            }
+        })
+    }
+
+    fn server_config() -> AppConfig {
+        let mut config = get_test_config();
+        config.database_url = "127.0.0.1:8000".to_string();
+        config
+    }
+
+    // The clock only moves when every task waits, so backoffs and health checks pass without actually waiting
+    fn paused_runtime() -> Runtime {
+        Builder::new_current_thread()
+            .enable_all()
+            .start_paused(true)
+            .build()
+            .unwrap()
+    }
+
     #[test]
     fn test_new_database() {
         let db = Database::new();
-        let conn = db.connection.lock().unwrap();
+        let conn = db.connection.load();
         assert!(conn.is_none());
+        assert_eq!(db.state(), ConnectionState::Disconnected);
     }
 
     #[test]
@@ -93,8 +308,9 @@ pub mod database_tests {
             let result = db.initiate_db(get_test_config()).await;
             assert!(result.is_ok());
 
-            let conn = db.connection.lock().unwrap();
+            let conn = db.connection.load();
             assert!(conn.is_some());
+            assert_eq!(db.state(), ConnectionState::Connected);
         });
     }
 
@@ -106,7 +322,227 @@ pub mod database_tests {
             db.initiate_db(get_test_config()).await.unwrap();
 
             let conn = db.get_connection().await;
-            assert!(conn.is_some());
+            assert!(conn.is_ok());
+        });
+    }
+
+    #[test]
+    fn test_clone_shares_connection() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let db = Database::new();
+            let cloned_db = db.clone();
+            db.initiate_db(get_test_config()).await.unwrap();
+
+            let conn = cloned_db.get_connection().await;
+            assert!(conn.is_ok());
+        });
+    }
+
+    #[test]
+    fn test_signin_missing_credentials() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let db = any::connect("mem://").await.unwrap();
+            let mut config = get_test_config();
+
+            config.auth = AuthConfig {
+                mode: AuthMode::Root,
+                username: Some("root".to_string()),
+                ..Default::default()
+            };
+            let result = Database::signin(&db, &config).await;
+            assert!(matches!(
+                result,
+                Err(DatabaseError::MissingCredential("auth.password"))
+            ));
+
+            config.auth.mode = AuthMode::Token;
+            let result = Database::signin(&db, &config).await;
+            assert!(matches!(
+                result,
+                Err(DatabaseError::MissingCredential("auth.token"))
+            ));
+        });
+    }
+
+    #[test]
+    fn test_signin_rejected() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            // An embedded database that requires the root user to sign in
+            let creds = Root {
+                username: "admin",
+                password: "secret",
+            };
+            let db = any::connect(("mem://", Config::new().user(creds)))
+                .await
+                .unwrap();
+            let mut config = get_test_config();
+
+            config.auth = AuthConfig {
+                mode: AuthMode::Root,
+                username: Some("admin".to_string()),
+                password: Some("wrong".to_string()),
+                token: None,
+            };
+            let result = Database::signin(&db, &config).await;
+            assert!(matches!(
+                result,
+                Err(DatabaseError::AuthenticationFailed { .. })
+            ));
+
+            config.auth.password = Some("secret".to_string());
+            let result = Database::signin(&db, &config).await;
+            assert!(result.is_ok());
+        });
+    }
+
+    #[test]
+    fn test_mock_dbs_are_isolated() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let db1 = mock_db().await;
+            let db2 = mock_db().await;
+
+            db1.create_patient(crate::db::types::Patient {
+                name: "John Doe".to_string(),
+                phone_number: "1234567890".to_string(),
+                insurance_number: None,
+            })
+            .await
+            .unwrap();
+
+            assert_eq!(db1.read_all_patients().await.unwrap().len(), 1);
+            assert!(db2.read_all_patients().await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_ping() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let db = Database::new();
+            assert!(matches!(
+                db.ping().await,
+                Err(DatabaseError::ConnectionLost)
+            ));
+
+            db.initiate_db(get_test_config()).await.unwrap();
+            assert!(db.ping().await.is_ok());
+        });
+    }
+
+    #[test]
+    fn test_reconnect() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let db = mock_db().await;
+
+            // Queries fail fast with a distinct error while the connection is re-established
+            db.set_state(ConnectionState::Reconnecting);
+            assert!(matches!(
+                db.get_connection().await,
+                Err(DatabaseError::Reconnecting)
+            ));
+            assert!(matches!(
+                db.read_all_patients().await,
+                Err(DatabaseError::Reconnecting)
+            ));
+
+            db.set_state(ConnectionState::Connected);
+        });
+    }
+
+    #[test]
+    fn test_reconnect_backs_off_until_connected() {
+        paused_runtime().block_on(async {
+            let attempts = Arc::new(AtomicUsize::new(0));
+            let db = flaky_db(attempts.clone(), 1, 4);
+            db.initiate_db(server_config()).await.unwrap();
+            db.create_patient(crate::db::types::Patient {
+                name: "John Doe".to_string(),
+                phone_number: "1234567890".to_string(),
+                insurance_number: None,
+            })
+            .await
+            .unwrap();
+
+            let reconnect = tokio::spawn({
+                let db = db.clone();
+                async move { db.reconnect().await }
+            });
+            tokio::task::yield_now().await;
+            assert_eq!(db.state(), ConnectionState::Reconnecting);
+            assert!(matches!(
+                db.read_all_patients().await,
+                Err(DatabaseError::Reconnecting)
+            ));
+
+            // Three failed attempts, waiting twice as long after every one of them
+            let started = tokio::time::Instant::now();
+            reconnect.await.unwrap();
+            assert_eq!(attempts.load(Ordering::SeqCst), 5);
+            assert!(started.elapsed() >= INITIAL_BACKOFF * (1 + 2 + 4));
+            assert!(started.elapsed() < INITIAL_BACKOFF * (1 + 2 + 4 + 8));
+
+            // The new connection replaced the old one, which held the patient
+            assert_eq!(db.state(), ConnectionState::Connected);
+            assert!(db.read_all_patients().await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_health_monitor_reconnects() {
+        paused_runtime().block_on(async {
+            let attempts = Arc::new(AtomicUsize::new(0));
+            let db = flaky_db(attempts.clone(), 1, 2);
+            db.initiate_db(server_config()).await.unwrap();
+            let monitor = db.spawn_health_monitor().unwrap();
+
+            // Nothing happens while the database answers
+            tokio::time::sleep(HEALTH_CHECK_INTERVAL * 3).await;
+            assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+            // The next failed ping starts reconnecting, which succeeds on the second attempt
+            db.connection.store(None);
+            tokio::time::sleep(HEALTH_CHECK_INTERVAL + INITIAL_BACKOFF * 2).await;
+            assert_eq!(attempts.load(Ordering::SeqCst), 3);
+            assert_eq!(db.state(), ConnectionState::Connected);
+            assert!(db.ping().await.is_ok());
+
+            monitor.abort();
+        });
+    }
+
+    #[test]
+    fn test_embedded_database_isnt_replaced() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let db = mock_db().await;
+            db.create_patient(crate::db::types::Patient {
+                name: "John Doe".to_string(),
+                phone_number: "1234567890".to_string(),
+                insurance_number: None,
+            })
+            .await
+            .unwrap();
+
+            db.reconnect().await;
+            assert_eq!(db.state(), ConnectionState::Connected);
+            assert_eq!(db.read_all_patients().await.unwrap().len(), 1);
+        });
+    }
+
+    #[test]
+    fn test_embedded_database_isnt_monitored() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let db = Database::new();
+            assert!(db.spawn_health_monitor().is_none());
+
+            db.initiate_db(get_test_config()).await.unwrap();
+            assert!(db.spawn_health_monitor().is_none());
         });
     }
 }
@@ -0,0 +1,152 @@
+use surrealdb::sql::Value;
+
+use super::{
+    db::Database,
+    pagination::Bindings,
+    types::{DatabaseError, Device, DeviceFilter, DeviceRecord, DeviceRecordId},
+};
+
+fn filter_condition(filter: &DeviceFilter) -> (String, Bindings) {
+    let mut conditions = Vec::new();
+    let mut bindings = Bindings::new();
+
+    if let Some(device_type) = &filter.device_type {
+        conditions.push("device_type = $device_type");
+        bindings.push(("device_type", Value::from(device_type.as_str())));
+    }
+
+    if conditions.is_empty() {
+        return ("true".to_string(), bindings);
+    }
+    (conditions.join(" AND "), bindings)
+}
+
+impl Database {
+    pub async fn create_device(&self, device: Device) -> Result<Vec<DeviceRecord>, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        conn.create("device")
+            .content(device)
+            .await
+            .map_err(DatabaseError::from)
+    }
+
+    pub async fn read_devices(
+        &self,
+        filter: &DeviceFilter,
+    ) -> Result<Vec<DeviceRecord>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let (condition, bindings) = filter_condition(filter);
+
+        let mut query = conn.query(format!(
+            "SELECT * FROM device WHERE {condition} ORDER BY name"
+        ));
+        for binding in bindings {
+            query = query.bind(binding);
+        }
+
+        query
+            .await
+            .map_err(DatabaseError::from)?
+            .take(0)
+            .map_err(DatabaseError::from)
+    }
+
+    pub async fn read_device(&self, id: &str) -> Result<DeviceRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .select(("device", id))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    pub async fn update_device(
+        &self,
+        id: &str,
+        device: DeviceRecord,
+    ) -> Result<DeviceRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .update(("device", id))
+            .merge(device)
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    // Devices booked for appointments can't be deleted, since the appointments would lose them
+    pub async fn delete_device(&self, id: &str) -> Result<DeviceRecord, DatabaseError> {
+        self.delete_unless_booked(
+            DeviceRecordId::new(id).as_thing(),
+            "$record INSIDE devices",
+            "Device",
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+pub mod device_db_tests {
+    use crate::db::db::database_tests::mock_db;
+
+    use super::*;
+
+    pub async fn create_dummy_devices(db: &Database, count: u32) -> Vec<DeviceRecordId> {
+        let mut device_ids = Vec::new();
+        for i in 0..count {
+            let device = Device {
+                name: format!("Ultrasound {}", i),
+                device_type: "ultrasound".to_string(),
+            };
+
+            let device = db.create_device(device).await.unwrap();
+            device_ids.push(DeviceRecordId::from(device[0].id.clone()));
+        }
+        device_ids
+    }
+
+    #[tokio::test]
+    async fn test_devices() {
+        let mock_db = mock_db().await;
+
+        for (name, device_type) in [("ECG cart", "ecg"), ("Ultrasound 1", "ultrasound")] {
+            mock_db
+                .create_device(Device {
+                    name: name.to_string(),
+                    device_type: device_type.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let result = mock_db
+            .read_devices(&DeviceFilter::default())
+            .await
+            .unwrap();
+        let names: Vec<&str> = result.iter().map(|device| device.name.as_str()).collect();
+        assert_eq!(names, ["ECG cart", "Ultrasound 1"]);
+
+        let result = mock_db
+            .read_devices(&DeviceFilter {
+                device_type: Some("ultrasound".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        let id = result[0].id.id.to_raw();
+
+        let mut device = mock_db.read_device(&id).await.unwrap();
+        device.name = "Ultrasound 2".to_string();
+        let result = mock_db.update_device(&id, device).await.unwrap();
+        assert_eq!(result.name, "Ultrasound 2");
+
+        mock_db.delete_device(&id).await.unwrap();
+        let result = mock_db.read_device(&id).await;
+        assert!(matches!(result, Err(DatabaseError::NothingFound)));
+    }
+}
@@ -0,0 +1,439 @@
+use surrealdb::sql::Value;
+
+use super::{
+    db::Database,
+    pagination::{Bindings, PageQuery},
+    types::{
+        DatabaseError, Doctor, DoctorFilter, DoctorRecord, DoctorRecordId, DoctorSort, Page,
+        Pagination, Schedule, Shift,
+    },
+};
+
+fn filter_condition(filter: &DoctorFilter) -> (String, Bindings) {
+    let mut conditions = Vec::new();
+    let mut bindings = Bindings::new();
+
+    if let Some(specialty) = &filter.specialty {
+        conditions.push("specialty = $specialty");
+        bindings.push(("specialty", Value::from(specialty.as_str())));
+    }
+    if let Some(active) = filter.active {
+        conditions.push("active = $active");
+        bindings.push(("active", Value::from(active)));
+    }
+
+    if conditions.is_empty() {
+        return ("true".to_string(), bindings);
+    }
+    (conditions.join(" AND "), bindings)
+}
+
+impl Database {
+    pub async fn create_doctor(&self, doctor: Doctor) -> Result<Vec<DoctorRecord>, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        conn.create("doctor")
+            .content(doctor)
+            .await
+            .map_err(DatabaseError::from)
+    }
+
+    pub async fn read_doctor_page(
+        &self,
+        filter: &DoctorFilter,
+        pagination: &Pagination<DoctorSort>,
+    ) -> Result<Page<DoctorRecord>, DatabaseError> {
+        let (condition, bindings) = filter_condition(filter);
+
+        self.read_page(
+            PageQuery {
+                table: "doctor",
+                condition: &condition,
+                bindings,
+                fetch: None,
+            },
+            pagination,
+        )
+        .await
+    }
+
+    pub async fn read_doctor(&self, id: &str) -> Result<DoctorRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .select(("doctor", id))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    pub async fn update_doctor(
+        &self,
+        id: &str,
+        doctor: DoctorRecord,
+    ) -> Result<DoctorRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .update(("doctor", id))
+            .merge(doctor)
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    // Doctors with appointments can't be deleted, since the appointments would lose their doctor or a member of their
+    // staff. They're deactivated instead.
+    pub async fn delete_doctor(&self, id: &str) -> Result<DoctorRecord, DatabaseError> {
+        let doctor = DoctorRecordId::new(id);
+        let doctor_record = self
+            .delete_unless_booked(
+                doctor.as_thing(),
+                "doctor = $record OR $record INSIDE staff",
+                "Doctor",
+            )
+            .await?;
+
+        // Delete the schedule and the absences of the doctor
+        let conn = self.get_connection().await?;
+        conn.query("DELETE shift WHERE doctor = $doctor; DELETE absence WHERE doctor = $doctor;")
+            .bind(("doctor", doctor))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        Ok(doctor_record)
+    }
+
+    pub async fn read_schedule(&self, doctor: &DoctorRecordId) -> Result<Schedule, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let mut shifts: Vec<Shift> = conn
+            .query("SELECT weekday, start_time, end_time FROM shift WHERE doctor = $doctor")
+            .bind(("doctor", doctor))
+            .await
+            .map_err(DatabaseError::from)?
+            .take(0)?;
+        shifts.sort_by_key(|shift| (shift.weekday, shift.start_time));
+
+        Ok(Schedule { shifts })
+    }
+
+    // Replaces the whole weekly schedule of a doctor at once
+    pub async fn update_schedule(
+        &self,
+        doctor: &DoctorRecordId,
+        schedule: &Schedule,
+    ) -> Result<Schedule, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        conn.query(
+            "BEGIN TRANSACTION;
+            DELETE shift WHERE doctor = $doctor;
+            FOR $shift IN $shifts {
+                CREATE shift CONTENT {
+                    doctor: $doctor,
+                    weekday: $shift.weekday,
+                    start_time: $shift.start_time,
+                    end_time: $shift.end_time,
+                };
+            };
+            COMMIT TRANSACTION;",
+        )
+        .bind(("doctor", doctor))
+        .bind(("shifts", &schedule.shifts))
+        .await
+        .map_err(DatabaseError::from)?
+        .check()
+        .map_err(DatabaseError::from)?;
+
+        self.read_schedule(doctor).await
+    }
+}
+
+#[cfg(test)]
+pub mod doctor_db_tests {
+    use crate::db::{
+        db::database_tests::mock_db,
+        room_db::room_db_tests::create_dummy_rooms,
+        types::{
+            Absence, AbsenceFilter, Appointment, AppointmentType, Buffers, Patient,
+            PatientRecordId, Weekday,
+        },
+    };
+    use chrono::Duration;
+
+    use super::*;
+
+    pub async fn create_dummy_doctors(db: &Database, count: u32) -> Vec<DoctorRecordId> {
+        let mut doctor_ids = Vec::new();
+        for i in 0..count {
+            let doctor = Doctor {
+                name: format!("Dr. Jane Doe {}", i),
+                specialty: "general".to_string(),
+                phone_number: None,
+                email: None,
+                active: true,
+            };
+
+            let doctor = db.create_doctor(doctor).await.unwrap();
+            doctor_ids.push(DoctorRecordId::from(doctor[0].id.clone()));
+        }
+        doctor_ids
+    }
+
+    fn doctor(name: &str, specialty: &str, active: bool) -> Doctor {
+        Doctor {
+            name: name.to_string(),
+            specialty: specialty.to_string(),
+            phone_number: Some("1234567890".to_string()),
+            email: None,
+            active,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_and_read_doctor() {
+        let mock_db = mock_db().await;
+
+        let created = mock_db
+            .create_doctor(doctor("Dr. House", "diagnostics", true))
+            .await
+            .unwrap();
+        assert_eq!(created.len(), 1);
+
+        let result = mock_db
+            .read_doctor(&created[0].id.id.to_raw())
+            .await
+            .unwrap();
+        assert_eq!(result, created[0]);
+        assert_eq!(result.name, "Dr. House");
+        assert!(result.active);
+
+        let result = mock_db.read_doctor("doesnt_exist").await;
+        assert!(matches!(result, Err(DatabaseError::NothingFound)));
+    }
+
+    #[tokio::test]
+    async fn test_read_doctor_page_filters() {
+        let mock_db = mock_db().await;
+
+        mock_db
+            .create_doctor(doctor("Dr. Cid", "surgery", true))
+            .await
+            .unwrap();
+        mock_db
+            .create_doctor(doctor("Dr. Ann", "general", true))
+            .await
+            .unwrap();
+        mock_db
+            .create_doctor(doctor("Dr. Bob", "surgery", false))
+            .await
+            .unwrap();
+
+        let pagination = Pagination {
+            limit: 10,
+            offset: 0,
+            cursor: None,
+            sort: DoctorSort::Name,
+            order: Default::default(),
+        };
+
+        let filters = [
+            (
+                DoctorFilter::default(),
+                vec!["Dr. Ann", "Dr. Bob", "Dr. Cid"],
+            ),
+            (
+                DoctorFilter {
+                    specialty: Some("surgery".to_string()),
+                    active: None,
+                },
+                vec!["Dr. Bob", "Dr. Cid"],
+            ),
+            (
+                DoctorFilter {
+                    specialty: Some("surgery".to_string()),
+                    active: Some(true),
+                },
+                vec!["Dr. Cid"],
+            ),
+        ];
+
+        for (filter, expected) in filters {
+            let page = mock_db
+                .read_doctor_page(&filter, &pagination)
+                .await
+                .unwrap();
+            let names: Vec<String> = page.items.into_iter().map(|doctor| doctor.name).collect();
+            assert_eq!(names, expected, "{:?}", filter);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_doctor() {
+        let mock_db = mock_db().await;
+
+        let created = mock_db
+            .create_doctor(doctor("Dr. House", "diagnostics", true))
+            .await
+            .unwrap();
+
+        let mut updated_doctor = created[0].clone();
+        updated_doctor.active = false;
+        updated_doctor.email = Some("house@example.com".to_string());
+
+        let result = mock_db
+            .update_doctor(&created[0].id.id.to_raw(), updated_doctor.clone())
+            .await
+            .unwrap();
+        assert_eq!(result, updated_doctor);
+    }
+
+    #[tokio::test]
+    async fn test_delete_doctor() {
+        let mock_db = mock_db().await;
+
+        let doctor_ids = create_dummy_doctors(&mock_db, 2).await;
+
+        let room_ids = create_dummy_rooms(&mock_db, 1).await;
+        let patient = mock_db
+            .create_patient(Patient {
+                name: "John Doe".to_string(),
+                phone_number: "1234567890".to_string(),
+                insurance_number: None,
+            })
+            .await
+            .unwrap();
+
+        let appointment = Appointment {
+            start_time: "2023-10-01T10:00:00".to_string(),
+            appointment_type: AppointmentType::new("quick_checkup"),
+            patient_id: PatientRecordId::from(patient[0].id.clone()),
+            doctor: doctor_ids[0].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
+        };
+        mock_db
+            .create_appointment(
+                appointment
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // The doctor with an appointment stays
+        let result = mock_db.delete_doctor(&doctor_ids[0].get_unique_id()).await;
+        assert!(matches!(
+            result,
+            Err(DatabaseError::HasAppointments("Doctor"))
+        ));
+        assert!(mock_db
+            .read_doctor(&doctor_ids[0].get_unique_id())
+            .await
+            .is_ok());
+
+        // The one without is deleted, along with their absences
+        mock_db
+            .create_absence(Absence {
+                doctor: doctor_ids[1].clone(),
+                start_date: "2023-10-02".parse().unwrap(),
+                end_date: "2023-10-02".parse().unwrap(),
+                start_time: None,
+                end_time: None,
+                reason: "Vacation".to_string(),
+            })
+            .await
+            .unwrap();
+        let result = mock_db
+            .delete_doctor(&doctor_ids[1].get_unique_id())
+            .await
+            .unwrap();
+        assert_eq!(result.id, *doctor_ids[1].as_thing());
+        assert!(mock_db
+            .read_doctor(&doctor_ids[1].get_unique_id())
+            .await
+            .is_err());
+        assert!(mock_db
+            .read_absences(&AbsenceFilter::default())
+            .await
+            .unwrap()
+            .is_empty());
+
+        let result = mock_db.delete_doctor("doesnt_exist").await;
+        assert!(matches!(result, Err(DatabaseError::NothingFound)));
+    }
+
+    #[tokio::test]
+    async fn test_update_schedule() {
+        let mock_db = mock_db().await;
+
+        let doctor_ids = create_dummy_doctors(&mock_db, 2).await;
+        let shift = |weekday, start_time: &str, end_time: &str| Shift {
+            weekday,
+            start_time: start_time.parse().unwrap(),
+            end_time: end_time.parse().unwrap(),
+        };
+
+        // Doctors start without a schedule
+        let result = mock_db.read_schedule(&doctor_ids[0]).await.unwrap();
+        assert_eq!(result, Schedule::default());
+
+        let schedule = Schedule {
+            shifts: vec![
+                shift(Weekday::Tuesday, "08:00:00", "12:00:00"),
+                shift(Weekday::Monday, "14:00:00", "17:00:00"),
+                shift(Weekday::Monday, "08:00:00", "12:00:00"),
+            ],
+        };
+        let result = mock_db
+            .update_schedule(&doctor_ids[0], &schedule)
+            .await
+            .unwrap();
+
+        // Shifts are sorted by weekday and time
+        assert_eq!(
+            result.shifts,
+            [
+                schedule.shifts[2].clone(),
+                schedule.shifts[1].clone(),
+                schedule.shifts[0].clone()
+            ]
+        );
+
+        // Updating replaces the whole schedule, and only the one of this doctor
+        let schedule = Schedule {
+            shifts: vec![shift(Weekday::Friday, "09:00:00", "13:00:00")],
+        };
+        mock_db
+            .update_schedule(&doctor_ids[1], &schedule)
+            .await
+            .unwrap();
+        let result = mock_db
+            .update_schedule(&doctor_ids[0], &schedule)
+            .await
+            .unwrap();
+        assert_eq!(result, schedule);
+        assert_eq!(
+            mock_db.read_schedule(&doctor_ids[1]).await.unwrap(),
+            schedule
+        );
+
+        // Deleting the doctor deletes their schedule
+        mock_db
+            .delete_doctor(&doctor_ids[0].get_unique_id())
+            .await
+            .unwrap();
+        let conn = mock_db.get_connection().await.unwrap();
+        let shifts: Vec<Shift> = conn
+            .query("SELECT weekday, start_time, end_time FROM shift")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(shifts, schedule.shifts);
+    }
+}
@@ -0,0 +1,568 @@
+use serde::Deserialize;
+
+use super::{db::Database, types::DatabaseError};
+
+// Migrations are applied in order of their version, and every applied version is recorded in the `migration` table.
+// Never change a migration that has been released, add a new one with the next version instead.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub statements: &'static str,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        statements: include_str!("migrations/0001_initial_schema.surql"),
+    },
+    Migration {
+        version: 2,
+        name: "patient_record_links",
+        statements: include_str!("migrations/0002_patient_record_links.surql"),
+    },
+    Migration {
+        version: 3,
+        name: "doctor_registry",
+        statements: include_str!("migrations/0003_doctor_registry.surql"),
+    },
+    Migration {
+        version: 4,
+        name: "room_registry",
+        statements: include_str!("migrations/0004_room_registry.surql"),
+    },
+    Migration {
+        version: 5,
+        name: "doctor_schedules",
+        statements: include_str!("migrations/0005_doctor_schedules.surql"),
+    },
+    Migration {
+        version: 6,
+        name: "clinic_calendar",
+        statements: include_str!("migrations/0006_clinic_calendar.surql"),
+    },
+    Migration {
+        version: 7,
+        name: "appointment_types",
+        statements: include_str!("migrations/0007_appointment_types.surql"),
+    },
+    Migration {
+        version: 8,
+        name: "doctor_absences",
+        statements: include_str!("migrations/0008_doctor_absences.surql"),
+    },
+    Migration {
+        version: 9,
+        name: "appointment_status",
+        statements: include_str!("migrations/0009_appointment_status.surql"),
+    },
+    Migration {
+        version: 10,
+        name: "appointment_series",
+        statements: include_str!("migrations/0010_appointment_series.surql"),
+    },
+    Migration {
+        version: 11,
+        name: "waitlist",
+        statements: include_str!("migrations/0011_waitlist.surql"),
+    },
+    Migration {
+        version: 12,
+        name: "buffers",
+        statements: include_str!("migrations/0012_buffers.surql"),
+    },
+    Migration {
+        version: 13,
+        name: "appointment_resources",
+        statements: include_str!("migrations/0013_appointment_resources.surql"),
+    },
+    Migration {
+        version: 14,
+        name: "offer_expiry",
+        statements: include_str!("migrations/0014_offer_expiry.surql"),
+    },
+];
+
+const DEFINE_MIGRATION_TABLE: &str = "
+    DEFINE TABLE migration SCHEMAFULL;
+    DEFINE FIELD version ON migration TYPE int;
+    DEFINE FIELD name ON migration TYPE string;
+    DEFINE FIELD applied_at ON migration TYPE datetime;
+";
+
+#[derive(Debug, Deserialize)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub name: String,
+}
+
+impl Database {
+    pub async fn read_applied_migrations(&self) -> Result<Vec<AppliedMigration>, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let mut result = conn
+            .query("SELECT version, name FROM migration ORDER BY version")
+            .await
+            .map_err(DatabaseError::from)?;
+
+        Ok(result.take(0)?)
+    }
+
+    // Applies every migration that isn't recorded yet. Each migration runs in its own transaction together with
+    // the record of it being applied, so a failing migration leaves no trace and is retried on the next start.
+    pub async fn run_migrations(&self) -> Result<Vec<u32>, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        conn.query(DEFINE_MIGRATION_TABLE)
+            .await
+            .map_err(DatabaseError::from)?
+            .check()?;
+
+        let applied: Vec<u32> = self
+            .read_applied_migrations()
+            .await?
+            .into_iter()
+            .map(|migration| migration.version)
+            .collect();
+
+        let mut newly_applied = Vec::new();
+
+        for migration in MIGRATIONS {
+            if applied.contains(&migration.version) {
+                continue;
+            }
+
+            let mut response = conn
+                .query(format!(
+                    "BEGIN TRANSACTION;
+                    {}
+                    CREATE type::thing('migration', $version)
+                        SET version = $version, name = $name, applied_at = time::now();
+                    COMMIT TRANSACTION;",
+                    migration.statements
+                ))
+                .bind(("version", migration.version))
+                .bind(("name", migration.name))
+                .await
+                .map_err(DatabaseError::from)?;
+
+            let errors = response.take_errors();
+            // The statement that actually failed is the one that doesn't just report the cancelled transaction
+            let reason = errors
+                .values()
+                .map(|err| err.to_string())
+                .find(|err| !err.contains("failed transaction"))
+                .or_else(|| errors.values().next().map(|err| err.to_string()));
+            if let Some(reason) = reason {
+                return Err(DatabaseError::Migration {
+                    version: migration.version,
+                    reason,
+                });
+            }
+
+            newly_applied.push(migration.version);
+        }
+
+        Ok(newly_applied)
+    }
+}
+
+#[cfg(test)]
+mod migration_tests {
+    use surrealdb::sql::Thing;
+
+    use crate::db::{
+        appointment_db::appointment_db_tests::create_dummy_patients, db::database_tests::mock_db,
+        doctor_db::doctor_db_tests::create_dummy_doctors,
+        room_db::room_db_tests::create_dummy_rooms,
+    };
+
+    use super::*;
+
+    // Replaces the schema of an empty database with the one of the given version, so a migration can be tested
+    // against data in the format it converts
+    async fn roll_back_to(db: &Database, version: u32) {
+        let conn = db.get_connection().await.unwrap();
+
+        let mut query = conn
+            .query("REMOVE TABLE patient; REMOVE TABLE appointment; REMOVE TABLE doctor; REMOVE TABLE room; REMOVE TABLE shift; REMOVE TABLE closure; REMOVE TABLE opening_override; REMOVE TABLE appointment_type; REMOVE TABLE absence; REMOVE TABLE appointment_series; REMOVE TABLE waitlist; REMOVE TABLE waitlist_offer; REMOVE TABLE notification_outbox;")
+            .query("DELETE migration WHERE version > $version")
+            .bind(("version", version));
+        for migration in MIGRATIONS.iter().filter(|m| m.version <= version) {
+            query = query.query(migration.statements);
+        }
+        query.await.unwrap().check().unwrap();
+    }
+
+    #[test]
+    fn test_migration_versions_are_ordered() {
+        for pair in MIGRATIONS.windows(2) {
+            assert!(pair[0].version < pair[1].version);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_migrations() {
+        // The mock database is migrated when it's initiated
+        let mock_db = mock_db().await;
+
+        let applied = mock_db.read_applied_migrations().await.unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert_eq!(applied[0].version, 1);
+        assert_eq!(applied[0].name, "initial_schema");
+
+        // Running them again doesn't apply anything twice
+        let newly_applied = mock_db.run_migrations().await.unwrap();
+        assert!(newly_applied.is_empty());
+        assert_eq!(
+            mock_db.read_applied_migrations().await.unwrap().len(),
+            MIGRATIONS.len()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_patient_links_are_converted() {
+        let mock_db = mock_db().await;
+        let conn = mock_db.get_connection().await.unwrap();
+
+        // Roll the database back to version 1 with an appointment linking its patient by string
+        roll_back_to(&mock_db, 1).await;
+        conn.query("CREATE patient:john SET name = 'John Doe', phone_number = '1234567890'")
+            .query(
+                "CREATE appointment:one CONTENT {
+                    start_time: '2023-10-01T10:00:00',
+                    end_time: '2023-10-01T10:30:00',
+                    appointment_type: 'quick_checkup',
+                    patient_id: <string> 'patient:john',
+                    doctor: 0,
+                    room_nr: 0,
+                }",
+            )
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+
+        let newly_applied = mock_db.run_migrations().await.unwrap();
+        assert_eq!(
+            newly_applied,
+            vec![2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14]
+        );
+
+        let mut result = conn
+            .query("SELECT VALUE type::is::record(patient_id) FROM appointment:one")
+            .await
+            .unwrap();
+        let is_record: Option<bool> = result.take(0).unwrap();
+        assert_eq!(is_record, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_doctor_numbers_are_converted() {
+        let mock_db = mock_db().await;
+        let conn = mock_db.get_connection().await.unwrap();
+
+        // Roll the database back to version 2 with appointments of doctors 0 and 3
+        roll_back_to(&mock_db, 2).await;
+        conn.query("CREATE patient:john SET name = 'John Doe', phone_number = '1234567890'")
+            .query(
+                "FOR $appointment IN [
+                    { start_time: '2023-10-01T10:00:00', doctor: 3 },
+                    { start_time: '2023-10-01T11:00:00', doctor: 3 },
+                    { start_time: '2023-10-01T10:00:00', doctor: 0 },
+                ] {
+                    CREATE appointment CONTENT {
+                        start_time: $appointment.start_time,
+                        end_time: $appointment.start_time,
+                        appointment_type: 'quick_checkup',
+                        patient_id: patient:john,
+                        doctor: $appointment.doctor,
+                        room_nr: 0,
+                    };
+                }",
+            )
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+
+        let newly_applied = mock_db.run_migrations().await.unwrap();
+        assert_eq!(newly_applied, vec![3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14]);
+
+        let mut result = conn
+            .query("SELECT VALUE name FROM doctor ORDER BY name")
+            .query("SELECT VALUE doctor.name FROM appointment WHERE start_time = '2023-10-01T11:00:00'")
+            .await
+            .unwrap();
+        let names: Vec<String> = result.take(0).unwrap();
+        assert_eq!(names, ["Doctor 0", "Doctor 3"]);
+        let linked: Vec<String> = result.take(1).unwrap();
+        assert_eq!(linked, ["Doctor 3"]);
+    }
+
+    #[tokio::test]
+    async fn test_room_numbers_are_converted() {
+        let mock_db = mock_db().await;
+        let conn = mock_db.get_connection().await.unwrap();
+
+        // Roll the database back to version 3 with a surgery in room 1 and checkups in rooms 1 and 2
+        roll_back_to(&mock_db, 3).await;
+        conn.query("CREATE patient:john SET name = 'John Doe', phone_number = '1234567890'")
+            .query("CREATE doctor:ann SET name = 'Dr. Ann', specialty = 'general'")
+            .query(
+                "FOR $appointment IN [
+                    { start_time: '2023-10-01T10:00:00', appointment_type: 'surgery', room_nr: 1 },
+                    { start_time: '2023-10-02T10:00:00', appointment_type: 'quick_checkup', room_nr: 1 },
+                    { start_time: '2023-10-03T10:00:00', appointment_type: 'quick_checkup', room_nr: 2 },
+                ] {
+                    CREATE appointment CONTENT {
+                        start_time: $appointment.start_time,
+                        end_time: $appointment.start_time,
+                        appointment_type: $appointment.appointment_type,
+                        patient_id: patient:john,
+                        doctor: doctor:ann,
+                        room_nr: $appointment.room_nr,
+                    };
+                }",
+            )
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+
+        let newly_applied = mock_db.run_migrations().await.unwrap();
+        assert_eq!(newly_applied, vec![4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14]);
+
+        let mut result = conn
+            .query("SELECT VALUE [name, room_type] FROM (SELECT * FROM room ORDER BY name)")
+            .query(
+                "SELECT VALUE [room.name, room_nr]
+                    FROM (SELECT * FROM appointment ORDER BY start_time)",
+            )
+            .await
+            .unwrap();
+        let rooms: Vec<(String, String)> = result.take(0).unwrap();
+        assert_eq!(
+            rooms,
+            [
+                ("Room 1".to_string(), "operating_room".to_string()),
+                ("Room 2".to_string(), "consultation".to_string()),
+            ]
+        );
+        // The number itself is gone
+        let linked: Vec<(String, Option<u32>)> = result.take(1).unwrap();
+        assert_eq!(
+            linked,
+            [
+                ("Room 1".to_string(), None),
+                ("Room 1".to_string(), None),
+                ("Room 2".to_string(), None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_appointment_types_are_seeded() {
+        let mock_db = mock_db().await;
+        let conn = mock_db.get_connection().await.unwrap();
+
+        // Roll the database back to version 6 with an appointment of one of the former fixed types
+        roll_back_to(&mock_db, 6).await;
+        conn.query("CREATE patient:john SET name = 'John Doe', phone_number = '1234567890'")
+            .query("CREATE doctor:ann SET name = 'Dr. Ann', specialty = 'general'")
+            .query("CREATE room:one SET name = 'Room 1', room_type = 'examination'")
+            .query(
+                "CREATE appointment:one CONTENT {
+                    start_time: '2023-10-02T10:00:00',
+                    end_time: '2023-10-02T11:00:00',
+                    appointment_type: 'extensive_care',
+                    patient_id: patient:john,
+                    doctor: doctor:ann,
+                    room: room:one,
+                }",
+            )
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+
+        let newly_applied = mock_db.run_migrations().await.unwrap();
+        assert_eq!(newly_applied, vec![7, 8, 9, 10, 11, 12, 13, 14]);
+
+        let mut result = conn
+            .query(
+                "SELECT VALUE [meta::id(id), duration_minutes]
+                    FROM (SELECT * FROM appointment_type ORDER BY duration_minutes)",
+            )
+            .query("SELECT VALUE appointment_type FROM appointment:one")
+            .await
+            .unwrap();
+        let types: Vec<(String, u32)> = result.take(0).unwrap();
+        assert_eq!(
+            types,
+            [
+                ("quick_checkup".to_string(), 30),
+                ("extensive_care".to_string(), 60),
+                ("surgery".to_string(), 120),
+            ]
+        );
+        let appointment_type: Option<String> = result.take(1).unwrap();
+        assert_eq!(appointment_type, Some("extensive_care".to_string()));
+
+        // Types that don't exist are rejected
+        let mut response = conn
+            .query("UPDATE appointment:one SET appointment_type = 'vaccination'")
+            .await
+            .unwrap();
+        assert!(!response.take_errors().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_existing_appointments_are_scheduled() {
+        let mock_db = mock_db().await;
+        let conn = mock_db.get_connection().await.unwrap();
+
+        // Roll the database back to version 8 with an appointment that has no status yet
+        roll_back_to(&mock_db, 8).await;
+        conn.query("CREATE patient:john SET name = 'John Doe', phone_number = '1234567890'")
+            .query("CREATE doctor:ann SET name = 'Dr. Ann', specialty = 'general'")
+            .query("CREATE room:one SET name = 'Room 1', room_type = 'examination'")
+            .query(
+                "CREATE appointment:one CONTENT {
+                    start_time: '2023-10-02T10:00:00',
+                    end_time: '2023-10-02T11:00:00',
+                    appointment_type: 'extensive_care',
+                    patient_id: patient:john,
+                    doctor: doctor:ann,
+                    room: room:one,
+                }",
+            )
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+
+        let newly_applied = mock_db.run_migrations().await.unwrap();
+        assert_eq!(newly_applied, vec![9, 10, 11, 12, 13, 14]);
+
+        let mut result = conn
+            .query("SELECT VALUE [status, array::len(status_changes)] FROM appointment:one")
+            .await
+            .unwrap();
+        let status: Option<(String, usize)> = result.take(0).unwrap();
+        assert_eq!(status, Some(("scheduled".to_string(), 0)));
+
+        // Unknown statuses are rejected
+        let mut response = conn
+            .query("UPDATE appointment:one SET status = 'postponed'")
+            .await
+            .unwrap();
+        assert!(!response.take_errors().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_existing_appointments_keep_their_buffer() {
+        let mock_db = mock_db().await;
+        let conn = mock_db.get_connection().await.unwrap();
+
+        // Roll the database back to version 11 with a surgery that is followed by a buffer of 30 minutes
+        roll_back_to(&mock_db, 11).await;
+        conn.query("UPDATE appointment_type:surgery SET buffer_minutes = 30")
+            .query("CREATE patient:john SET name = 'John Doe', phone_number = '1234567890'")
+            .query("CREATE doctor:ann SET name = 'Dr. Ann', specialty = 'general'")
+            .query("CREATE room:one SET name = 'OR 1', room_type = 'operating_room'")
+            .query(
+                "CREATE appointment:one CONTENT {
+                    start_time: '2023-10-02T10:00:00',
+                    end_time: '2023-10-02T12:00:00',
+                    appointment_type: 'surgery',
+                    patient_id: patient:john,
+                    doctor: doctor:ann,
+                    room: room:one,
+                }",
+            )
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+
+        let newly_applied = mock_db.run_migrations().await.unwrap();
+        assert_eq!(newly_applied, vec![12, 13, 14]);
+
+        let mut result = conn
+            .query(
+                "SELECT VALUE [buffer_before_minutes, buffer_after_minutes, buffer_minutes]
+                    FROM appointment_type:surgery",
+            )
+            .query("SELECT VALUE [buffer_before_minutes, buffer_after_minutes] FROM room:one")
+            .query(
+                "SELECT VALUE [blocked.doctor_from, blocked.doctor_until, blocked.room_until]
+                    FROM appointment:one",
+            )
+            .query("SELECT VALUE array::len(staff) + array::len(devices) FROM appointment:one")
+            .await
+            .unwrap();
+        let buffers: Option<(u32, u32, Option<u32>)> = result.take(0).unwrap();
+        assert_eq!(buffers, Some((0, 30, None)));
+        let buffers: Option<(u32, u32)> = result.take(1).unwrap();
+        assert_eq!(buffers, Some((0, 0)));
+        let blocked: Option<(String, String, String)> = result.take(2).unwrap();
+        assert_eq!(
+            blocked,
+            Some((
+                "2023-10-02T10:00:00".to_string(),
+                "2023-10-02T12:30:00".to_string(),
+                "2023-10-02T12:30:00".to_string()
+            ))
+        );
+        // Appointments booked before staff and devices could be reserved have neither
+        let resources: Option<usize> = result.take(3).unwrap();
+        assert_eq!(resources, Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_schema_rejects_invalid_appointment() {
+        let mock_db = mock_db().await;
+        let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 1).await;
+        let room_ids = create_dummy_rooms(&mock_db, 1).await;
+        let conn = mock_db.get_connection().await.unwrap();
+
+        let create = |patient_id: Thing| {
+            conn.query(
+                "CREATE appointment CONTENT {
+                    start_time: '2023-10-01T10:00:00',
+                    end_time: '2023-10-01T10:30:00',
+                    blocked: {
+                        doctor_from: '2023-10-01T10:00:00',
+                        doctor_until: '2023-10-01T10:30:00',
+                        room_from: '2023-10-01T10:00:00',
+                        room_until: '2023-10-01T10:30:00',
+                    },
+                    appointment_type: 'quick_checkup',
+                    patient_id: $patient_id,
+                    doctor: $doctor,
+                    room: $room,
+                }",
+            )
+            .bind(("patient_id", patient_id))
+            .bind(("doctor", doctor_ids[0].as_thing()))
+            .bind(("room", room_ids[0].as_thing()))
+        };
+
+        let mut response = create(patient_ids[0].as_thing().clone()).await.unwrap();
+        assert!(response.take_errors().is_empty());
+
+        // The patient has to exist
+        let mut response = create(Thing::from(("patient", "doesnt_exist")))
+            .await
+            .unwrap();
+        let errors: Vec<String> = response
+            .take_errors()
+            .into_values()
+            .map(|err| err.to_string())
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("`patient_id`"), "{}", errors[0]);
+    }
+}
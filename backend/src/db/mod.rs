@@ -1,4 +1,16 @@
+pub mod absence_db;
 pub mod appointment_db;
+pub mod appointment_type_db;
+pub mod calendar_db;
+#[allow(clippy::module_inception)]
 pub mod db;
+pub mod device_db;
+pub mod doctor_db;
+pub mod migrations;
+pub mod notification_db;
+pub mod pagination;
 pub mod patient_db;
+pub mod room_db;
+pub mod series_db;
 pub mod types;
+pub mod waitlist_db;
@@ -0,0 +1,170 @@
+use chrono::NaiveDateTime;
+use surrealdb::sql::Value;
+
+use super::{
+    db::Database,
+    pagination::Bindings,
+    types::{DatabaseError, NotificationFilter, NotificationRecord},
+};
+
+fn filter_condition(filter: &NotificationFilter) -> (String, Bindings) {
+    let mut conditions = Vec::new();
+    let mut bindings = Bindings::new();
+
+    if let Some(patient_id) = &filter.patient_id {
+        conditions.push("patient_id = $patient_id");
+        bindings.push(("patient_id", Value::from(patient_id.as_thing().clone())));
+    }
+    match filter.sent {
+        Some(true) => conditions.push("sent_at != NONE"),
+        Some(false) => conditions.push("sent_at = NONE"),
+        None => {}
+    }
+
+    if conditions.is_empty() {
+        return ("true".to_string(), bindings);
+    }
+    (conditions.join(" AND "), bindings)
+}
+
+impl Database {
+    pub async fn read_notifications(
+        &self,
+        filter: &NotificationFilter,
+    ) -> Result<Vec<NotificationRecord>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let (condition, bindings) = filter_condition(filter);
+
+        let mut query = conn.query(format!(
+            "SELECT * FROM notification_outbox WHERE {condition} ORDER BY created_at, id"
+        ));
+        for binding in bindings {
+            query = query.bind(binding);
+        }
+
+        query
+            .await
+            .map_err(DatabaseError::from)?
+            .take(0)
+            .map_err(DatabaseError::from)
+    }
+
+    // Marking a notification that was sent already keeps the time it was first sent at
+    pub async fn mark_notification_sent(
+        &self,
+        id: &str,
+        sent_at: NaiveDateTime,
+    ) -> Result<NotificationRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let mut result = conn
+            .query(
+                "UPDATE notification_outbox SET sent_at = sent_at ?? $sent_at
+                    WHERE id = type::thing('notification_outbox', $id)",
+            )
+            .bind(("id", id))
+            .bind(("sent_at", sent_at))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        let result: Option<NotificationRecord> = result.take(0)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+}
+
+#[cfg(test)]
+mod notification_db_tests {
+    use chrono::Duration;
+
+    use crate::{
+        db::{
+            appointment_db::appointment_db_tests::create_dummy_patients,
+            db::database_tests::mock_db,
+            doctor_db::doctor_db_tests::create_dummy_doctors,
+            room_db::room_db_tests::create_dummy_rooms,
+            types::{WaitlistOffer, WaitlistRecordId},
+            waitlist_db::waitlist_db_tests::entry,
+        },
+        util::util_tests::time,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_notification_outbox() {
+        let mock_db = mock_db().await;
+        let patient_ids = create_dummy_patients(&mock_db, 2).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 1).await;
+        let room_ids = create_dummy_rooms(&mock_db, 1).await;
+
+        // Every offer writes a notification for its patient
+        for (n, patient_id) in patient_ids.iter().enumerate() {
+            let created = mock_db
+                .create_waitlist_entry(
+                    entry(patient_id, None, "2024-03-04", "2024-03-08"),
+                    time("2024-03-01T09:00:00"),
+                )
+                .await
+                .unwrap();
+            let start_time = time("2024-03-04T10:00:00") + Duration::hours(n as i64);
+            mock_db
+                .create_offer(
+                    WaitlistOffer {
+                        entry: WaitlistRecordId::from(created[0].id.clone()),
+                        patient_id: patient_id.clone(),
+                        appointment_type: created[0].appointment_type.clone(),
+                        start_time,
+                        end_time: start_time + Duration::minutes(30),
+                        doctor: doctor_ids[0].clone(),
+                        room: room_ids[0].clone(),
+                    },
+                    format!("Offer {}", n),
+                    time("2024-03-02T09:00:00") + Duration::minutes(n as i64),
+                )
+                .await
+                .unwrap();
+        }
+
+        let unsent = NotificationFilter {
+            sent: Some(false),
+            ..Default::default()
+        };
+        let notifications = mock_db.read_notifications(&unsent).await.unwrap();
+        let messages: Vec<_> = notifications
+            .iter()
+            .map(|notification| notification.message.as_str())
+            .collect();
+        assert_eq!(messages, ["Offer 0", "Offer 1"]);
+
+        // Marking it again keeps the time it was first sent at
+        let id = notifications[0].id.id.to_raw();
+        let sent = mock_db
+            .mark_notification_sent(&id, time("2024-03-02T10:00:00"))
+            .await
+            .unwrap();
+        assert_eq!(sent.sent_at, Some(time("2024-03-02T10:00:00")));
+        let sent = mock_db
+            .mark_notification_sent(&id, time("2024-03-02T11:00:00"))
+            .await
+            .unwrap();
+        assert_eq!(sent.sent_at, Some(time("2024-03-02T10:00:00")));
+
+        assert_eq!(mock_db.read_notifications(&unsent).await.unwrap().len(), 1);
+        let sent_to_first = mock_db
+            .read_notifications(&NotificationFilter {
+                patient_id: Some(patient_ids[0].clone()),
+                sent: Some(true),
+            })
+            .await
+            .unwrap();
+        assert_eq!(sent_to_first, [sent]);
+
+        assert!(matches!(
+            mock_db
+                .mark_notification_sent("doesnt_exist", time("2024-03-02T10:00:00"))
+                .await,
+            Err(DatabaseError::NothingFound)
+        ));
+    }
+}
@@ -0,0 +1,262 @@
+use serde::de::DeserializeOwned;
+use surrealdb::sql::Value;
+
+use super::{
+    db::Database,
+    types::{DatabaseError, Page, Pagination, Record, SortKey, SortOrder},
+};
+
+// Thrown when the entry a cursor points to doesn't exist (anymore)
+const INVALID_CURSOR: &str = "invalid_cursor";
+
+pub(super) type Bindings = Vec<(&'static str, Value)>;
+
+pub(super) struct PageQuery<'a> {
+    pub table: &'static str,
+    // Must not contain user input, values are passed in `bindings`
+    pub condition: &'a str,
+    pub bindings: Bindings,
+    // Record links to resolve, e.g. `patient_id`
+    pub fetch: Option<&'static str>,
+}
+
+impl Database {
+    // Reads one page of entries matching the condition. Entries are sorted by the sort key and then by ID, so the
+    // order is stable even if several entries share the same sort value, and a cursor continues right after the
+    // entry it points to.
+    pub(super) async fn read_page<T, S>(
+        &self,
+        query: PageQuery<'_>,
+        pagination: &Pagination<S>,
+    ) -> Result<Page<T>, DatabaseError>
+    where
+        T: DeserializeOwned + Record,
+        S: SortKey,
+    {
+        let conn = self.get_connection().await?;
+
+        let sort = pagination.sort.expression();
+        let (direction, comparison) = match pagination.order {
+            SortOrder::Asc => ("ASC", ">"),
+            SortOrder::Desc => ("DESC", "<"),
+        };
+
+        let mut statements = String::new();
+        let mut condition = query.condition.to_string();
+        if pagination.cursor.is_some() {
+            statements.push_str(&format!(
+                "LET $after = (SELECT {sort} AS sort_value, id FROM $cursor)[0];
+                IF $after = NONE {{ THROW '{INVALID_CURSOR}'; }};"
+            ));
+            condition = format!(
+                "({condition}) AND ({sort} {comparison} $after.sort_value
+                    OR ({sort} = $after.sort_value AND id {comparison} $after.id))"
+            );
+        }
+        let fetch = query
+            .fetch
+            .map(|fetch| format!("FETCH {}", fetch))
+            .unwrap_or_default();
+        statements.push_str(&format!(
+            "SELECT *, {sort} AS sort_value FROM {table} WHERE {condition}
+                ORDER BY sort_value {direction}, id {direction} LIMIT $limit START $offset {fetch};
+            RETURN count((SELECT VALUE id FROM {table} WHERE {base_condition}));",
+            table = query.table,
+            base_condition = query.condition,
+        ));
+
+        // One more entry than requested tells whether there's a next page
+        let mut request = conn
+            .query(statements)
+            .bind(("cursor", &pagination.cursor))
+            .bind(("limit", pagination.limit + 1))
+            .bind(("offset", pagination.offset));
+        for binding in query.bindings {
+            request = request.bind(binding);
+        }
+        let mut response = request.await.map_err(DatabaseError::from)?;
+
+        let errors = response.take_errors();
+        if errors
+            .values()
+            .any(|err| err.to_string().contains(INVALID_CURSOR))
+        {
+            return Err(DatabaseError::InvalidCursor);
+        }
+        if let Some(err) = errors.into_values().next() {
+            return Err(DatabaseError::from(err));
+        }
+
+        let last_statement = response.num_statements() - 1;
+        let total: Option<u64> = response.take(last_statement)?;
+        let mut items: Vec<T> = response.take(last_statement - 1)?;
+
+        let mut next_cursor = None;
+        if items.len() > pagination.limit as usize {
+            items.truncate(pagination.limit as usize);
+            next_cursor = items.last().map(|item| item.id().to_string());
+        }
+
+        Ok(Page {
+            items,
+            total: total.unwrap_or_default(),
+            next_cursor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod pagination_tests {
+    use chrono::Duration;
+    use surrealdb::sql::Thing;
+
+    use crate::db::{
+        db::database_tests::mock_db,
+        doctor_db::doctor_db_tests::create_dummy_doctors,
+        room_db::room_db_tests::create_dummy_rooms,
+        types::{
+            Appointment, AppointmentFilter, AppointmentSort, AppointmentType, Buffers, Patient,
+            PatientRecordId, PatientSort,
+        },
+    };
+
+    use super::*;
+
+    fn pagination<S>(limit: u32, sort: S, order: SortOrder) -> Pagination<S> {
+        Pagination {
+            limit,
+            offset: 0,
+            cursor: None,
+            sort,
+            order,
+        }
+    }
+
+    async fn create_patients(db: &Database, names: &[&str]) -> Vec<PatientRecordId> {
+        let mut patient_ids = Vec::new();
+        for name in names {
+            let patient = Patient {
+                name: name.to_string(),
+                phone_number: "1234567890".to_string(),
+                insurance_number: None,
+            };
+            let patient = db.create_patient(patient).await.unwrap();
+            patient_ids.push(PatientRecordId::from(patient[0].id.clone()));
+        }
+        patient_ids
+    }
+
+    #[tokio::test]
+    async fn test_follow_cursors() {
+        let mock_db = mock_db().await;
+        create_patients(&mock_db, &["Eve", "Bob", "Dan", "Ann", "Cid"]).await;
+
+        let mut pagination = pagination(2, PatientSort::Name, SortOrder::Asc);
+        let mut names = Vec::new();
+        let mut pages = 0;
+        loop {
+            let page = mock_db.read_patient_page(&pagination).await.unwrap();
+            assert_eq!(page.total, 5);
+            assert!(page.items.len() <= 2);
+            names.extend(page.items.into_iter().map(|patient| patient.name));
+            pages += 1;
+
+            match page.next_cursor {
+                Some(cursor) => pagination.cursor = Some(cursor.parse::<Thing>().unwrap()),
+                None => break,
+            }
+        }
+
+        assert_eq!(pages, 3);
+        assert_eq!(names, ["Ann", "Bob", "Cid", "Dan", "Eve"]);
+    }
+
+    #[tokio::test]
+    async fn test_offset_and_order() {
+        let mock_db = mock_db().await;
+        create_patients(&mock_db, &["Ann", "Bob", "Cid"]).await;
+
+        let mut pagination = pagination(2, PatientSort::Name, SortOrder::Desc);
+        pagination.offset = 1;
+        let page = mock_db.read_patient_page(&pagination).await.unwrap();
+
+        let names: Vec<String> = page.items.into_iter().map(|patient| patient.name).collect();
+        assert_eq!(names, ["Bob", "Ann"]);
+        assert_eq!(page.total, 3);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cursor_with_equal_sort_values() {
+        let mock_db = mock_db().await;
+        let patient_ids = create_patients(&mock_db, &["Ann", "Bob"]).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 3).await;
+        let room_ids = create_dummy_rooms(&mock_db, 3).await;
+
+        // Three appointments at the same time, only the ID tells them apart
+        for (nr, patient_id) in [
+            patient_ids[1].clone(),
+            patient_ids[0].clone(),
+            patient_ids[1].clone(),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let appointment = Appointment {
+                start_time: "2023-10-02T10:00:00".to_string(),
+                appointment_type: AppointmentType::new("quick_checkup"),
+                patient_id,
+                doctor: doctor_ids[nr].clone(),
+                room: room_ids[nr].clone(),
+                staff: vec![],
+                devices: vec![],
+            };
+            mock_db
+                .create_appointment(
+                    appointment
+                        .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let filter = AppointmentFilter {
+            day: chrono::NaiveDate::from_ymd_opt(2023, 10, 2),
+            ..Default::default()
+        };
+        let mut pagination = pagination(1, AppointmentSort::PatientName, SortOrder::Asc);
+        let mut seen = Vec::new();
+        loop {
+            let page = mock_db
+                .read_appointment_page(&filter, &pagination)
+                .await
+                .unwrap();
+            seen.extend(page.items);
+
+            match page.next_cursor {
+                Some(cursor) => pagination.cursor = Some(cursor.parse::<Thing>().unwrap()),
+                None => break,
+            }
+        }
+
+        // Every appointment shows up exactly once, sorted by the name of the patient
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[0].patient.name, "Ann");
+        assert_eq!(seen[1].patient.name, "Bob");
+        assert_eq!(seen[2].patient.name, "Bob");
+        assert_ne!(seen[1].id, seen[2].id);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_cursor() {
+        let mock_db = mock_db().await;
+        create_patients(&mock_db, &["Ann"]).await;
+
+        let mut pagination = pagination(2, PatientSort::Name, SortOrder::Asc);
+        pagination.cursor = Some(Thing::from(("patient", "doesnt_exist")));
+
+        let result = mock_db.read_patient_page(&pagination).await;
+        assert!(matches!(result, Err(DatabaseError::InvalidCursor)));
+    }
+}
@@ -1,6 +1,9 @@
 use super::{
     db::Database,
-    types::{DatabaseError, Patient, PatientRecord},
+    pagination::PageQuery,
+    types::{
+        DatabaseError, Page, Pagination, Patient, PatientRecord, PatientRecordId, PatientSort,
+    },
 };
 
 impl Database {
@@ -8,10 +11,7 @@ impl Database {
         &self,
         patient: Patient,
     ) -> Result<Vec<PatientRecord>, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
+        let conn = self.get_connection().await?;
 
         conn.create("patient")
             .content(patient)
@@ -20,19 +20,29 @@ impl Database {
     }
 
     pub async fn read_all_patients(&self) -> Result<Vec<PatientRecord>, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
+        let conn = self.get_connection().await?;
 
         conn.select("patient").await.map_err(DatabaseError::from)
     }
 
+    pub async fn read_patient_page(
+        &self,
+        pagination: &Pagination<PatientSort>,
+    ) -> Result<Page<PatientRecord>, DatabaseError> {
+        self.read_page(
+            PageQuery {
+                table: "patient",
+                condition: "true",
+                bindings: vec![],
+                fetch: None,
+            },
+            pagination,
+        )
+        .await
+    }
+
     pub async fn read_patient(&self, id: &str) -> Result<PatientRecord, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
+        let conn = self.get_connection().await?;
 
         let result = conn
             .select(("patient", id))
@@ -47,10 +57,7 @@ impl Database {
         id: &str,
         patient: PatientRecord,
     ) -> Result<PatientRecord, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
+        let conn = self.get_connection().await?;
 
         let result = conn
             .update(("patient", id))
@@ -62,10 +69,7 @@ impl Database {
     }
 
     pub async fn delete_patient(&self, id: &str) -> Result<PatientRecord, DatabaseError> {
-        let conn = self
-            .get_connection()
-            .await
-            .ok_or(DatabaseError::ConnectionLost)?;
+        let conn = self.get_connection().await?;
 
         // Delete the patient
         let result = conn
@@ -77,8 +81,8 @@ impl Database {
         let patient_record = result.ok_or(DatabaseError::NothingFound)?;
 
         // Delete any appointments associated with the patient
-        conn.query("DELETE FROM appointment WHERE patient_id = $id")
-            .bind(("id", id))
+        conn.query("DELETE FROM appointment WHERE patient_id = $patient_id")
+            .bind(("patient_id", PatientRecordId::new(id)))
             .await
             .map_err(DatabaseError::from)?;
 
@@ -88,7 +92,13 @@ impl Database {
 
 #[cfg(test)]
 mod patient_db_tests {
-    use crate::db::db::database_tests::mock_db;
+    use crate::db::{
+        db::database_tests::mock_db,
+        doctor_db::doctor_db_tests::create_dummy_doctors,
+        room_db::room_db_tests::create_dummy_rooms,
+        types::{Appointment, AppointmentFilter, AppointmentType, Buffers},
+    };
+    use chrono::Duration;
 
     use super::*;
 
@@ -214,6 +224,26 @@ mod patient_db_tests {
         };
 
         let to_delete = mock_db.create_patient(patient.clone()).await.unwrap();
+        let doctor_ids = create_dummy_doctors(&mock_db, 1).await;
+        let room_ids = create_dummy_rooms(&mock_db, 1).await;
+
+        let appointment = Appointment {
+            start_time: "2023-10-01T10:00:00".to_string(),
+            appointment_type: AppointmentType::new("quick_checkup"),
+            patient_id: PatientRecordId::from(to_delete[0].id.clone()),
+            doctor: doctor_ids[0].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
+        };
+        mock_db
+            .create_appointment(
+                appointment
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
 
         // Delete the patient data from the database
         let result = mock_db
@@ -230,5 +260,12 @@ mod patient_db_tests {
         let result = mock_db.read_patient(&to_delete[0].id.id.to_raw()).await;
 
         assert!(result.is_err());
+
+        // Assert that the appointments of the patient were deleted with them
+        let result = mock_db
+            .read_appointments(&AppointmentFilter::default())
+            .await
+            .unwrap();
+        assert!(result.is_empty());
     }
 }
@@ -0,0 +1,218 @@
+use surrealdb::sql::Value;
+
+use super::{
+    db::Database,
+    pagination::{Bindings, PageQuery},
+    types::{
+        DatabaseError, Page, Pagination, Room, RoomFilter, RoomRecord, RoomRecordId, RoomSort,
+    },
+};
+
+fn filter_condition(filter: &RoomFilter) -> (String, Bindings) {
+    let mut conditions = Vec::new();
+    let mut bindings = Bindings::new();
+
+    if let Some(room_type) = &filter.room_type {
+        conditions.push("room_type = $room_type");
+        bindings.push(("room_type", Value::from(room_type.as_str())));
+    }
+    if let Some(equipment) = &filter.equipment {
+        conditions.push("equipment CONTAINS $equipment");
+        bindings.push(("equipment", Value::from(equipment.as_str())));
+    }
+
+    if conditions.is_empty() {
+        return ("true".to_string(), bindings);
+    }
+    (conditions.join(" AND "), bindings)
+}
+
+impl Database {
+    pub async fn create_room(&self, room: Room) -> Result<Vec<RoomRecord>, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        conn.create("room")
+            .content(room)
+            .await
+            .map_err(DatabaseError::from)
+    }
+
+    pub async fn read_room_page(
+        &self,
+        filter: &RoomFilter,
+        pagination: &Pagination<RoomSort>,
+    ) -> Result<Page<RoomRecord>, DatabaseError> {
+        let (condition, bindings) = filter_condition(filter);
+
+        self.read_page(
+            PageQuery {
+                table: "room",
+                condition: &condition,
+                bindings,
+                fetch: None,
+            },
+            pagination,
+        )
+        .await
+    }
+
+    pub async fn read_room(&self, id: &str) -> Result<RoomRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .select(("room", id))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    pub async fn update_room(
+        &self,
+        id: &str,
+        room: RoomRecord,
+    ) -> Result<RoomRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .update(("room", id))
+            .merge(room)
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    // Rooms with appointments can't be deleted, since the appointments would lose their room
+    pub async fn delete_room(&self, id: &str) -> Result<RoomRecord, DatabaseError> {
+        self.delete_unless_booked(RoomRecordId::new(id).as_thing(), "room = $record", "Room")
+            .await
+    }
+}
+
+#[cfg(test)]
+pub mod room_db_tests {
+    use crate::db::{db::database_tests::mock_db, types::RoomType};
+
+    use super::*;
+
+    // Examination rooms, which every appointment type but surgeries can take place in
+    pub async fn create_dummy_rooms(db: &Database, count: u32) -> Vec<RoomRecordId> {
+        let mut room_ids = Vec::new();
+        for i in 0..count {
+            let room = Room {
+                name: format!("Room {}", i),
+                room_type: RoomType::Examination,
+                equipment: vec![],
+                buffer_before_minutes: 0,
+                buffer_after_minutes: 0,
+            };
+
+            let room = db.create_room(room).await.unwrap();
+            room_ids.push(RoomRecordId::from(room[0].id.clone()));
+        }
+        room_ids
+    }
+
+    fn room(name: &str, room_type: RoomType, equipment: &[&str]) -> Room {
+        Room {
+            name: name.to_string(),
+            room_type,
+            equipment: equipment.iter().map(|tag| tag.to_string()).collect(),
+            buffer_before_minutes: 0,
+            buffer_after_minutes: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_and_read_room() {
+        let mock_db = mock_db().await;
+
+        let created = mock_db
+            .create_room(room("OR 1", RoomType::OperatingRoom, &["anesthesia"]))
+            .await
+            .unwrap();
+        assert_eq!(created.len(), 1);
+
+        let result = mock_db.read_room(&created[0].id.id.to_raw()).await.unwrap();
+        assert_eq!(result, created[0]);
+        assert_eq!(result.room_type, RoomType::OperatingRoom);
+        assert_eq!(result.equipment, ["anesthesia"]);
+
+        let result = mock_db.read_room("doesnt_exist").await;
+        assert!(matches!(result, Err(DatabaseError::NothingFound)));
+    }
+
+    #[tokio::test]
+    async fn test_read_room_page_filters() {
+        let mock_db = mock_db().await;
+
+        mock_db
+            .create_room(room("OR 1", RoomType::OperatingRoom, &["anesthesia"]))
+            .await
+            .unwrap();
+        mock_db
+            .create_room(room("Exam 1", RoomType::Examination, &["ecg", "x_ray"]))
+            .await
+            .unwrap();
+        mock_db
+            .create_room(room("Exam 2", RoomType::Examination, &[]))
+            .await
+            .unwrap();
+
+        let pagination = Pagination {
+            limit: 10,
+            offset: 0,
+            cursor: None,
+            sort: RoomSort::Name,
+            order: Default::default(),
+        };
+
+        let filters = [
+            (RoomFilter::default(), vec!["Exam 1", "Exam 2", "OR 1"]),
+            (
+                RoomFilter {
+                    room_type: Some(RoomType::Examination),
+                    equipment: None,
+                },
+                vec!["Exam 1", "Exam 2"],
+            ),
+            (
+                RoomFilter {
+                    room_type: None,
+                    equipment: Some("x_ray".to_string()),
+                },
+                vec!["Exam 1"],
+            ),
+        ];
+
+        for (filter, expected) in filters {
+            let page = mock_db.read_room_page(&filter, &pagination).await.unwrap();
+            let names: Vec<String> = page.items.into_iter().map(|room| room.name).collect();
+            assert_eq!(names, expected, "{:?}", filter);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_and_delete_room() {
+        let mock_db = mock_db().await;
+
+        let created = mock_db
+            .create_room(room("Exam 1", RoomType::Examination, &[]))
+            .await
+            .unwrap();
+        let id = created[0].id.id.to_raw();
+
+        let mut updated_room = created[0].clone();
+        updated_room.equipment = vec!["ecg".to_string()];
+        let result = mock_db
+            .update_room(&id, updated_room.clone())
+            .await
+            .unwrap();
+        assert_eq!(result, updated_room);
+
+        let result = mock_db.delete_room(&id).await.unwrap();
+        assert_eq!(result, updated_room);
+        assert!(mock_db.read_room(&id).await.is_err());
+    }
+}
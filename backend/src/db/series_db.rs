@@ -0,0 +1,338 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use surrealdb::sql::{Id, Thing};
+
+use super::{
+    appointment_db::{booking_error, is_conflict, lock_days, Timeframe, RESERVE_TIMEFRAME},
+    db::Database,
+    types::{
+        AppointmentRecord, AppointmentSeries, AppointmentSeriesRecord, AppointmentWithTime,
+        DatabaseError, SeriesRecordId, StatusChange,
+    },
+};
+
+impl Database {
+    // Creates the series along with its appointments, or nothing if any of their timeframes was taken in the meantime.
+    // The appointments are linked to the series, whatever they linked to before is replaced.
+    pub async fn create_series(
+        &self,
+        series: AppointmentSeries,
+        mut appointments: Vec<AppointmentWithTime>,
+    ) -> Result<(AppointmentSeriesRecord, Vec<AppointmentRecord>), DatabaseError> {
+        if self
+            .read_patient(&series.patient_id.get_unique_id())
+            .await
+            .is_err()
+        {
+            return Err(DatabaseError::NothingFound);
+        }
+
+        #[derive(Serialize)]
+        struct Booking<'a> {
+            days: Vec<String>,
+            #[serde(flatten)]
+            timeframe: Timeframe<'a>,
+            appointment: &'a AppointmentWithTime,
+        }
+
+        let series_id = Thing::from(("appointment_series", Id::rand()));
+        for appointment in &mut appointments {
+            appointment.series = Some(SeriesRecordId::from(series_id.clone()));
+        }
+        let bookings: Vec<Booking> = appointments
+            .iter()
+            .map(|appointment| Booking {
+                days: lock_days(&appointment.blocked),
+                timeframe: Timeframe {
+                    blocked: &appointment.blocked,
+                    doctor: &appointment.doctor,
+                    room: &appointment.room,
+                    staff: &appointment.staff,
+                    devices: &appointment.devices,
+                    exclude: None,
+                },
+                appointment,
+            })
+            .collect();
+
+        let conn = self.get_connection().await?;
+        let mut response = conn
+            .query(format!(
+                "BEGIN TRANSACTION;
+                CREATE $series_id CONTENT $series;
+                FOR $booking IN $bookings {{
+                    LET $days = $booking.days;
+                    LET $blocked = $booking.blocked;
+                    LET $doctor = $booking.doctor;
+                    LET $room = $booking.room;
+                    LET $staff = $booking.staff;
+                    LET $devices = $booking.devices;
+                    LET $exclude = $booking.exclude;
+                    {RESERVE_TIMEFRAME}
+                    CREATE appointment CONTENT $booking.appointment;
+                }};
+                COMMIT TRANSACTION;
+                SELECT * FROM $series_id;
+                SELECT * FROM appointment WHERE series = $series_id ORDER BY start_time;"
+            ))
+            .bind(("series_id", &series_id))
+            .bind(("series", &series))
+            .bind(("bookings", bookings))
+            .await
+            .map_err(booking_error)?;
+
+        let errors = response.take_errors();
+        if errors.values().any(|err| is_conflict(&err.to_string())) {
+            return Err(DatabaseError::AppointmentConflict);
+        }
+        if let Some(err) = errors.into_values().next() {
+            return Err(DatabaseError::from(err));
+        }
+
+        let last_statement = response.num_statements() - 1;
+        let series: Option<AppointmentSeriesRecord> = response.take(last_statement - 1)?;
+        let appointments: Vec<AppointmentRecord> = response.take(last_statement)?;
+
+        Ok((series.ok_or(DatabaseError::NothingFound)?, appointments))
+    }
+
+    pub async fn read_series(&self, id: &str) -> Result<AppointmentSeriesRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .select(("appointment_series", id))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    // Changes the status of every appointment of the series that starts at or after `from` and is in a status the new
+    // one can follow. The others, like completed ones, are left as they are.
+    pub async fn update_series_status(
+        &self,
+        series: &SeriesRecordId,
+        from: Option<NaiveDateTime>,
+        change: StatusChange,
+    ) -> Result<Vec<AppointmentRecord>, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let condition = match from {
+            Some(_) => "series = $series AND start_time >= $from AND status INSIDE $previous",
+            None => "series = $series AND status INSIDE $previous",
+        };
+        let mut result = conn
+            .query(format!(
+                "UPDATE appointment SET status = $change.status, status_changes += $change
+                    WHERE {condition}"
+            ))
+            .bind(("series", series))
+            .bind(("from", from))
+            .bind(("previous", change.status.previous()))
+            .bind(("change", &change))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        let mut appointments: Vec<AppointmentRecord> = result.take(0)?;
+        appointments.sort_by_key(|appointment| appointment.start_time);
+
+        Ok(appointments)
+    }
+}
+
+#[cfg(test)]
+mod series_db_tests {
+    use chrono::Duration;
+
+    use crate::{
+        db::{
+            appointment_db::appointment_db_tests::create_dummy_patients,
+            db::database_tests::mock_db,
+            doctor_db::doctor_db_tests::create_dummy_doctors,
+            room_db::room_db_tests::create_dummy_rooms,
+            types::{
+                Appointment, AppointmentFilter, AppointmentStatus, AppointmentType, Buffers,
+                Frequency, RecurrenceRule,
+            },
+        },
+        util::util_tests::time,
+    };
+
+    use super::*;
+
+    async fn weekly_series(
+        db: &Database,
+        count: u32,
+    ) -> (AppointmentSeries, Vec<AppointmentWithTime>) {
+        let patient_ids = create_dummy_patients(db, 1).await;
+        let doctor_ids = create_dummy_doctors(db, 1).await;
+        let room_ids = create_dummy_rooms(db, 1).await;
+
+        let series = AppointmentSeries {
+            start_time: time("2024-03-04T10:00:00"),
+            appointment_type: AppointmentType::new("quick_checkup"),
+            patient_id: patient_ids[0].clone(),
+            doctor: doctor_ids[0].clone(),
+            room: room_ids[0].clone(),
+            staff: vec![],
+            devices: vec![],
+            rule: RecurrenceRule {
+                frequency: Frequency::Weekly,
+                interval: 1,
+                count: Some(count),
+                until: None,
+                exceptions: vec![],
+            },
+        };
+        let appointments = series
+            .rule
+            .occurrences(series.start_time)
+            .into_iter()
+            .map(|start_time| AppointmentWithTime {
+                start_time,
+                end_time: start_time + Duration::minutes(30),
+                blocked: Buffers::default()
+                    .blocked_time(start_time, start_time + Duration::minutes(30)),
+                appointment_type: series.appointment_type.clone(),
+                patient_id: series.patient_id.clone(),
+                doctor: series.doctor.clone(),
+                room: series.room.clone(),
+                staff: vec![],
+                devices: vec![],
+                series: None,
+            })
+            .collect();
+
+        (series, appointments)
+    }
+
+    #[tokio::test]
+    async fn test_create_series() {
+        let mock_db = mock_db().await;
+        let (series, appointments) = weekly_series(&mock_db, 3).await;
+
+        let (created, booked) = mock_db
+            .create_series(series.clone(), appointments)
+            .await
+            .unwrap();
+        assert_eq!(created.rule, series.rule);
+        let start_times: Vec<_> = booked
+            .iter()
+            .map(|appointment| appointment.start_time)
+            .collect();
+        assert_eq!(
+            start_times,
+            [
+                time("2024-03-04T10:00:00"),
+                time("2024-03-11T10:00:00"),
+                time("2024-03-18T10:00:00")
+            ]
+        );
+
+        // The appointments can be found by their series
+        let series_id = SeriesRecordId::from(created.id.clone());
+        let found = mock_db
+            .read_appointments(&AppointmentFilter {
+                series: Some(series_id.clone()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[0].series, Some(series_id));
+
+        let read = mock_db.read_series(&created.id.id.to_raw()).await.unwrap();
+        assert_eq!(read, created);
+    }
+
+    #[tokio::test]
+    async fn test_create_series_conflict() {
+        let mock_db = mock_db().await;
+        let (series, appointments) = weekly_series(&mock_db, 3).await;
+
+        // The doctor is booked at the time of the second occurrence
+        mock_db
+            .create_appointment(
+                Appointment {
+                    start_time: "2024-03-11T10:15:00".to_string(),
+                    appointment_type: AppointmentType::new("quick_checkup"),
+                    patient_id: series.patient_id.clone(),
+                    doctor: series.doctor.clone(),
+                    room: series.room.clone(),
+                    staff: vec![],
+                    devices: vec![],
+                }
+                .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let result = mock_db.create_series(series, appointments).await;
+        assert!(matches!(result, Err(DatabaseError::AppointmentConflict)));
+
+        // Neither the series nor any of its appointments was created
+        let appointments = mock_db
+            .read_appointments(&AppointmentFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(appointments.len(), 1);
+        let conn = mock_db.get_connection().await.unwrap();
+        let series: Vec<AppointmentSeriesRecord> = conn.select("appointment_series").await.unwrap();
+        assert!(series.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_update_series_status() {
+        let mock_db = mock_db().await;
+        let (series, appointments) = weekly_series(&mock_db, 4).await;
+        let (series, booked) = mock_db.create_series(series, appointments).await.unwrap();
+        let series = SeriesRecordId::from(series.id);
+
+        // The first one already took place
+        for status in [
+            AppointmentStatus::CheckedIn,
+            AppointmentStatus::InProgress,
+            AppointmentStatus::Completed,
+        ] {
+            mock_db
+                .update_appointment_status(
+                    &booked[0].id.id.to_raw(),
+                    StatusChange {
+                        status,
+                        changed_at: time("2024-03-04T10:00:00"),
+                        reason: None,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+
+        let cancel = StatusChange {
+            status: AppointmentStatus::Cancelled,
+            changed_at: time("2024-03-05T09:00:00"),
+            reason: Some("Moving away".to_string()),
+        };
+        let cancelled = mock_db
+            .update_series_status(&series, Some(booked[2].start_time), cancel.clone())
+            .await
+            .unwrap();
+        let ids: Vec<_> = cancelled
+            .iter()
+            .map(|appointment| &appointment.id)
+            .collect();
+        assert_eq!(ids, [&booked[2].id, &booked[3].id]);
+        assert_eq!(cancelled[0].status_changes[0], cancel);
+
+        // The whole series only leaves the completed one out
+        let cancelled = mock_db
+            .update_series_status(&series, None, cancel)
+            .await
+            .unwrap();
+        let ids: Vec<_> = cancelled
+            .iter()
+            .map(|appointment| &appointment.id)
+            .collect();
+        assert_eq!(ids, [&booked[1].id]);
+    }
+}
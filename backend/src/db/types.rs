@@ -1,19 +1,36 @@
-use std::fmt;
+use std::str::FromStr;
 
-use chrono::{Duration, NaiveDateTime};
-use serde::{
-    de::{self, Visitor},
-    Deserialize, Deserializer, Serialize,
-};
+use chrono::{Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use surrealdb::sql::Thing;
 use thiserror::Error;
 
+use crate::types::MAX_PAGE_LIMIT;
+
 #[derive(Debug, Error)]
 pub enum DatabaseError {
     #[error("Database connection lost")]
     ConnectionLost,
+    #[error("Database connection is being re-established")]
+    Reconnecting,
+    #[error("Database didn't answer in time")]
+    Timeout,
     #[error("No entry found")]
     NothingFound,
+    #[error("Appointment overlaps with another appointment")]
+    AppointmentConflict,
+    #[error("The cursor doesn't point to an existing entry")]
+    InvalidCursor,
+    #[error("{0} still has appointments")]
+    HasAppointments(&'static str),
+    #[error("The status of the appointment doesn't allow that change")]
+    InvalidStatusChange,
+    #[error("Missing database credential: {0} needs to be configured")]
+    MissingCredential(&'static str),
+    #[error("Database rejected the {mode} login: {reason}")]
+    AuthenticationFailed { mode: String, reason: String },
+    #[error("Migration {version} failed: {reason}")]
+    Migration { version: u32, reason: String },
     #[error("SurrealDB error: {0}")]
     SurrealDBError(#[from] surrealdb::Error),
     #[error("Chrono parse error: {0}")]
@@ -23,6 +40,25 @@ pub enum DatabaseError {
     Other(String),
 }
 
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Disconnected = 0,
+    Connected = 1,
+    // The connection dropped and a new one is being established
+    Reconnecting = 2,
+}
+
+impl From<u8> for ConnectionState {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => ConnectionState::Connected,
+            2 => ConnectionState::Reconnecting,
+            _ => ConnectionState::Disconnected,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Patient {
     pub name: String,
@@ -38,183 +74,1316 @@ pub struct PatientRecord {
     pub insurance_number: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Doctor {
+    pub name: String,
+    pub specialty: String,
+    pub phone_number: Option<String>,
+    pub email: Option<String>,
+    // Inactive doctors keep their appointments, but can't be booked anymore
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DoctorRecord {
+    pub id: Thing,
+    pub name: String,
+    pub specialty: String,
+    pub phone_number: Option<String>,
+    pub email: Option<String>,
+    pub active: bool,
+}
+
+// Criteria doctors have to match, every criterion that is set has to apply
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct DoctorFilter {
+    pub specialty: Option<String>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomType {
+    Consultation,
+    Examination,
+    OperatingRoom,
+}
+
+impl RoomType {
+    // The name it's stored with
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoomType::Consultation => "consultation",
+            RoomType::Examination => "examination",
+            RoomType::OperatingRoom => "operating_room",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Room {
+    pub name: String,
+    pub room_type: RoomType,
+    // Free-form tags like "x_ray" or "ecg"
+    #[serde(default)]
+    pub equipment: Vec<String>,
+    // Time to keep the room free before and after every appointment in it, like for cleaning it
+    #[serde(default)]
+    pub buffer_before_minutes: u32,
+    #[serde(default)]
+    pub buffer_after_minutes: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RoomRecord {
+    pub id: Thing,
+    pub name: String,
+    pub room_type: RoomType,
+    pub equipment: Vec<String>,
+    pub buffer_before_minutes: u32,
+    pub buffer_after_minutes: u32,
+}
+
+// Criteria rooms have to match, every criterion that is set has to apply
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct RoomFilter {
+    pub room_type: Option<RoomType>,
+    // Rooms tagged with this equipment
+    pub equipment: Option<String>,
+}
+
+// Mobile equipment that is booked along with a room, like an ultrasound unit. Unlike the equipment of a room, a
+// device can only be in one appointment at a time.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Device {
+    pub name: String,
+    // A free-form type like "ultrasound"
+    pub device_type: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DeviceRecord {
+    pub id: Thing,
+    pub name: String,
+    pub device_type: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct DeviceFilter {
+    pub device_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl From<chrono::Weekday> for Weekday {
+    fn from(weekday: chrono::Weekday) -> Self {
+        match weekday {
+            chrono::Weekday::Mon => Weekday::Monday,
+            chrono::Weekday::Tue => Weekday::Tuesday,
+            chrono::Weekday::Wed => Weekday::Wednesday,
+            chrono::Weekday::Thu => Weekday::Thursday,
+            chrono::Weekday::Fri => Weekday::Friday,
+            chrono::Weekday::Sat => Weekday::Saturday,
+            chrono::Weekday::Sun => Weekday::Sunday,
+        }
+    }
+}
+
+// A span of time a doctor works on every given weekday
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Shift {
+    pub weekday: Weekday,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+}
+
+// The weekly working hours of a doctor. A doctor without any shifts works whenever the clinic is open.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Schedule {
+    pub shifts: Vec<Shift>,
+}
+
+impl Schedule {
+    // Shifts have to end after they start, and shifts on the same weekday can't overlap
+    pub fn validate(&self) -> Result<(), String> {
+        for shift in &self.shifts {
+            if shift.start_time >= shift.end_time {
+                return Err(format!(
+                    "The shift on {:?} starting at {} has to end after it starts",
+                    shift.weekday, shift.start_time
+                ));
+            }
+        }
+
+        let mut shifts: Vec<&Shift> = self.shifts.iter().collect();
+        shifts.sort_by_key(|shift| (shift.weekday, shift.start_time));
+        for pair in shifts.windows(2) {
+            if pair[0].weekday == pair[1].weekday && pair[1].start_time < pair[0].end_time {
+                return Err(format!(
+                    "The shifts on {:?} starting at {} and {} overlap",
+                    pair[0].weekday, pair[0].start_time, pair[1].start_time
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Whether the doctor works the whole time from start to end, without a gap between shifts
+    pub fn is_working(&self, start_time: NaiveDateTime, end_time: NaiveDateTime) -> bool {
+        if self.shifts.is_empty() {
+            return true;
+        }
+
+        start_time.date() == end_time.date()
+            && self.is_working_on(
+                Weekday::from(start_time.weekday()),
+                start_time.time(),
+                end_time.time(),
+            )
+    }
+
+    // Whether the doctor works from start to end on every given weekday
+    pub fn is_working_on(
+        &self,
+        weekday: Weekday,
+        start_time: NaiveTime,
+        end_time: NaiveTime,
+    ) -> bool {
+        self.shifts.is_empty()
+            || self.shifts.iter().any(|shift| {
+                shift.weekday == weekday
+                    && shift.start_time <= start_time
+                    && end_time <= shift.end_time
+            })
+    }
+}
+
+// A day the clinic is closed on, like a public holiday
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Closure {
+    pub date: NaiveDate,
+    pub name: String,
+}
+
+// Opening hours that differ from the configured ones on a single date
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OpeningOverride {
+    pub date: NaiveDate,
+    pub opening_time: NaiveTime,
+    pub closing_time: NaiveTime,
+}
+
+// Time a doctor can't be booked, like a sick leave or a vacation, from `start_date` to `end_date`. The first and last
+// day are partial ones if `start_time` or `end_time` is set: the doctor leaves at `start_time` on the first day and
+// is back at `end_time` on the last one.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Absence {
+    pub doctor: DoctorRecordId,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+    pub reason: String,
+}
+
+impl Absence {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.reason.trim().is_empty() {
+            return Err("The absence needs a reason".to_string());
+        }
+        if self.start() >= self.end() {
+            return Err(format!(
+                "The absence can't end at {} before it starts at {}",
+                self.end(),
+                self.start()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn start(&self) -> NaiveDateTime {
+        self.start_date
+            .and_time(self.start_time.unwrap_or(NaiveTime::MIN))
+    }
+
+    pub fn end(&self) -> NaiveDateTime {
+        match self.end_time {
+            Some(end_time) => self.end_date.and_time(end_time),
+            None => (self.end_date + Duration::days(1)).and_time(NaiveTime::MIN),
+        }
+    }
+
+    // Whether the doctor is away at any time between start and end
+    pub fn overlaps(&self, start_time: NaiveDateTime, end_time: NaiveDateTime) -> bool {
+        start_time < self.end() && end_time > self.start()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AbsenceRecord {
+    pub id: Thing,
+    pub doctor: DoctorRecordId,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+    pub reason: String,
+}
+
+impl From<AbsenceRecord> for Absence {
+    fn from(record: AbsenceRecord) -> Self {
+        Absence {
+            doctor: record.doctor,
+            start_date: record.start_date,
+            end_date: record.end_date,
+            start_time: record.start_time,
+            end_time: record.end_time,
+            reason: record.reason,
+        }
+    }
+}
+
+// Absences of the doctor, or of every doctor, that last into the dates between from and to
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct AbsenceFilter {
+    pub doctor: Option<DoctorRecordId>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+// Dates between from and to, both included. Either end can be left open.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Appointment {
     pub start_time: String,
     pub appointment_type: AppointmentType,
     pub patient_id: PatientRecordId,
-    pub doctor: u32,
-    pub room_nr: u32,
+    pub doctor: DoctorRecordId,
+    pub room: RoomRecordId,
+    // Further staff taking part besides the doctor, like an anaesthetist or a nurse
+    #[serde(default)]
+    pub staff: Vec<DoctorRecordId>,
+    // Devices brought into the room for the appointment
+    #[serde(default)]
+    pub devices: Vec<DeviceRecordId>,
 }
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppointmentWithTime {
     pub start_time: NaiveDateTime,
     pub end_time: NaiveDateTime,
+    pub blocked: BlockedTime,
     pub appointment_type: AppointmentType,
     pub patient_id: PatientRecordId,
-    pub doctor: u32,
-    pub room_nr: u32,
+    pub doctor: DoctorRecordId,
+    pub room: RoomRecordId,
+    pub staff: Vec<DoctorRecordId>,
+    pub devices: Vec<DeviceRecordId>,
+    pub series: Option<SeriesRecordId>,
 }
 
 impl Appointment {
-    pub fn into_appointment_with_time(self) -> Result<AppointmentWithTime, chrono::ParseError> {
+    // The duration and buffers are the ones of the appointment type and room
+    pub fn into_appointment_with_time(
+        self,
+        duration: Duration,
+        buffers: &Buffers,
+    ) -> Result<AppointmentWithTime, chrono::ParseError> {
         let start_time = NaiveDateTime::parse_from_str(&self.start_time, "%Y-%m-%dT%H:%M:%S")?;
-        let end_time = start_time + self.appointment_type.duration();
+        let end_time = start_time + duration;
         Ok(AppointmentWithTime {
             start_time,
             end_time,
+            blocked: buffers.blocked_time(start_time, end_time),
             appointment_type: self.appointment_type,
             patient_id: self.patient_id,
             doctor: self.doctor,
-            room_nr: self.room_nr,
+            room: self.room,
+            staff: self.staff,
+            devices: self.devices,
+            series: None,
         })
     }
 }
 
+// Appointments start out scheduled. Completed, cancelled and no-show appointments can't change anymore, and only
+// scheduled ones can be moved.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AppointmentStatus {
+    #[default]
+    Scheduled,
+    CheckedIn,
+    InProgress,
+    Completed,
+    NoShow,
+    Cancelled,
+}
+
+impl AppointmentStatus {
+    // The name it's stored with
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AppointmentStatus::Scheduled => "scheduled",
+            AppointmentStatus::CheckedIn => "checked_in",
+            AppointmentStatus::InProgress => "in_progress",
+            AppointmentStatus::Completed => "completed",
+            AppointmentStatus::NoShow => "no_show",
+            AppointmentStatus::Cancelled => "cancelled",
+        }
+    }
+
+    // The statuses an appointment can change to the given one from
+    pub fn previous(&self) -> &'static [AppointmentStatus] {
+        match self {
+            AppointmentStatus::Scheduled => &[],
+            AppointmentStatus::CheckedIn | AppointmentStatus::NoShow => {
+                &[AppointmentStatus::Scheduled]
+            }
+            AppointmentStatus::InProgress => &[AppointmentStatus::CheckedIn],
+            AppointmentStatus::Completed => &[AppointmentStatus::InProgress],
+            AppointmentStatus::Cancelled => {
+                &[AppointmentStatus::Scheduled, AppointmentStatus::CheckedIn]
+            }
+        }
+    }
+
+    pub fn can_change_to(&self, status: AppointmentStatus) -> bool {
+        status.previous().contains(self)
+    }
+
+    // Cancelled and no-show appointments stay in the history, but their doctor and room can be booked again
+    pub fn blocks_slot(&self) -> bool {
+        !matches!(
+            self,
+            AppointmentStatus::Cancelled | AppointmentStatus::NoShow
+        )
+    }
+}
+
+// When an appointment changed to the status, and why if a reason was given
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StatusChange {
+    pub status: AppointmentStatus,
+    pub changed_at: NaiveDateTime,
+    pub reason: Option<String>,
+}
+
+// Time kept free before and after an appointment. The buffers of its type keep its doctor and its room busy, like
+// for preparing a consultation, the ones of its room only the room.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Buffers {
+    pub doctor_before: Duration,
+    pub doctor_after: Duration,
+    pub room_before: Duration,
+    pub room_after: Duration,
+}
+
+impl Buffers {
+    // The room is cleaned while the doctor wraps up, so it's kept free for the longer of the two buffers
+    pub fn new(appointment_type: &AppointmentTypeRecord, room: &RoomRecord) -> Self {
+        let minutes = |minutes: u32| Duration::minutes(minutes.into());
+        let doctor_before = minutes(appointment_type.buffer_before_minutes);
+        let doctor_after = minutes(appointment_type.buffer_after_minutes);
+        Buffers {
+            doctor_before,
+            doctor_after,
+            room_before: doctor_before.max(minutes(room.buffer_before_minutes)),
+            room_after: doctor_after.max(minutes(room.buffer_after_minutes)),
+        }
+    }
+
+    pub fn blocked_time(&self, start_time: NaiveDateTime, end_time: NaiveDateTime) -> BlockedTime {
+        BlockedTime {
+            doctor_from: start_time - self.doctor_before,
+            doctor_until: end_time + self.doctor_after,
+            room_from: start_time - self.room_before,
+            room_until: end_time + self.room_after,
+        }
+    }
+}
+
+// The time an appointment keeps its doctor and its room busy, its buffers included. It's what counts when checking
+// for overlaps, while the start and end time stay the ones the patient sees. Like the end time, it's stored with the
+// appointment, so changed buffers only apply to appointments that are booked or moved afterwards. The staff of the
+// appointment is kept busy as long as the doctor, its devices as long as the room, where they're prepared and cleaned.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct BlockedTime {
+    pub doctor_from: NaiveDateTime,
+    pub doctor_until: NaiveDateTime,
+    pub room_from: NaiveDateTime,
+    pub room_until: NaiveDateTime,
+}
+
+impl BlockedTime {
+    pub fn overlaps_doctor(&self, other: &BlockedTime) -> bool {
+        self.doctor_from < other.doctor_until && self.doctor_until > other.doctor_from
+    }
+
+    pub fn overlaps_room(&self, other: &BlockedTime) -> bool {
+        self.room_from < other.room_until && self.room_until > other.room_from
+    }
+
+    // Every day the doctor or the room is blocked on. Buffers can push the blocked time across midnight, into the
+    // day before or after the appointment.
+    pub fn days(&self) -> Vec<NaiveDate> {
+        let first = self.doctor_from.min(self.room_from).date();
+        let until = self.doctor_until.max(self.room_until);
+        // The blocked time ends right before `until`
+        let last = (until - Duration::seconds(1)).date().max(first);
+        first.iter_days().take_while(|day| *day <= last).collect()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppointmentRecord {
     pub id: Thing,
     pub start_time: NaiveDateTime,
     pub end_time: NaiveDateTime,
+    pub blocked: BlockedTime,
     pub appointment_type: AppointmentType,
     pub patient_id: PatientRecordId,
-    pub doctor: u32,
-    pub room_nr: u32,
-}
-impl AppointmentRecord {
-    pub fn calculate_end_time(&self) -> NaiveDateTime {
-        self.start_time + self.appointment_type.duration()
-    }
+    pub doctor: DoctorRecordId,
+    pub room: RoomRecordId,
+    pub staff: Vec<DoctorRecordId>,
+    pub devices: Vec<DeviceRecordId>,
+    pub status: AppointmentStatus,
+    // Every status change, oldest first
+    pub status_changes: Vec<StatusChange>,
+    // The series the appointment is an occurrence of, if any
+    pub series: Option<SeriesRecordId>,
 }
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppointmentRecordWithPatient {
     pub id: Thing,
     pub start_time: NaiveDateTime,
     pub end_time: NaiveDateTime,
+    pub blocked: BlockedTime,
     pub appointment_type: AppointmentType,
+    // Fetched from the `patient_id` link
+    #[serde(alias = "patient_id")]
     pub patient: PatientRecord,
-    pub doctor: u32,
-    pub room_nr: u32,
+    // Fetched from the `doctor` link
+    pub doctor: DoctorRecord,
+    // Fetched from the `room` link
+    pub room: RoomRecord,
+    // Fetched from the `staff` and `devices` links
+    pub staff: Vec<DoctorRecord>,
+    pub devices: Vec<DeviceRecord>,
+    pub status: AppointmentStatus,
+    pub status_changes: Vec<StatusChange>,
+    pub series: Option<SeriesRecordId>,
 }
 impl AppointmentRecordWithPatient {
-    pub fn from_appointment_record(
-        appointment_record: AppointmentRecord,
-        patient_record: PatientRecord,
-    ) -> Self {
-        AppointmentRecordWithPatient {
-            id: appointment_record.id,
-            start_time: appointment_record.start_time,
-            end_time: appointment_record.end_time,
-            appointment_type: appointment_record.appointment_type,
-            patient: patient_record,
-            doctor: appointment_record.doctor,
-            room_nr: appointment_record.room_nr,
-        }
-    }
     pub fn into_appointment_record(self) -> AppointmentRecord {
         AppointmentRecord {
             id: self.id,
             start_time: self.start_time,
             end_time: self.end_time,
+            blocked: self.blocked,
             appointment_type: self.appointment_type,
-            patient_id: PatientRecordId::new(&self.patient.id.id.to_raw()),
-            doctor: self.doctor,
-            room_nr: self.room_nr,
+            patient_id: PatientRecordId::from(self.patient.id),
+            doctor: DoctorRecordId::from(self.doctor.id),
+            room: RoomRecordId::from(self.room.id),
+            staff: self
+                .staff
+                .into_iter()
+                .map(|member| DoctorRecordId::from(member.id))
+                .collect(),
+            devices: self
+                .devices
+                .into_iter()
+                .map(|device| DeviceRecordId::from(device.id))
+                .collect(),
+            status: self.status,
+            status_changes: self.status_changes,
+            series: self.series,
         }
     }
 }
-#[derive(Debug, Deserialize)]
-#[serde(tag = "filter", content = "value", rename_all = "snake_case")]
-pub enum AppointmentFilter {
-    Month(String),
-    Day(String),
-    PatientId(PatientRecordId),
-    Doctor(u32),
-    RoomNr(u32),
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+// The longest series that can be booked at once, two years of weekly appointments
+pub const MAX_OCCURRENCES: usize = 104;
+
+fn default_interval() -> u32 {
+    1
+}
+
+// When a series repeats, in the style of an RFC 5545 RRULE: every `interval` days, weeks or months from the first
+// occurrence, until `count` occurrences took place or up to the date `until`. The dates in `exceptions` are left
+// out like an EXDATE, but still count towards `count`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    #[serde(default = "default_interval")]
+    pub interval: u32,
+    pub count: Option<u32>,
+    pub until: Option<NaiveDate>,
+    #[serde(default)]
+    pub exceptions: Vec<NaiveDate>,
 }
 
-impl AppointmentFilter {
-    pub fn from_filter_request(filter: &str, value: &str) -> Result<Self, String> {
-        match filter {
-            "month" => Ok(AppointmentFilter::Month(value.to_string())),
-            "day" => Ok(AppointmentFilter::Day(value.to_string())),
-            "patient_id" => Ok(AppointmentFilter::PatientId(PatientRecordId::new(value))),
-            "doctor" => {
-                let doctor = value.parse::<u32>().map_err(|e| e.to_string())?;
-                Ok(AppointmentFilter::Doctor(doctor))
+impl RecurrenceRule {
+    pub fn validate(&self, start_time: NaiveDateTime) -> Result<(), String> {
+        if self.interval == 0 {
+            return Err("The interval needs to be at least 1".to_string());
+        }
+        match (self.count, self.until) {
+            (Some(_), Some(_)) => {
+                return Err("A series ends either after a count or on a date, not both".to_string())
+            }
+            (None, None) => return Err("A series needs a count or an end date".to_string()),
+            (Some(0), _) => return Err("A series needs at least one occurrence".to_string()),
+            (_, Some(until)) if until < start_time.date() => {
+                return Err(format!(
+                    "The series can't end on {} before it starts on {}",
+                    until,
+                    start_time.date()
+                ))
+            }
+            _ => {}
+        }
+
+        let occurrences = self.occurrences(start_time).len();
+        if occurrences > MAX_OCCURRENCES {
+            return Err(format!(
+                "A series can have at most {} occurrences",
+                MAX_OCCURRENCES
+            ));
+        }
+        if occurrences == 0 {
+            return Err("Every occurrence of the series is an exception".to_string());
+        }
+        Ok(())
+    }
+
+    // The start times of the occurrences, the first one being `start_time`. Stops one past `MAX_OCCURRENCES`, so
+    // `validate` can tell a series is too long.
+    pub fn occurrences(&self, start_time: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let start = start_time.date();
+        let mut occurrences = Vec::new();
+        let mut counted = 0;
+
+        for n in 0.. {
+            let step = n * self.interval;
+            let date = match self.frequency {
+                Frequency::Daily => start.checked_add_days(Days::new(step.into())),
+                Frequency::Weekly => start.checked_add_days(Days::new(u64::from(step) * 7)),
+                Frequency::Monthly => start.checked_add_months(Months::new(step)),
+            };
+            let Some(date) = date else {
+                break;
+            };
+            // Months that don't have the day, like a 31st, are skipped instead of moving to their last day
+            if self.frequency == Frequency::Monthly && date.day() != start.day() {
+                continue;
+            }
+            if self.until.is_some_and(|until| date > until)
+                || self.count.is_some_and(|count| counted >= count)
+                || occurrences.len() > MAX_OCCURRENCES
+            {
+                break;
             }
-            "room_nr" => {
-                let room_nr = value.parse::<u32>().map_err(|e| e.to_string())?;
-                Ok(AppointmentFilter::RoomNr(room_nr))
+
+            counted += 1;
+            if !self.exceptions.contains(&date) {
+                occurrences.push(date.and_time(start_time.time()));
             }
-            _ => Err(format!("Unknown filter type: {}", filter)),
         }
+
+        occurrences
     }
 }
-#[derive(Debug, Serialize, Clone, PartialEq)]
-pub struct PatientRecordId(String);
 
-impl PatientRecordId {
-    #[allow(dead_code)]
-    pub fn new(unique_id: &str) -> Self {
-        PatientRecordId(format!("patient:{}", unique_id))
+// Appointments that repeat by a rule, starting with the one at `start_time`. The occurrences are regular
+// appointments that link to the series, so they can be changed one by one. The series keeps the rule and the details
+// it was created with.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AppointmentSeries {
+    pub start_time: NaiveDateTime,
+    pub appointment_type: AppointmentType,
+    pub patient_id: PatientRecordId,
+    pub doctor: DoctorRecordId,
+    pub room: RoomRecordId,
+    #[serde(default)]
+    pub staff: Vec<DoctorRecordId>,
+    #[serde(default)]
+    pub devices: Vec<DeviceRecordId>,
+    pub rule: RecurrenceRule,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AppointmentSeriesRecord {
+    pub id: Thing,
+    pub start_time: NaiveDateTime,
+    pub appointment_type: AppointmentType,
+    pub patient_id: PatientRecordId,
+    pub doctor: DoctorRecordId,
+    pub room: RoomRecordId,
+    pub staff: Vec<DoctorRecordId>,
+    pub devices: Vec<DeviceRecordId>,
+    pub rule: RecurrenceRule,
+}
+// What a patient on the waitlist is waiting for: an appointment of the type, with the doctor if one is set, on any
+// date from `start_date` to `end_date`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WaitlistEntry {
+    pub patient_id: PatientRecordId,
+    pub appointment_type: AppointmentType,
+    // Any doctor if not set
+    pub doctor: Option<DoctorRecordId>,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+}
+
+impl WaitlistEntry {
+    pub fn validate(&self, today: NaiveDate) -> Result<(), String> {
+        if self.end_date < self.start_date {
+            return Err(format!(
+                "The window can't end on {} before it starts on {}",
+                self.end_date, self.start_date
+            ));
+        }
+        if self.end_date < today {
+            return Err(format!("The window ended on {} already", self.end_date));
+        }
+        Ok(())
     }
+}
 
-    #[allow(dead_code)]
-    pub fn as_str(&self) -> &str {
-        &self.0
+// Entries wait until a slot is offered to them, and go back to waiting if the offer is declined
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitlistStatus {
+    #[default]
+    Waiting,
+    Offered,
+    Booked,
+}
+
+impl WaitlistStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WaitlistStatus::Waiting => "waiting",
+            WaitlistStatus::Offered => "offered",
+            WaitlistStatus::Booked => "booked",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WaitlistEntryRecord {
+    pub id: Thing,
+    pub patient_id: PatientRecordId,
+    pub appointment_type: AppointmentType,
+    pub doctor: Option<DoctorRecordId>,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub status: WaitlistStatus,
+    // Entries are offered slots in the order they were registered in
+    pub created_at: NaiveDateTime,
+}
+
+impl WaitlistEntryRecord {
+    // Whether an appointment on the date with the doctor is one the patient is waiting for
+    pub fn accepts(&self, date: NaiveDate, doctor: &DoctorRecordId) -> bool {
+        self.start_date <= date
+            && date <= self.end_date
+            && self.doctor.as_ref().is_none_or(|wanted| wanted == doctor)
     }
+}
 
-    pub fn get_unique_id(&self) -> &str {
-        self.0.split(':').nth(1).unwrap()
+impl From<WaitlistEntryRecord> for WaitlistEntry {
+    fn from(record: WaitlistEntryRecord) -> Self {
+        WaitlistEntry {
+            patient_id: record.patient_id,
+            appointment_type: record.appointment_type,
+            doctor: record.doctor,
+            start_date: record.start_date,
+            end_date: record.end_date,
+        }
     }
 }
-impl<'de> Deserialize<'de> for PatientRecordId {
+
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct WaitlistFilter {
+    #[serde(default, deserialize_with = "deserialize_link_filter")]
+    pub patient_id: Option<PatientRecordId>,
+    pub status: Option<WaitlistStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OfferStatus {
+    #[default]
+    Pending,
+    Accepted,
+    Declined,
+    // The slot started before the offer was answered
+    Expired,
+}
+
+impl OfferStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OfferStatus::Pending => "pending",
+            OfferStatus::Accepted => "accepted",
+            OfferStatus::Declined => "declined",
+            OfferStatus::Expired => "expired",
+        }
+    }
+}
+
+// A freed timeframe offered to a waitlist entry. It isn't reserved, so it can still be booked by anyone else until
+// the offer is accepted.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WaitlistOffer {
+    pub entry: WaitlistRecordId,
+    pub patient_id: PatientRecordId,
+    pub appointment_type: AppointmentType,
+    pub start_time: NaiveDateTime,
+    pub end_time: NaiveDateTime,
+    pub doctor: DoctorRecordId,
+    pub room: RoomRecordId,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WaitlistOfferRecord {
+    pub id: Thing,
+    pub entry: WaitlistRecordId,
+    pub patient_id: PatientRecordId,
+    pub appointment_type: AppointmentType,
+    pub start_time: NaiveDateTime,
+    pub end_time: NaiveDateTime,
+    pub doctor: DoctorRecordId,
+    pub room: RoomRecordId,
+    pub status: OfferStatus,
+    pub created_at: NaiveDateTime,
+    // The appointment that was booked once the offer is accepted
+    pub appointment: Option<Thing>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct OfferFilter {
+    #[serde(default, deserialize_with = "deserialize_link_filter")]
+    pub patient_id: Option<PatientRecordId>,
+    #[serde(default, deserialize_with = "deserialize_link_filter")]
+    pub entry: Option<WaitlistRecordId>,
+    pub status: Option<OfferStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    WaitlistOffer,
+}
+
+// A message for a patient, written in the same transaction as the change it's about. Whatever delivers the messages
+// reads the unsent ones and marks them as sent.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NotificationRecord {
+    pub id: Thing,
+    pub kind: NotificationKind,
+    pub patient_id: PatientRecordId,
+    // The record the notification is about, like the offer
+    pub subject: Thing,
+    pub message: String,
+    pub created_at: NaiveDateTime,
+    pub sent_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct NotificationFilter {
+    #[serde(default, deserialize_with = "deserialize_link_filter")]
+    pub patient_id: Option<PatientRecordId>,
+    // Only the ones that were or weren't sent yet
+    pub sent: Option<bool>,
+}
+
+// Criteria appointments have to match, every criterion that is set has to apply
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct AppointmentFilter {
+    pub day: Option<NaiveDate>,
+    pub month: Option<YearMonth>,
+    // Appointments starting at or after this time, or on or after this day
+    #[serde(default, deserialize_with = "deserialize_from_filter")]
+    pub from: Option<NaiveDateTime>,
+    // Appointments starting before this time, or on or before this day
+    #[serde(default, deserialize_with = "deserialize_to_filter")]
+    pub to: Option<NaiveDateTime>,
+    #[serde(default, deserialize_with = "deserialize_link_filter")]
+    pub patient_id: Option<PatientRecordId>,
+    #[serde(default, deserialize_with = "deserialize_link_filter")]
+    pub doctor: Option<DoctorRecordId>,
+    // Appointments the doctor takes part in, either as their doctor or among their staff
+    #[serde(default, deserialize_with = "deserialize_link_filter")]
+    pub participant: Option<DoctorRecordId>,
+    #[serde(default, deserialize_with = "deserialize_link_filter")]
+    pub room: Option<RoomRecordId>,
+    #[serde(default, deserialize_with = "deserialize_link_filter")]
+    pub device: Option<DeviceRecordId>,
+    pub appointment_type: Option<AppointmentType>,
+    pub status: Option<AppointmentStatus>,
+    #[serde(default, deserialize_with = "deserialize_link_filter")]
+    pub series: Option<SeriesRecordId>,
+}
+
+// Accepts the unique ID on its own as well as '{table}:{unique_id}'
+fn deserialize_link_filter<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: RecordLink,
+{
+    let value = String::deserialize(deserializer)?;
+    let thing = match value.contains(':') {
+        true => Thing::from_str(&value)
+            .ok()
+            .filter(|thing| thing.tb == T::TABLE),
+        false => Some(Thing::from((T::TABLE, value.as_str()))),
+    };
+    match thing {
+        Some(thing) => Ok(Some(T::from(thing))),
+        None => Err(de::Error::custom(format!(
+            "ID must be formatted as '{}:{{unique_id}}', got '{}'",
+            T::TABLE,
+            value
+        ))),
+    }
+}
+
+// Accepts a day formatted as 'YYYY-MM-DD' as well as a time. A day stands for its start, or for the start of the next
+// day with `next_day`, so that a range includes the day it ends on.
+fn parse_time_filter(value: &str, next_day: bool) -> Result<NaiveDateTime, String> {
+    if let Ok(time) = value.parse::<NaiveDateTime>() {
+        return Ok(time);
+    }
+    value
+        .parse::<NaiveDate>()
+        .ok()
+        .and_then(|day| match next_day {
+            true => day.succ_opt(),
+            false => Some(day),
+        })
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .ok_or(format!(
+            "Time must be formatted as 'YYYY-MM-DD' or 'YYYY-MM-DDTHH:MM:SS', got '{}'",
+            value
+        ))
+}
+
+fn deserialize_from_filter<'de, D>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    parse_time_filter(&value, false)
+        .map(Some)
+        .map_err(de::Error::custom)
+}
+
+fn deserialize_to_filter<'de, D>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    parse_time_filter(&value, true)
+        .map(Some)
+        .map_err(de::Error::custom)
+}
+
+// A month formatted as 'YYYY-MM'
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct YearMonth(NaiveDate);
+
+impl YearMonth {
+    pub fn first_day(&self) -> NaiveDate {
+        self.0
+    }
+
+    pub fn first_day_of_next_month(&self) -> NaiveDate {
+        self.0 + Months::new(1)
+    }
+}
+
+impl FromStr for YearMonth {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d")
+            .map(YearMonth)
+            .map_err(|_| format!("Month must be formatted as 'YYYY-MM', got '{}'", value))
+    }
+}
+
+impl<'de> Deserialize<'de> for YearMonth {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        struct PatientRecordIdVisitor;
+        let value = String::deserialize(deserializer)?;
+        YearMonth::from_str(&value).map_err(de::Error::custom)
+    }
+}
+
+// A native link to a record of one table. The API accepts it formatted as '{table}:{unique_id}', the database stores
+// it as a record link, so appointments can resolve what they link to with a FETCH instead of a query per appointment.
+pub trait RecordLink: From<Thing> {
+    const TABLE: &'static str;
+}
+
+macro_rules! record_link {
+    ($name:ident, $table:literal) => {
+        #[derive(Debug, Serialize, Clone, PartialEq)]
+        #[serde(transparent)]
+        pub struct $name(Thing);
+
+        impl $name {
+            pub fn new(unique_id: &str) -> Self {
+                $name(Thing::from(($table, unique_id)))
+            }
+
+            pub fn parse(value: &str) -> Result<Self, String> {
+                match Thing::from_str(value) {
+                    Ok(thing) if thing.tb == $table => Ok($name(thing)),
+                    _ => Err(format!(
+                        concat!(
+                            "ID must be formatted as '",
+                            $table,
+                            ":{{unique_id}}', got '{}'"
+                        ),
+                        value
+                    )),
+                }
+            }
+
+            pub fn as_thing(&self) -> &Thing {
+                &self.0
+            }
+
+            pub fn get_unique_id(&self) -> String {
+                self.0.id.to_raw()
+            }
+        }
 
-        impl<'de> Visitor<'de> for PatientRecordIdVisitor {
-            type Value = PatientRecordId;
+        impl RecordLink for $name {
+            const TABLE: &'static str = $table;
+        }
 
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("a string formatted as 'patient:{unique_id}'")
+        impl From<Thing> for $name {
+            fn from(thing: Thing) -> Self {
+                $name(thing)
             }
+        }
 
-            fn visit_str<E>(self, value: &str) -> Result<PatientRecordId, E>
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
             where
-                E: de::Error,
+                D: Deserializer<'de>,
             {
-                if value.starts_with("patient:") {
-                    Ok(PatientRecordId(value.to_string()))
-                } else {
-                    Err(de::Error::custom(
-                        "ID must be formatted as 'patient:{unique_id}'",
-                    ))
+                // Requests send the formatted string, the database returns the record link itself
+                #[derive(Deserialize)]
+                #[serde(untagged)]
+                enum Repr {
+                    Formatted(String),
+                    Link(Thing),
+                }
+
+                match Repr::deserialize(deserializer)? {
+                    Repr::Formatted(value) => $name::parse(&value).map_err(de::Error::custom),
+                    Repr::Link(thing) if thing.tb == $table => Ok($name(thing)),
+                    Repr::Link(thing) => Err(de::Error::custom(format!(
+                        concat!("expected a link to a ", $table, ", got '{}'"),
+                        thing
+                    ))),
                 }
             }
         }
+    };
+}
+
+record_link!(PatientRecordId, "patient");
+record_link!(DoctorRecordId, "doctor");
+record_link!(RoomRecordId, "room");
+record_link!(DeviceRecordId, "device");
+record_link!(SeriesRecordId, "appointment_series");
+record_link!(WaitlistRecordId, "waitlist");
+
+// Anything that can be paginated by cursor, which is the ID of the last entry of the previous page
+pub trait Record {
+    fn id(&self) -> &Thing;
+}
 
-        deserializer.deserialize_str(PatientRecordIdVisitor)
+impl Record for PatientRecord {
+    fn id(&self) -> &Thing {
+        &self.id
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+impl Record for DoctorRecord {
+    fn id(&self) -> &Thing {
+        &self.id
+    }
+}
+
+impl Record for RoomRecord {
+    fn id(&self) -> &Thing {
+        &self.id
+    }
+}
+
+impl Record for AppointmentRecordWithPatient {
+    fn id(&self) -> &Thing {
+        &self.id
+    }
+}
+
+pub trait SortKey {
+    // The SurrealQL expression that is sorted by
+    fn expression(&self) -> &'static str;
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AppointmentSort {
+    #[default]
+    StartTime,
+    Doctor,
+    Room,
+    PatientName,
+}
+
+impl SortKey for AppointmentSort {
+    fn expression(&self) -> &'static str {
+        match self {
+            AppointmentSort::StartTime => "start_time",
+            AppointmentSort::Doctor => "doctor.name",
+            AppointmentSort::Room => "room.name",
+            AppointmentSort::PatientName => "patient_id.name",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PatientSort {
+    #[default]
+    Name,
+    PhoneNumber,
+}
+
+impl SortKey for PatientSort {
+    fn expression(&self) -> &'static str {
+        match self {
+            PatientSort::Name => "name",
+            PatientSort::PhoneNumber => "phone_number",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
 #[serde(rename_all = "snake_case")]
-pub enum AppointmentType {
-    QuickCheckup,
-    ExtensiveCare,
-    Surgery,
+pub enum DoctorSort {
+    #[default]
+    Name,
+    Specialty,
 }
 
+impl SortKey for DoctorSort {
+    fn expression(&self) -> &'static str {
+        match self {
+            DoctorSort::Name => "name",
+            DoctorSort::Specialty => "specialty",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomSort {
+    #[default]
+    Name,
+    RoomType,
+}
+
+impl SortKey for RoomSort {
+    fn expression(&self) -> &'static str {
+        match self {
+            RoomSort::Name => "name",
+            RoomSort::RoomType => "room_type",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pagination<S> {
+    pub limit: u32,
+    pub offset: u32,
+    // Continue after this entry
+    pub cursor: Option<Thing>,
+    pub sort: S,
+    pub order: SortOrder,
+}
+
+impl<S> Pagination<S> {
+    // A single page with as many entries as a page can have, for lists that are always short like doctors or rooms
+    pub fn all(sort: S) -> Self {
+        Pagination {
+            limit: MAX_PAGE_LIMIT,
+            offset: 0,
+            cursor: None,
+            sort,
+            order: SortOrder::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    // Amount of entries across all pages
+    pub total: u64,
+    pub next_cursor: Option<String>,
+}
+
+// The ID of an appointment type, which is what appointments store. The types themselves are records in the
+// database, so clinics can add their own.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct AppointmentType(String);
+
 impl AppointmentType {
+    pub fn new(id: &str) -> Self {
+        AppointmentType(id.to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AppointmentTypeDefinition {
+    pub name: String,
+    pub duration_minutes: u32,
+    // Time to keep the doctor and the room free before and after the appointment, like for preparing it
+    #[serde(default)]
+    pub buffer_before_minutes: u32,
+    #[serde(default)]
+    pub buffer_after_minutes: u32,
+    // Hex colour like "#1e88e5" the type is shown in
+    pub colour: String,
+    // The kinds of rooms it can take place in
+    pub room_types: Vec<RoomType>,
+}
+
+impl AppointmentTypeDefinition {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("The appointment type needs a name".to_string());
+        }
+        if self.duration_minutes == 0 {
+            return Err(format!("{} needs a duration", self.name));
+        }
+        let is_colour = self.colour.len() == 7
+            && self.colour.starts_with('#')
+            && self.colour[1..]
+                .chars()
+                .all(|char| char.is_ascii_hexdigit());
+        if !is_colour {
+            return Err(format!("{} isn't a colour like #1e88e5", self.colour));
+        }
+        if self.room_types.is_empty() {
+            return Err(format!(
+                "{} needs at least one room type it can take place in",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+}
+
+// IDs are used in URLs and stored with every appointment, so they're kept to lowercase letters, digits and
+// underscores like "quick_checkup"
+pub fn validate_appointment_type_id(id: &str) -> Result<(), String> {
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|char| char.is_ascii_lowercase() || char.is_ascii_digit() || char == '_')
+    {
+        return Err(format!(
+            "{} isn't a valid ID, use lowercase letters, digits and underscores",
+            id
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AppointmentTypeRecord {
+    pub id: Thing,
+    pub name: String,
+    pub duration_minutes: u32,
+    pub buffer_before_minutes: u32,
+    pub buffer_after_minutes: u32,
+    pub colour: String,
+    pub room_types: Vec<RoomType>,
+}
+
+impl AppointmentTypeRecord {
+    pub fn appointment_type(&self) -> AppointmentType {
+        AppointmentType(self.id.id.to_raw())
+    }
+
     pub fn duration(&self) -> Duration {
-        match self {
-            AppointmentType::QuickCheckup => Duration::minutes(30),
-            AppointmentType::ExtensiveCare => Duration::hours(1),
-            AppointmentType::Surgery => Duration::hours(2),
+        Duration::minutes(self.duration_minutes.into())
+    }
+}
+
+impl From<AppointmentTypeRecord> for AppointmentTypeDefinition {
+    fn from(record: AppointmentTypeRecord) -> Self {
+        AppointmentTypeDefinition {
+            name: record.name,
+            duration_minutes: record.duration_minutes,
+            buffer_before_minutes: record.buffer_before_minutes,
+            buffer_after_minutes: record.buffer_after_minutes,
+            colour: record.colour,
+            room_types: record.room_types,
         }
     }
 }
@@ -234,28 +1403,339 @@ mod tests {
 
         let deserialized: Result<PatientRecordId, _> = serde_json::from_str(invalid_id);
         assert!(deserialized.is_err());
+
+        // Malformed IDs are rejected instead of panicking later on
+        for malformed_id in ["\"patient\"", "\"patient:\"", "\":12345\"", "\"\""] {
+            let deserialized: Result<PatientRecordId, _> = serde_json::from_str(malformed_id);
+            assert!(deserialized.is_err());
+        }
+
+        // Record links returned by the database
+        let link = serde_json::to_string(&Thing::from(("patient", "12345"))).unwrap();
+        let deserialized: PatientRecordId = serde_json::from_str(&link).unwrap();
+        assert_eq!(deserialized, PatientRecordId::new("12345"));
+        assert_eq!(deserialized.get_unique_id(), "12345");
+    }
+
+    #[test]
+    fn test_blocked_time_days() {
+        let time = |time: &str| NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M").unwrap();
+        let day = |day: &str| NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap();
+        let blocked = |doctor_from, doctor_until, room_from, room_until| BlockedTime {
+            doctor_from: time(doctor_from),
+            doctor_until: time(doctor_until),
+            room_from: time(room_from),
+            room_until: time(room_until),
+        };
+
+        let same_day = blocked(
+            "2023-10-02T10:00",
+            "2023-10-02T10:30",
+            "2023-10-02T09:50",
+            "2023-10-02T10:40",
+        );
+        assert_eq!(same_day.days(), vec![day("2023-10-02")]);
+
+        // Ending at midnight doesn't block the next day
+        let until_midnight = blocked(
+            "2023-10-02T23:00",
+            "2023-10-03T00:00",
+            "2023-10-02T23:00",
+            "2023-10-03T00:00",
+        );
+        assert_eq!(until_midnight.days(), vec![day("2023-10-02")]);
+
+        // The buffers of the room cross midnight on both sides
+        let across_midnight = blocked(
+            "2023-10-03T00:00",
+            "2023-10-03T23:50",
+            "2023-10-02T23:45",
+            "2023-10-04T00:15",
+        );
+        assert_eq!(
+            across_midnight.days(),
+            vec![day("2023-10-02"), day("2023-10-03"), day("2023-10-04")]
+        );
+    }
+
+    #[test]
+    fn test_time_filters() {
+        let filter: AppointmentFilter =
+            serde_json::from_str(r#"{ "from": "2024-03-01", "to": "2024-03-31" }"#).unwrap();
+        assert_eq!(filter.from, Some("2024-03-01T00:00:00".parse().unwrap()));
+        assert_eq!(filter.to, Some("2024-04-01T00:00:00".parse().unwrap()));
+
+        // Times are taken as they are
+        let filter: AppointmentFilter = serde_json::from_str(
+            r#"{ "from": "2024-03-01T10:00:00", "to": "2024-03-31T12:00:00" }"#,
+        )
+        .unwrap();
+        assert_eq!(filter.from, Some("2024-03-01T10:00:00".parse().unwrap()));
+        assert_eq!(filter.to, Some("2024-03-31T12:00:00".parse().unwrap()));
+
+        for malformed in ["2024-03", "01.03.2024", "2024-03-01 10:00"] {
+            let filter: Result<AppointmentFilter, _> =
+                serde_json::from_str(&format!(r#"{{ "from": "{}" }}"#, malformed));
+            assert!(filter.is_err(), "{}", malformed);
+        }
     }
 
     #[test]
     fn test_appointment_type_serialization() {
-        let quick_checkup = AppointmentType::QuickCheckup;
+        let quick_checkup = AppointmentType::new("quick_checkup");
         let serialized = serde_json::to_string(&quick_checkup).unwrap();
         assert_eq!(serialized, "\"quick_checkup\"");
 
         let deserialized: AppointmentType = serde_json::from_str(&serialized).unwrap();
-        assert_eq!(deserialized, AppointmentType::QuickCheckup);
+        assert_eq!(deserialized, quick_checkup);
+    }
+
+    #[test]
+    fn test_appointment_type_validation() {
+        let vaccination = AppointmentTypeDefinition {
+            name: "Vaccination".to_string(),
+            duration_minutes: 15,
+            buffer_before_minutes: 0,
+            buffer_after_minutes: 0,
+            colour: "#43A047".to_string(),
+            room_types: vec![RoomType::Consultation],
+        };
+        assert!(vaccination.validate().is_ok());
+
+        let invalid = [
+            AppointmentTypeDefinition {
+                name: " ".to_string(),
+                ..vaccination.clone()
+            },
+            AppointmentTypeDefinition {
+                duration_minutes: 0,
+                ..vaccination.clone()
+            },
+            AppointmentTypeDefinition {
+                colour: "green".to_string(),
+                ..vaccination.clone()
+            },
+            AppointmentTypeDefinition {
+                colour: "#43A04".to_string(),
+                ..vaccination.clone()
+            },
+            AppointmentTypeDefinition {
+                room_types: vec![],
+                ..vaccination.clone()
+            },
+        ];
+        for definition in invalid {
+            assert!(definition.validate().is_err(), "{:?}", definition);
+        }
+
+        assert!(validate_appointment_type_id("consultation_45").is_ok());
+        for id in ["", "Vaccination", "flu shot", "type:one"] {
+            assert!(validate_appointment_type_id(id).is_err(), "{}", id);
+        }
     }
 
     #[test]
-    fn test_appointment_type_duration() {
+    fn test_room_type_serialization() {
+        for room_type in [
+            RoomType::Consultation,
+            RoomType::Examination,
+            RoomType::OperatingRoom,
+        ] {
+            let serialized = serde_json::to_string(&room_type).unwrap();
+            assert_eq!(serialized, format!("\"{}\"", room_type.as_str()));
+        }
+    }
+
+    fn shift(weekday: Weekday, start_time: &str, end_time: &str) -> Shift {
+        Shift {
+            weekday,
+            start_time: start_time.parse().unwrap(),
+            end_time: end_time.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_schedule_validation() {
+        let split_shift = Schedule {
+            shifts: vec![
+                shift(Weekday::Monday, "13:00:00", "17:00:00"),
+                shift(Weekday::Monday, "08:00:00", "12:00:00"),
+                shift(Weekday::Tuesday, "08:00:00", "17:00:00"),
+            ],
+        };
+        assert!(split_shift.validate().is_ok());
+
+        let overlapping = Schedule {
+            shifts: vec![
+                shift(Weekday::Monday, "08:00:00", "12:00:00"),
+                shift(Weekday::Monday, "11:00:00", "17:00:00"),
+            ],
+        };
+        assert!(overlapping.validate().is_err());
+
+        let backwards = Schedule {
+            shifts: vec![shift(Weekday::Friday, "12:00:00", "08:00:00")],
+        };
+        assert!(backwards.validate().is_err());
+    }
+
+    #[test]
+    fn test_schedule_is_working() {
+        let time = |time: &str| NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S").unwrap();
+
+        // Without shifts, only the opening hours of the clinic apply
+        assert!(Schedule::default()
+            .is_working(time("2024-03-04T07:00:00"), time("2024-03-04T08:00:00")));
+
+        // Monday with a split shift
+        let schedule = Schedule {
+            shifts: vec![
+                shift(Weekday::Monday, "08:00:00", "12:00:00"),
+                shift(Weekday::Monday, "14:00:00", "17:00:00"),
+            ],
+        };
+        let cases = [
+            ("2024-03-04T08:00:00", "2024-03-04T09:00:00", true),
+            ("2024-03-04T11:30:00", "2024-03-04T12:00:00", true),
+            ("2024-03-04T16:00:00", "2024-03-04T17:00:00", true),
+            // In between the shifts
+            ("2024-03-04T12:30:00", "2024-03-04T13:00:00", false),
+            // Across the gap between the shifts
+            ("2024-03-04T11:30:00", "2024-03-04T14:30:00", false),
+            // Tuesday isn't a working day
+            ("2024-03-05T08:00:00", "2024-03-05T09:00:00", false),
+        ];
+        for (start_time, end_time, expected) in cases {
+            assert_eq!(
+                schedule.is_working(time(start_time), time(end_time)),
+                expected,
+                "{} - {}",
+                start_time,
+                end_time
+            );
+        }
+    }
+
+    fn rule(frequency: Frequency, interval: u32) -> RecurrenceRule {
+        RecurrenceRule {
+            frequency,
+            interval,
+            count: None,
+            until: None,
+            exceptions: vec![],
+        }
+    }
+
+    #[test]
+    fn test_recurrence_rule_occurrences() {
+        let time = |time: &str| NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S").unwrap();
+        let date = |date: &str| date.parse::<NaiveDate>().unwrap();
+        let occurrences = |rule: RecurrenceRule, start_time: &str| -> Vec<String> {
+            rule.occurrences(time(start_time))
+                .iter()
+                .map(|occurrence| occurrence.to_string())
+                .collect()
+        };
+
+        // Every other week, three times, at the time of the first one
+        let biweekly = RecurrenceRule {
+            count: Some(3),
+            ..rule(Frequency::Weekly, 2)
+        };
         assert_eq!(
-            AppointmentType::QuickCheckup.duration(),
-            Duration::minutes(30)
+            occurrences(biweekly, "2024-03-04T10:30:00"),
+            [
+                "2024-03-04 10:30:00",
+                "2024-03-18 10:30:00",
+                "2024-04-01 10:30:00"
+            ]
         );
+
+        // Daily up to and including the end date, except for the exception, which still counts
+        let daily = RecurrenceRule {
+            until: Some(date("2024-03-07")),
+            exceptions: vec![date("2024-03-05")],
+            ..rule(Frequency::Daily, 1)
+        };
+        assert_eq!(
+            occurrences(daily.clone(), "2024-03-04T10:00:00"),
+            [
+                "2024-03-04 10:00:00",
+                "2024-03-06 10:00:00",
+                "2024-03-07 10:00:00"
+            ]
+        );
+        let counted = RecurrenceRule {
+            until: None,
+            count: Some(3),
+            ..daily
+        };
+        assert_eq!(occurrences(counted, "2024-03-04T10:00:00").len(), 2);
+
+        // Months without a 31st are skipped
+        let monthly = RecurrenceRule {
+            count: Some(3),
+            ..rule(Frequency::Monthly, 1)
+        };
         assert_eq!(
-            AppointmentType::ExtensiveCare.duration(),
-            Duration::hours(1)
+            occurrences(monthly, "2024-01-31T09:00:00"),
+            [
+                "2024-01-31 09:00:00",
+                "2024-03-31 09:00:00",
+                "2024-05-31 09:00:00"
+            ]
         );
-        assert_eq!(AppointmentType::Surgery.duration(), Duration::hours(2));
+    }
+
+    #[test]
+    fn test_recurrence_rule_validation() {
+        let start_time =
+            NaiveDateTime::parse_from_str("2024-03-04T10:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+        let date = |date: &str| date.parse::<NaiveDate>().unwrap();
+
+        let valid = RecurrenceRule {
+            count: Some(10),
+            ..rule(Frequency::Weekly, 1)
+        };
+        assert!(valid.validate(start_time).is_ok());
+
+        let invalid = [
+            // No end
+            rule(Frequency::Weekly, 1),
+            // Both ends
+            RecurrenceRule {
+                until: Some(date("2024-04-01")),
+                ..valid.clone()
+            },
+            RecurrenceRule {
+                interval: 0,
+                ..valid.clone()
+            },
+            RecurrenceRule {
+                count: Some(0),
+                ..valid.clone()
+            },
+            // Ends before it starts
+            RecurrenceRule {
+                count: None,
+                until: Some(date("2024-03-01")),
+                ..valid.clone()
+            },
+            // Too long
+            RecurrenceRule {
+                count: None,
+                until: Some(date("2030-01-01")),
+                ..valid.clone()
+            },
+            // Nothing left
+            RecurrenceRule {
+                count: Some(1),
+                exceptions: vec![date("2024-03-04")],
+                ..valid.clone()
+            },
+        ];
+        for rule in invalid {
+            assert!(rule.validate(start_time).is_err(), "{:?}", rule);
+        }
     }
 }
@@ -0,0 +1,801 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use surrealdb::sql::{Id, Thing, Value};
+
+use super::{
+    appointment_db::{
+        booking_error, is_conflict, lock_days, INVALID_STATUS_CHANGE, RESERVE_TIMEFRAME,
+    },
+    db::Database,
+    pagination::Bindings,
+    types::{
+        AppointmentWithTime, DatabaseError, DoctorRecordId, OfferFilter, OfferStatus,
+        WaitlistEntry, WaitlistEntryRecord, WaitlistFilter, WaitlistOffer, WaitlistOfferRecord,
+        WaitlistStatus,
+    },
+};
+
+// Thrown inside a transaction when the entry or offer it changes doesn't exist
+const WAITLIST_MISSING: &str = "waitlist_missing";
+
+fn filter_condition(filter: &WaitlistFilter) -> (String, Bindings) {
+    let mut conditions = Vec::new();
+    let mut bindings = Bindings::new();
+
+    if let Some(patient_id) = &filter.patient_id {
+        conditions.push("patient_id = $patient_id");
+        bindings.push(("patient_id", Value::from(patient_id.as_thing().clone())));
+    }
+    if let Some(status) = filter.status {
+        conditions.push("status = $status");
+        bindings.push(("status", Value::from(status.as_str())));
+    }
+
+    if conditions.is_empty() {
+        return ("true".to_string(), bindings);
+    }
+    (conditions.join(" AND "), bindings)
+}
+
+fn offer_filter_condition(filter: &OfferFilter) -> (String, Bindings) {
+    let mut conditions = Vec::new();
+    let mut bindings = Bindings::new();
+
+    if let Some(patient_id) = &filter.patient_id {
+        conditions.push("patient_id = $patient_id");
+        bindings.push(("patient_id", Value::from(patient_id.as_thing().clone())));
+    }
+    if let Some(entry) = &filter.entry {
+        conditions.push("entry = $entry");
+        bindings.push(("entry", Value::from(entry.as_thing().clone())));
+    }
+    if let Some(status) = filter.status {
+        conditions.push("status = $status");
+        bindings.push(("status", Value::from(status.as_str())));
+    }
+
+    if conditions.is_empty() {
+        return ("true".to_string(), bindings);
+    }
+    (conditions.join(" AND "), bindings)
+}
+
+impl Database {
+    pub async fn create_waitlist_entry(
+        &self,
+        entry: WaitlistEntry,
+        created_at: NaiveDateTime,
+    ) -> Result<Vec<WaitlistEntryRecord>, DatabaseError> {
+        #[derive(Serialize)]
+        struct NewEntry {
+            #[serde(flatten)]
+            entry: WaitlistEntry,
+            status: WaitlistStatus,
+            created_at: NaiveDateTime,
+        }
+
+        let conn = self.get_connection().await?;
+
+        conn.create("waitlist")
+            .content(NewEntry {
+                entry,
+                status: WaitlistStatus::Waiting,
+                created_at,
+            })
+            .await
+            .map_err(DatabaseError::from)
+    }
+
+    pub async fn read_waitlist(
+        &self,
+        filter: &WaitlistFilter,
+    ) -> Result<Vec<WaitlistEntryRecord>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let (condition, bindings) = filter_condition(filter);
+
+        let mut query = conn.query(format!(
+            "SELECT * FROM waitlist WHERE {condition} ORDER BY created_at, id"
+        ));
+        for binding in bindings {
+            query = query.bind(binding);
+        }
+
+        query
+            .await
+            .map_err(DatabaseError::from)?
+            .take(0)
+            .map_err(DatabaseError::from)
+    }
+
+    pub async fn read_waitlist_entry(
+        &self,
+        id: &str,
+    ) -> Result<WaitlistEntryRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .select(("waitlist", id))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    // Deletes the entry and declines its pending offers, which are returned along with it so their slots can be
+    // offered to someone else
+    pub async fn delete_waitlist_entry(
+        &self,
+        id: &str,
+    ) -> Result<(WaitlistEntryRecord, Vec<WaitlistOfferRecord>), DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let mut response = conn
+            .query(format!(
+                "BEGIN TRANSACTION;
+                LET $entry = type::thing('waitlist', $id);
+                IF $entry.id = NONE {{ THROW '{WAITLIST_MISSING}'; }};
+                UPDATE waitlist_offer SET status = 'declined' WHERE entry = $entry AND status = 'pending';
+                DELETE $entry RETURN BEFORE;
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("id", id))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        let errors = response.take_errors();
+        for err in errors.values() {
+            let err = err.to_string();
+            if err.contains(WAITLIST_MISSING) {
+                return Err(DatabaseError::NothingFound);
+            }
+        }
+        if let Some(err) = errors.into_values().next() {
+            return Err(DatabaseError::from(err));
+        }
+
+        let last_statement = response.num_statements() - 1;
+        let declined: Vec<WaitlistOfferRecord> = response.take(last_statement - 1)?;
+        let entry: Option<WaitlistEntryRecord> = response.take(last_statement)?;
+
+        Ok((entry.ok_or(DatabaseError::NothingFound)?, declined))
+    }
+
+    // The waiting entries that take an appointment with the doctor on the day of `start_time`, oldest first. Entries
+    // that declined this time with the doctor before aren't asked again.
+    pub async fn read_waiting_entries(
+        &self,
+        doctor: &DoctorRecordId,
+        start_time: NaiveDateTime,
+    ) -> Result<Vec<WaitlistEntryRecord>, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let mut result = conn
+            .query(
+                "SELECT * FROM waitlist
+                    WHERE status = 'waiting'
+                    AND start_date <= $day
+                    AND end_date >= $day
+                    AND (doctor = NONE OR doctor = $doctor)
+                    AND patient_id.id != NONE
+                    AND id NOTINSIDE (SELECT VALUE entry FROM waitlist_offer
+                        WHERE status = 'declined' AND doctor = $doctor AND start_time = $start_time)
+                    ORDER BY created_at, id",
+            )
+            .bind(("day", start_time.date().to_string()))
+            .bind(("doctor", doctor))
+            .bind(("start_time", start_time))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        Ok(result.take(0)?)
+    }
+
+    // Creates the offer together with the notification about it, as long as the entry is still waiting
+    pub async fn create_offer(
+        &self,
+        offer: WaitlistOffer,
+        message: String,
+        created_at: NaiveDateTime,
+    ) -> Result<WaitlistOfferRecord, DatabaseError> {
+        #[derive(Serialize)]
+        struct NewOffer {
+            #[serde(flatten)]
+            offer: WaitlistOffer,
+            status: OfferStatus,
+            created_at: NaiveDateTime,
+        }
+
+        let offer_id = Thing::from(("waitlist_offer", Id::rand()));
+        let conn = self.get_connection().await?;
+        let mut response = conn
+            .query(format!(
+                "BEGIN TRANSACTION;
+                IF $offer.entry.status != 'waiting' {{ THROW '{INVALID_STATUS_CHANGE}'; }};
+                CREATE $offer_id CONTENT $offer;
+                UPDATE $offer.entry SET status = 'offered';
+                CREATE notification_outbox CONTENT {{
+                    kind: 'waitlist_offer',
+                    patient_id: $offer.patient_id,
+                    subject: $offer_id,
+                    message: $message,
+                    created_at: $offer.created_at,
+                }};
+                COMMIT TRANSACTION;
+                SELECT * FROM $offer_id;"
+            ))
+            .bind(("offer_id", &offer_id))
+            .bind((
+                "offer",
+                NewOffer {
+                    offer,
+                    status: OfferStatus::Pending,
+                    created_at,
+                },
+            ))
+            .bind(("message", message))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        let errors = response.take_errors();
+        for err in errors.values() {
+            let err = err.to_string();
+            if err.contains(INVALID_STATUS_CHANGE) {
+                return Err(DatabaseError::InvalidStatusChange);
+            }
+        }
+        if let Some(err) = errors.into_values().next() {
+            return Err(DatabaseError::from(err));
+        }
+
+        let last_statement = response.num_statements() - 1;
+        let result: Option<WaitlistOfferRecord> = response.take(last_statement)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    // Expires the pending offers whose slot started by `now`, and puts their entries back on the waitlist. Returns the
+    // expired offers.
+    pub async fn expire_offers(
+        &self,
+        now: NaiveDateTime,
+    ) -> Result<Vec<WaitlistOfferRecord>, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let mut response = conn
+            .query(
+                "BEGIN TRANSACTION;
+                LET $expired = (SELECT VALUE id FROM waitlist_offer
+                    WHERE status = 'pending' AND start_time <= $now);
+                UPDATE waitlist SET status = 'waiting'
+                    WHERE status = 'offered' AND id INSIDE (SELECT VALUE entry FROM $expired);
+                UPDATE $expired SET status = 'expired';
+                COMMIT TRANSACTION;",
+            )
+            .bind(("now", now))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        if let Some(err) = response.take_errors().into_values().next() {
+            return Err(DatabaseError::from(err));
+        }
+
+        let last_statement = response.num_statements() - 1;
+        Ok(response.take(last_statement)?)
+    }
+
+    pub async fn read_offers(
+        &self,
+        filter: &OfferFilter,
+    ) -> Result<Vec<WaitlistOfferRecord>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let (condition, bindings) = offer_filter_condition(filter);
+
+        let mut query = conn.query(format!(
+            "SELECT * FROM waitlist_offer WHERE {condition} ORDER BY created_at, id"
+        ));
+        for binding in bindings {
+            query = query.bind(binding);
+        }
+
+        query
+            .await
+            .map_err(DatabaseError::from)?
+            .take(0)
+            .map_err(DatabaseError::from)
+    }
+
+    pub async fn read_offer(&self, id: &str) -> Result<WaitlistOfferRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let result = conn
+            .select(("waitlist_offer", id))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    // Books the appointment of the offer if the offer is still pending and its timeframe is still free, checked in one
+    // transaction like any other booking
+    pub async fn accept_offer(
+        &self,
+        id: &str,
+        appointment: AppointmentWithTime,
+    ) -> Result<WaitlistOfferRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let mut response = conn
+            .query(format!(
+                "BEGIN TRANSACTION;
+                LET $offer = type::thing('waitlist_offer', $id);
+                IF $offer.id = NONE {{ THROW '{WAITLIST_MISSING}'; }};
+                IF $offer.status != 'pending' {{ THROW '{INVALID_STATUS_CHANGE}'; }};
+                {RESERVE_TIMEFRAME}
+                LET $booked = (CREATE appointment CONTENT $appointment);
+                UPDATE $offer.entry SET status = 'booked';
+                UPDATE $offer SET status = 'accepted', appointment = $booked[0].id;
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("id", id))
+            .bind(("days", lock_days(&appointment.blocked)))
+            .bind(("blocked", appointment.blocked))
+            .bind(("doctor", &appointment.doctor))
+            .bind(("room", &appointment.room))
+            .bind(("staff", &appointment.staff))
+            .bind(("devices", &appointment.devices))
+            .bind(("exclude", None::<Thing>))
+            .bind(("appointment", &appointment))
+            .await
+            .map_err(booking_error)?;
+
+        let errors = response.take_errors();
+        for err in errors.values() {
+            let err = err.to_string();
+            if is_conflict(&err) {
+                return Err(DatabaseError::AppointmentConflict);
+            }
+            if err.contains(WAITLIST_MISSING) {
+                return Err(DatabaseError::NothingFound);
+            }
+            if err.contains(INVALID_STATUS_CHANGE) {
+                return Err(DatabaseError::InvalidStatusChange);
+            }
+        }
+        if let Some(err) = errors.into_values().next() {
+            return Err(DatabaseError::from(err));
+        }
+
+        let last_statement = response.num_statements() - 1;
+        let result: Option<WaitlistOfferRecord> = response.take(last_statement)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+
+    // Declines the offer if it's still pending, and puts its entry back on the waitlist
+    pub async fn decline_offer(&self, id: &str) -> Result<WaitlistOfferRecord, DatabaseError> {
+        let conn = self.get_connection().await?;
+
+        let mut response = conn
+            .query(format!(
+                "BEGIN TRANSACTION;
+                LET $offer = type::thing('waitlist_offer', $id);
+                IF $offer.id = NONE {{ THROW '{WAITLIST_MISSING}'; }};
+                IF $offer.status != 'pending' {{ THROW '{INVALID_STATUS_CHANGE}'; }};
+                IF $offer.entry.id != NONE {{ UPDATE $offer.entry SET status = 'waiting'; }};
+                UPDATE $offer SET status = 'declined';
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("id", id))
+            .await
+            .map_err(DatabaseError::from)?;
+
+        let errors = response.take_errors();
+        for err in errors.values() {
+            let err = err.to_string();
+            if err.contains(WAITLIST_MISSING) {
+                return Err(DatabaseError::NothingFound);
+            }
+            if err.contains(INVALID_STATUS_CHANGE) {
+                return Err(DatabaseError::InvalidStatusChange);
+            }
+        }
+        if let Some(err) = errors.into_values().next() {
+            return Err(DatabaseError::from(err));
+        }
+
+        let last_statement = response.num_statements() - 1;
+        let result: Option<WaitlistOfferRecord> = response.take(last_statement)?;
+
+        result.ok_or(DatabaseError::NothingFound)
+    }
+}
+
+#[cfg(test)]
+pub mod waitlist_db_tests {
+    use chrono::{Duration, NaiveDate};
+
+    use crate::{
+        db::{
+            appointment_db::appointment_db_tests::create_dummy_patients,
+            db::database_tests::mock_db,
+            doctor_db::doctor_db_tests::create_dummy_doctors,
+            room_db::room_db_tests::create_dummy_rooms,
+            types::{
+                Appointment, AppointmentFilter, AppointmentType, Buffers, NotificationFilter,
+                PatientRecordId, RoomRecordId, WaitlistRecordId,
+            },
+        },
+        util::util_tests::time,
+    };
+
+    use super::*;
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    pub fn entry(
+        patient_id: &PatientRecordId,
+        doctor: Option<&DoctorRecordId>,
+        start_date: &str,
+        end_date: &str,
+    ) -> WaitlistEntry {
+        WaitlistEntry {
+            patient_id: patient_id.clone(),
+            appointment_type: AppointmentType::new("quick_checkup"),
+            doctor: doctor.cloned(),
+            start_date: date(start_date),
+            end_date: date(end_date),
+        }
+    }
+
+    fn offer(
+        entry: &WaitlistEntryRecord,
+        start_time: &str,
+        doctor: &DoctorRecordId,
+        room: &RoomRecordId,
+    ) -> WaitlistOffer {
+        WaitlistOffer {
+            entry: WaitlistRecordId::from(entry.id.clone()),
+            patient_id: entry.patient_id.clone(),
+            appointment_type: entry.appointment_type.clone(),
+            start_time: time(start_time),
+            end_time: time(start_time) + Duration::minutes(30),
+            doctor: doctor.clone(),
+            room: room.clone(),
+        }
+    }
+
+    fn appointment(offer: &WaitlistOfferRecord) -> AppointmentWithTime {
+        AppointmentWithTime {
+            start_time: offer.start_time,
+            end_time: offer.end_time,
+            blocked: Buffers::default().blocked_time(offer.start_time, offer.end_time),
+            appointment_type: offer.appointment_type.clone(),
+            patient_id: offer.patient_id.clone(),
+            doctor: offer.doctor.clone(),
+            room: offer.room.clone(),
+            staff: vec![],
+            devices: vec![],
+            series: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_waitlist_entries() {
+        let mock_db = mock_db().await;
+        let patient_ids = create_dummy_patients(&mock_db, 3).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 2).await;
+
+        let anyone = mock_db
+            .create_waitlist_entry(
+                entry(&patient_ids[0], None, "2024-03-04", "2024-03-08"),
+                time("2024-03-01T09:00:00"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(anyone[0].status, WaitlistStatus::Waiting);
+        mock_db
+            .create_waitlist_entry(
+                entry(
+                    &patient_ids[1],
+                    Some(&doctor_ids[0]),
+                    "2024-03-04",
+                    "2024-03-04",
+                ),
+                time("2024-03-01T08:00:00"),
+            )
+            .await
+            .unwrap();
+        mock_db
+            .create_waitlist_entry(
+                entry(&patient_ids[2], None, "2024-03-11", "2024-03-15"),
+                time("2024-03-01T10:00:00"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            mock_db
+                .read_waitlist_entry(&anyone[0].id.id.to_raw())
+                .await
+                .unwrap(),
+            anyone[0]
+        );
+
+        let for_patient = mock_db
+            .read_waitlist(&WaitlistFilter {
+                patient_id: Some(patient_ids[0].clone()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(for_patient, anyone);
+
+        // The entry that was registered first comes first, entries that want another doctor or day are left out
+        let waiting = mock_db
+            .read_waiting_entries(&doctor_ids[0], time("2024-03-04T10:00:00"))
+            .await
+            .unwrap();
+        let patients: Vec<_> = waiting.iter().map(|entry| &entry.patient_id).collect();
+        assert_eq!(patients, [&patient_ids[1], &patient_ids[0]]);
+        let waiting = mock_db
+            .read_waiting_entries(&doctor_ids[1], time("2024-03-04T10:00:00"))
+            .await
+            .unwrap();
+        assert_eq!(waiting, anyone);
+        let waiting = mock_db
+            .read_waiting_entries(&doctor_ids[1], time("2024-03-18T10:00:00"))
+            .await
+            .unwrap();
+        assert!(waiting.is_empty());
+
+        // The window can't end before it starts
+        let result = mock_db
+            .create_waitlist_entry(
+                entry(&patient_ids[0], None, "2024-03-08", "2024-03-04"),
+                time("2024-03-01T09:00:00"),
+            )
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_offer_lifecycle() {
+        let mock_db = mock_db().await;
+        let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 1).await;
+        let room_ids = create_dummy_rooms(&mock_db, 1).await;
+        let created = mock_db
+            .create_waitlist_entry(
+                entry(&patient_ids[0], None, "2024-03-04", "2024-03-08"),
+                time("2024-03-01T09:00:00"),
+            )
+            .await
+            .unwrap();
+        let entry_id = created[0].id.id.to_raw();
+
+        // The offer comes with a notification, and the entry isn't offered anything else in the meantime
+        let first = mock_db
+            .create_offer(
+                offer(
+                    &created[0],
+                    "2024-03-04T10:00:00",
+                    &doctor_ids[0],
+                    &room_ids[0],
+                ),
+                "Offered".to_string(),
+                time("2024-03-02T09:00:00"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(first.status, OfferStatus::Pending);
+        let entry = mock_db.read_waitlist_entry(&entry_id).await.unwrap();
+        assert_eq!(entry.status, WaitlistStatus::Offered);
+        let notifications = mock_db
+            .read_notifications(&NotificationFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].subject, first.id);
+        assert_eq!(notifications[0].patient_id, patient_ids[0]);
+        let result = mock_db
+            .create_offer(
+                offer(
+                    &created[0],
+                    "2024-03-05T10:00:00",
+                    &doctor_ids[0],
+                    &room_ids[0],
+                ),
+                "Offered".to_string(),
+                time("2024-03-02T09:00:00"),
+            )
+            .await;
+        assert!(matches!(result, Err(DatabaseError::InvalidStatusChange)));
+
+        // Declining puts the entry back on the waitlist, but it isn't asked about the same time again
+        let declined = mock_db.decline_offer(&first.id.id.to_raw()).await.unwrap();
+        assert_eq!(declined.status, OfferStatus::Declined);
+        let result = mock_db.decline_offer(&first.id.id.to_raw()).await;
+        assert!(matches!(result, Err(DatabaseError::InvalidStatusChange)));
+        let waiting = mock_db
+            .read_waiting_entries(&doctor_ids[0], time("2024-03-04T10:00:00"))
+            .await
+            .unwrap();
+        assert!(waiting.is_empty());
+        let waiting = mock_db
+            .read_waiting_entries(&doctor_ids[0], time("2024-03-05T10:00:00"))
+            .await
+            .unwrap();
+        assert_eq!(waiting.len(), 1);
+
+        // Accepting books the appointment, unless its timeframe was taken in the meantime
+        let second = mock_db
+            .create_offer(
+                offer(
+                    &created[0],
+                    "2024-03-05T10:00:00",
+                    &doctor_ids[0],
+                    &room_ids[0],
+                ),
+                "Offered".to_string(),
+                time("2024-03-02T10:00:00"),
+            )
+            .await
+            .unwrap();
+        let taken = mock_db
+            .create_appointment(
+                Appointment {
+                    start_time: "2024-03-05T10:15:00".to_string(),
+                    appointment_type: AppointmentType::new("quick_checkup"),
+                    patient_id: patient_ids[0].clone(),
+                    doctor: doctor_ids[0].clone(),
+                    room: room_ids[0].clone(),
+                    staff: vec![],
+                    devices: vec![],
+                }
+                .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+        let result = mock_db
+            .accept_offer(&second.id.id.to_raw(), appointment(&second))
+            .await;
+        assert!(matches!(result, Err(DatabaseError::AppointmentConflict)));
+
+        mock_db
+            .delete_appointment(&taken[0].id.id.to_raw())
+            .await
+            .unwrap();
+        let accepted = mock_db
+            .accept_offer(&second.id.id.to_raw(), appointment(&second))
+            .await
+            .unwrap();
+        assert_eq!(accepted.status, OfferStatus::Accepted);
+        let booked = mock_db
+            .read_appointments(&AppointmentFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(booked.len(), 1);
+        assert_eq!(accepted.appointment, Some(booked[0].id.clone()));
+        let entry = mock_db.read_waitlist_entry(&entry_id).await.unwrap();
+        assert_eq!(entry.status, WaitlistStatus::Booked);
+
+        let result = mock_db
+            .accept_offer(&second.id.id.to_raw(), appointment(&second))
+            .await;
+        assert!(matches!(result, Err(DatabaseError::InvalidStatusChange)));
+    }
+
+    #[tokio::test]
+    async fn test_expire_offers() {
+        let mock_db = mock_db().await;
+        let patient_ids = create_dummy_patients(&mock_db, 2).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 1).await;
+        let room_ids = create_dummy_rooms(&mock_db, 1).await;
+        let mut offers = Vec::new();
+        for (patient_id, start_time) in patient_ids
+            .iter()
+            .zip(["2024-03-04T10:00:00", "2024-03-05T10:00:00"])
+        {
+            let created = mock_db
+                .create_waitlist_entry(
+                    entry(patient_id, None, "2024-03-04", "2024-03-08"),
+                    time("2024-03-01T09:00:00"),
+                )
+                .await
+                .unwrap();
+            let pending = mock_db
+                .create_offer(
+                    offer(&created[0], start_time, &doctor_ids[0], &room_ids[0]),
+                    "Offered".to_string(),
+                    time("2024-03-02T09:00:00"),
+                )
+                .await
+                .unwrap();
+            offers.push(pending);
+        }
+
+        // Only the offer whose slot has started expires, and its entry waits again
+        let expired = mock_db
+            .expire_offers(time("2024-03-04T10:00:00"))
+            .await
+            .unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, offers[0].id);
+        assert_eq!(expired[0].status, OfferStatus::Expired);
+        let entry = mock_db
+            .read_waitlist_entry(&offers[0].entry.get_unique_id())
+            .await
+            .unwrap();
+        assert_eq!(entry.status, WaitlistStatus::Waiting);
+        let entry = mock_db
+            .read_waitlist_entry(&offers[1].entry.get_unique_id())
+            .await
+            .unwrap();
+        assert_eq!(entry.status, WaitlistStatus::Offered);
+
+        let pending = mock_db
+            .read_offers(&OfferFilter {
+                status: Some(OfferStatus::Pending),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, offers[1].id);
+
+        // An expired offer can't be answered anymore
+        let result = mock_db.decline_offer(&offers[0].id.id.to_raw()).await;
+        assert!(matches!(result, Err(DatabaseError::InvalidStatusChange)));
+        assert!(mock_db
+            .expire_offers(time("2024-03-04T10:00:00"))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_waitlist_entry() {
+        let mock_db = mock_db().await;
+        let patient_ids = create_dummy_patients(&mock_db, 1).await;
+        let doctor_ids = create_dummy_doctors(&mock_db, 1).await;
+        let room_ids = create_dummy_rooms(&mock_db, 1).await;
+        let created = mock_db
+            .create_waitlist_entry(
+                entry(&patient_ids[0], None, "2024-03-04", "2024-03-08"),
+                time("2024-03-01T09:00:00"),
+            )
+            .await
+            .unwrap();
+        let pending = mock_db
+            .create_offer(
+                offer(
+                    &created[0],
+                    "2024-03-04T10:00:00",
+                    &doctor_ids[0],
+                    &room_ids[0],
+                ),
+                "Offered".to_string(),
+                time("2024-03-02T09:00:00"),
+            )
+            .await
+            .unwrap();
+
+        let id = created[0].id.id.to_raw();
+        let (deleted, declined) = mock_db.delete_waitlist_entry(&id).await.unwrap();
+        assert_eq!(deleted.id, created[0].id);
+        assert_eq!(declined.len(), 1);
+        assert_eq!(declined[0].id, pending.id);
+        assert_eq!(declined[0].status, OfferStatus::Declined);
+
+        assert!(matches!(
+            mock_db.read_waitlist_entry(&id).await,
+            Err(DatabaseError::NothingFound)
+        ));
+        assert!(matches!(
+            mock_db.delete_waitlist_entry(&id).await,
+            Err(DatabaseError::NothingFound)
+        ));
+    }
+}
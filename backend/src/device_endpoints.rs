@@ -0,0 +1,90 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+use crate::db::{
+    db::Database,
+    types::{Device, DeviceFilter},
+};
+use crate::errors::{not_found, ApiError};
+use crate::types::ApiResponse;
+
+// Device Types
+#[derive(Deserialize)]
+pub struct DeviceId {
+    id: String,
+}
+#[derive(Deserialize)]
+pub struct UpdateDevice {
+    name: Option<String>,
+    device_type: Option<String>,
+}
+
+// Endpoints
+
+// There are few enough devices to list all of them at once
+pub async fn read_all_devices(
+    database: web::Data<Database>,
+    filter: web::Query<DeviceFilter>,
+) -> Result<HttpResponse, ApiError> {
+    let devices = database.read_devices(&filter).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: devices }))
+}
+
+pub async fn create_device(
+    database: web::Data<Database>,
+    device: web::Json<Device>,
+) -> Result<HttpResponse, ApiError> {
+    let result = database.create_device(device.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+pub async fn delete_device(
+    database: web::Data<Database>,
+    device_id: web::Path<DeviceId>,
+) -> Result<HttpResponse, ApiError> {
+    let device = database
+        .delete_device(&device_id.id)
+        .await
+        .map_err(not_found("Device"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: device }))
+}
+
+pub async fn update_device(
+    database: web::Data<Database>,
+    device_id: web::Path<DeviceId>,
+    update: web::Json<UpdateDevice>,
+) -> Result<HttpResponse, ApiError> {
+    let mut device = database
+        .read_device(&device_id.id)
+        .await
+        .map_err(not_found("Device"))?;
+
+    if let Some(name) = &update.name {
+        device.name = name.clone();
+    }
+    if let Some(device_type) = &update.device_type {
+        device.device_type = device_type.clone();
+    }
+
+    let result = database
+        .update_device(&device_id.id, device)
+        .await
+        .map_err(not_found("Device"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+pub async fn read_device(
+    database: web::Data<Database>,
+    device_id: web::Path<DeviceId>,
+) -> Result<HttpResponse, ApiError> {
+    let device = database
+        .read_device(&device_id.id)
+        .await
+        .map_err(not_found("Device"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: device }))
+}
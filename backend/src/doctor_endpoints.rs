@@ -0,0 +1,142 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+use crate::db::{
+    db::Database,
+    types::{Doctor, DoctorFilter, DoctorRecordId, DoctorSort, Schedule},
+};
+use crate::errors::{not_found, ApiError};
+use crate::types::{ApiResponse, PageRequest, PaginatedResponse};
+
+// Doctor Types
+#[derive(Deserialize)]
+pub struct DoctorId {
+    id: String,
+}
+#[derive(Deserialize)]
+pub struct UpdateDoctor {
+    name: Option<String>,
+    specialty: Option<String>,
+    phone_number: Option<String>,
+    email: Option<String>,
+    active: Option<bool>,
+}
+
+// Endpoints
+pub async fn read_all_doctors(
+    database: web::Data<Database>,
+    filter: web::Query<DoctorFilter>,
+    page_request: web::Query<PageRequest<DoctorSort>>,
+) -> Result<HttpResponse, ApiError> {
+    let pagination = page_request
+        .into_inner()
+        .into_pagination("doctor")
+        .map_err(ApiError::InvalidRequest)?;
+
+    let page = database.read_doctor_page(&filter, &pagination).await?;
+
+    Ok(HttpResponse::Ok().json(PaginatedResponse::from(page)))
+}
+
+pub async fn create_doctor(
+    database: web::Data<Database>,
+    doctor: web::Json<Doctor>,
+) -> Result<HttpResponse, ApiError> {
+    let result = database.create_doctor(doctor.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+pub async fn delete_doctor(
+    database: web::Data<Database>,
+    doctor_id: web::Path<DoctorId>,
+) -> Result<HttpResponse, ApiError> {
+    let doctor = database
+        .delete_doctor(&doctor_id.id)
+        .await
+        .map_err(not_found("Doctor"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: doctor }))
+}
+
+pub async fn update_doctor(
+    database: web::Data<Database>,
+    doctor_id: web::Path<DoctorId>,
+    update: web::Json<UpdateDoctor>,
+) -> Result<HttpResponse, ApiError> {
+    let mut doctor = database
+        .read_doctor(&doctor_id.id)
+        .await
+        .map_err(not_found("Doctor"))?;
+
+    if let Some(name) = &update.name {
+        doctor.name = name.clone();
+    }
+    if let Some(specialty) = &update.specialty {
+        doctor.specialty = specialty.clone();
+    }
+    if let Some(phone_number) = &update.phone_number {
+        doctor.phone_number = Some(phone_number.clone());
+    }
+    if let Some(email) = &update.email {
+        doctor.email = Some(email.clone());
+    }
+    if let Some(active) = update.active {
+        doctor.active = active;
+    }
+
+    let result = database
+        .update_doctor(&doctor_id.id, doctor)
+        .await
+        .map_err(not_found("Doctor"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+pub async fn read_doctor(
+    database: web::Data<Database>,
+    doctor_id: web::Path<DoctorId>,
+) -> Result<HttpResponse, ApiError> {
+    let doctor = database
+        .read_doctor(&doctor_id.id)
+        .await
+        .map_err(not_found("Doctor"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: doctor }))
+}
+
+pub async fn read_doctor_schedule(
+    database: web::Data<Database>,
+    doctor_id: web::Path<DoctorId>,
+) -> Result<HttpResponse, ApiError> {
+    database
+        .read_doctor(&doctor_id.id)
+        .await
+        .map_err(not_found("Doctor"))?;
+
+    let schedule = database
+        .read_schedule(&DoctorRecordId::new(&doctor_id.id))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: schedule }))
+}
+
+// Appointments that are already booked stay, even if they're outside of the new schedule
+pub async fn update_doctor_schedule(
+    database: web::Data<Database>,
+    doctor_id: web::Path<DoctorId>,
+    schedule: web::Json<Schedule>,
+) -> Result<HttpResponse, ApiError> {
+    schedule.validate().map_err(ApiError::InvalidRequest)?;
+
+    database
+        .read_doctor(&doctor_id.id)
+        .await
+        .map_err(not_found("Doctor"))?;
+
+    let result = database
+        .update_schedule(&DoctorRecordId::new(&doctor_id.id), &schedule)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
@@ -0,0 +1,240 @@
+use actix_web::{
+    error::{JsonPayloadError, PathError, QueryPayloadError},
+    http::StatusCode,
+    web, HttpRequest, HttpResponse, ResponseError,
+};
+use log::error;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{db::types::DatabaseError, util::TimeframeError};
+
+// Every error a handler returns ends up as a JSON body like
+// `{"error": {"code": "outside_opening_hours", "message": "Appointment is outside of opening hours"}}`.
+// Codes are stable, so clients can rely on them, while messages are meant for humans and may change.
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("{0} not found")]
+    NotFound(&'static str),
+    #[error("{0}")]
+    InvalidRequest(String),
+    // The room can't host the type of appointment
+    #[error("{0}")]
+    IncompatibleRoom(String),
+    // The appointment's status doesn't allow the change, like checking in a cancelled appointment
+    #[error("{0}")]
+    InvalidStatusChange(String),
+    // Some appointments of a series can't be changed, the message names them
+    #[error("{0}")]
+    SeriesConflict(String),
+    #[error(transparent)]
+    Timeframe(#[from] TimeframeError),
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
+    #[error("{0}")]
+    Internal(String),
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    code: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::NotFound(_) => "not_found",
+            ApiError::InvalidRequest(_) => "invalid_request",
+            ApiError::IncompatibleRoom(_) => "incompatible_room",
+            ApiError::InvalidStatusChange(_) => "invalid_status_change",
+            ApiError::SeriesConflict(_) => "series_conflict",
+            ApiError::Timeframe(err) => err.code(),
+            ApiError::Database(err) => match err {
+                DatabaseError::ConnectionLost
+                | DatabaseError::Reconnecting
+                | DatabaseError::Timeout => "database_unavailable",
+                DatabaseError::NothingFound => "not_found",
+                DatabaseError::AppointmentConflict => "appointment_conflict",
+                DatabaseError::InvalidCursor => "invalid_cursor",
+                DatabaseError::HasAppointments(_) => "has_appointments",
+                DatabaseError::InvalidStatusChange => "invalid_status_change",
+                DatabaseError::ChronoError(_) => "invalid_time",
+                DatabaseError::MissingCredential(_)
+                | DatabaseError::AuthenticationFailed { .. }
+                | DatabaseError::Migration { .. }
+                | DatabaseError::SurrealDBError(_)
+                | DatabaseError::Other(_) => "internal_error",
+            },
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    // Internal errors may contain details of the database, those are logged but never sent to the client
+    fn message(&self) -> String {
+        match self.status_code() {
+            StatusCode::INTERNAL_SERVER_ERROR => "An internal error occurred".to_string(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::IncompatibleRoom(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::InvalidStatusChange(_) | ApiError::SeriesConflict(_) => StatusCode::CONFLICT,
+            ApiError::Timeframe(TimeframeError::Overlap) => StatusCode::CONFLICT,
+            ApiError::Timeframe(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Database(err) => match err {
+                DatabaseError::ConnectionLost
+                | DatabaseError::Reconnecting
+                | DatabaseError::Timeout => StatusCode::SERVICE_UNAVAILABLE,
+                DatabaseError::NothingFound => StatusCode::NOT_FOUND,
+                DatabaseError::AppointmentConflict
+                | DatabaseError::HasAppointments(_)
+                | DatabaseError::InvalidStatusChange => StatusCode::CONFLICT,
+                DatabaseError::InvalidCursor | DatabaseError::ChronoError(_) => {
+                    StatusCode::BAD_REQUEST
+                }
+                DatabaseError::MissingCredential(_)
+                | DatabaseError::AuthenticationFailed { .. }
+                | DatabaseError::Migration { .. }
+                | DatabaseError::SurrealDBError(_)
+                | DatabaseError::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if self.status_code().is_server_error() {
+            error!("{}: {}", self.code(), self);
+        }
+
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
+            error: ErrorBody {
+                code: self.code(),
+                message: self.message(),
+            },
+        })
+    }
+}
+
+// Names what wasn't found, instead of the generic message of `DatabaseError::NothingFound`
+pub fn not_found(entity: &'static str) -> impl FnOnce(DatabaseError) -> ApiError {
+    move |err| match err {
+        DatabaseError::NothingFound => ApiError::NotFound(entity),
+        err => ApiError::Database(err),
+    }
+}
+
+// Requests that can't be deserialized get the same JSON error body as every other error
+pub fn configure_error_handlers(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(web::JsonConfig::default().error_handler(
+        |err: JsonPayloadError, _: &HttpRequest| ApiError::InvalidRequest(err.to_string()).into(),
+    ))
+    .app_data(web::QueryConfig::default().error_handler(
+        |err: QueryPayloadError, _: &HttpRequest| ApiError::InvalidRequest(err.to_string()).into(),
+    ))
+    .app_data(
+        web::PathConfig::default().error_handler(|err: PathError, _: &HttpRequest| {
+            ApiError::InvalidRequest(err.to_string()).into()
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::to_bytes;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_error_response_body() {
+        let err = ApiError::from(TimeframeError::OutsideOpeningHours);
+        assert_eq!(err.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body = to_bytes(err.error_response().into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "outside_opening_hours");
+        assert_eq!(
+            body["error"]["message"],
+            "Appointment is outside of opening hours"
+        );
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let cases = [
+            (
+                ApiError::from(DatabaseError::AppointmentConflict),
+                "appointment_conflict",
+                StatusCode::CONFLICT,
+            ),
+            (
+                ApiError::from(TimeframeError::Overlap),
+                "appointment_conflict",
+                StatusCode::CONFLICT,
+            ),
+            (
+                ApiError::from(DatabaseError::HasAppointments("Doctor")),
+                "has_appointments",
+                StatusCode::CONFLICT,
+            ),
+            (
+                ApiError::from(DatabaseError::InvalidStatusChange),
+                "invalid_status_change",
+                StatusCode::CONFLICT,
+            ),
+            (
+                ApiError::SeriesConflict("2024-03-11T10:00:00 (clinic_closed)".to_string()),
+                "series_conflict",
+                StatusCode::CONFLICT,
+            ),
+            (
+                ApiError::from(TimeframeError::OutsideWorkingHours),
+                "outside_working_hours",
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (
+                ApiError::from(DatabaseError::NothingFound),
+                "not_found",
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                ApiError::from(DatabaseError::Reconnecting),
+                "database_unavailable",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                ApiError::InvalidRequest("Invalid cursor".to_string()),
+                "invalid_request",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                ApiError::from(DatabaseError::Other("details".to_string())),
+                "internal_error",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+
+        for (err, code, status) in cases {
+            assert_eq!(err.code(), code);
+            assert_eq!(err.status_code(), status);
+        }
+    }
+
+    #[test]
+    fn test_internal_errors_are_hidden() {
+        let err = ApiError::from(DatabaseError::Other("surrealdb internals".to_string()));
+        assert!(!err.message().contains("surrealdb internals"));
+    }
+}
@@ -0,0 +1,53 @@
+use actix_web::{web, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::db::{db::Database, types::ConnectionState};
+use crate::types::{ApiResponse, ServerInfo};
+
+// Health Types
+#[derive(Serialize)]
+pub struct DatabaseHealth {
+    state: ConnectionState,
+    latency_ms: Option<f64>,
+    error: Option<String>,
+}
+#[derive(Serialize)]
+pub struct HealthReport {
+    status: &'static str,
+    database: DatabaseHealth,
+    uptime_seconds: u64,
+}
+
+// Endpoints
+pub async fn get_health(
+    database: web::Data<Database>,
+    server_info: web::Data<ServerInfo>,
+) -> impl Responder {
+    let state = database.state();
+
+    let (latency_ms, error) = match database.get_connection().await {
+        Ok(_) => match database.ping().await {
+            Ok(latency) => (Some(latency.as_secs_f64() * 1000.0), None),
+            Err(err) => (None, Some(err.to_string())),
+        },
+        Err(err) => (None, Some(err.to_string())),
+    };
+
+    let healthy = error.is_none();
+    let report = HealthReport {
+        status: if healthy { "ok" } else { "degraded" },
+        database: DatabaseHealth {
+            state,
+            latency_ms,
+            error,
+        },
+        uptime_seconds: server_info.started_at.elapsed().as_secs(),
+    };
+
+    // Monitoring only needs to look at the status code to alert on a degraded backend
+    if healthy {
+        HttpResponse::Ok().json(ApiResponse { data: report })
+    } else {
+        HttpResponse::ServiceUnavailable().json(ApiResponse { data: report })
+    }
+}
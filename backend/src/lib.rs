@@ -1,7 +1,22 @@
+pub mod absence_endpoints;
 pub mod appointment_endpoints;
+pub mod appointment_type_endpoints;
+pub mod availability;
+pub mod availability_endpoints;
+pub mod calendar;
+pub mod calendar_endpoints;
 pub mod config;
-pub mod config_endpoints;
 pub mod db;
+pub mod device_endpoints;
+pub mod doctor_endpoints;
+pub mod errors;
+pub mod health_endpoints;
+pub mod notification_endpoints;
 pub mod patient_endpoints;
+pub mod rescheduling;
+pub mod room_endpoints;
+pub mod series_endpoints;
 pub mod types;
 pub mod util;
+pub mod waitlist;
+pub mod waitlist_endpoints;
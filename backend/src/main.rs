@@ -1,34 +1,67 @@
 use actix_cors::Cors;
 use actix_web::{web, App, HttpServer};
+use backend::absence_endpoints::{create_absence, delete_absence, read_absence, read_absences};
 use backend::appointment_endpoints::{
-    create_appointment, delete_appointment, mass_reschedule_doctor, read_all_appointments_handler,
-    read_appointment, update_appointment,
+    cancel_appointment, check_in_appointment, complete_appointment, create_appointment,
+    delete_appointment, mark_appointment_no_show, mass_reschedule_doctor,
+    read_all_appointments_handler, read_appointment, start_appointment, update_appointment,
+};
+use backend::appointment_type_endpoints::{
+    create_appointment_type, delete_appointment_type, read_all_appointment_types,
+    read_appointment_type, update_appointment_type,
+};
+use backend::availability_endpoints::read_availability;
+use backend::calendar_endpoints::{
+    create_closure, delete_closure, delete_opening_override, import_closures, read_closures,
+    read_opening_overrides, update_opening_override,
 };
 use backend::config::AppConfig;
-use backend::config_endpoints::{get_doctor_amount, get_room_amount};
 use backend::db::db::Database;
+use backend::device_endpoints::{
+    create_device, delete_device, read_all_devices, read_device, update_device,
+};
+use backend::doctor_endpoints::{
+    create_doctor, delete_doctor, read_all_doctors, read_doctor, read_doctor_schedule,
+    update_doctor, update_doctor_schedule,
+};
+use backend::errors::configure_error_handlers;
+use backend::health_endpoints::get_health;
+use backend::notification_endpoints::{mark_notification_sent, read_notifications};
 use backend::patient_endpoints::{
     create_patient, delete_patient, read_all_patients, read_patient, update_patient,
 };
-use std::sync::{Arc, Mutex};
+use backend::room_endpoints::{create_room, delete_room, read_all_rooms, read_room, update_room};
+use backend::series_endpoints::{create_appointment_series, read_appointment_series};
+use backend::types::ServerInfo;
+use backend::waitlist::spawn_offer_expiry;
+use backend::waitlist_endpoints::{
+    accept_offer, create_waitlist_entry, decline_offer, delete_waitlist_entry, read_offer,
+    read_offers, read_waitlist, read_waitlist_entry,
+};
+use std::time::Instant;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
     env_logger::init();
 
+    let server_info = ServerInfo {
+        started_at: Instant::now(),
+    };
+
     let config = AppConfig::new();
 
     let port = config.port;
 
-    let database = Arc::new(Mutex::new(Database::new()));
-    database
-        .lock()
-        .unwrap()
-        .initiate_db(config.clone())
-        .await
-        .expect(
-            "Couldn't initiate database. Make sure the server is running and configured correctly.",
+    let database = Database::new();
+    if let Err(err) = database.initiate_db(config.clone()).await {
+        eprintln!(
+            "Couldn't initiate database: {}\nMake sure the server is running and the credentials in server.toml are correct.",
+            err
         );
+        std::process::exit(1);
+    }
+    database.spawn_health_monitor();
+    spawn_offer_expiry(database.clone());
 
     HttpServer::new(move || {
         let cors = Cors::default()
@@ -39,10 +72,13 @@ async fn main() -> std::io::Result<()> {
 
         App::new()
             .wrap(cors)
+            .configure(configure_error_handlers)
             .app_data(web::Data::new(database.clone()))
             .app_data(web::Data::new(config.clone()))
+            .app_data(web::Data::new(server_info))
             .service(
                 web::scope("/api")
+                    .service(web::resource("/health").route(web::get().to(get_health)))
                     .service(
                         web::resource("/patient")
                             .route(web::post().to(create_patient))
@@ -54,11 +90,101 @@ async fn main() -> std::io::Result<()> {
                             .route(web::put().to(update_patient))
                             .route(web::delete().to(delete_patient)),
                     )
+                    .service(
+                        web::resource("/doctor")
+                            .route(web::post().to(create_doctor))
+                            .route(web::get().to(read_all_doctors)),
+                    )
+                    .service(
+                        web::resource("/doctor/{id}")
+                            .route(web::get().to(read_doctor))
+                            .route(web::put().to(update_doctor))
+                            .route(web::delete().to(delete_doctor)),
+                    )
+                    .service(
+                        web::resource("/doctor/{id}/schedule")
+                            .route(web::get().to(read_doctor_schedule))
+                            .route(web::put().to(update_doctor_schedule)),
+                    )
+                    .service(
+                        web::resource("/absence")
+                            .route(web::post().to(create_absence))
+                            .route(web::get().to(read_absences)),
+                    )
+                    .service(
+                        web::resource("/absence/{id}")
+                            .route(web::get().to(read_absence))
+                            .route(web::delete().to(delete_absence)),
+                    )
+                    .service(
+                        web::resource("/room")
+                            .route(web::post().to(create_room))
+                            .route(web::get().to(read_all_rooms)),
+                    )
+                    .service(
+                        web::resource("/room/{id}")
+                            .route(web::get().to(read_room))
+                            .route(web::put().to(update_room))
+                            .route(web::delete().to(delete_room)),
+                    )
+                    .service(
+                        web::resource("/device")
+                            .route(web::post().to(create_device))
+                            .route(web::get().to(read_all_devices)),
+                    )
+                    .service(
+                        web::resource("/device/{id}")
+                            .route(web::get().to(read_device))
+                            .route(web::put().to(update_device))
+                            .route(web::delete().to(delete_device)),
+                    )
+                    .service(
+                        web::resource("/appointment_type")
+                            .route(web::post().to(create_appointment_type))
+                            .route(web::get().to(read_all_appointment_types)),
+                    )
+                    .service(
+                        web::resource("/appointment_type/{id}")
+                            .route(web::get().to(read_appointment_type))
+                            .route(web::put().to(update_appointment_type))
+                            .route(web::delete().to(delete_appointment_type)),
+                    )
+                    .service(web::resource("/availability").route(web::get().to(read_availability)))
+                    .service(
+                        web::resource("/calendar/closure")
+                            .route(web::post().to(create_closure))
+                            .route(web::get().to(read_closures)),
+                    )
+                    .service(
+                        web::resource("/calendar/closure/import")
+                            .route(web::post().to(import_closures)),
+                    )
+                    .service(
+                        web::resource("/calendar/closure/{date}")
+                            .route(web::delete().to(delete_closure)),
+                    )
+                    .service(
+                        web::resource("/calendar/opening_override")
+                            .route(web::get().to(read_opening_overrides)),
+                    )
+                    .service(
+                        web::resource("/calendar/opening_override/{date}")
+                            .route(web::put().to(update_opening_override))
+                            .route(web::delete().to(delete_opening_override)),
+                    )
                     .service(
                         web::resource("/appointment")
                             .route(web::post().to(create_appointment))
                             .route(web::get().to(read_all_appointments_handler)),
                     )
+                    .service(
+                        web::resource("/appointment_series")
+                            .route(web::post().to(create_appointment_series)),
+                    )
+                    .service(
+                        web::resource("/appointment_series/{id}")
+                            .route(web::get().to(read_appointment_series)),
+                    )
                     .service(
                         web::resource("/appointment/mass_reschedule")
                             .route(web::post().to(mass_reschedule_doctor)),
@@ -70,11 +196,52 @@ async fn main() -> std::io::Result<()> {
                             .route(web::delete().to(delete_appointment)),
                     )
                     .service(
-                        web::resource("/config/doctor_amount")
-                            .route(web::get().to(get_doctor_amount)),
+                        web::resource("/appointment/{id}/check_in")
+                            .route(web::post().to(check_in_appointment)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}/start")
+                            .route(web::post().to(start_appointment)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}/complete")
+                            .route(web::post().to(complete_appointment)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}/no_show")
+                            .route(web::post().to(mark_appointment_no_show)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}/cancel")
+                            .route(web::post().to(cancel_appointment)),
+                    )
+                    .service(
+                        web::resource("/waitlist")
+                            .route(web::post().to(create_waitlist_entry))
+                            .route(web::get().to(read_waitlist)),
+                    )
+                    .service(
+                        web::resource("/waitlist/{id}")
+                            .route(web::get().to(read_waitlist_entry))
+                            .route(web::delete().to(delete_waitlist_entry)),
+                    )
+                    .service(web::resource("/waitlist_offer").route(web::get().to(read_offers)))
+                    .service(web::resource("/waitlist_offer/{id}").route(web::get().to(read_offer)))
+                    .service(
+                        web::resource("/waitlist_offer/{id}/accept")
+                            .route(web::post().to(accept_offer)),
+                    )
+                    .service(
+                        web::resource("/waitlist_offer/{id}/decline")
+                            .route(web::post().to(decline_offer)),
+                    )
+                    .service(
+                        web::resource("/notification_outbox")
+                            .route(web::get().to(read_notifications)),
                     )
                     .service(
-                        web::resource("/config/room_amount").route(web::get().to(get_room_amount)),
+                        web::resource("/notification_outbox/{id}/sent")
+                            .route(web::post().to(mark_notification_sent)),
                     ),
             )
     })
@@ -0,0 +1,39 @@
+use actix_web::{web, HttpResponse};
+use chrono::{Local, SubsecRound};
+use serde::Deserialize;
+
+use crate::db::{db::Database, types::NotificationFilter};
+use crate::errors::{not_found, ApiError};
+use crate::types::ApiResponse;
+
+// Notification Types
+#[derive(Deserialize)]
+pub struct NotificationId {
+    id: String,
+}
+
+// Endpoints
+
+// Oldest first, so whatever delivers the notifications sends them in the order they were written in
+pub async fn read_notifications(
+    database: web::Data<Database>,
+    filter: web::Query<NotificationFilter>,
+) -> Result<HttpResponse, ApiError> {
+    let notifications = database.read_notifications(&filter).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        data: notifications,
+    }))
+}
+
+pub async fn mark_notification_sent(
+    database: web::Data<Database>,
+    path: web::Path<NotificationId>,
+) -> Result<HttpResponse, ApiError> {
+    let notification = database
+        .mark_notification_sent(&path.id, Local::now().naive_local().trunc_subsecs(0))
+        .await
+        .map_err(not_found("Notification"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: notification }))
+}
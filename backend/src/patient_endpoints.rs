@@ -1,13 +1,12 @@
-use std::sync::{Arc, Mutex};
-
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpResponse};
 use serde::Deserialize;
 
 use crate::db::{
     db::Database,
-    types::{DatabaseError, Patient},
+    types::{Patient, PatientSort},
 };
-use crate::types::ApiResponse;
+use crate::errors::{not_found, ApiError};
+use crate::types::{ApiResponse, PageRequest, PaginatedResponse};
 
 // Patient Types
 #[derive(Deserialize)]
@@ -22,76 +21,50 @@ pub struct UpdatePatient {
 }
 
 // Endpoints
-pub async fn read_all_patients(database: web::Data<Arc<Mutex<Database>>>) -> impl Responder {
-    let db = match database.lock() {
-        Ok(guard) => guard,
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Lock error: {:?}", err))
-        }
-    };
+pub async fn read_all_patients(
+    database: web::Data<Database>,
+    page_request: web::Query<PageRequest<PatientSort>>,
+) -> Result<HttpResponse, ApiError> {
+    let pagination = page_request
+        .into_inner()
+        .into_pagination("patient")
+        .map_err(ApiError::InvalidRequest)?;
 
-    match db.read_all_patients().await {
-        Ok(patients) => HttpResponse::Ok().json(ApiResponse { data: patients }),
-        Err(err) => HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
-    }
+    let page = database.read_patient_page(&pagination).await?;
+
+    Ok(HttpResponse::Ok().json(PaginatedResponse::from(page)))
 }
 
 pub async fn create_patient(
-    database: web::Data<Arc<Mutex<Database>>>,
+    database: web::Data<Database>,
     patient: web::Json<Patient>,
-) -> impl Responder {
-    let db = match database.lock() {
-        Ok(guard) => guard,
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Lock error: {:?}", err))
-        }
-    };
+) -> Result<HttpResponse, ApiError> {
+    let result = database.create_patient(patient.into_inner()).await?;
 
-    match db.create_patient(patient.into_inner()).await {
-        Ok(result) => HttpResponse::Ok().json(ApiResponse { data: result }),
-        Err(err) => HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
-    }
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
 }
 
 pub async fn delete_patient(
-    database: web::Data<Arc<Mutex<Database>>>,
+    database: web::Data<Database>,
     patient_id: web::Path<PatientId>,
-) -> impl Responder {
-    let db = match database.lock() {
-        Ok(guard) => guard,
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Lock error: {:?}", err))
-        }
-    };
-    match db.delete_patient(&patient_id.id).await {
-        Ok(patients) => HttpResponse::Ok().json(ApiResponse { data: patients }),
-        Err(err) => match err {
-            DatabaseError::NothingFound => HttpResponse::NotFound().body("Patient not found"),
-            _ => HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
-        },
-    }
+) -> Result<HttpResponse, ApiError> {
+    let patient = database
+        .delete_patient(&patient_id.id)
+        .await
+        .map_err(not_found("Patient"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: patient }))
 }
 
 pub async fn update_patient(
-    database: web::Data<Arc<Mutex<Database>>>,
+    database: web::Data<Database>,
     patient_id: web::Path<PatientId>,
     update: web::Json<UpdatePatient>,
-) -> impl Responder {
-    let db = match database.lock() {
-        Ok(guard) => guard,
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Lock error: {:?}", err))
-        }
-    };
-    let mut patient = match db.read_patient(&patient_id.id).await {
-        Ok(patient) => patient,
-        Err(err) => match err {
-            DatabaseError::NothingFound => {
-                return HttpResponse::NotFound().body("Patient not found")
-            }
-            _ => return HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
-        },
-    };
+) -> Result<HttpResponse, ApiError> {
+    let mut patient = database
+        .read_patient(&patient_id.id)
+        .await
+        .map_err(not_found("Patient"))?;
 
     if let Some(name) = &update.name {
         patient.name = name.clone();
@@ -103,27 +76,22 @@ pub async fn update_patient(
         patient.insurance_number = Some(insurance_number.clone());
     }
 
-    match db.update_patient(&patient_id.id, patient).await {
-        Ok(result) => HttpResponse::Ok().json(ApiResponse { data: result }),
-        Err(err) => HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
-    }
+    let result = database
+        .update_patient(&patient_id.id, patient)
+        .await
+        .map_err(not_found("Patient"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
 }
 
 pub async fn read_patient(
-    database: web::Data<Arc<Mutex<Database>>>,
+    database: web::Data<Database>,
     patient_id: web::Path<PatientId>,
-) -> impl Responder {
-    let db = match database.lock() {
-        Ok(guard) => guard,
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Lock error: {:?}", err))
-        }
-    };
-    match db.read_patient(&patient_id.id).await {
-        Ok(patients) => HttpResponse::Ok().json(ApiResponse { data: patients }),
-        Err(err) => match err {
-            DatabaseError::NothingFound => HttpResponse::NotFound().body("Patient not found"),
-            _ => HttpResponse::InternalServerError().body(format!("Error: {:?}", err)),
-        },
-    }
+) -> Result<HttpResponse, ApiError> {
+    let patient = database
+        .read_patient(&patient_id.id)
+        .await
+        .map_err(not_found("Patient"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: patient }))
 }
@@ -0,0 +1,517 @@
+use chrono::{NaiveDate, NaiveTime};
+
+use crate::{
+    availability::{
+        find_free_slots, AvailableDoctor, AvailableRoom, FreeSlot, SlotRequirements, TimeOfDay,
+    },
+    calendar::ClinicCalendar,
+    db::types::{
+        AppointmentRecordWithPatient, AppointmentTypeRecord, Buffers, DeviceRecordId, DoctorRecord,
+        DoctorRecordId, RoomRecord, RoomRecordId, Schedule,
+    },
+    util::{is_valid_timeframe, Booking},
+};
+
+// A doctor appointments can be moved to
+#[derive(Debug, Clone)]
+pub struct CandidateDoctor {
+    pub record: DoctorRecord,
+    pub schedule: Schedule,
+}
+
+impl CandidateDoctor {
+    fn available(&self) -> AvailableDoctor {
+        AvailableDoctor {
+            doctor: DoctorRecordId::from(self.record.id.clone()),
+            schedule: self.schedule.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReschedulePlan {
+    // The appointments with their new time, doctor and room
+    pub moved: Vec<AppointmentRecordWithPatient>,
+    // The appointments no slot was found for within the horizon, unchanged
+    pub unplaced: Vec<AppointmentRecordWithPatient>,
+}
+
+// Moves the appointments of a doctor that is absent up to `absent_until` to the nearest free slots. The doctor
+// takes them back on the first day that has a free slot after the absence, unless a substitute has one earlier.
+// On each day the slot closest to the original time of day is picked, and the doctor is preferred over substitutes.
+// Appointments the doctor only takes part in as staff keep their time, a substitute takes the doctor's place instead.
+pub struct Rescheduler {
+    pub absent_doctor: DoctorRecordId,
+    pub absent_until: NaiveDate,
+    // The last day slots are searched on
+    pub last_day: NaiveDate,
+    // None if the doctor can't take the appointments back, like when they're inactive
+    pub doctor: Option<CandidateDoctor>,
+    pub substitutes: Vec<CandidateDoctor>,
+    // The staff of the affected appointments with their schedules. They take part in the appointments wherever
+    // they're moved, so they have to be free as well.
+    pub staff: Vec<AvailableDoctor>,
+    pub rooms: Vec<RoomRecord>,
+    pub appointment_types: Vec<AppointmentTypeRecord>,
+    pub calendar: ClinicCalendar,
+}
+
+impl Rescheduler {
+    fn appointment_type_of(
+        &self,
+        appointment: &AppointmentRecordWithPatient,
+    ) -> Option<&AppointmentTypeRecord> {
+        self.appointment_types.iter().find(|appointment_type| {
+            appointment_type.appointment_type() == appointment.appointment_type
+        })
+    }
+
+    // The buffers of the appointment's type in the room, none if the type isn't known
+    fn buffers(&self, appointment: &AppointmentRecordWithPatient, room: &RoomRecord) -> Buffers {
+        self.appointment_type_of(appointment)
+            .map(|appointment_type| Buffers::new(appointment_type, room))
+            .unwrap_or_default()
+    }
+
+    // The original room is preferred, then the other rooms the appointment type can take place in
+    fn rooms_for(&self, appointment: &AppointmentRecordWithPatient) -> Vec<AvailableRoom> {
+        let room_types = self
+            .appointment_type_of(appointment)
+            .map(|appointment_type| appointment_type.room_types.as_slice())
+            .unwrap_or_default();
+
+        std::iter::once(&appointment.room)
+            .chain(self.rooms.iter().filter(|room| {
+                room.id != appointment.room.id && room_types.contains(&room.room_type)
+            }))
+            .map(|room| AvailableRoom {
+                room: RoomRecordId::from(room.id.clone()),
+                buffers: self.buffers(appointment, room),
+            })
+            .collect()
+    }
+
+    // The appointment keeps its length, staff and devices
+    fn requirements_of(&self, appointment: &AppointmentRecordWithPatient) -> SlotRequirements {
+        SlotRequirements {
+            duration: appointment.end_time - appointment.start_time,
+            staff: appointment
+                .staff
+                .iter()
+                .map(|member| {
+                    let doctor = DoctorRecordId::from(member.id.clone());
+                    let schedule = self
+                        .staff
+                        .iter()
+                        .find(|available| available.doctor == doctor)
+                        .map(|available| available.schedule.clone())
+                        .unwrap_or_default();
+                    AvailableDoctor { doctor, schedule }
+                })
+                .collect(),
+            devices: appointment
+                .devices
+                .iter()
+                .map(|device| DeviceRecordId::from(device.id.clone()))
+                .collect(),
+            starts_after: None,
+        }
+    }
+
+    // The slot on the date that starts closest to the time, or None if there's none
+    fn closest(slots: Vec<FreeSlot>, time: NaiveTime) -> Option<FreeSlot> {
+        slots
+            .into_iter()
+            .min_by_key(|slot| (slot.start_time.time() - time).num_seconds().abs())
+    }
+
+    async fn find_slot(
+        &self,
+        appointment: &AppointmentRecordWithPatient,
+        booked: &[AppointmentRecordWithPatient],
+    ) -> Option<FreeSlot> {
+        let requirements = self.requirements_of(appointment);
+        let time = appointment.start_time.time();
+        let rooms = self.rooms_for(appointment);
+        let substitutes: Vec<AvailableDoctor> = self
+            .substitutes
+            .iter()
+            .map(CandidateDoctor::available)
+            .collect();
+
+        // Substitutes can take over right away, the doctor only after their absence
+        let first_day = if substitutes.is_empty() {
+            self.absent_until.succ_opt()?
+        } else {
+            appointment.start_time.date()
+        };
+
+        for date in first_day
+            .iter_days()
+            .take_while(|date| *date <= self.last_day)
+        {
+            let appointments_of_day: Vec<AppointmentRecordWithPatient> = booked
+                .iter()
+                .filter(|other| other.id != appointment.id && other.start_time.date() == date)
+                .cloned()
+                .collect();
+
+            let mut candidates = Vec::new();
+            if let Some(doctor) = self.doctor.as_ref().filter(|_| date > self.absent_until) {
+                candidates.push(vec![doctor.available()]);
+            }
+            candidates.push(substitutes.clone());
+
+            for doctors in candidates {
+                let slots = find_free_slots(
+                    date,
+                    &requirements,
+                    &doctors,
+                    &rooms,
+                    &appointments_of_day,
+                    &self.calendar,
+                    TimeOfDay::default(),
+                )
+                .await;
+                if let Some(slot) = Self::closest(slots, time) {
+                    return Some(slot);
+                }
+            }
+        }
+
+        None
+    }
+
+    // The appointment with a substitute in the absent doctor's place among its staff, or None if none of them is free
+    async fn replace_staff(
+        &self,
+        appointment: &AppointmentRecordWithPatient,
+        booked: &[AppointmentRecordWithPatient],
+    ) -> Option<AppointmentRecordWithPatient> {
+        let doctor = DoctorRecordId::from(appointment.doctor.id.clone());
+        let room = RoomRecordId::from(appointment.room.id.clone());
+        let devices: Vec<DeviceRecordId> = appointment
+            .devices
+            .iter()
+            .map(|device| DeviceRecordId::from(device.id.clone()))
+            .collect();
+        let others: Vec<DoctorRecordId> = appointment
+            .staff
+            .iter()
+            .map(|member| DoctorRecordId::from(member.id.clone()))
+            .filter(|member| *member != self.absent_doctor)
+            .collect();
+        let appointments_of_day: Vec<AppointmentRecordWithPatient> = booked
+            .iter()
+            .filter(|other| {
+                other.id != appointment.id
+                    && other.start_time.date() == appointment.start_time.date()
+            })
+            .cloned()
+            .collect();
+
+        for substitute in &self.substitutes {
+            let candidate = DoctorRecordId::from(substitute.record.id.clone());
+            if candidate == doctor || others.contains(&candidate) {
+                continue;
+            }
+            let staff: Vec<DoctorRecordId> = others
+                .iter()
+                .cloned()
+                .chain(std::iter::once(candidate))
+                .collect();
+            let booking = Booking {
+                start_time: appointment.start_time,
+                end_time: appointment.end_time,
+                blocked: appointment.blocked,
+                doctor: &doctor,
+                room: &room,
+                staff: &staff,
+                devices: &devices,
+            };
+            // The others are booked for the appointment already, so only the substitute's schedule is checked
+            if is_valid_timeframe(
+                &booking,
+                std::slice::from_ref(&substitute.schedule),
+                &appointments_of_day,
+                &self.calendar,
+            )
+            .await
+            .is_ok()
+            {
+                let mut replaced = appointment.clone();
+                for member in &mut replaced.staff {
+                    if member.id == *self.absent_doctor.as_thing() {
+                        *member = substitute.record.clone();
+                    }
+                }
+                return Some(replaced);
+            }
+        }
+
+        None
+    }
+
+    fn doctor_record(&self, id: &DoctorRecordId) -> Option<&DoctorRecord> {
+        self.doctor
+            .iter()
+            .chain(self.substitutes.iter())
+            .map(|doctor| &doctor.record)
+            .find(|record| record.id == *id.as_thing())
+    }
+
+    // Places the affected appointments one after the other, earliest first. `booked` are the appointments on the
+    // days that are searched, the affected ones included, since they keep blocking their room until they're moved.
+    pub async fn plan(
+        &self,
+        mut affected: Vec<AppointmentRecordWithPatient>,
+        mut booked: Vec<AppointmentRecordWithPatient>,
+    ) -> ReschedulePlan {
+        affected.sort_by_key(|appointment| appointment.start_time);
+
+        let mut plan = ReschedulePlan::default();
+        for appointment in affected {
+            if appointment.doctor.id != *self.absent_doctor.as_thing() {
+                match self.replace_staff(&appointment, &booked).await {
+                    Some(replaced) => {
+                        booked.retain(|other| other.id != replaced.id);
+                        booked.push(replaced.clone());
+                        plan.moved.push(replaced);
+                    }
+                    None => plan.unplaced.push(appointment),
+                }
+                continue;
+            }
+
+            let Some(slot) = self.find_slot(&appointment, &booked).await else {
+                plan.unplaced.push(appointment);
+                continue;
+            };
+
+            let mut moved = appointment.clone();
+            moved.start_time = slot.start_time;
+            moved.end_time = slot.end_time;
+            if let Some(doctor) = self.doctor_record(&slot.doctor) {
+                moved.doctor = doctor.clone();
+            }
+            if let Some(room) = self
+                .rooms
+                .iter()
+                .find(|room| room.id == *slot.room.as_thing())
+            {
+                moved.room = room.clone();
+            }
+            moved.blocked = self
+                .buffers(&appointment, &moved.room)
+                .blocked_time(moved.start_time, moved.end_time);
+
+            booked.retain(|other| other.id != moved.id);
+            booked.push(moved.clone());
+            plan.moved.push(moved);
+        }
+
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::config_tests::get_test_config,
+        db::types::{AppointmentType, RoomType, Shift, Weekday},
+        util::util_tests::{appointment, time},
+    };
+    use surrealdb::sql::Thing;
+
+    use super::*;
+
+    fn candidate(id: &str, schedule: Schedule) -> CandidateDoctor {
+        CandidateDoctor {
+            record: appointment(id, "1", "2023-10-02T10:00:00", "2023-10-02T11:00:00").doctor,
+            schedule,
+        }
+    }
+
+    fn rescheduler(substitutes: Vec<CandidateDoctor>) -> Rescheduler {
+        Rescheduler {
+            absent_doctor: DoctorRecordId::new("1"),
+            absent_until: time("2023-10-02T00:00:00").date(),
+            last_day: time("2023-10-06T00:00:00").date(),
+            doctor: Some(candidate("1", Schedule::default())),
+            substitutes,
+            staff: vec![],
+            rooms: vec![
+                appointment("1", "1", "2023-10-02T10:00:00", "2023-10-02T11:00:00").room,
+                appointment("1", "2", "2023-10-02T10:00:00", "2023-10-02T11:00:00").room,
+            ],
+            appointment_types: vec![AppointmentTypeRecord {
+                id: Thing::from(("appointment_type", "extensive_care")),
+                name: "Extensive Care".to_string(),
+                duration_minutes: 60,
+                buffer_before_minutes: 0,
+                buffer_after_minutes: 0,
+                colour: "#1e88e5".to_string(),
+                room_types: vec![RoomType::Examination],
+            }],
+            calendar: ClinicCalendar::new(&get_test_config(), vec![], vec![], vec![]),
+        }
+    }
+
+    fn with_id(
+        mut appointment: AppointmentRecordWithPatient,
+        id: &str,
+    ) -> AppointmentRecordWithPatient {
+        appointment.id = Thing::from(("appointment", id));
+        appointment
+    }
+
+    #[tokio::test]
+    async fn test_plan_moves_to_nearest_slot() {
+        // Doctor 1 is absent on Monday. On Tuesday they have an appointment at the same time already, and both
+        // rooms are taken for the hour before it.
+        let absent = with_id(
+            appointment("1", "1", "2023-10-02T10:00:00", "2023-10-02T11:00:00"),
+            "absent",
+        );
+        let booked = vec![
+            absent.clone(),
+            with_id(
+                appointment("1", "2", "2023-10-03T10:00:00", "2023-10-03T11:00:00"),
+                "same_time",
+            ),
+            with_id(
+                appointment("3", "1", "2023-10-03T08:00:00", "2023-10-03T09:30:00"),
+                "other_room",
+            ),
+            with_id(
+                appointment("4", "2", "2023-10-03T08:00:00", "2023-10-03T09:15:00"),
+                "both_rooms",
+            ),
+        ];
+
+        let plan = rescheduler(vec![])
+            .plan(vec![absent.clone()], booked.clone())
+            .await;
+        assert!(plan.unplaced.is_empty());
+        assert_eq!(plan.moved[0].start_time, time("2023-10-03T11:00:00"));
+        assert_eq!(plan.moved[0].end_time, time("2023-10-03T12:00:00"));
+        assert_eq!(plan.moved[0].room.id, absent.room.id);
+
+        // Doctor 5 takes part as well, and is busy right after the appointment on Tuesday, so it's moved after the
+        // lunch break
+        let mut with_staff = absent.clone();
+        with_staff.staff =
+            vec![appointment("5", "3", "2023-10-03T11:00:00", "2023-10-03T12:00:00").doctor];
+        let mut busy_staff = booked.clone();
+        busy_staff.push(with_id(
+            appointment("5", "3", "2023-10-03T11:00:00", "2023-10-03T12:00:00"),
+            "staff",
+        ));
+        let plan = rescheduler(vec![]).plan(vec![with_staff], busy_staff).await;
+        assert_eq!(plan.moved[0].start_time, time("2023-10-03T13:00:00"));
+
+        // A substitute takes it over on the same day
+        let plan = rescheduler(vec![candidate("2", Schedule::default())])
+            .plan(vec![absent.clone()], booked)
+            .await;
+        assert_eq!(plan.moved[0].start_time, absent.start_time);
+        assert_eq!(
+            plan.moved[0].doctor.id,
+            *DoctorRecordId::new("2").as_thing()
+        );
+        assert_eq!(
+            plan.moved[0].appointment_type,
+            AppointmentType::new("extensive_care")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plan_replaces_absent_staff() {
+        // Doctor 1 only takes part in doctor 3's appointment, so it keeps its time and a substitute joins instead
+        let mut with_staff = with_id(
+            appointment("3", "1", "2023-10-02T10:00:00", "2023-10-02T11:00:00"),
+            "with_staff",
+        );
+        with_staff.staff =
+            vec![appointment("1", "1", "2023-10-02T10:00:00", "2023-10-02T11:00:00").doctor];
+        let busy = with_id(
+            appointment("2", "2", "2023-10-02T10:30:00", "2023-10-02T11:00:00"),
+            "busy",
+        );
+
+        let plan = rescheduler(vec![
+            candidate("2", Schedule::default()),
+            candidate("4", Schedule::default()),
+        ])
+        .plan(vec![with_staff.clone()], vec![with_staff.clone(), busy])
+        .await;
+        assert!(plan.unplaced.is_empty());
+        assert_eq!(plan.moved[0].start_time, with_staff.start_time);
+        assert_eq!(plan.moved[0].doctor.id, with_staff.doctor.id);
+        // Doctor 2 is busy at the time, so doctor 4 takes part
+        let staff: Vec<_> = plan.moved[0]
+            .staff
+            .iter()
+            .map(|member| &member.id)
+            .collect();
+        assert_eq!(staff, [DoctorRecordId::new("4").as_thing()]);
+
+        // Without substitutes, nobody can take the doctor's place
+        let plan = rescheduler(vec![])
+            .plan(vec![with_staff.clone()], vec![with_staff])
+            .await;
+        assert!(plan.moved.is_empty());
+        assert_eq!(plan.unplaced.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_plan_places_appointments_after_each_other() {
+        let first = with_id(
+            appointment("1", "1", "2023-10-02T10:00:00", "2023-10-02T11:00:00"),
+            "first",
+        );
+        let second = with_id(
+            appointment("1", "2", "2023-10-02T10:00:00", "2023-10-02T11:00:00"),
+            "second",
+        );
+
+        let plan = rescheduler(vec![])
+            .plan(
+                vec![second.clone(), first.clone()],
+                vec![first.clone(), second.clone()],
+            )
+            .await;
+        let start_times: Vec<_> = plan.moved.iter().map(|moved| moved.start_time).collect();
+        assert_eq!(
+            start_times,
+            [time("2023-10-03T10:00:00"), time("2023-10-03T09:00:00")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plan_reports_unplaced_appointments() {
+        // The doctor only works on Saturdays, which the clinic is closed on
+        let mut rescheduler = rescheduler(vec![]);
+        rescheduler.doctor = Some(candidate(
+            "1",
+            Schedule {
+                shifts: vec![Shift {
+                    weekday: Weekday::Saturday,
+                    start_time: "08:00:00".parse().unwrap(),
+                    end_time: "17:00:00".parse().unwrap(),
+                }],
+            },
+        ));
+        let absent = appointment("1", "1", "2023-10-02T10:00:00", "2023-10-02T11:00:00");
+
+        let plan = rescheduler.plan(vec![absent.clone()], vec![absent]).await;
+        assert!(plan.moved.is_empty());
+        assert_eq!(plan.unplaced.len(), 1);
+
+        // Without the doctor there's nobody to take it
+        rescheduler.doctor = None;
+        let absent = appointment("1", "1", "2023-10-02T10:00:00", "2023-10-02T11:00:00");
+        let plan = rescheduler.plan(vec![absent.clone()], vec![absent]).await;
+        assert_eq!(plan.unplaced.len(), 1);
+    }
+}
@@ -0,0 +1,106 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+use crate::db::{
+    db::Database,
+    types::{Room, RoomFilter, RoomSort, RoomType},
+};
+use crate::errors::{not_found, ApiError};
+use crate::types::{ApiResponse, PageRequest, PaginatedResponse};
+
+// Room Types
+#[derive(Deserialize)]
+pub struct RoomId {
+    id: String,
+}
+#[derive(Deserialize)]
+pub struct UpdateRoom {
+    name: Option<String>,
+    room_type: Option<RoomType>,
+    equipment: Option<Vec<String>>,
+    buffer_before_minutes: Option<u32>,
+    buffer_after_minutes: Option<u32>,
+}
+
+// Endpoints
+pub async fn read_all_rooms(
+    database: web::Data<Database>,
+    filter: web::Query<RoomFilter>,
+    page_request: web::Query<PageRequest<RoomSort>>,
+) -> Result<HttpResponse, ApiError> {
+    let pagination = page_request
+        .into_inner()
+        .into_pagination("room")
+        .map_err(ApiError::InvalidRequest)?;
+
+    let page = database.read_room_page(&filter, &pagination).await?;
+
+    Ok(HttpResponse::Ok().json(PaginatedResponse::from(page)))
+}
+
+pub async fn create_room(
+    database: web::Data<Database>,
+    room: web::Json<Room>,
+) -> Result<HttpResponse, ApiError> {
+    let result = database.create_room(room.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+pub async fn delete_room(
+    database: web::Data<Database>,
+    room_id: web::Path<RoomId>,
+) -> Result<HttpResponse, ApiError> {
+    let room = database
+        .delete_room(&room_id.id)
+        .await
+        .map_err(not_found("Room"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: room }))
+}
+
+pub async fn update_room(
+    database: web::Data<Database>,
+    room_id: web::Path<RoomId>,
+    update: web::Json<UpdateRoom>,
+) -> Result<HttpResponse, ApiError> {
+    let mut room = database
+        .read_room(&room_id.id)
+        .await
+        .map_err(not_found("Room"))?;
+
+    if let Some(name) = &update.name {
+        room.name = name.clone();
+    }
+    if let Some(room_type) = update.room_type {
+        room.room_type = room_type;
+    }
+    if let Some(equipment) = &update.equipment {
+        room.equipment = equipment.clone();
+    }
+    if let Some(buffer_before_minutes) = update.buffer_before_minutes {
+        room.buffer_before_minutes = buffer_before_minutes;
+    }
+    if let Some(buffer_after_minutes) = update.buffer_after_minutes {
+        room.buffer_after_minutes = buffer_after_minutes;
+    }
+
+    let result = database
+        .update_room(&room_id.id, room)
+        .await
+        .map_err(not_found("Room"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+pub async fn read_room(
+    database: web::Data<Database>,
+    room_id: web::Path<RoomId>,
+) -> Result<HttpResponse, ApiError> {
+    let room = database
+        .read_room(&room_id.id)
+        .await
+        .map_err(not_found("Room"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: room }))
+}
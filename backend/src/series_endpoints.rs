@@ -0,0 +1,179 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+
+use crate::appointment_endpoints::{
+    date_range_filter, read_appointment_type, read_schedules, validate_doctor, validate_resources,
+    validate_room,
+};
+use crate::calendar::ClinicCalendar;
+use crate::config::AppConfig;
+use crate::db::{
+    db::Database,
+    types::{AppointmentSeries, AppointmentSeriesRecord, AppointmentWithTime, Buffers, DateRange},
+};
+use crate::errors::{not_found, ApiError};
+use crate::types::ApiResponse;
+use crate::util::{is_valid_timeframe, Booking};
+
+// Series Types
+#[derive(Deserialize)]
+pub struct SeriesId {
+    id: String,
+}
+#[derive(Deserialize)]
+pub struct CreateSeriesRequest {
+    #[serde(flatten)]
+    series: AppointmentSeries,
+    // Only checks the occurrences, without booking any
+    #[serde(default)]
+    dry_run: bool,
+}
+#[derive(Serialize)]
+pub struct Occurrence {
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    // The booked appointment, None on a dry run
+    appointment: Option<Thing>,
+}
+#[derive(Serialize)]
+pub struct OccurrenceConflict {
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    // The error booking the occurrence on its own would get
+    code: &'static str,
+    message: String,
+}
+#[derive(Serialize)]
+pub struct CreateSeriesResponse {
+    // Whether the occurrences were only checked
+    dry_run: bool,
+    // None on a dry run
+    series: Option<AppointmentSeriesRecord>,
+    // The occurrences that are free
+    occurrences: Vec<Occurrence>,
+    // The occurrences that can't be booked, they're left out of the series
+    conflicts: Vec<OccurrenceConflict>,
+}
+
+// Endpoints
+
+// Every occurrence is checked like a single booking. The free ones are booked along with the series, the others are
+// reported and left out.
+pub async fn create_appointment_series(
+    database: web::Data<Database>,
+    config: web::Data<AppConfig>,
+    request: web::Json<CreateSeriesRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let CreateSeriesRequest { series, dry_run } = request.into_inner();
+    series
+        .rule
+        .validate(series.start_time)
+        .map_err(ApiError::InvalidRequest)?;
+    let appointment_type = read_appointment_type(&database, &series.appointment_type).await?;
+    validate_doctor(&database, &series.doctor).await?;
+    validate_resources(&database, &series.doctor, &series.staff, &series.devices).await?;
+    let room = validate_room(&database, &series.room, &appointment_type).await?;
+    let buffers = Buffers::new(&appointment_type, &room);
+    database
+        .read_patient(&series.patient_id.get_unique_id())
+        .await
+        .map_err(not_found("Patient"))?;
+
+    let start_times = series.rule.occurrences(series.start_time);
+    let range = DateRange {
+        from: start_times.first().map(|start_time| start_time.date()),
+        to: start_times.last().map(|start_time| start_time.date()),
+    };
+    let booked = database
+        .read_appointments(&date_range_filter(range))
+        .await?;
+    let schedules = read_schedules(&database, &series.doctor, &series.staff).await?;
+    let calendar = ClinicCalendar::load(&database, &config, range).await?;
+
+    let mut free = Vec::new();
+    let mut conflicts = Vec::new();
+    for start_time in start_times {
+        let end_time = start_time + appointment_type.duration();
+        let appointment = AppointmentWithTime {
+            start_time,
+            end_time,
+            blocked: buffers.blocked_time(start_time, end_time),
+            appointment_type: series.appointment_type.clone(),
+            patient_id: series.patient_id.clone(),
+            doctor: series.doctor.clone(),
+            room: series.room.clone(),
+            staff: series.staff.clone(),
+            devices: series.devices.clone(),
+            series: None,
+        };
+        match is_valid_timeframe(&Booking::from(&appointment), &schedules, &booked, &calendar).await
+        {
+            Ok(()) => free.push(appointment),
+            Err(err) => conflicts.push((start_time, end_time, err)),
+        }
+    }
+
+    // A series without a single appointment isn't created, the first reason is as good as any
+    if free.is_empty() && !dry_run {
+        if let Some((_, _, err)) = conflicts.first() {
+            return Err(ApiError::Timeframe(*err));
+        }
+    }
+
+    let (series, occurrences) = if dry_run {
+        let occurrences = free
+            .into_iter()
+            .map(|appointment| Occurrence {
+                start_time: appointment.start_time,
+                end_time: appointment.end_time,
+                appointment: None,
+            })
+            .collect();
+        (None, occurrences)
+    } else {
+        let (series, appointments) = database
+            .create_series(series, free)
+            .await
+            .map_err(not_found("Patient"))?;
+        let occurrences = appointments
+            .into_iter()
+            .map(|appointment| Occurrence {
+                start_time: appointment.start_time,
+                end_time: appointment.end_time,
+                appointment: Some(appointment.id),
+            })
+            .collect();
+        (Some(series), occurrences)
+    };
+
+    Ok(HttpResponse::Ok().json(CreateSeriesResponse {
+        dry_run,
+        series,
+        occurrences,
+        conflicts: conflicts
+            .into_iter()
+            .map(|(start_time, end_time, err)| OccurrenceConflict {
+                start_time,
+                end_time,
+                code: err.code(),
+                message: err.to_string(),
+            })
+            .collect(),
+    }))
+}
+
+// The rule and details the series was created with, its appointments are read with the `series` filter of
+// `GET /appointment`
+pub async fn read_appointment_series(
+    database: web::Data<Database>,
+    path: web::Path<SeriesId>,
+) -> Result<HttpResponse, ApiError> {
+    let series = database
+        .read_series(&path.id)
+        .await
+        .map_err(not_found("Appointment series"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: series }))
+}
@@ -1,8 +1,78 @@
 // This is only for global types that have no association with e.g. exclusively patient endpoints
 
-use serde::Serialize;
+use std::{str::FromStr, time::Instant};
+
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+
+use crate::db::types::{Page, Pagination, SortOrder};
+
+pub const DEFAULT_PAGE_LIMIT: u32 = 100;
+pub const MAX_PAGE_LIMIT: u32 = 1000;
 
 #[derive(Serialize)]
 pub(crate) struct ApiResponse<T> {
     pub data: T,
 }
+
+// List endpoints return one page at a time. `next_cursor` is passed as `cursor` to get the next page, and is
+// missing on the last one.
+#[derive(Serialize)]
+pub(crate) struct PaginatedResponse<T> {
+    pub data: Vec<T>,
+    pub total: u64,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> From<Page<T>> for PaginatedResponse<T> {
+    fn from(page: Page<T>) -> Self {
+        PaginatedResponse {
+            data: page.items,
+            total: page.total,
+            next_cursor: page.next_cursor,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PageRequest<S> {
+    limit: Option<u32>,
+    offset: Option<u32>,
+    cursor: Option<String>,
+    sort: Option<S>,
+    order: Option<SortOrder>,
+}
+
+impl<S: Default> PageRequest<S> {
+    // `table` is the table the cursor has to point into
+    pub fn into_pagination(self, table: &str) -> Result<Pagination<S>, String> {
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        if limit == 0 || limit > MAX_PAGE_LIMIT {
+            return Err(format!(
+                "limit must be between 1 and {}, got {}",
+                MAX_PAGE_LIMIT, limit
+            ));
+        }
+
+        let cursor = match self.cursor {
+            Some(cursor) => match Thing::from_str(&cursor) {
+                Ok(thing) if thing.tb == table => Some(thing),
+                _ => return Err(format!("Invalid cursor: {}", cursor)),
+            },
+            None => None,
+        };
+
+        Ok(Pagination {
+            limit,
+            offset: self.offset.unwrap_or_default(),
+            cursor,
+            sort: self.sort.unwrap_or_default(),
+            order: self.order.unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ServerInfo {
+    pub started_at: Instant,
+}
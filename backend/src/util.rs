@@ -1,37 +1,176 @@
-use crate::{config::AppConfig, db::types::AppointmentRecordWithPatient};
-use chrono::{Duration, NaiveDateTime};
+use crate::{
+    calendar::ClinicCalendar,
+    db::types::{
+        AppointmentRecord, AppointmentRecordWithPatient, AppointmentWithTime, BlockedTime,
+        DeviceRecordId, DoctorRecordId, RoomRecordId, Schedule,
+    },
+};
+use chrono::NaiveDateTime;
+use thiserror::Error;
 
+#[derive(Debug, Error, Clone, Copy, PartialEq)]
+pub enum TimeframeError {
+    #[error("The clinic is closed on that day")]
+    ClinicClosed,
+    #[error("Appointment is outside of opening hours")]
+    OutsideOpeningHours,
+    #[error("Appointment is during break time")]
+    DuringBreak,
+    #[error("Appointment cannot span across break time")]
+    SpansBreak,
+    #[error("Appointment is outside of the working hours of the doctor or the staff")]
+    OutsideWorkingHours,
+    #[error("The doctor or a member of the staff is absent at that time")]
+    DoctorAbsent,
+    #[error("Appointment overlaps with another appointment")]
+    Overlap,
+}
+
+impl TimeframeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TimeframeError::ClinicClosed => "clinic_closed",
+            TimeframeError::OutsideOpeningHours => "outside_opening_hours",
+            TimeframeError::DuringBreak => "during_break",
+            TimeframeError::SpansBreak => "spans_break",
+            TimeframeError::OutsideWorkingHours => "outside_working_hours",
+            TimeframeError::DoctorAbsent => "doctor_absent",
+            // Same code as a conflict detected by the database, it's the same problem
+            TimeframeError::Overlap => "appointment_conflict",
+        }
+    }
+}
+
+// An appointment that is checked before it's booked or moved
+#[derive(Debug, Clone, Copy)]
+pub struct Booking<'a> {
+    pub start_time: NaiveDateTime,
+    pub end_time: NaiveDateTime,
+    // Only used to check for overlaps, the other checks are about the time the patient is there
+    pub blocked: BlockedTime,
+    pub doctor: &'a DoctorRecordId,
+    pub room: &'a RoomRecordId,
+    pub staff: &'a [DoctorRecordId],
+    pub devices: &'a [DeviceRecordId],
+}
+
+impl Booking<'_> {
+    // The doctor and the staff, everyone who has to be there
+    pub fn participants(&self) -> impl Iterator<Item = &DoctorRecordId> {
+        std::iter::once(self.doctor).chain(self.staff)
+    }
+}
+
+impl<'a> From<&'a AppointmentWithTime> for Booking<'a> {
+    fn from(appointment: &'a AppointmentWithTime) -> Self {
+        Booking {
+            start_time: appointment.start_time,
+            end_time: appointment.end_time,
+            blocked: appointment.blocked,
+            doctor: &appointment.doctor,
+            room: &appointment.room,
+            staff: &appointment.staff,
+            devices: &appointment.devices,
+        }
+    }
+}
+
+impl<'a> From<&'a AppointmentRecord> for Booking<'a> {
+    fn from(appointment: &'a AppointmentRecord) -> Self {
+        Booking {
+            start_time: appointment.start_time,
+            end_time: appointment.end_time,
+            blocked: appointment.blocked,
+            doctor: &appointment.doctor,
+            room: &appointment.room,
+            staff: &appointment.staff,
+            devices: &appointment.devices,
+        }
+    }
+}
+
+// `schedules` are the ones of the doctor and the staff of the booking, every one of them has to be working. Breaks and
+// absences count for each of them as well.
 pub async fn is_valid_timeframe(
-    start_time: NaiveDateTime,
-    end_time: NaiveDateTime,
-    doctor: u32,
-    room_nr: u32,
-    appointments: &Vec<AppointmentRecordWithPatient>,
-    config: &AppConfig,
-) -> Result<(), String> {
-    // Check if the appointment is within opening hours
-    let break_end_time = config.break_time + Duration::hours(1);
-
-    if start_time.time() < config.opening_time || end_time.time() > config.closing_time {
-        return Err("Appointment is outside of opening hours".to_string());
-    }
-
-    if (start_time.time() >= config.break_time && start_time.time() < break_end_time)
-        || (end_time.time() > config.break_time && end_time.time() <= break_end_time)
+    booking: &Booking<'_>,
+    schedules: &[Schedule],
+    appointments: &[AppointmentRecordWithPatient],
+    calendar: &ClinicCalendar,
+) -> Result<(), TimeframeError> {
+    let Booking {
+        start_time,
+        end_time,
+        room,
+        devices,
+        ..
+    } = *booking;
+
+    // Check if the clinic is open on that day, and if the appointment is within its opening hours
+    let (opening_time, closing_time) = calendar
+        .opening_hours(start_time.date())
+        .ok_or(TimeframeError::ClinicClosed)?;
+
+    if start_time.time() < opening_time || end_time.time() > closing_time {
+        return Err(TimeframeError::OutsideOpeningHours);
+    }
+
+    for clinic_break in booking
+        .participants()
+        .flat_map(|participant| calendar.breaks(start_time.date(), participant))
     {
-        return Err("Appointment is during break time".to_string());
+        let break_time = clinic_break.start_time;
+        let break_end_time = clinic_break.end_time();
+
+        if (start_time.time() >= break_time && start_time.time() < break_end_time)
+            || (end_time.time() > break_time && end_time.time() <= break_end_time)
+        {
+            return Err(TimeframeError::DuringBreak);
+        }
+
+        if start_time.time() < break_time && end_time.time() > break_time {
+            return Err(TimeframeError::SpansBreak);
+        }
+    }
+
+    // Check if the doctor and the staff are working at that time
+    if !schedules
+        .iter()
+        .all(|schedule| schedule.is_working(start_time, end_time))
+    {
+        return Err(TimeframeError::OutsideWorkingHours);
     }
 
-    if start_time.time() < config.break_time && end_time.time() > config.break_time {
-        return Err("Appointment cannot span across break time".to_string());
+    // Check if any of them is away, like on a sick leave
+    if booking.participants().any(|participant| {
+        calendar
+            .absence(participant, start_time, end_time)
+            .is_some()
+    }) {
+        return Err(TimeframeError::DoctorAbsent);
     }
 
-    // Check for overlapping appointments
-    for appointment in appointments {
-        if (appointment.doctor == doctor || appointment.room_nr == room_nr)
-            && (start_time < appointment.end_time && end_time > appointment.start_time)
+    // Check for overlapping appointments, their buffers included. Staff members are busy like the doctor, devices like
+    // the room.
+    for appointment in appointments
+        .iter()
+        .filter(|appointment| appointment.status.blocks_slot())
+    {
+        let shares_participant = std::iter::once(&appointment.doctor)
+            .chain(&appointment.staff)
+            .any(|member| {
+                booking
+                    .participants()
+                    .any(|participant| member.id == *participant.as_thing())
+            });
+        let shares_device = appointment
+            .devices
+            .iter()
+            .any(|device| devices.iter().any(|other| device.id == *other.as_thing()));
+        if (shares_participant && booking.blocked.overlaps_doctor(&appointment.blocked))
+            || ((appointment.room.id == *room.as_thing() || shares_device)
+                && booking.blocked.overlaps_room(&appointment.blocked))
         {
-            return Err("Appointment overlaps with another appointment".to_string());
+            return Err(TimeframeError::Overlap);
         }
     }
 
@@ -39,166 +178,507 @@ pub async fn is_valid_timeframe(
 }
 
 #[cfg(test)]
-mod tests {
-    use chrono::{Duration, NaiveDateTime, NaiveTime};
-
-    struct Config {
-        opening_time: NaiveTime,
-        closing_time: NaiveTime,
-        break_time: NaiveTime,
-        break_duration: Duration,
-    }
-
-    struct Appointment {
-        doctor: u32,
-        room_nr: u32,
-        start_time: NaiveDateTime,
-        end_time: NaiveDateTime,
-    }
-
-    fn is_valid_timeframe(
-        config: &Config,
-        doctor: u32,
-        room_nr: u32,
-        start_time: NaiveDateTime,
-        end_time: NaiveDateTime,
-        appointments: &[Appointment],
-    ) -> Result<(), String> {
-        let break_end_time = config.break_time + config.break_duration;
-
-        if start_time.time() < config.opening_time || end_time.time() > config.closing_time {
-            return Err("Appointment is outside of opening hours".to_string());
-        }
+pub mod util_tests {
+    use chrono::{Duration, NaiveTime};
+    use surrealdb::sql::Thing;
 
-        if (start_time.time() >= config.break_time && start_time.time() < break_end_time)
-            || (end_time.time() > config.break_time && end_time.time() <= break_end_time)
-        {
-            return Err("Appointment is during break time".to_string());
+    use super::*;
+    use crate::{
+        config::{config_tests::get_test_config, Break},
+        db::types::{
+            Absence, AppointmentStatus, AppointmentType, Buffers, Closure, DeviceRecord,
+            DoctorRecord, PatientRecord, RoomRecord, RoomType, Shift, Weekday,
+        },
+    };
+
+    pub fn time(time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S").unwrap()
+    }
+
+    pub fn appointment(
+        doctor: &str,
+        room: &str,
+        start_time: &str,
+        end_time: &str,
+    ) -> AppointmentRecordWithPatient {
+        AppointmentRecordWithPatient {
+            id: Thing::from(("appointment", "1")),
+            start_time: time(start_time),
+            end_time: time(end_time),
+            blocked: Buffers::default().blocked_time(time(start_time), time(end_time)),
+            appointment_type: AppointmentType::new("extensive_care"),
+            patient: PatientRecord {
+                id: Thing::from(("patient", "1")),
+                name: "John Doe".to_string(),
+                phone_number: "1234567890".to_string(),
+                insurance_number: None,
+            },
+            doctor: DoctorRecord {
+                id: DoctorRecordId::new(doctor).as_thing().clone(),
+                name: "Dr. Jane Doe".to_string(),
+                specialty: "general".to_string(),
+                phone_number: None,
+                email: None,
+                active: true,
+            },
+            room: RoomRecord {
+                id: RoomRecordId::new(room).as_thing().clone(),
+                name: "Room 1".to_string(),
+                room_type: RoomType::Examination,
+                equipment: vec![],
+                buffer_before_minutes: 0,
+                buffer_after_minutes: 0,
+            },
+            staff: vec![],
+            devices: vec![],
+            status: AppointmentStatus::Scheduled,
+            status_changes: vec![],
+            series: None,
         }
+    }
+
+    // Checks an appointment of doctor 1 in room 1 on a clinic that is open from 8 to 17, with a lunch break from 12
+    // to 13
+    async fn check(
+        start_time: &str,
+        end_time: &str,
+        schedule: &Schedule,
+        appointments: &[AppointmentRecordWithPatient],
+        calendar: &ClinicCalendar,
+    ) -> Result<(), TimeframeError> {
+        let booking = Booking {
+            start_time: time(start_time),
+            end_time: time(end_time),
+            blocked: Buffers::default().blocked_time(time(start_time), time(end_time)),
+            doctor: &DoctorRecordId::new("1"),
+            room: &RoomRecordId::new("1"),
+            staff: &[],
+            devices: &[],
+        };
+        is_valid_timeframe(
+            &booking,
+            std::slice::from_ref(schedule),
+            appointments,
+            calendar,
+        )
+        .await
+    }
+
+    fn calendar() -> ClinicCalendar {
+        ClinicCalendar::new(&get_test_config(), vec![], vec![], vec![])
+    }
+
+    #[tokio::test]
+    async fn test_appointment_outside_opening_hours() {
+        let schedule = Schedule::default();
 
-        if start_time.time() < config.break_time && end_time.time() > config.break_time {
-            return Err("Appointment cannot span across break time".to_string());
+        for (start_time, end_time) in [
+            ("2023-10-02T07:30:00", "2023-10-02T08:30:00"),
+            ("2023-10-02T16:30:00", "2023-10-02T17:30:00"),
+        ] {
+            let result = check(start_time, end_time, &schedule, &[], &calendar()).await;
+            assert_eq!(result, Err(TimeframeError::OutsideOpeningHours));
         }
+    }
+
+    #[tokio::test]
+    async fn test_appointment_when_clinic_is_closed() {
+        let config = get_test_config();
+        let calendar = ClinicCalendar::new(
+            &config,
+            vec![Closure {
+                date: "2023-10-03".parse().unwrap(),
+                name: "German Unity Day".to_string(),
+            }],
+            vec![],
+            vec![],
+        );
 
-        for appointment in appointments {
-            if (appointment.doctor == doctor || appointment.room_nr == room_nr)
-                && (start_time < appointment.end_time && end_time > appointment.start_time)
-            {
-                return Err("Appointment overlaps with another appointment".to_string());
-            }
+        // A Sunday and a closure
+        for (start_time, end_time) in [
+            ("2023-10-01T10:00:00", "2023-10-01T11:00:00"),
+            ("2023-10-03T10:00:00", "2023-10-03T11:00:00"),
+        ] {
+            let result = check(start_time, end_time, &Schedule::default(), &[], &calendar).await;
+            assert_eq!(result, Err(TimeframeError::ClinicClosed));
         }
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn test_appointment_during_break_time() {
+        for (start_time, end_time) in [
+            ("2023-10-02T12:30:00", "2023-10-02T13:00:00"),
+            ("2023-10-02T11:30:00", "2023-10-02T12:30:00"),
+        ] {
+            let result = check(start_time, end_time, &Schedule::default(), &[], &calendar()).await;
+            assert_eq!(result, Err(TimeframeError::DuringBreak));
+        }
     }
 
-    #[test]
-    fn test_appointment_outside_opening_hours() {
-        let config = Config {
-            opening_time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
-            closing_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
-            break_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
-            break_duration: Duration::minutes(60),
-        };
+    #[tokio::test]
+    async fn test_appointment_spanning_break_time() {
+        let result = check(
+            "2023-10-02T11:30:00",
+            "2023-10-02T13:30:00",
+            &Schedule::default(),
+            &[],
+            &calendar(),
+        )
+        .await;
+        assert_eq!(result, Err(TimeframeError::SpansBreak));
+    }
 
-        let start_time =
-            NaiveDateTime::parse_from_str("2023-10-01T08:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
-        let end_time =
-            NaiveDateTime::parse_from_str("2023-10-01T09:30:00", "%Y-%m-%dT%H:%M:%S").unwrap();
-        let appointments = vec![];
+    #[tokio::test]
+    async fn test_appointment_during_configured_breaks() {
+        let mut config = get_test_config();
+        config.breaks.push(Break {
+            name: "Coffee".to_string(),
+            start_time: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+            duration_minutes: 15,
+            weekdays: Some(vec![Weekday::Monday]),
+            doctor: None,
+        });
+        config.breaks.push(Break {
+            name: "Rounds".to_string(),
+            start_time: NaiveTime::from_hms_opt(15, 0, 0).unwrap(),
+            duration_minutes: 30,
+            weekdays: None,
+            doctor: Some(DoctorRecordId::new("1")),
+        });
+        config.breaks.push(Break {
+            name: "Meeting".to_string(),
+            start_time: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+            duration_minutes: 30,
+            weekdays: None,
+            doctor: Some(DoctorRecordId::new("2")),
+        });
+        let calendar = ClinicCalendar::new(&config, vec![], vec![], vec![]);
 
-        let result = is_valid_timeframe(&config, 1, 101, start_time, end_time, &appointments);
-        assert_eq!(
-            result,
-            Err("Appointment is outside of opening hours".to_string())
-        );
+        let cases = [
+            // The coffee break only applies on Mondays
+            (
+                "2023-10-02T10:00:00",
+                "2023-10-02T10:30:00",
+                Err(TimeframeError::DuringBreak),
+            ),
+            ("2023-10-03T10:00:00", "2023-10-03T10:30:00", Ok(())),
+            ("2023-10-02T10:15:00", "2023-10-02T10:45:00", Ok(())),
+            // Rounds are a break of this doctor, the meeting one of another doctor
+            (
+                "2023-10-03T15:15:00",
+                "2023-10-03T15:45:00",
+                Err(TimeframeError::DuringBreak),
+            ),
+            (
+                "2023-10-03T14:30:00",
+                "2023-10-03T15:45:00",
+                Err(TimeframeError::SpansBreak),
+            ),
+            ("2023-10-03T16:00:00", "2023-10-03T16:30:00", Ok(())),
+        ];
+        for (start_time, end_time, expected) in cases {
+            let result = check(start_time, end_time, &Schedule::default(), &[], &calendar).await;
+            assert_eq!(result, expected, "{}", start_time);
+        }
     }
 
-    #[test]
-    fn test_appointment_during_break_time() {
-        let config = Config {
-            opening_time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
-            closing_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
-            break_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
-            break_duration: Duration::minutes(60),
+    #[tokio::test]
+    async fn test_appointment_outside_working_hours() {
+        let schedule = Schedule {
+            shifts: vec![Shift {
+                weekday: Weekday::Monday,
+                start_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                end_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+            }],
         };
 
-        let start_time =
-            NaiveDateTime::parse_from_str("2023-10-01T12:30:00", "%Y-%m-%dT%H:%M:%S").unwrap();
-        let end_time =
-            NaiveDateTime::parse_from_str("2023-10-01T13:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
-        let appointments = vec![];
+        let result = check(
+            "2023-10-02T09:00:00",
+            "2023-10-02T10:00:00",
+            &schedule,
+            &[],
+            &calendar(),
+        )
+        .await;
+        assert_eq!(result, Ok(()));
 
-        let result = is_valid_timeframe(&config, 1, 101, start_time, end_time, &appointments);
-        assert_eq!(result, Err("Appointment is during break time".to_string()));
+        let result = check(
+            "2023-10-02T14:00:00",
+            "2023-10-02T15:00:00",
+            &schedule,
+            &[],
+            &calendar(),
+        )
+        .await;
+        assert_eq!(result, Err(TimeframeError::OutsideWorkingHours));
     }
 
-    #[test]
-    fn test_appointment_spanning_break_time() {
-        let config = Config {
-            opening_time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
-            closing_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
-            break_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
-            break_duration: Duration::minutes(60),
+    #[tokio::test]
+    async fn test_appointment_during_absence() {
+        // Doctor 1 leaves at 14:00 on Monday and is back on Wednesday at 10:00. Doctor 2 is away all Monday.
+        let absence = |doctor: &str, start_date: &str, end_date: &str| Absence {
+            doctor: DoctorRecordId::new(doctor),
+            start_date: start_date.parse().unwrap(),
+            end_date: end_date.parse().unwrap(),
+            start_time: None,
+            end_time: None,
+            reason: "Sick leave".to_string(),
         };
+        let mut leave = absence("1", "2023-10-02", "2023-10-04");
+        leave.start_time = NaiveTime::from_hms_opt(14, 0, 0);
+        leave.end_time = NaiveTime::from_hms_opt(10, 0, 0);
+        let calendar = ClinicCalendar::new(
+            &get_test_config(),
+            vec![],
+            vec![],
+            vec![leave, absence("2", "2023-10-02", "2023-10-02")],
+        );
 
-        let start_time =
-            NaiveDateTime::parse_from_str("2023-10-01T11:30:00", "%Y-%m-%dT%H:%M:%S").unwrap();
-        let end_time =
-            NaiveDateTime::parse_from_str("2023-10-01T13:30:00", "%Y-%m-%dT%H:%M:%S").unwrap();
-        let appointments = vec![];
+        let cases = [
+            ("2023-10-02T13:00:00", "2023-10-02T14:00:00", Ok(())),
+            (
+                "2023-10-02T13:30:00",
+                "2023-10-02T14:30:00",
+                Err(TimeframeError::DoctorAbsent),
+            ),
+            (
+                "2023-10-03T09:00:00",
+                "2023-10-03T10:00:00",
+                Err(TimeframeError::DoctorAbsent),
+            ),
+            (
+                "2023-10-04T09:30:00",
+                "2023-10-04T10:30:00",
+                Err(TimeframeError::DoctorAbsent),
+            ),
+            ("2023-10-04T10:00:00", "2023-10-04T11:00:00", Ok(())),
+        ];
+        for (start_time, end_time, expected) in cases {
+            let result = check(start_time, end_time, &Schedule::default(), &[], &calendar).await;
+            assert_eq!(result, expected, "{}", start_time);
+        }
+    }
 
-        let result = is_valid_timeframe(&config, 1, 101, start_time, end_time, &appointments);
-        assert_eq!(
-            result,
-            Err("Appointment cannot span across break time".to_string())
-        );
+    #[tokio::test]
+    async fn test_appointment_overlapping_another() {
+        let cases = [
+            // Same doctor, other room
+            (
+                appointment("1", "2", "2023-10-02T10:30:00", "2023-10-02T11:30:00"),
+                Err(TimeframeError::Overlap),
+            ),
+            // Other doctor, same room
+            (
+                appointment("2", "1", "2023-10-02T10:30:00", "2023-10-02T11:30:00"),
+                Err(TimeframeError::Overlap),
+            ),
+            // Other doctor and room
+            (
+                appointment("2", "2", "2023-10-02T10:30:00", "2023-10-02T11:30:00"),
+                Ok(()),
+            ),
+            // Right after
+            (
+                appointment("1", "1", "2023-10-02T11:00:00", "2023-10-02T12:00:00"),
+                Ok(()),
+            ),
+        ];
+
+        for (other, expected) in cases {
+            let result = check(
+                "2023-10-02T10:00:00",
+                "2023-10-02T11:00:00",
+                &Schedule::default(),
+                &[other],
+                &calendar(),
+            )
+            .await;
+            assert_eq!(result, expected);
+        }
     }
 
-    #[test]
-    fn test_appointment_overlapping_another() {
-        let config = Config {
-            opening_time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
-            closing_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
-            break_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
-            break_duration: Duration::minutes(60),
+    #[tokio::test]
+    async fn test_buffers_count_as_overlap() {
+        // Doctor 2 did a surgery in room 1 until 10:00, after which the room is cleaned for 30 minutes
+        let mut surgery = appointment("2", "1", "2023-10-02T08:00:00", "2023-10-02T10:00:00");
+        surgery.blocked.room_until = time("2023-10-02T10:30:00");
+        // Doctor 1 needs 10 minutes to prepare the consultations they start
+        let consultation = Buffers {
+            doctor_before: Duration::minutes(10),
+            room_before: Duration::minutes(10),
+            ..Default::default()
         };
+        let doctor_1 = DoctorRecordId::new("1");
+        let doctor_2 = DoctorRecordId::new("2");
+        let room_1 = RoomRecordId::new("1");
+        let room_2 = RoomRecordId::new("2");
 
-        let start_time =
-            NaiveDateTime::parse_from_str("2023-10-01T10:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
-        let end_time =
-            NaiveDateTime::parse_from_str("2023-10-01T11:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
-        let appointments = vec![Appointment {
-            doctor: 1,
-            room_nr: 101,
-            start_time: NaiveDateTime::parse_from_str("2023-10-01T10:30:00", "%Y-%m-%dT%H:%M:%S")
-                .unwrap(),
-            end_time: NaiveDateTime::parse_from_str("2023-10-01T11:30:00", "%Y-%m-%dT%H:%M:%S")
-                .unwrap(),
-        }];
+        let cases = [
+            // The room is still cleaned
+            (
+                &doctor_1,
+                &room_1,
+                "2023-10-02T10:15:00",
+                Buffers::default(),
+                Err(TimeframeError::Overlap),
+            ),
+            (
+                &doctor_1,
+                &room_1,
+                "2023-10-02T10:30:00",
+                Buffers::default(),
+                Ok(()),
+            ),
+            // The cleaning doesn't keep the doctor busy
+            (
+                &doctor_2,
+                &room_2,
+                "2023-10-02T10:00:00",
+                Buffers::default(),
+                Ok(()),
+            ),
+            // Preparing the consultation would start before the room is clean
+            (
+                &doctor_1,
+                &room_1,
+                "2023-10-02T10:30:00",
+                consultation,
+                Err(TimeframeError::Overlap),
+            ),
+            (
+                &doctor_1,
+                &room_1,
+                "2023-10-02T10:40:00",
+                consultation,
+                Ok(()),
+            ),
+        ];
+        for (doctor, room, start_time, buffers, expected) in cases {
+            let end_time = time(start_time) + Duration::minutes(30);
+            let booking = Booking {
+                start_time: time(start_time),
+                end_time,
+                blocked: buffers.blocked_time(time(start_time), end_time),
+                doctor,
+                room,
+                staff: &[],
+                devices: &[],
+            };
+            let result = is_valid_timeframe(
+                &booking,
+                &[Schedule::default()],
+                &[surgery.clone()],
+                &calendar(),
+            )
+            .await;
+            assert_eq!(result, expected, "{}", start_time);
+            // The times the patient sees stay the same
+            assert_eq!(booking.blocked.doctor_until, end_time);
+        }
+    }
 
-        let result = is_valid_timeframe(&config, 1, 101, start_time, end_time, &appointments);
-        assert_eq!(
-            result,
-            Err("Appointment overlaps with another appointment".to_string())
+    #[tokio::test]
+    async fn test_staff_and_devices_count_as_overlap() {
+        // Doctor 2 operates in room 2 with doctor 3 assisting and the ECG cart, doctor 4 is away all day
+        let mut surgery = appointment("2", "2", "2023-10-02T10:00:00", "2023-10-02T11:00:00");
+        surgery.staff =
+            vec![appointment("3", "2", "2023-10-02T10:00:00", "2023-10-02T11:00:00").doctor];
+        surgery.devices = vec![DeviceRecord {
+            id: DeviceRecordId::new("ecg").as_thing().clone(),
+            name: "ECG cart".to_string(),
+            device_type: "ecg".to_string(),
+        }];
+        let calendar = ClinicCalendar::new(
+            &get_test_config(),
+            vec![],
+            vec![],
+            vec![Absence {
+                doctor: DoctorRecordId::new("4"),
+                start_date: "2023-10-02".parse().unwrap(),
+                end_date: "2023-10-02".parse().unwrap(),
+                start_time: None,
+                end_time: None,
+                reason: "Vacation".to_string(),
+            }],
         );
-    }
+        let doctor_1 = DoctorRecordId::new("1");
+        let room_1 = RoomRecordId::new("1");
+        let ecg = DeviceRecordId::new("ecg");
+        let ultrasound = DeviceRecordId::new("ultrasound");
 
-    #[test]
-    fn test_valid_appointment() {
-        let config = Config {
-            opening_time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
-            closing_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
-            break_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
-            break_duration: Duration::minutes(60),
-        };
+        let cases = [
+            // The assistant of the surgery as staff
+            (
+                vec![DoctorRecordId::new("3")],
+                vec![],
+                Err(TimeframeError::Overlap),
+            ),
+            // The surgeon as staff
+            (
+                vec![DoctorRecordId::new("2")],
+                vec![],
+                Err(TimeframeError::Overlap),
+            ),
+            // The device that is used in the surgery
+            (vec![], vec![ecg.clone()], Err(TimeframeError::Overlap)),
+            (vec![], vec![ultrasound], Ok(())),
+            (
+                vec![DoctorRecordId::new("4")],
+                vec![],
+                Err(TimeframeError::DoctorAbsent),
+            ),
+            (vec![DoctorRecordId::new("5")], vec![], Ok(())),
+        ];
+        for (staff, devices, expected) in cases {
+            let booking = Booking {
+                start_time: time("2023-10-02T10:30:00"),
+                end_time: time("2023-10-02T11:30:00"),
+                blocked: Buffers::default()
+                    .blocked_time(time("2023-10-02T10:30:00"), time("2023-10-02T11:30:00")),
+                doctor: &doctor_1,
+                room: &room_1,
+                staff: &staff,
+                devices: &devices,
+            };
+            let schedules = vec![Schedule::default(); staff.len() + 1];
+            let result =
+                is_valid_timeframe(&booking, &schedules, &[surgery.clone()], &calendar).await;
+            assert_eq!(result, expected, "{:?} {:?}", staff, devices);
+        }
 
-        let start_time =
-            NaiveDateTime::parse_from_str("2023-10-01T10:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
-        let end_time =
-            NaiveDateTime::parse_from_str("2023-10-01T11:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
-        let appointments = vec![];
+        // Every one of them has to be working
+        let booking = Booking {
+            start_time: time("2023-10-02T14:00:00"),
+            end_time: time("2023-10-02T15:00:00"),
+            blocked: Buffers::default()
+                .blocked_time(time("2023-10-02T14:00:00"), time("2023-10-02T15:00:00")),
+            doctor: &doctor_1,
+            room: &room_1,
+            staff: &[DoctorRecordId::new("5")],
+            devices: &[],
+        };
+        let mornings = Schedule {
+            shifts: vec![Shift {
+                weekday: Weekday::Monday,
+                start_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                end_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+            }],
+        };
+        let result =
+            is_valid_timeframe(&booking, &[Schedule::default(), mornings], &[], &calendar).await;
+        assert_eq!(result, Err(TimeframeError::OutsideWorkingHours));
+    }
 
-        let result = is_valid_timeframe(&config, 1, 101, start_time, end_time, &appointments);
+    #[tokio::test]
+    async fn test_valid_appointment() {
+        let result = check(
+            "2023-10-02T10:00:00",
+            "2023-10-02T11:00:00",
+            &Schedule::default(),
+            &[],
+            &calendar(),
+        )
+        .await;
         assert_eq!(result, Ok(()));
     }
 }
@@ -0,0 +1,354 @@
+use std::time::Duration;
+
+use chrono::{Local, NaiveDateTime, SubsecRound};
+use log::warn;
+
+use crate::{
+    calendar::ClinicCalendar,
+    config::AppConfig,
+    db::{
+        db::Database,
+        types::{
+            AppointmentFilter, AppointmentRecord, AppointmentRecordWithPatient,
+            AppointmentTypeRecord, Buffers, DatabaseError, DateRange, DoctorRecordId, OfferFilter,
+            OfferStatus, RoomRecord, RoomRecordId, Schedule, WaitlistEntryRecord, WaitlistOffer,
+            WaitlistOfferRecord, WaitlistRecordId,
+        },
+    },
+    util::{is_valid_timeframe, Booking},
+};
+
+const OFFER_EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+// A timeframe that can be booked again, like the one of a cancelled appointment or of an offer that was declined
+#[derive(Debug, Clone, PartialEq)]
+pub struct FreedSlot {
+    pub start_time: NaiveDateTime,
+    pub end_time: NaiveDateTime,
+    pub doctor: DoctorRecordId,
+    pub room: RoomRecordId,
+}
+
+impl From<&AppointmentRecord> for FreedSlot {
+    fn from(appointment: &AppointmentRecord) -> Self {
+        FreedSlot {
+            start_time: appointment.start_time,
+            end_time: appointment.end_time,
+            doctor: appointment.doctor.clone(),
+            room: appointment.room.clone(),
+        }
+    }
+}
+
+impl From<&AppointmentRecordWithPatient> for FreedSlot {
+    fn from(appointment: &AppointmentRecordWithPatient) -> Self {
+        FreedSlot {
+            start_time: appointment.start_time,
+            end_time: appointment.end_time,
+            doctor: DoctorRecordId::from(appointment.doctor.id.clone()),
+            room: RoomRecordId::from(appointment.room.id.clone()),
+        }
+    }
+}
+
+impl From<&WaitlistOfferRecord> for FreedSlot {
+    fn from(offer: &WaitlistOfferRecord) -> Self {
+        FreedSlot {
+            start_time: offer.start_time,
+            end_time: offer.end_time,
+            doctor: offer.doctor.clone(),
+            room: offer.room.clone(),
+        }
+    }
+}
+
+// Everything needed to tell whether a waitlist entry can take a freed slot of the doctor in the room
+pub struct WaitlistMatcher {
+    pub room: RoomRecord,
+    pub schedule: Schedule,
+    pub appointment_types: Vec<AppointmentTypeRecord>,
+    // The appointments on the day of the slot
+    pub appointments: Vec<AppointmentRecordWithPatient>,
+    // Timeframes that are offered already aren't offered twice
+    pub pending_offers: Vec<WaitlistOfferRecord>,
+    pub calendar: ClinicCalendar,
+}
+
+impl WaitlistMatcher {
+    // The offer for the first of the entries, in the order they're passed in, that takes an appointment in the slot.
+    // The appointment starts when the slot starts and lasts as long as the entry's type, so it may end after the slot
+    // as long as the time after it is free as well.
+    pub async fn find_offer(
+        &self,
+        slot: &FreedSlot,
+        entries: &[WaitlistEntryRecord],
+    ) -> Option<WaitlistOffer> {
+        for entry in entries {
+            if !entry.accepts(slot.start_time.date(), &slot.doctor) {
+                continue;
+            }
+            let Some(appointment_type) = self.appointment_types.iter().find(|appointment_type| {
+                appointment_type.appointment_type() == entry.appointment_type
+            }) else {
+                continue;
+            };
+            if !appointment_type.room_types.contains(&self.room.room_type) {
+                continue;
+            }
+
+            let end_time = slot.start_time + appointment_type.duration();
+            let offered = self.pending_offers.iter().any(|offer| {
+                (offer.doctor == slot.doctor || offer.room == slot.room)
+                    && offer.start_time < end_time
+                    && offer.end_time > slot.start_time
+            });
+            if offered {
+                continue;
+            }
+
+            let booking = Booking {
+                start_time: slot.start_time,
+                end_time,
+                blocked: Buffers::new(appointment_type, &self.room)
+                    .blocked_time(slot.start_time, end_time),
+                doctor: &slot.doctor,
+                room: &slot.room,
+                staff: &[],
+                devices: &[],
+            };
+            if is_valid_timeframe(
+                &booking,
+                std::slice::from_ref(&self.schedule),
+                &self.appointments,
+                &self.calendar,
+            )
+            .await
+            .is_ok()
+            {
+                return Some(WaitlistOffer {
+                    entry: WaitlistRecordId::from(entry.id.clone()),
+                    patient_id: entry.patient_id.clone(),
+                    appointment_type: entry.appointment_type.clone(),
+                    start_time: slot.start_time,
+                    end_time,
+                    doctor: slot.doctor.clone(),
+                    room: slot.room.clone(),
+                });
+            }
+        }
+
+        None
+    }
+}
+
+// Offers each of the slots that hasn't started yet to the waitlist entry that has waited the longest for it. Slots of
+// inactive doctors, or of doctors and rooms that were deleted, aren't offered. Offers that expired in the meantime are
+// expired first, so their entries are asked again and their slots don't count as offered.
+pub async fn offer_freed_slots(
+    database: &Database,
+    config: &AppConfig,
+    slots: Vec<FreedSlot>,
+) -> Result<Vec<WaitlistOfferRecord>, DatabaseError> {
+    let now = Local::now().naive_local().trunc_subsecs(0);
+    let mut offers = Vec::new();
+    database.expire_offers(now).await?;
+
+    for slot in slots {
+        if slot.start_time <= now {
+            continue;
+        }
+        let entries = database
+            .read_waiting_entries(&slot.doctor, slot.start_time)
+            .await?;
+        if entries.is_empty() {
+            continue;
+        }
+        match database.read_doctor(&slot.doctor.get_unique_id()).await {
+            Ok(doctor) if doctor.active => {}
+            Ok(_) | Err(DatabaseError::NothingFound) => continue,
+            Err(err) => return Err(err),
+        }
+        let room = match database.read_room(&slot.room.get_unique_id()).await {
+            Ok(room) => room,
+            Err(DatabaseError::NothingFound) => continue,
+            Err(err) => return Err(err),
+        };
+
+        let day = slot.start_time.date();
+        let matcher = WaitlistMatcher {
+            room,
+            schedule: database.read_schedule(&slot.doctor).await?,
+            appointment_types: database.read_appointment_types().await?,
+            appointments: database
+                .read_appointments(&AppointmentFilter {
+                    day: Some(day),
+                    ..Default::default()
+                })
+                .await?,
+            pending_offers: database
+                .read_offers(&OfferFilter {
+                    status: Some(OfferStatus::Pending),
+                    ..Default::default()
+                })
+                .await?,
+            calendar: ClinicCalendar::load(
+                database,
+                config,
+                DateRange {
+                    from: Some(day),
+                    to: Some(day),
+                },
+            )
+            .await?,
+        };
+        let Some(offer) = matcher.find_offer(&slot, &entries).await else {
+            continue;
+        };
+
+        let message = format!(
+            "An appointment ({}) became available on {} at {}. Please accept or decline the offer.",
+            offer.appointment_type.as_str().replace('_', " "),
+            day,
+            offer.start_time.format("%H:%M")
+        );
+        match database.create_offer(offer, message, now).await {
+            Ok(offer) => offers.push(offer),
+            // The entry was offered another slot or deleted in the meantime
+            Err(DatabaseError::InvalidStatusChange) => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(offers)
+}
+
+// Expires the offers whose slot has started every minute, so their entries are back on the waitlist even if nobody
+// answers or offers anything. Offering slots and answering offers expire them as well.
+pub fn spawn_offer_expiry(database: Database) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(OFFER_EXPIRY_INTERVAL).await;
+            let now = Local::now().naive_local().trunc_subsecs(0);
+            if let Err(err) = database.expire_offers(now).await {
+                warn!("Couldn't expire waitlist offers: {}", err);
+            }
+        }
+    })
+}
+
+// The change that freed the slots is done either way, so failing to offer them is logged instead of failing the
+// request
+pub async fn offer_to_waitlist(database: &Database, config: &AppConfig, slots: Vec<FreedSlot>) {
+    if let Err(err) = offer_freed_slots(database, config, slots).await {
+        warn!("Couldn't offer freed slots to the waitlist: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use surrealdb::sql::Thing;
+
+    use crate::{
+        config::config_tests::get_test_config,
+        db::types::{AppointmentType, PatientRecordId, RoomType, WaitlistStatus},
+        util::util_tests::{appointment, time},
+    };
+
+    use super::*;
+
+    fn entry(id: &str, appointment_type: &str, doctor: Option<&str>) -> WaitlistEntryRecord {
+        WaitlistEntryRecord {
+            id: Thing::from(("waitlist", id)),
+            patient_id: PatientRecordId::new(id),
+            appointment_type: AppointmentType::new(appointment_type),
+            doctor: doctor.map(DoctorRecordId::new),
+            start_date: "2023-10-02".parse::<NaiveDate>().unwrap(),
+            end_date: "2023-10-06".parse::<NaiveDate>().unwrap(),
+            status: WaitlistStatus::Waiting,
+            created_at: time("2023-09-01T09:00:00"),
+        }
+    }
+
+    fn appointment_type(
+        id: &str,
+        duration_minutes: u32,
+        room_type: RoomType,
+    ) -> AppointmentTypeRecord {
+        AppointmentTypeRecord {
+            id: Thing::from(("appointment_type", id)),
+            name: id.to_string(),
+            duration_minutes,
+            buffer_before_minutes: 0,
+            buffer_after_minutes: 0,
+            colour: "#1e88e5".to_string(),
+            room_types: vec![room_type],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_offer() {
+        // Room 1 is free from 10:00 to 11:00, and booked again from 11:00
+        let appointments = vec![appointment(
+            "2",
+            "1",
+            "2023-10-02T11:00:00",
+            "2023-10-02T12:00:00",
+        )];
+        let mut matcher = WaitlistMatcher {
+            room: appointments[0].room.clone(),
+            schedule: Schedule::default(),
+            appointment_types: vec![
+                appointment_type("quick_checkup", 30, RoomType::Examination),
+                appointment_type("extensive_care", 90, RoomType::Examination),
+                appointment_type("surgery", 30, RoomType::OperatingRoom),
+            ],
+            appointments,
+            pending_offers: vec![],
+            calendar: ClinicCalendar::new(&get_test_config(), vec![], vec![], vec![]),
+        };
+        let slot = FreedSlot {
+            start_time: time("2023-10-02T10:00:00"),
+            end_time: time("2023-10-02T11:00:00"),
+            doctor: DoctorRecordId::new("1"),
+            room: RoomRecordId::new("1"),
+        };
+
+        // Entries that wait for another doctor, need another room or don't fit into the free time are skipped
+        let entries = [
+            entry("1", "quick_checkup", Some("2")),
+            entry("2", "surgery", None),
+            entry("3", "extensive_care", None),
+            entry("4", "quick_checkup", Some("1")),
+            entry("5", "quick_checkup", None),
+        ];
+        let offer = matcher.find_offer(&slot, &entries).await.unwrap();
+        assert_eq!(offer.entry, WaitlistRecordId::new("4"));
+        assert_eq!(offer.start_time, slot.start_time);
+        assert_eq!(offer.end_time, time("2023-10-02T10:30:00"));
+
+        // Nothing is offered twice, and nothing outside of the entries' windows
+        let pending = WaitlistOfferRecord {
+            id: Thing::from(("waitlist_offer", "1")),
+            status: OfferStatus::Pending,
+            created_at: time("2023-09-02T09:00:00"),
+            appointment: None,
+            entry: offer.entry,
+            patient_id: offer.patient_id,
+            appointment_type: offer.appointment_type,
+            start_time: offer.start_time,
+            end_time: offer.end_time,
+            doctor: offer.doctor,
+            room: offer.room,
+        };
+        matcher.pending_offers.push(pending);
+        assert!(matcher.find_offer(&slot, &entries[4..]).await.is_none());
+        matcher.pending_offers.clear();
+        let next_week = FreedSlot {
+            start_time: time("2023-10-09T10:00:00"),
+            end_time: time("2023-10-09T11:00:00"),
+            ..slot
+        };
+        assert!(matcher.find_offer(&next_week, &entries).await.is_none());
+    }
+}
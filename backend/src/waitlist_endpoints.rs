@@ -0,0 +1,215 @@
+use actix_web::{web, HttpResponse};
+use chrono::{Local, SubsecRound};
+use serde::Deserialize;
+
+use crate::appointment_endpoints::{read_appointment_type, validate_doctor, validate_room};
+use crate::calendar::ClinicCalendar;
+use crate::config::AppConfig;
+use crate::db::{
+    db::Database,
+    types::{
+        AppointmentFilter, AppointmentWithTime, Buffers, DateRange, OfferFilter, OfferStatus,
+        WaitlistEntry, WaitlistFilter, WaitlistOfferRecord,
+    },
+};
+use crate::errors::{not_found, ApiError};
+use crate::types::ApiResponse;
+use crate::util::{is_valid_timeframe, Booking};
+use crate::waitlist::{offer_to_waitlist, FreedSlot};
+
+// Waitlist Types
+#[derive(Deserialize)]
+pub struct WaitlistId {
+    id: String,
+}
+
+// Only pending offers can be accepted or declined
+fn ensure_pending(offer: &WaitlistOfferRecord) -> Result<(), String> {
+    match offer.status {
+        OfferStatus::Pending => Ok(()),
+        status => Err(format!("The offer was {} already", status.as_str())),
+    }
+}
+
+// Offers whose slot has started are expired in the background, and before an offer is answered so it can't be
+// answered late
+async fn expire_offers(database: &Database) -> Result<(), ApiError> {
+    let now = Local::now().naive_local().trunc_subsecs(0);
+    database.expire_offers(now).await?;
+    Ok(())
+}
+
+// Endpoints
+
+// Entries are offered freed slots in the order they were created in
+pub async fn create_waitlist_entry(
+    database: web::Data<Database>,
+    entry: web::Json<WaitlistEntry>,
+) -> Result<HttpResponse, ApiError> {
+    let now = Local::now().naive_local().trunc_subsecs(0);
+    entry
+        .validate(now.date())
+        .map_err(ApiError::InvalidRequest)?;
+    read_appointment_type(&database, &entry.appointment_type).await?;
+    if let Some(doctor) = &entry.doctor {
+        validate_doctor(&database, doctor).await?;
+    }
+    database
+        .read_patient(&entry.patient_id.get_unique_id())
+        .await
+        .map_err(not_found("Patient"))?;
+
+    let result = database
+        .create_waitlist_entry(entry.into_inner(), now)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+pub async fn read_waitlist(
+    database: web::Data<Database>,
+    filter: web::Query<WaitlistFilter>,
+) -> Result<HttpResponse, ApiError> {
+    let entries = database.read_waitlist(&filter).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: entries }))
+}
+
+pub async fn read_waitlist_entry(
+    database: web::Data<Database>,
+    path: web::Path<WaitlistId>,
+) -> Result<HttpResponse, ApiError> {
+    let entry = database
+        .read_waitlist_entry(&path.id)
+        .await
+        .map_err(not_found("Waitlist entry"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: entry }))
+}
+
+// Pending offers of the entry are declined, so their slots are offered to the next entry
+pub async fn delete_waitlist_entry(
+    database: web::Data<Database>,
+    config: web::Data<AppConfig>,
+    path: web::Path<WaitlistId>,
+) -> Result<HttpResponse, ApiError> {
+    let (entry, declined) = database
+        .delete_waitlist_entry(&path.id)
+        .await
+        .map_err(not_found("Waitlist entry"))?;
+
+    offer_to_waitlist(
+        &database,
+        &config,
+        declined.iter().map(FreedSlot::from).collect(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: entry }))
+}
+
+pub async fn read_offers(
+    database: web::Data<Database>,
+    filter: web::Query<OfferFilter>,
+) -> Result<HttpResponse, ApiError> {
+    let offers = database.read_offers(&filter).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: offers }))
+}
+
+pub async fn read_offer(
+    database: web::Data<Database>,
+    path: web::Path<WaitlistId>,
+) -> Result<HttpResponse, ApiError> {
+    let offer = database
+        .read_offer(&path.id)
+        .await
+        .map_err(not_found("Offer"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: offer }))
+}
+
+// Books the offered appointment. The slot wasn't reserved for the offer, so it's checked like any other booking.
+pub async fn accept_offer(
+    database: web::Data<Database>,
+    config: web::Data<AppConfig>,
+    path: web::Path<WaitlistId>,
+) -> Result<HttpResponse, ApiError> {
+    expire_offers(&database).await?;
+    let offer = database
+        .read_offer(&path.id)
+        .await
+        .map_err(not_found("Offer"))?;
+    ensure_pending(&offer).map_err(ApiError::InvalidStatusChange)?;
+    validate_doctor(&database, &offer.doctor).await?;
+    let appointment_type = read_appointment_type(&database, &offer.appointment_type).await?;
+    let room = validate_room(&database, &offer.room, &appointment_type).await?;
+    let appointment = AppointmentWithTime {
+        start_time: offer.start_time,
+        end_time: offer.end_time,
+        blocked: Buffers::new(&appointment_type, &room)
+            .blocked_time(offer.start_time, offer.end_time),
+        appointment_type: offer.appointment_type,
+        patient_id: offer.patient_id,
+        doctor: offer.doctor,
+        room: offer.room,
+        staff: vec![],
+        devices: vec![],
+        series: None,
+    };
+
+    let day = appointment.start_time.date();
+    let appointments = database
+        .read_appointments(&AppointmentFilter {
+            day: Some(day),
+            ..Default::default()
+        })
+        .await?;
+    let schedule = database.read_schedule(&appointment.doctor).await?;
+    let calendar = ClinicCalendar::load(
+        &database,
+        &config,
+        DateRange {
+            from: Some(day),
+            to: Some(day),
+        },
+    )
+    .await?;
+    is_valid_timeframe(
+        &Booking::from(&appointment),
+        std::slice::from_ref(&schedule),
+        &appointments,
+        &calendar,
+    )
+    .await?;
+
+    let result = database
+        .accept_offer(&path.id, appointment)
+        .await
+        .map_err(not_found("Offer"))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
+
+// The entry goes back on the waitlist, and the slot is offered to the next entry
+pub async fn decline_offer(
+    database: web::Data<Database>,
+    config: web::Data<AppConfig>,
+    path: web::Path<WaitlistId>,
+) -> Result<HttpResponse, ApiError> {
+    expire_offers(&database).await?;
+    let offer = database
+        .read_offer(&path.id)
+        .await
+        .map_err(not_found("Offer"))?;
+    ensure_pending(&offer).map_err(ApiError::InvalidStatusChange)?;
+
+    let result = database
+        .decline_offer(&path.id)
+        .await
+        .map_err(not_found("Offer"))?;
+
+    offer_to_waitlist(&database, &config, vec![FreedSlot::from(&result)]).await;
+
+    Ok(HttpResponse::Ok().json(ApiResponse { data: result }))
+}
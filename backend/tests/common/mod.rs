@@ -0,0 +1,73 @@
+use backend::config::{AppConfig, AuthConfig, AuthMode, Break};
+use backend::db::db::Database;
+use backend::db::types::{Doctor, DoctorRecordId, Room, RoomRecordId, RoomType, Weekday};
+use chrono::NaiveTime;
+
+pub async fn get_test_config() -> AppConfig {
+    AppConfig {
+        port: 8080,
+        namespace: "test".to_string(),
+        database: "test".to_string(),
+        database_url: "mem://".to_string(),
+        auth: AuthConfig {
+            mode: AuthMode::None,
+            ..Default::default()
+        },
+        opening_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+        closing_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        breaks: vec![Break {
+            name: "Lunch".to_string(),
+            start_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+            duration_minutes: 60,
+            weekdays: None,
+            doctor: None,
+        }],
+        break_time: None,
+        opening_days: vec![
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+        ],
+    }
+}
+
+// Every call starts its own in-memory database, so tests can't see each other's data
+pub async fn mock_db() -> Database {
+    let db = Database::new();
+    db.initiate_db(get_test_config().await).await.unwrap();
+    db
+}
+
+// Not every test binary books appointments
+#[allow(dead_code)]
+pub async fn create_doctor(database: &Database, name: &str, active: bool) -> DoctorRecordId {
+    let doctor = database
+        .create_doctor(Doctor {
+            name: name.to_string(),
+            specialty: "general".to_string(),
+            phone_number: None,
+            email: None,
+            active,
+        })
+        .await
+        .unwrap();
+    DoctorRecordId::from(doctor[0].id.clone())
+}
+
+// Not every test binary books appointments
+#[allow(dead_code)]
+pub async fn create_room(database: &Database, name: &str, room_type: RoomType) -> RoomRecordId {
+    let room = database
+        .create_room(Room {
+            name: name.to_string(),
+            room_type,
+            equipment: vec![],
+            buffer_before_minutes: 0,
+            buffer_after_minutes: 0,
+        })
+        .await
+        .unwrap();
+    RoomRecordId::from(room[0].id.clone())
+}
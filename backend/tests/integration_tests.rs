@@ -1,36 +1,46 @@
-use std::sync::{Arc, Mutex};
+mod common;
 
 use actix_web::{test, web, App};
+use backend::absence_endpoints::{create_absence, delete_absence, read_absence, read_absences};
 use backend::appointment_endpoints::{
-    create_appointment, delete_appointment, mass_reschedule_doctor, read_all_appointments_handler,
-    read_appointment, update_appointment,
+    cancel_appointment, check_in_appointment, complete_appointment, create_appointment,
+    delete_appointment, mark_appointment_no_show, mass_reschedule_doctor,
+    read_all_appointments_handler, read_appointment, start_appointment, update_appointment,
+};
+use backend::appointment_type_endpoints::{
+    create_appointment_type, delete_appointment_type, read_all_appointment_types,
+    read_appointment_type, update_appointment_type,
+};
+use backend::availability_endpoints::read_availability;
+use backend::calendar_endpoints::{
+    delete_closure, import_closures, read_closures, update_opening_override,
 };
-use backend::config::AppConfig;
 use backend::db::db::Database;
+use backend::db::types::{
+    Absence, AbsenceRecord, Appointment, AppointmentFilter, AppointmentType, Buffers,
+    DoctorRecordId, OfferFilter, Patient, PatientRecordId, RoomType, WaitlistEntry,
+};
+use backend::device_endpoints::{create_device, delete_device, read_all_devices};
+use backend::doctor_endpoints::{
+    create_doctor, delete_doctor, read_all_doctors, read_doctor, read_doctor_schedule,
+    update_doctor, update_doctor_schedule,
+};
+use backend::errors::configure_error_handlers;
+use backend::health_endpoints::get_health;
+use backend::notification_endpoints::{mark_notification_sent, read_notifications};
 use backend::patient_endpoints::{
     create_patient, delete_patient, read_all_patients, read_patient, update_patient,
 };
-use chrono::NaiveTime;
-
-async fn get_test_config() -> AppConfig {
-    AppConfig {
-        port: 8080,
-        doctor_amount: 5,
-        room_amount: 10,
-        namespace: "test".to_string(),
-        database: "test".to_string(),
-        database_url: "127.0.0.1:8000".to_string(),
-        opening_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
-        closing_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
-        break_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
-    }
-}
-
-async fn mock_db() -> Arc<Mutex<Database>> {
-    let db = Database::new();
-    db.initiate_db(get_test_config().await).await.unwrap();
-    Arc::new(Mutex::new(db))
-}
+use backend::room_endpoints::{create_room, delete_room, read_all_rooms, read_room, update_room};
+use backend::series_endpoints::{create_appointment_series, read_appointment_series};
+use backend::types::ServerInfo;
+use backend::waitlist_endpoints::{
+    accept_offer, create_waitlist_entry, decline_offer, delete_waitlist_entry, read_offers,
+    read_waitlist, read_waitlist_entry,
+};
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use common::{get_test_config, mock_db};
+use std::time::Instant;
 
 #[actix_rt::test]
 async fn test_endpoint_create_patient() {
@@ -39,7 +49,7 @@ async fn test_endpoint_create_patient() {
     let database = mock_db().await;
 
     // Initialize the Actix web application
-    let mut app = test::init_service(
+    let app = test::init_service(
         App::new()
             .app_data(web::Data::new(database.clone()))
             .app_data(web::Data::new(config.clone()))
@@ -53,14 +63,14 @@ async fn test_endpoint_create_patient() {
     // Create a test request to the /api/patient endpoint
     let req = test::TestRequest::post()
         .uri("/api/patient")
-        .set_json(&serde_json::json!({
+        .set_json(serde_json::json!({
             "name": "John Doe",
             "phone_number": "1234567890"
         }))
         .to_request();
 
     // Call the service and get the response
-    let resp = test::call_service(&mut app, req).await;
+    let resp = test::call_service(&app, req).await;
 
     // Assert that the response status is successful
     assert!(resp.status().is_success());
@@ -73,7 +83,7 @@ async fn test_endpoint_read_patient_by_id() {
     let database = mock_db().await;
 
     // Initialize the Actix web application
-    let mut app = test::init_service(
+    let app = test::init_service(
         App::new()
             .app_data(web::Data::new(database.clone()))
             .app_data(web::Data::new(config.clone()))
@@ -91,7 +101,7 @@ async fn test_endpoint_read_patient_by_id() {
         .to_request();
 
     // Call the service and get the response
-    let resp = test::call_service(&mut app, req).await;
+    let resp = test::call_service(&app, req).await;
 
     // Assert that the response status is successful
     assert!(resp.status().is_client_error());
@@ -104,7 +114,7 @@ async fn test_endpoint_read_all_patients() {
     let database = mock_db().await;
 
     // Initialize the Actix web application
-    let mut app = test::init_service(
+    let app = test::init_service(
         App::new()
             .app_data(web::Data::new(database.clone()))
             .app_data(web::Data::new(config.clone()))
@@ -119,12 +129,78 @@ async fn test_endpoint_read_all_patients() {
     let req = test::TestRequest::get().uri("/api/patient").to_request();
 
     // Call the service and get the response
-    let resp = test::call_service(&mut app, req).await;
+    let resp = test::call_service(&app, req).await;
 
     // Assert that the response status is successful
     assert!(resp.status().is_success());
 }
 
+#[actix_rt::test]
+async fn test_endpoint_read_all_patients_paginated() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    for name in ["Cid", "Ann", "Bob"] {
+        database
+            .create_patient(Patient {
+                name: name.to_string(),
+                phone_number: "1234567890".to_string(),
+                insurance_number: None,
+            })
+            .await
+            .unwrap();
+    }
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(
+                web::scope("/api")
+                    .service(web::resource("/patient").route(web::get().to(read_all_patients))),
+            ),
+    )
+    .await;
+
+    // Request the first page
+    let req = test::TestRequest::get()
+        .uri("/api/patient?limit=2&sort=name&order=desc")
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(body["total"], 3);
+    assert_eq!(body["data"][0]["name"], "Cid");
+    assert_eq!(body["data"][1]["name"], "Bob");
+    let cursor = body["next_cursor"].as_str().unwrap();
+
+    // Continue after the cursor
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/api/patient?limit=2&sort=name&order=desc&cursor={}",
+            cursor
+        ))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["name"], "Ann");
+    assert!(body["next_cursor"].is_null());
+
+    // Assert that invalid page requests are rejected
+    for uri in [
+        "/api/patient?limit=0",
+        "/api/patient?sort=unknown",
+        "/api/patient?cursor=appointment:abc",
+        "/api/patient?cursor=patient:doesnt_exist",
+    ] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_client_error());
+    }
+}
+
 #[actix_rt::test]
 async fn test_endpoint_delete_patient() {
     // Initialize the configuration and database
@@ -132,7 +208,7 @@ async fn test_endpoint_delete_patient() {
     let database = mock_db().await;
 
     // Initialize the Actix web application
-    let mut app =
+    let app =
         test::init_service(
             App::new()
                 .app_data(web::Data::new(database.clone()))
@@ -149,7 +225,7 @@ async fn test_endpoint_delete_patient() {
         .to_request();
 
     // Call the service and get the response
-    let resp = test::call_service(&mut app, req).await;
+    let resp = test::call_service(&app, req).await;
 
     // Assert that the response status is successful
     assert!(resp.status().is_client_error());
@@ -162,7 +238,7 @@ async fn test_endpoint_update_patient() {
     let database = mock_db().await;
 
     // Initialize the Actix web application
-    let mut app = test::init_service(
+    let app = test::init_service(
         App::new()
             .app_data(web::Data::new(database.clone()))
             .app_data(web::Data::new(config.clone()))
@@ -179,201 +255,2743 @@ async fn test_endpoint_update_patient() {
         .to_request();
 
     // Call the service and get the response
-    let resp = test::call_service(&mut app, req).await;
+    let resp = test::call_service(&app, req).await;
 
     // Assert that the response status is successful
     assert!(resp.status().is_client_error());
 }
 
 #[actix_rt::test]
-async fn test_endpoint_create_appointment() {
+async fn test_endpoint_doctors() {
     // Initialize the configuration and database
     let config = get_test_config().await;
     let database = mock_db().await;
 
     // Initialize the Actix web application
-    let mut app =
-        test::init_service(
-            App::new()
-                .app_data(web::Data::new(database.clone()))
-                .app_data(web::Data::new(config.clone()))
-                .service(web::scope("/api").service(
-                    web::resource("/appointment").route(web::post().to(create_appointment)),
-                )),
-        )
-        .await;
+    let app = test::init_service(
+        App::new()
+            .configure(configure_error_handlers)
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(
+                web::scope("/api")
+                    .service(
+                        web::resource("/doctor")
+                            .route(web::post().to(create_doctor))
+                            .route(web::get().to(read_all_doctors)),
+                    )
+                    .service(
+                        web::resource("/doctor/{id}")
+                            .route(web::get().to(read_doctor))
+                            .route(web::put().to(update_doctor))
+                            .route(web::delete().to(delete_doctor)),
+                    ),
+            ),
+    )
+    .await;
 
-    // Create a test request to the /api/patient endpoint
-    let req = test::TestRequest::post()
-        .uri("/api/appointment")
-        .set_json(&serde_json::json!({
-            "start_time": "2021-01-01T08:00:00",
-            "appointment_type": "quick_checkup",
-            "patient_id": "some_id",
-            "doctor": 5,
-            "room_nr": 1,
-        }))
+    for (name, specialty) in [("Dr. House", "diagnostics"), ("Dr. Grey", "surgery")] {
+        let req = test::TestRequest::post()
+            .uri("/api/doctor")
+            .set_json(serde_json::json!({
+                "name": name,
+                "specialty": specialty,
+                "email": "doctor@example.com",
+            }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // New doctors are active unless stated otherwise
+        assert_eq!(resp["data"][0]["active"], true);
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/doctor?specialty=surgery")
         .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["total"], 1);
+    assert_eq!(resp["data"][0]["name"], "Dr. Grey");
+    let id = resp["data"][0]["id"]["id"]["String"].as_str().unwrap();
 
-    // Call the service and get the response
-    let resp = test::call_service(&mut app, req).await;
+    // Deactivate the doctor
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/doctor/{}", id))
+        .set_json(serde_json::json!({ "active": false }))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"]["active"], false);
+    assert_eq!(resp["data"]["name"], "Dr. Grey");
 
-    // Assert that the response status is successful
-    assert!(resp.status().is_client_error());
+    let req = test::TestRequest::get()
+        .uri("/api/doctor?active=true")
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["total"], 1);
+    assert_eq!(resp["data"][0]["name"], "Dr. House");
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/doctor/{}", id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/doctor/{}", id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 404);
 }
 
 #[actix_rt::test]
-async fn test_endpoint_delete_appointment() {
+async fn test_endpoint_rooms() {
     // Initialize the configuration and database
     let config = get_test_config().await;
     let database = mock_db().await;
 
     // Initialize the Actix web application
-    let mut app = test::init_service(
+    let app = test::init_service(
         App::new()
+            .configure(configure_error_handlers)
             .app_data(web::Data::new(database.clone()))
             .app_data(web::Data::new(config.clone()))
-            .service(web::scope("/api").service(
-                web::resource("/appointment/{id}").route(web::delete().to(delete_appointment)),
-            )),
+            .service(
+                web::scope("/api")
+                    .service(
+                        web::resource("/room")
+                            .route(web::post().to(create_room))
+                            .route(web::get().to(read_all_rooms)),
+                    )
+                    .service(
+                        web::resource("/room/{id}")
+                            .route(web::get().to(read_room))
+                            .route(web::put().to(update_room))
+                            .route(web::delete().to(delete_room)),
+                    ),
+            ),
     )
     .await;
 
-    // Create a test request to the /api/patient endpoint
-    let req = test::TestRequest::delete()
-        .uri("/api/appointment/some_id")
+    for (name, room_type) in [("OR 1", "operating_room"), ("Exam 1", "examination")] {
+        let req = test::TestRequest::post()
+            .uri("/api/room")
+            .set_json(serde_json::json!({ "name": name, "room_type": room_type }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // Rooms without equipment get an empty list
+        assert_eq!(resp["data"][0]["equipment"], serde_json::json!([]));
+    }
+
+    // Unknown room types are rejected
+    let req = test::TestRequest::post()
+        .uri("/api/room")
+        .set_json(serde_json::json!({ "name": "Lab", "room_type": "laboratory" }))
         .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 400);
 
-    // Call the service and get the response
-    let resp = test::call_service(&mut app, req).await;
+    let req = test::TestRequest::get()
+        .uri("/api/room?room_type=examination")
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["total"], 1);
+    assert_eq!(resp["data"][0]["name"], "Exam 1");
+    let id = resp["data"][0]["id"]["id"]["String"].as_str().unwrap();
 
-    // Assert that the response status is successful
-    assert!(resp.status().is_client_error());
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/room/{}", id))
+        .set_json(serde_json::json!({ "equipment": ["ecg"] }))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"]["equipment"], serde_json::json!(["ecg"]));
+
+    let req = test::TestRequest::get()
+        .uri("/api/room?equipment=ecg")
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["total"], 1);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/room/{}", id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/room/{}", id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 404);
 }
 
 #[actix_rt::test]
-async fn test_endpoint_mass_reschedule() {
+async fn test_endpoint_appointment_types() {
     // Initialize the configuration and database
     let config = get_test_config().await;
     let database = mock_db().await;
 
+    let patient = database
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let patient_id = patient[0].id.to_string();
+    let doctor = common::create_doctor(&database, "Dr. Ann", true).await;
+    let room = common::create_room(&database, "Consultation 1", RoomType::Consultation)
+        .await
+        .as_thing()
+        .to_string();
+
     // Initialize the Actix web application
-    let mut app = test::init_service(
+    let app = test::init_service(
         App::new()
+            .configure(configure_error_handlers)
             .app_data(web::Data::new(database.clone()))
             .app_data(web::Data::new(config.clone()))
             .service(
-                web::scope("/api").service(
-                    web::resource("/appointment/mass_reschedule")
-                        .route(web::post().to(mass_reschedule_doctor)),
-                ),
+                web::scope("/api")
+                    .service(
+                        web::resource("/appointment_type")
+                            .route(web::post().to(create_appointment_type))
+                            .route(web::get().to(read_all_appointment_types)),
+                    )
+                    .service(
+                        web::resource("/appointment_type/{id}")
+                            .route(web::get().to(read_appointment_type))
+                            .route(web::put().to(update_appointment_type))
+                            .route(web::delete().to(delete_appointment_type)),
+                    )
+                    .service(
+                        web::resource("/appointment").route(web::post().to(create_appointment)),
+                    ),
             ),
     )
     .await;
 
-    // Create a test request to the /api/patient endpoint
+    let vaccination = serde_json::json!({
+        "id": "vaccination",
+        "name": "Vaccination",
+        "duration_minutes": 15,
+        "colour": "#fdd835",
+        "room_types": ["consultation"],
+    });
     let req = test::TestRequest::post()
-        .uri("/api/appointment/mass_reschedule")
-        .set_json(&serde_json::json!({
-            "doctor_id": 1,
-            "start_date": "2021-01-01",
-            "end_date": "2021-01-31"
+        .uri("/api/appointment_type")
+        .set_json(&vaccination)
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"]["name"], "Vaccination");
+    assert_eq!(resp["data"]["buffer_before_minutes"], 0);
+    assert_eq!(resp["data"]["buffer_after_minutes"], 0);
+
+    // The ID can only be taken once, and invalid types are rejected
+    let invalid = [
+        vaccination.clone(),
+        serde_json::json!({ "id": "Flu Shot", "name": "Flu Shot", "duration_minutes": 15,
+            "colour": "#fdd835", "room_types": ["consultation"] }),
+        serde_json::json!({ "id": "flu_shot", "name": "Flu Shot", "duration_minutes": 15,
+            "colour": "yellow", "room_types": ["consultation"] }),
+        serde_json::json!({ "id": "flu_shot", "name": "Flu Shot", "duration_minutes": 15,
+            "colour": "#fdd835", "room_types": ["laboratory"] }),
+    ];
+    for appointment_type in invalid {
+        let req = test::TestRequest::post()
+            .uri("/api/appointment_type")
+            .set_json(&appointment_type)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 400, "{}", appointment_type);
+    }
+
+    // The defaults are there next to the new type
+    let req = test::TestRequest::get()
+        .uri("/api/appointment_type")
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let names: Vec<&str> = resp["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|appointment_type| appointment_type["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        ["Extensive Care", "Quick Checkup", "Surgery", "Vaccination"]
+    );
+
+    let req = test::TestRequest::put()
+        .uri("/api/appointment_type/vaccination")
+        .set_json(serde_json::json!({ "duration_minutes": 20 }))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"]["duration_minutes"], 20);
+
+    // Appointments last as long as their type
+    let req = test::TestRequest::post()
+        .uri("/api/appointment")
+        .set_json(serde_json::json!({
+            "start_time": "2024-03-04T10:00:00",
+            "appointment_type": "vaccination",
+            "patient_id": patient_id,
+            "doctor": doctor,
+            "room": room,
         }))
         .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"][0]["end_time"], "2024-03-04T10:20:00");
 
-    // Call the service and get the response
-    let resp = test::call_service(&mut app, req).await;
+    let req = test::TestRequest::post()
+        .uri("/api/appointment")
+        .set_json(serde_json::json!({
+            "start_time": "2024-03-04T11:00:00",
+            "appointment_type": "flu_shot",
+            "patient_id": patient_id,
+            "doctor": doctor,
+            "room": room,
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 404);
 
-    // Assert that the response status is successful
+    // Types with appointments can't be deleted
+    let req = test::TestRequest::delete()
+        .uri("/api/appointment_type/vaccination")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 409);
+
+    let req = test::TestRequest::delete()
+        .uri("/api/appointment_type/surgery")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
     assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri("/api/appointment_type/surgery")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 404);
 }
 
 #[actix_rt::test]
-async fn test_endpoint_read_all_appointments() {
+async fn test_endpoint_availability() {
     // Initialize the configuration and database
     let config = get_test_config().await;
     let database = mock_db().await;
 
+    // Slots that have started aren't free anymore, so the test searches a week in the future
+    let today = Local::now().date_naive();
+    let monday = today + Duration::days(14 - i64::from(today.weekday().num_days_from_monday()));
+    let friday = monday + Duration::days(4);
+    let at = |day: NaiveDate, time: &str| format!("{}T{}", day, time);
+
+    let patient = database
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let doctor = common::create_doctor(&database, "Dr. Ann", true).await;
+    common::create_doctor(&database, "Dr. Bob", false).await;
+    let room = common::create_room(&database, "Consultation 1", RoomType::Consultation).await;
+    let operating_room = common::create_room(&database, "OR 1", RoomType::OperatingRoom).await;
+
+    // Dr. Ann is busy for the first half hour of the Monday
+    let appointment = Appointment {
+        start_time: at(monday, "08:00:00"),
+        appointment_type: AppointmentType::new("quick_checkup"),
+        patient_id: PatientRecordId::from(patient[0].id.clone()),
+        doctor: doctor.clone(),
+        room: room.clone(),
+        staff: vec![],
+        devices: vec![],
+    };
+    database
+        .create_appointment(
+            appointment
+                .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
     // Initialize the Actix web application
-    let mut app = test::init_service(
-        App::new()
-            .app_data(web::Data::new(database.clone()))
-            .app_data(web::Data::new(config.clone()))
-            .service(web::scope("/api").service(
-                web::resource("/appointment").route(web::get().to(read_all_appointments_handler)),
-            )),
-    )
-    .await;
+    let app =
+        test::init_service(
+            App::new()
+                .configure(configure_error_handlers)
+                .app_data(web::Data::new(database.clone()))
+                .app_data(web::Data::new(config.clone()))
+                .service(web::scope("/api").service(
+                    web::resource("/availability").route(web::get().to(read_availability)),
+                )),
+        )
+        .await;
+
+    let start_times = |resp: &serde_json::Value| -> Vec<String> {
+        resp["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|slot| slot["start_time"].as_str().unwrap().to_string())
+            .collect()
+    };
 
-    // Create a test request to the /api/patient endpoint
     let req = test::TestRequest::get()
-        .uri("/api/appointment")
+        .uri(&format!(
+            "/api/availability?appointment_type=quick_checkup&from={}&limit=3",
+            monday
+        ))
         .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        start_times(&resp),
+        [
+            at(monday, "08:30:00"),
+            at(monday, "08:45:00"),
+            at(monday, "09:00:00")
+        ]
+    );
+    // Only the active doctor and the consultation room can take it
+    assert_eq!(resp["data"][0]["end_time"], at(monday, "09:00:00"));
+    assert_eq!(
+        resp["data"][0]["doctor"],
+        serde_json::to_value(&doctor).unwrap()
+    );
+    assert_eq!(
+        resp["data"][0]["room"],
+        serde_json::to_value(&room).unwrap()
+    );
 
-    // Call the service and get the response
-    let resp = test::call_service(&mut app, req).await;
+    // Late slots run over into the next day, which skips the weekend
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/api/availability?appointment_type=quick_checkup&doctor={}&from={}&after=16:15:00&limit=4",
+            doctor.as_thing(),
+            friday
+        ))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let next_monday = monday + Duration::days(7);
+    assert_eq!(
+        start_times(&resp),
+        [
+            at(friday, "16:15:00"),
+            at(friday, "16:30:00"),
+            at(next_monday, "16:15:00"),
+            at(next_monday, "16:30:00")
+        ]
+    );
 
-    // Assert that the response status is successful
-    assert!(resp.status().is_success());
+    // Nothing is free on a weekend
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/api/availability?appointment_type=quick_checkup&from={}&to={}",
+            friday + Duration::days(1),
+            friday + Duration::days(2)
+        ))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"], serde_json::json!([]));
+
+    // Or on days that have passed
+    let req = test::TestRequest::get()
+        .uri("/api/availability?appointment_type=quick_checkup&from=2024-03-04&to=2024-03-08")
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"], serde_json::json!([]));
+
+    for (query, status) in [
+        ("appointment_type=vaccination&from=2024-03-04", 404),
+        (
+            &format!(
+                "appointment_type=quick_checkup&from=2024-03-04&room={}",
+                operating_room.as_thing()
+            ),
+            422,
+        ),
+        (
+            "appointment_type=quick_checkup&from=2024-03-04&to=2024-03-01",
+            400,
+        ),
+        (
+            "appointment_type=quick_checkup&from=2024-01-01&to=2024-12-31",
+            400,
+        ),
+        (
+            "appointment_type=quick_checkup&after=12:00:00&before=09:00:00",
+            400,
+        ),
+        ("appointment_type=quick_checkup&limit=0", 400),
+        ("from=2024-03-04", 400),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/availability?{}", query))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), status, "{}", query);
+    }
+
+    // The default end of the search would lie after the last date there is
+    let req = test::TestRequest::get()
+        .uri("/api/availability?appointment_type=quick_checkup&from=%2B262142-12-25")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 400);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("+262142-12-25"));
 }
 
 #[actix_rt::test]
-async fn test_endpoint_read_appointment_by_id() {
+async fn test_endpoint_doctor_schedule() {
     // Initialize the configuration and database
     let config = get_test_config().await;
     let database = mock_db().await;
 
+    let patient = database
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let patient_id = patient[0].id.to_string();
+    let doctor = common::create_doctor(&database, "Dr. Ann", true).await;
+    let room = common::create_room(&database, "Exam 1", RoomType::Examination)
+        .await
+        .as_thing()
+        .to_string();
+
     // Initialize the Actix web application
-    let mut app = test::init_service(
+    let app = test::init_service(
         App::new()
+            .configure(configure_error_handlers)
             .app_data(web::Data::new(database.clone()))
             .app_data(web::Data::new(config.clone()))
-            .service(web::scope("/api").service(
-                web::resource("/appointment/{id}").route(web::get().to(read_appointment)),
-            )),
+            .service(
+                web::scope("/api")
+                    .service(
+                        web::resource("/doctor/{id}/schedule")
+                            .route(web::get().to(read_doctor_schedule))
+                            .route(web::put().to(update_doctor_schedule)),
+                    )
+                    .service(
+                        web::resource("/appointment").route(web::post().to(create_appointment)),
+                    ),
+            ),
     )
     .await;
 
-    // Create a test request to the /api/patient endpoint
-    let req = test::TestRequest::get()
-        .uri("/api/appointment/some_id")
+    let schedule_uri = format!("/api/doctor/{}/schedule", doctor.get_unique_id());
+
+    // A split shift on Mondays
+    let req = test::TestRequest::put()
+        .uri(&schedule_uri)
+        .set_json(serde_json::json!({
+            "shifts": [
+                { "weekday": "monday", "start_time": "14:00:00", "end_time": "17:00:00" },
+                { "weekday": "monday", "start_time": "08:00:00", "end_time": "11:00:00" },
+            ]
+        }))
         .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
 
-    // Call the service and get the response
-    let resp = test::call_service(&mut app, req).await;
+    let req = test::TestRequest::get().uri(&schedule_uri).to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"]["shifts"][0]["start_time"], "08:00:00");
+    assert_eq!(resp["data"]["shifts"][1]["start_time"], "14:00:00");
 
-    // Assert that the response status is successful
-    assert!(resp.status().is_client_error());
+    let invalid_schedules = [
+        (
+            schedule_uri.clone(),
+            serde_json::json!({ "shifts": [
+                { "weekday": "monday", "start_time": "08:00:00", "end_time": "12:00:00" },
+                { "weekday": "monday", "start_time": "11:00:00", "end_time": "13:00:00" },
+            ]}),
+            400,
+        ),
+        (
+            schedule_uri.clone(),
+            serde_json::json!({ "shifts": [
+                { "weekday": "someday", "start_time": "08:00:00", "end_time": "12:00:00" },
+            ]}),
+            400,
+        ),
+        (
+            "/api/doctor/doesnt_exist/schedule".to_string(),
+            serde_json::json!({ "shifts": [] }),
+            404,
+        ),
+    ];
+    for (uri, schedule, status) in invalid_schedules {
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .set_json(&schedule)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), status, "{}", schedule);
+    }
+
+    // 2024-03-04 is a Monday
+    let cases = [
+        ("2024-03-04T08:00:00", 200),
+        ("2024-03-04T10:30:00", 200),
+        // Between the shifts, while the clinic is open
+        ("2024-03-04T11:00:00", 422),
+        ("2024-03-04T13:00:00", 422),
+        ("2024-03-04T14:00:00", 200),
+        // Not a working day
+        ("2024-03-05T08:00:00", 422),
+    ];
+    for (start_time, status) in cases {
+        let req = test::TestRequest::post()
+            .uri("/api/appointment")
+            .set_json(serde_json::json!({
+                "start_time": start_time,
+                "appointment_type": "quick_checkup",
+                "patient_id": patient_id,
+                "doctor": doctor.as_thing().to_string(),
+                "room": room,
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), status, "{}", start_time);
+
+        if status == 422 {
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["error"]["code"], "outside_working_hours");
+        }
+    }
 }
 
 #[actix_rt::test]
-async fn test_endpoint_update_appointment() {
+async fn test_endpoint_doctor_absences() {
     // Initialize the configuration and database
     let config = get_test_config().await;
     let database = mock_db().await;
 
+    let patient = database
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let patient_id = patient[0].id.to_string();
+    let doctor = common::create_doctor(&database, "Dr. Ann", true)
+        .await
+        .as_thing()
+        .to_string();
+    let room = common::create_room(&database, "Exam 1", RoomType::Examination)
+        .await
+        .as_thing()
+        .to_string();
+
     // Initialize the Actix web application
-    let mut app = test::init_service(
+    let app = test::init_service(
         App::new()
+            .configure(configure_error_handlers)
             .app_data(web::Data::new(database.clone()))
             .app_data(web::Data::new(config.clone()))
-            .service(web::scope("/api").service(
-                web::resource("/appointment/{id}").route(web::put().to(update_appointment)),
-            )),
+            .service(
+                web::scope("/api")
+                    .service(
+                        web::resource("/absence")
+                            .route(web::post().to(create_absence))
+                            .route(web::get().to(read_absences)),
+                    )
+                    .service(
+                        web::resource("/absence/{id}")
+                            .route(web::get().to(read_absence))
+                            .route(web::delete().to(delete_absence)),
+                    )
+                    .service(
+                        web::resource("/appointment").route(web::post().to(create_appointment)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}").route(web::put().to(update_appointment)),
+                    ),
+            ),
     )
     .await;
 
-    // Create a test request to the /api/patient endpoint
+    // Dr. Ann leaves at 14:00 on Monday and is away all Tuesday
+    let req = test::TestRequest::post()
+        .uri("/api/absence")
+        .set_json(serde_json::json!({
+            "doctor": doctor,
+            "start_date": "2024-03-04",
+            "end_date": "2024-03-05",
+            "start_time": "14:00:00",
+            "reason": "Sick leave"
+        }))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let absence_id = resp["data"][0]["id"]["id"]["String"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let mut booked = Vec::new();
+    for (start_time, status) in [
+        ("2024-03-04T10:00:00", 200),
+        ("2024-03-04T13:45:00", 422),
+        ("2024-03-05T09:00:00", 422),
+        ("2024-03-06T09:00:00", 200),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/appointment")
+            .set_json(serde_json::json!({
+                "start_time": start_time,
+                "appointment_type": "quick_checkup",
+                "patient_id": patient_id,
+                "doctor": doctor,
+                "room": room,
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), status, "{}", start_time);
+
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        if status == 200 {
+            booked.push(
+                body["data"][0]["id"]["id"]["String"]
+                    .as_str()
+                    .unwrap()
+                    .to_string(),
+            );
+        } else {
+            assert_eq!(body["error"]["code"], "doctor_absent", "{}", start_time);
+        }
+    }
+
+    // Appointments can't be moved into the absence either
     let req = test::TestRequest::put()
-        .uri("/api/patient/some_id")
-        .set_json(&serde_json::json!({
-            "doctor": 0,
+        .uri(&format!("/api/appointment/{}", booked[1]))
+        .set_json(serde_json::json!({ "start_time": "2024-03-05T09:00:00" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 422);
+
+    for (body, status) in [
+        (
+            serde_json::json!({ "doctor": doctor, "start_date": "2024-03-05",
+                "end_date": "2024-03-04", "reason": "Vacation" }),
+            400,
+        ),
+        (
+            serde_json::json!({ "doctor": doctor, "start_date": "2024-03-04",
+                "end_date": "2024-03-04", "start_time": "14:00:00", "end_time": "10:00:00",
+                "reason": "Training" }),
+            400,
+        ),
+        (
+            serde_json::json!({ "doctor": doctor, "start_date": "2024-03-04",
+                "end_date": "2024-03-04", "reason": " " }),
+            400,
+        ),
+        (
+            serde_json::json!({ "doctor": "doctor:doesnt_exist", "start_date": "2024-03-04",
+                "end_date": "2024-03-04", "reason": "Vacation" }),
+            404,
+        ),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/absence")
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), status, "{}", body);
+    }
+
+    // Only upcoming absences are listed, unless an earlier date is asked for
+    let req = test::TestRequest::post()
+        .uri("/api/absence")
+        .set_json(serde_json::json!({
+            "doctor": doctor,
+            "start_date": "2099-01-05",
+            "end_date": "2099-01-09",
+            "reason": "Vacation"
         }))
         .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
 
-    // Call the service and get the response
-    let resp = test::call_service(&mut app, req).await;
+    for (uri, expected) in [
+        ("/api/absence".to_string(), vec!["Vacation"]),
+        (
+            "/api/absence?from=2024-03-05".to_string(),
+            vec!["Sick leave", "Vacation"],
+        ),
+        (
+            "/api/absence?from=2024-03-01&doctor=doctor:someone_else".to_string(),
+            vec![],
+        ),
+    ] {
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let reasons: Vec<&str> = resp["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|absence| absence["reason"].as_str().unwrap())
+            .collect();
+        assert_eq!(reasons, expected, "{}", uri);
+    }
 
-    // Assert that the response status is successful
-    assert!(resp.status().is_client_error());
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/absence/{}", absence_id))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"]["start_time"], "14:00:00");
+    assert_eq!(resp["data"]["end_time"], serde_json::Value::Null);
+
+    // Once the absence is deleted, the doctor can be booked again
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/absence/{}", absence_id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/appointment/{}", booked[1]))
+        .set_json(serde_json::json!({ "start_time": "2024-03-05T09:00:00" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/absence/{}", absence_id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 404);
+}
+
+#[actix_rt::test]
+async fn test_endpoint_create_appointment() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    // Initialize the Actix web application
+    let app =
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(database.clone()))
+                .app_data(web::Data::new(config.clone()))
+                .service(web::scope("/api").service(
+                    web::resource("/appointment").route(web::post().to(create_appointment)),
+                )),
+        )
+        .await;
+
+    // Create a test request to the /api/patient endpoint
+    let req = test::TestRequest::post()
+        .uri("/api/appointment")
+        .set_json(serde_json::json!({
+            "start_time": "2021-01-01T08:00:00",
+            "appointment_type": "quick_checkup",
+            "patient_id": "some_id",
+            "doctor": "doctor:5",
+            "room": "room:1",
+        }))
+        .to_request();
+
+    // Call the service and get the response
+    let resp = test::call_service(&app, req).await;
+
+    // Assert that the response status is successful
+    assert!(resp.status().is_client_error());
+}
+
+#[actix_rt::test]
+async fn test_endpoint_create_appointment_error_codes() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    let patient = database
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let patient_id = patient[0].id.to_string();
+    let doctor = common::create_doctor(&database, "Dr. Ann", true)
+        .await
+        .as_thing()
+        .to_string();
+    let other_doctor = common::create_doctor(&database, "Dr. Bob", true)
+        .await
+        .as_thing()
+        .to_string();
+    let inactive_doctor = common::create_doctor(&database, "Dr. Cid", false)
+        .await
+        .as_thing()
+        .to_string();
+    let room = common::create_room(&database, "Exam 1", RoomType::Examination)
+        .await
+        .as_thing()
+        .to_string();
+
+    // Initialize the Actix web application
+    let app =
+        test::init_service(
+            App::new()
+                .configure(configure_error_handlers)
+                .app_data(web::Data::new(database.clone()))
+                .app_data(web::Data::new(config.clone()))
+                .service(web::scope("/api").service(
+                    web::resource("/appointment").route(web::post().to(create_appointment)),
+                )),
+        )
+        .await;
+
+    let appointment = |start_time: &str, patient_id: &str, doctor: &str| {
+        serde_json::json!({
+            "start_time": start_time,
+            "appointment_type": "quick_checkup",
+            "patient_id": patient_id,
+            "doctor": doctor,
+            "room": room,
+        })
+    };
+    let mut surgery = appointment("2021-01-01T14:00:00", &patient_id, &doctor);
+    surgery["appointment_type"] = "surgery".into();
+    let mut unknown_room = appointment("2021-01-01T14:00:00", &patient_id, &doctor);
+    unknown_room["room"] = "room:doesnt_exist".into();
+
+    let cases = [
+        (
+            appointment("2021-01-01T10:00:00", &patient_id, &doctor),
+            200,
+            None,
+        ),
+        (
+            appointment("2021-01-01T10:15:00", &patient_id, &other_doctor),
+            409,
+            Some("appointment_conflict"),
+        ),
+        (
+            appointment("2021-01-01T07:00:00", &patient_id, &doctor),
+            422,
+            Some("outside_opening_hours"),
+        ),
+        (
+            appointment("2021-01-01T12:00:00", &patient_id, &doctor),
+            422,
+            Some("during_break"),
+        ),
+        (
+            appointment("2021-01-01T14:00:00", "patient:doesnt_exist", &doctor),
+            404,
+            Some("not_found"),
+        ),
+        (
+            appointment("2021-01-01T14:00:00", &patient_id, "doctor:doesnt_exist"),
+            404,
+            Some("not_found"),
+        ),
+        (
+            appointment("2021-01-01T14:00:00", &patient_id, &inactive_doctor),
+            400,
+            Some("invalid_request"),
+        ),
+        (surgery, 422, Some("incompatible_room")),
+        (unknown_room, 404, Some("not_found")),
+        (
+            appointment("2021-01-01T14:00:00", "some_id", &doctor),
+            400,
+            Some("invalid_request"),
+        ),
+    ];
+
+    for (body, status, code) in cases {
+        let req = test::TestRequest::post()
+            .uri("/api/appointment")
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), status, "{}", body);
+        if let Some(code) = code {
+            let error: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(error["error"]["code"], code, "{}", body);
+            assert!(error["error"]["message"].is_string());
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn test_endpoint_appointment_buffers() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    let patient = database
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let patient_id = patient[0].id.to_string();
+    let doctor = common::create_doctor(&database, "Dr. Ann", true)
+        .await
+        .as_thing()
+        .to_string();
+    let other_doctor = common::create_doctor(&database, "Dr. Bob", true)
+        .await
+        .as_thing()
+        .to_string();
+    let room_id = common::create_room(&database, "Exam 1", RoomType::Examination).await;
+    let room = room_id.as_thing().to_string();
+    let other_room = common::create_room(&database, "Exam 2", RoomType::Examination)
+        .await
+        .as_thing()
+        .to_string();
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .configure(configure_error_handlers)
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(
+                web::scope("/api")
+                    .service(web::resource("/room/{id}").route(web::put().to(update_room)))
+                    .service(
+                        web::resource("/appointment").route(web::post().to(create_appointment)),
+                    ),
+            ),
+    )
+    .await;
+
+    // The first room needs 15 minutes of cleaning after each appointment
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/room/{}", room_id.get_unique_id()))
+        .set_json(serde_json::json!({ "buffer_after_minutes": 15 }))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"]["buffer_after_minutes"], 15);
+
+    let appointment = |start_time: &str, doctor: &str, room: &str| {
+        serde_json::json!({
+            "start_time": start_time,
+            "appointment_type": "quick_checkup",
+            "patient_id": patient_id,
+            "doctor": doctor,
+            "room": room,
+        })
+    };
+
+    // The buffer doesn't change the appointment's own times
+    let req = test::TestRequest::post()
+        .uri("/api/appointment")
+        .set_json(appointment("2021-01-01T10:00:00", &doctor, &room))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"][0]["end_time"], "2021-01-01T10:30:00");
+    assert_eq!(
+        resp["data"][0]["blocked"]["room_until"],
+        "2021-01-01T10:45:00"
+    );
+    assert_eq!(
+        resp["data"][0]["blocked"]["doctor_until"],
+        "2021-01-01T10:30:00"
+    );
+
+    // The room is blocked while it's cleaned, while the doctor can see the next patient elsewhere
+    let cases = [
+        (
+            appointment("2021-01-01T10:30:00", &other_doctor, &room),
+            409,
+        ),
+        (
+            appointment("2021-01-01T10:30:00", &doctor, &other_room),
+            200,
+        ),
+        (
+            appointment("2021-01-01T10:45:00", &other_doctor, &room),
+            200,
+        ),
+    ];
+    for (body, status) in cases {
+        let req = test::TestRequest::post()
+            .uri("/api/appointment")
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), status, "{}", body);
+    }
+}
+
+#[actix_rt::test]
+async fn test_endpoint_multi_resource_appointment() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    let patient = database
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let patient_id = patient[0].id.to_string();
+    let mut doctor_ids = Vec::new();
+    let mut doctors = Vec::new();
+    for name in ["Dr. Ann", "Dr. Bob", "Dr. Cid"] {
+        let doctor = common::create_doctor(&database, name, true).await;
+        doctors.push(doctor.as_thing().to_string());
+        doctor_ids.push(doctor.get_unique_id());
+    }
+    let room = common::create_room(&database, "Exam 1", RoomType::Examination)
+        .await
+        .as_thing()
+        .to_string();
+    let other_room = common::create_room(&database, "Exam 2", RoomType::Examination)
+        .await
+        .as_thing()
+        .to_string();
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .configure(configure_error_handlers)
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(
+                web::scope("/api")
+                    .service(
+                        web::resource("/device")
+                            .route(web::post().to(create_device))
+                            .route(web::get().to(read_all_devices)),
+                    )
+                    .service(web::resource("/device/{id}").route(web::delete().to(delete_device)))
+                    .service(
+                        web::resource("/appointment")
+                            .route(web::post().to(create_appointment))
+                            .route(web::get().to(read_all_appointments_handler)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}").route(web::put().to(update_appointment)),
+                    ),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/device")
+        .set_json(serde_json::json!({ "name": "ECG cart", "device_type": "ecg" }))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let device_id = resp["data"][0]["id"]["id"]["String"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let device = format!("device:{}", device_id);
+
+    let req = test::TestRequest::get()
+        .uri("/api/device?device_type=ecg")
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+
+    let appointment = |doctor: &str, room: &str, staff: &[&str], devices: &[&str]| {
+        serde_json::json!({
+            "start_time": "2021-01-01T10:00:00",
+            "appointment_type": "quick_checkup",
+            "patient_id": patient_id,
+            "doctor": doctor,
+            "room": room,
+            "staff": staff,
+            "devices": devices,
+        })
+    };
+
+    // Dr. Ann sees the patient with Dr. Bob and the ECG cart
+    let req = test::TestRequest::post()
+        .uri("/api/appointment")
+        .set_json(appointment(&doctors[0], &room, &[&doctors[1]], &[&device]))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"][0]["staff"][0]["id"]["String"], doctor_ids[1]);
+    assert_eq!(resp["data"][0]["devices"][0]["id"]["String"], device_id);
+
+    // Every one of them is busy, and nobody can take part twice or be booked without existing
+    let cases = [
+        (appointment(&doctors[1], &other_room, &[], &[]), 409),
+        (
+            appointment(&doctors[2], &other_room, &[&doctors[0]], &[]),
+            409,
+        ),
+        (appointment(&doctors[2], &other_room, &[], &[&device]), 409),
+        (
+            appointment(&doctors[2], &other_room, &[&doctors[2]], &[]),
+            400,
+        ),
+        (
+            appointment(&doctors[2], &other_room, &[], &["device:missing"]),
+            404,
+        ),
+        (appointment(&doctors[2], &other_room, &[], &[]), 200),
+    ];
+    let mut last = serde_json::Value::Null;
+    for (body, status) in cases {
+        let req = test::TestRequest::post()
+            .uri("/api/appointment")
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), status, "{}", body);
+        last = test::read_body_json(resp).await;
+    }
+
+    // Dr. Bob can't join Dr. Cid's appointment at the same time either
+    let req = test::TestRequest::put()
+        .uri(&format!(
+            "/api/appointment/{}",
+            last["data"][0]["id"]["id"]["String"].as_str().unwrap()
+        ))
+        .set_json(serde_json::json!({ "staff": [doctors[1]] }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 409);
+
+    // Appointments can be found by anyone taking part, and by the devices
+    let cases = [
+        (format!("participant={}", doctors[1]), 1, "Dr. Ann"),
+        (format!("participant={}", doctors[2]), 1, "Dr. Cid"),
+        (format!("device={}", device), 1, "Dr. Ann"),
+    ];
+    for (query, total, doctor) in cases {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/appointment?{}", query))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["total"], total, "{}", query);
+        assert_eq!(resp["data"][0]["doctor"]["name"], doctor, "{}", query);
+    }
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/appointment?participant={}", doctors[1]))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"][0]["staff"][0]["name"], "Dr. Bob");
+    assert_eq!(resp["data"][0]["devices"][0]["name"], "ECG cart");
+
+    // The booked device stays
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/device/{}", device_id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 409);
+}
+
+#[actix_rt::test]
+async fn test_endpoint_delete_appointment() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(web::scope("/api").service(
+                web::resource("/appointment/{id}").route(web::delete().to(delete_appointment)),
+            )),
+    )
+    .await;
+
+    // Create a test request to the /api/patient endpoint
+    let req = test::TestRequest::delete()
+        .uri("/api/appointment/some_id")
+        .to_request();
+
+    // Call the service and get the response
+    let resp = test::call_service(&app, req).await;
+
+    // Assert that the response status is successful
+    assert!(resp.status().is_client_error());
+}
+
+#[actix_rt::test]
+async fn test_endpoint_appointment_status() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    let patient = database
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let patient_id = patient[0].id.to_string();
+    let doctor = common::create_doctor(&database, "Dr. Ann", true)
+        .await
+        .as_thing()
+        .to_string();
+    let room = common::create_room(&database, "Exam 1", RoomType::Examination)
+        .await
+        .as_thing()
+        .to_string();
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .configure(configure_error_handlers)
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(
+                web::scope("/api")
+                    .service(
+                        web::resource("/appointment")
+                            .route(web::post().to(create_appointment))
+                            .route(web::get().to(read_all_appointments_handler)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}").route(web::put().to(update_appointment)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}/check_in")
+                            .route(web::post().to(check_in_appointment)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}/start")
+                            .route(web::post().to(start_appointment)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}/complete")
+                            .route(web::post().to(complete_appointment)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}/no_show")
+                            .route(web::post().to(mark_appointment_no_show)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}/cancel")
+                            .route(web::post().to(cancel_appointment)),
+                    ),
+            ),
+    )
+    .await;
+
+    let create = |start_time: &str| {
+        test::TestRequest::post()
+            .uri("/api/appointment")
+            .set_json(serde_json::json!({
+                "start_time": start_time,
+                "appointment_type": "quick_checkup",
+                "patient_id": patient_id,
+                "doctor": doctor,
+                "room": room,
+            }))
+            .to_request()
+    };
+    let mut ids = Vec::new();
+    for start_time in [
+        "2024-03-04T10:00:00",
+        "2024-03-04T11:00:00",
+        "2024-03-04T14:00:00",
+    ] {
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, create(start_time)).await;
+        assert_eq!(resp["data"][0]["status"], "scheduled");
+        ids.push(
+            resp["data"][0]["id"]["id"]["String"]
+                .as_str()
+                .unwrap()
+                .to_string(),
+        );
+    }
+
+    // The first one is attended from check-in to completion, and can't be changed after it's checked in
+    let cases = [
+        ("start", None, 409),
+        ("check_in", None, 200),
+        ("check_in", None, 409),
+        ("start", None, 200),
+        ("complete", None, 200),
+        (
+            "cancel",
+            Some(serde_json::json!({ "reason": "Too late" })),
+            409,
+        ),
+    ];
+    for (action, body, status) in cases {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/appointment/{}/{}", ids[0], action))
+            .set_json(body.unwrap_or_default())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), status, "{}", action);
+
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        if status == 409 {
+            assert_eq!(body["error"]["code"], "invalid_status_change");
+        }
+    }
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/appointment/{}", ids[0]))
+        .set_json(serde_json::json!({ "start_time": "2024-03-05T10:00:00" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 409);
+
+    // The patient of the second one doesn't show up, the third one is cancelled, which needs a reason
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/appointment/{}/no_show", ids[1]))
+        .set_json(serde_json::json!({}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    for (body, status) in [
+        (serde_json::json!({}), 400),
+        (serde_json::json!({ "reason": " " }), 400),
+        (serde_json::json!({ "reason": "Patient is ill" }), 200),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/appointment/{}/cancel", ids[2]))
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), status, "{}", body);
+    }
+
+    // Their times can be booked again, while they stay in the history
+    for start_time in [
+        "2024-03-04T10:00:00",
+        "2024-03-04T11:00:00",
+        "2024-03-04T14:00:00",
+    ] {
+        let resp = test::call_service(&app, create(start_time)).await;
+        let expected = if start_time.ends_with("10:00:00") {
+            409
+        } else {
+            200
+        };
+        assert_eq!(resp.status().as_u16(), expected, "{}", start_time);
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/appointment?status=cancelled")
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let cancelled = resp["data"].as_array().unwrap();
+    assert_eq!(cancelled.len(), 1);
+    let changes = cancelled[0]["status_changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0]["status"], "cancelled");
+    assert_eq!(changes[0]["reason"], "Patient is ill");
+    assert!(changes[0]["changed_at"].is_string());
+
+    let req = test::TestRequest::post()
+        .uri("/api/appointment/doesnt_exist/check_in")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 404);
+}
+
+#[actix_rt::test]
+async fn test_endpoint_appointment_series() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    let patient = database
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let patient_id = PatientRecordId::from(patient[0].id.clone());
+    let doctor = common::create_doctor(&database, "Dr. Ann", true).await;
+    let room = common::create_room(&database, "Exam 1", RoomType::Examination).await;
+
+    // The doctor already has an appointment at the time of the second occurrence
+    database
+        .create_appointment(
+            Appointment {
+                start_time: "2024-03-11T10:00:00".to_string(),
+                appointment_type: AppointmentType::new("quick_checkup"),
+                patient_id: patient_id.clone(),
+                doctor: doctor.clone(),
+                room: room.clone(),
+                staff: vec![],
+                devices: vec![],
+            }
+            .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .configure(configure_error_handlers)
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(
+                web::scope("/api")
+                    .service(
+                        web::resource("/appointment")
+                            .route(web::get().to(read_all_appointments_handler)),
+                    )
+                    .service(
+                        web::resource("/appointment_series")
+                            .route(web::post().to(create_appointment_series)),
+                    )
+                    .service(
+                        web::resource("/appointment_series/{id}")
+                            .route(web::get().to(read_appointment_series)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}").route(web::put().to(update_appointment)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}/cancel")
+                            .route(web::post().to(cancel_appointment)),
+                    ),
+            ),
+    )
+    .await;
+
+    let series = |rule: serde_json::Value, dry_run: bool| {
+        test::TestRequest::post()
+            .uri("/api/appointment_series")
+            .set_json(serde_json::json!({
+                "start_time": "2024-03-04T10:00:00",
+                "appointment_type": "quick_checkup",
+                "patient_id": patient_id,
+                "doctor": doctor,
+                "room": room,
+                "rule": rule,
+                "dry_run": dry_run,
+            }))
+            .to_request()
+    };
+    let weekly = serde_json::json!({ "frequency": "weekly", "count": 4 });
+
+    // A rule needs an end
+    let resp = test::call_service(
+        &app,
+        series(serde_json::json!({ "frequency": "weekly" }), false),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 400);
+
+    // The dry run reports the conflict without booking anything
+    let resp: serde_json::Value =
+        test::call_and_read_body_json(&app, series(weekly.clone(), true)).await;
+    assert_eq!(resp["dry_run"], true);
+    assert!(resp["series"].is_null());
+    assert_eq!(resp["occurrences"].as_array().unwrap().len(), 3);
+    assert_eq!(resp["conflicts"][0]["start_time"], "2024-03-11T10:00:00");
+    assert_eq!(resp["conflicts"][0]["code"], "appointment_conflict");
+
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, series(weekly, false)).await;
+    let series_id = resp["series"]["id"]["id"]["String"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let ids: Vec<String> = resp["occurrences"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|occurrence| {
+            occurrence["appointment"]["id"]["String"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+    assert_eq!(ids.len(), 3);
+    assert_eq!(resp["conflicts"].as_array().unwrap().len(), 1);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/appointment_series/{}", series_id))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"]["rule"]["count"], 4);
+
+    let start_times = || async {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/appointment?series={}", series_id))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        resp["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|appointment| appointment["start_time"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        start_times().await,
+        [
+            "2024-03-04T10:00:00",
+            "2024-03-18T10:00:00",
+            "2024-03-25T10:00:00"
+        ]
+    );
+
+    // Moving the second one a day and an hour later moves the one after it as well
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/appointment/{}?scope=following", ids[1]))
+        .set_json(serde_json::json!({ "start_time": "2024-03-19T11:00:00" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!(
+        start_times().await,
+        [
+            "2024-03-04T10:00:00",
+            "2024-03-19T11:00:00",
+            "2024-03-26T11:00:00"
+        ]
+    );
+
+    // Moving all of them into the lunch break fails for every one, and nothing is moved
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/appointment/{}?scope=all", ids[0]))
+        .set_json(serde_json::json!({ "start_time": "2024-03-04T12:00:00" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 409);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"]["code"], "series_conflict");
+    assert_eq!(start_times().await[0], "2024-03-04T10:00:00");
+
+    // Cancelling the whole series leaves the appointments that have started alone, which are all of this one
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/appointment/{}/cancel?scope=all", ids[2]))
+        .set_json(serde_json::json!({ "reason": "Therapy ended early" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 409);
+
+    // Cancelling from the first one cancels every appointment after it though
+    let req = test::TestRequest::post()
+        .uri(&format!(
+            "/api/appointment/{}/cancel?scope=following",
+            ids[0]
+        ))
+        .set_json(serde_json::json!({ "reason": "Therapy ended early" }))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"].as_array().unwrap().len(), 3);
+    assert_eq!(resp["data"][0]["status"], "cancelled");
+
+    // A series that runs until after today is only cancelled from now on. Its occurrences are three weeks apart, the
+    // first one at least a week ago and the second one at least a week ahead.
+    let today = Local::now().date_naive();
+    let monday = today + Duration::days(14 - i64::from(today.weekday().num_days_from_monday()));
+    let req = test::TestRequest::post()
+        .uri("/api/appointment_series")
+        .set_json(serde_json::json!({
+            "start_time": format!("{}T10:00:00", monday - Duration::days(21)),
+            "appointment_type": "quick_checkup",
+            "patient_id": patient_id,
+            "doctor": doctor,
+            "room": room,
+            "rule": { "frequency": "weekly", "interval": 3, "count": 2 },
+        }))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let ongoing: Vec<&str> = resp["occurrences"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|occurrence| occurrence["appointment"]["id"]["String"].as_str().unwrap())
+        .collect();
+    assert_eq!(ongoing.len(), 2);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/appointment/{}/cancel?scope=all", ongoing[0]))
+        .set_json(serde_json::json!({ "reason": "Therapy ended early" }))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+    assert_eq!(resp["data"][0]["id"]["id"]["String"], ongoing[1]);
+    assert_eq!(resp["data"][0]["status"], "cancelled");
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/api/appointment?day={}",
+            monday - Duration::days(21)
+        ))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"][0]["status"], "scheduled");
+
+    // Appointments that were booked on their own have no series to change
+    let req = test::TestRequest::get()
+        .uri("/api/appointment?day=2024-03-11")
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let single = resp["data"][0]["id"]["id"]["String"].as_str().unwrap();
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/appointment/{}?scope=all", single))
+        .set_json(serde_json::json!({ "start_time": "2024-03-11T14:00:00" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 400);
+}
+
+#[actix_rt::test]
+async fn test_endpoint_waitlist() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    // Offers are only made for slots that haven't started yet, so the test books a Monday in the future
+    let today = Local::now().date_naive();
+    let monday = today + Duration::days(14 - i64::from(today.weekday().num_days_from_monday()));
+    let at = |time: &str| format!("{}T{}", monday, time);
+
+    let mut patient_ids = Vec::new();
+    for name in ["John Doe", "Jane Roe", "Max Mustermann"] {
+        let patient = database
+            .create_patient(Patient {
+                name: name.to_string(),
+                phone_number: "1234567890".to_string(),
+                insurance_number: None,
+            })
+            .await
+            .unwrap();
+        patient_ids.push(PatientRecordId::from(patient[0].id.clone()));
+    }
+    let doctor = common::create_doctor(&database, "Dr. Ann", true).await;
+    let room = common::create_room(&database, "Exam 1", RoomType::Examination).await;
+    let booked = database
+        .create_appointment(
+            Appointment {
+                start_time: at("10:00:00"),
+                appointment_type: AppointmentType::new("quick_checkup"),
+                patient_id: patient_ids[0].clone(),
+                doctor: doctor.clone(),
+                room: room.clone(),
+                staff: vec![],
+                devices: vec![],
+            }
+            .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    let booked_id = booked[0].id.id.to_raw();
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .configure(configure_error_handlers)
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(
+                web::scope("/api")
+                    .service(
+                        web::resource("/appointment/{id}")
+                            .route(web::delete().to(delete_appointment)),
+                    )
+                    .service(
+                        web::resource("/appointment/{id}/cancel")
+                            .route(web::post().to(cancel_appointment)),
+                    )
+                    .service(
+                        web::resource("/waitlist")
+                            .route(web::post().to(create_waitlist_entry))
+                            .route(web::get().to(read_waitlist)),
+                    )
+                    .service(
+                        web::resource("/waitlist/{id}")
+                            .route(web::get().to(read_waitlist_entry))
+                            .route(web::delete().to(delete_waitlist_entry)),
+                    )
+                    .service(web::resource("/waitlist_offer").route(web::get().to(read_offers)))
+                    .service(
+                        web::resource("/waitlist_offer/{id}/accept")
+                            .route(web::post().to(accept_offer)),
+                    )
+                    .service(
+                        web::resource("/waitlist_offer/{id}/decline")
+                            .route(web::post().to(decline_offer)),
+                    )
+                    .service(
+                        web::resource("/notification_outbox")
+                            .route(web::get().to(read_notifications)),
+                    )
+                    .service(
+                        web::resource("/notification_outbox/{id}/sent")
+                            .route(web::post().to(mark_notification_sent)),
+                    ),
+            ),
+    )
+    .await;
+
+    let register = |patient_id: &PatientRecordId, doctor: Option<&str>, end_date: NaiveDate| {
+        test::TestRequest::post()
+            .uri("/api/waitlist")
+            .set_json(serde_json::json!({
+                "patient_id": patient_id,
+                "appointment_type": "quick_checkup",
+                "doctor": doctor,
+                "start_date": monday,
+                "end_date": end_date,
+            }))
+            .to_request()
+    };
+    let read = |uri: String| {
+        let app = &app;
+        async move {
+            let req = test::TestRequest::get().uri(&uri).to_request();
+            let resp: serde_json::Value = test::call_and_read_body_json(app, req).await;
+            resp["data"].as_array().unwrap().clone()
+        }
+    };
+    let id_of =
+        |record: &serde_json::Value| record["id"]["id"]["String"].as_str().unwrap().to_string();
+
+    // The window can't end before it starts
+    let resp = test::call_service(
+        &app,
+        register(&patient_ids[1], None, monday - Duration::days(1)),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 400);
+    let resp = test::call_service(
+        &app,
+        register(&PatientRecordId::new("doesnt_exist"), None, monday),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 404);
+
+    // Jane waits for any doctor during the week, Max only for Dr. Ann on Monday
+    let friday = monday + Duration::days(4);
+    let resp = test::call_service(&app, register(&patient_ids[1], None, friday)).await;
+    assert!(resp.status().is_success());
+    // Entries are stored with the second they were created in, so Max registers a second later to come after Jane
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    let doctor_link = doctor.as_thing().to_string();
+    let resp: serde_json::Value =
+        test::call_and_read_body_json(&app, register(&patient_ids[2], Some(&doctor_link), monday))
+            .await;
+    let max_entry = id_of(&resp["data"][0]);
+    assert_eq!(
+        read("/api/waitlist?status=waiting".to_string()).await.len(),
+        2
+    );
+
+    // Cancelling offers the slot to Jane, who registered first, and writes a notification for her
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/appointment/{}/cancel", booked_id))
+        .set_json(serde_json::json!({ "reason": "Feeling better" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let offers = read("/api/waitlist_offer?status=pending".to_string()).await;
+    assert_eq!(offers.len(), 1);
+    assert_eq!(offers[0]["patient_id"], serde_json::json!(patient_ids[1]));
+    assert_eq!(offers[0]["start_time"], at("10:00:00"));
+    let notifications = read("/api/notification_outbox?sent=false".to_string()).await;
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0]["kind"], "waitlist_offer");
+    assert_eq!(notifications[0]["subject"], offers[0]["id"]);
+
+    // Jane declines, so Max is offered the slot next
+    let req = test::TestRequest::post()
+        .uri(&format!(
+            "/api/waitlist_offer/{}/decline",
+            id_of(&offers[0])
+        ))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"]["status"], "declined");
+    let req = test::TestRequest::post()
+        .uri(&format!(
+            "/api/waitlist_offer/{}/decline",
+            id_of(&offers[0])
+        ))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 409);
+    let offers = read("/api/waitlist_offer?status=pending".to_string()).await;
+    assert_eq!(offers.len(), 1);
+    assert_eq!(offers[0]["patient_id"], serde_json::json!(patient_ids[2]));
+
+    // Max accepts, which books the appointment
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/waitlist_offer/{}/accept", id_of(&offers[0])))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"]["status"], "accepted");
+    let appointment_id = resp["data"]["appointment"]["id"]["String"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/waitlist/{}", max_entry))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"]["status"], "booked");
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/waitlist_offer/{}/accept", id_of(&offers[0])))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 409);
+
+    // Jane declined this time already, so deleting Max's appointment doesn't offer it to her again
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/appointment/{}", appointment_id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert!(read("/api/waitlist_offer?status=pending".to_string())
+        .await
+        .is_empty());
+
+    let notifications = read("/api/notification_outbox?sent=false".to_string()).await;
+    assert_eq!(notifications.len(), 2);
+    let req = test::TestRequest::post()
+        .uri(&format!(
+            "/api/notification_outbox/{}/sent",
+            id_of(&notifications[0])
+        ))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(resp["data"]["sent_at"].is_string());
+    assert_eq!(
+        read("/api/notification_outbox?sent=false".to_string())
+            .await
+            .len(),
+        1
+    );
+
+    // Jane leaves the waitlist
+    let jane = read(format!(
+        "/api/waitlist?patient_id={}",
+        patient_ids[1].get_unique_id()
+    ))
+    .await;
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/waitlist/{}", id_of(&jane[0])))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!(read("/api/waitlist".to_string()).await.len(), 1);
+}
+
+async fn absence(
+    database: &Database,
+    doctor: &DoctorRecordId,
+    start_date: &str,
+    end_date: &str,
+) -> AbsenceRecord {
+    let absence = database
+        .create_absence(Absence {
+            doctor: doctor.clone(),
+            start_date: start_date.parse().unwrap(),
+            end_date: end_date.parse().unwrap(),
+            start_time: None,
+            end_time: None,
+            reason: "Sick leave".to_string(),
+        })
+        .await
+        .unwrap();
+    absence[0].clone()
+}
+
+#[actix_rt::test]
+async fn test_endpoint_mass_reschedule() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    let patient = database
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let patient_id = PatientRecordId::from(patient[0].id.clone());
+    let doctor = common::create_doctor(&database, "Dr. Ann", true).await;
+    let substitute = common::create_doctor(&database, "Dr. Bob", true).await;
+    let room = common::create_room(&database, "Exam 1", RoomType::Examination).await;
+
+    // Dr. Ann is away on Monday, and has the hours around 10:00 booked on Tuesday already
+    for (start_time, doctor) in [
+        ("2024-03-04T10:00:00", &doctor),
+        ("2024-03-04T14:00:00", &doctor),
+        ("2024-03-05T09:30:00", &doctor),
+        ("2024-03-05T10:00:00", &doctor),
+        ("2024-03-05T10:30:00", &doctor),
+    ] {
+        let appointment = Appointment {
+            start_time: start_time.to_string(),
+            appointment_type: AppointmentType::new("quick_checkup"),
+            patient_id: patient_id.clone(),
+            doctor: doctor.clone(),
+            room: room.clone(),
+            staff: vec![],
+            devices: vec![],
+        };
+        database
+            .create_appointment(
+                appointment
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .configure(configure_error_handlers)
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(
+                web::scope("/api").service(
+                    web::resource("/appointment/mass_reschedule")
+                        .route(web::post().to(mass_reschedule_doctor)),
+                ),
+            ),
+    )
+    .await;
+
+    // The leave has to be stored as an absence first
+    let week = serde_json::json!({
+        "doctor_id": doctor.as_thing().to_string(),
+        "start_date": "2024-03-04",
+        "end_date": "2024-03-08",
+        "max_days": 1
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/appointment/mass_reschedule")
+        .set_json(&week)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 400);
+
+    // The only day searched after the leave is a Saturday, so nothing can be placed
+    let vacation = absence(&database, &doctor, "2024-03-04", "2024-03-08").await;
+    let req = test::TestRequest::post()
+        .uri("/api/appointment/mass_reschedule")
+        .set_json(&week)
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["moved"], serde_json::json!([]));
+    assert_eq!(resp["unplaced"].as_array().unwrap().len(), 5);
+    database
+        .delete_absence(&vacation.id.id.to_raw())
+        .await
+        .unwrap();
+
+    // A dry run only plans to move the appointments to the closest free times on the next day
+    absence(&database, &doctor, "2024-03-04", "2024-03-04").await;
+    let monday = AppointmentFilter {
+        day: NaiveDate::from_ymd_opt(2024, 3, 4),
+        ..Default::default()
+    };
+    let body = serde_json::json!({
+        "doctor_id": doctor.as_thing().to_string(),
+        "start_date": "2024-03-04",
+        "end_date": "2024-03-04",
+        "dry_run": true
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/appointment/mass_reschedule")
+        .set_json(&body)
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["dry_run"], true);
+    let moves: Vec<(&str, &str)> = resp["moved"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|moved| {
+            (
+                moved["from"]["start_time"].as_str().unwrap(),
+                moved["to"]["start_time"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        moves,
+        [
+            ("2024-03-04T10:00:00", "2024-03-05T09:00:00"),
+            ("2024-03-04T14:00:00", "2024-03-05T14:00:00")
+        ]
+    );
+    assert_eq!(database.read_appointments(&monday).await.unwrap().len(), 2);
+
+    // Without it the same plan is applied
+    let mut body = body;
+    body["dry_run"] = serde_json::json!(false);
+    let req = test::TestRequest::post()
+        .uri("/api/appointment/mass_reschedule")
+        .set_json(&body)
+        .to_request();
+    let applied: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(applied["dry_run"], false);
+    assert_eq!(applied["moved"], resp["moved"]);
+    assert!(database
+        .read_appointments(&monday)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // A substitute takes them over on the same day instead
+    absence(&database, &doctor, "2024-03-05", "2024-03-05").await;
+    let req = test::TestRequest::post()
+        .uri("/api/appointment/mass_reschedule")
+        .set_json(serde_json::json!({
+            "doctor_id": doctor.as_thing().to_string(),
+            "start_date": "2024-03-05",
+            "end_date": "2024-03-05",
+            "allow_substitutes": true
+        }))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let moved = resp["moved"].as_array().unwrap();
+    assert_eq!(moved.len(), 5);
+    for appointment in moved {
+        assert_eq!(
+            appointment["to"]["doctor"],
+            serde_json::to_value(&substitute).unwrap()
+        );
+        assert!(appointment["to"]["start_time"]
+            .as_str()
+            .unwrap()
+            .starts_with("2024-03-05"));
+    }
+
+    // Dr. Ann only takes part in an appointment of Dr. Bob on Wednesday, which keeps its time
+    database
+        .create_appointment(
+            Appointment {
+                start_time: "2024-03-06T10:00:00".to_string(),
+                appointment_type: AppointmentType::new("quick_checkup"),
+                patient_id: patient_id.clone(),
+                doctor: substitute.clone(),
+                room: room.clone(),
+                staff: vec![doctor.clone()],
+                devices: vec![],
+            }
+            .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    absence(&database, &doctor, "2024-03-06", "2024-03-06").await;
+    let wednesday = serde_json::json!({
+        "doctor_id": doctor.as_thing().to_string(),
+        "start_date": "2024-03-06",
+        "end_date": "2024-03-06",
+        "allow_substitutes": true
+    });
+
+    // Dr. Bob can't take Dr. Ann's place in his own appointment
+    let req = test::TestRequest::post()
+        .uri("/api/appointment/mass_reschedule")
+        .set_json(&wednesday)
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["moved"], serde_json::json!([]));
+    assert_eq!(resp["unplaced"].as_array().unwrap().len(), 1);
+    assert_eq!(resp["unplaced"][0]["start_time"], "2024-03-06T10:00:00");
+
+    // Dr. Cid can
+    let colleague = common::create_doctor(&database, "Dr. Cid", true).await;
+    let req = test::TestRequest::post()
+        .uri("/api/appointment/mass_reschedule")
+        .set_json(&wednesday)
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["unplaced"], serde_json::json!([]));
+    let moved = &resp["moved"][0]["to"];
+    assert_eq!(moved["start_time"], "2024-03-06T10:00:00");
+    assert_eq!(moved["doctor"], serde_json::to_value(&substitute).unwrap());
+    assert_eq!(moved["staff"], serde_json::json!([colleague]));
+    let participants = database
+        .read_appointments(&AppointmentFilter {
+            participant: Some(colleague.clone()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(participants.len(), 1);
+
+    for (body, status) in [
+        (
+            serde_json::json!({ "doctor_id": doctor.as_thing().to_string(),
+                "start_date": "2024-03-05", "end_date": "2024-03-04" }),
+            400,
+        ),
+        (
+            serde_json::json!({ "doctor_id": doctor.as_thing().to_string(),
+                "start_date": "2024-03-04", "end_date": "2024-03-04", "max_days": 1000 }),
+            400,
+        ),
+        (
+            serde_json::json!({ "doctor_id": "doctor:doesnt_exist",
+                "start_date": "2024-03-04", "end_date": "2024-03-04" }),
+            404,
+        ),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/appointment/mass_reschedule")
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), status, "{}", body);
+    }
+}
+
+#[actix_rt::test]
+async fn test_endpoint_mass_reschedule_keeps_slots_from_the_waitlist() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    // Freed slots are only offered if they haven't started, so the leave is in the future
+    let today = Local::now().date_naive();
+    let monday = today + Duration::days(14 - i64::from(today.weekday().num_days_from_monday()));
+
+    let mut patient_ids = Vec::new();
+    for name in ["John Doe", "Jane Roe"] {
+        let patient = database
+            .create_patient(Patient {
+                name: name.to_string(),
+                phone_number: "1234567890".to_string(),
+                insurance_number: None,
+            })
+            .await
+            .unwrap();
+        patient_ids.push(PatientRecordId::from(patient[0].id.clone()));
+    }
+    let doctor = common::create_doctor(&database, "Dr. Ann", true).await;
+    let room = common::create_room(&database, "Exam 1", RoomType::Examination).await;
+    database
+        .create_appointment(
+            Appointment {
+                start_time: format!("{}T10:00:00", monday),
+                appointment_type: AppointmentType::new("quick_checkup"),
+                patient_id: patient_ids[0].clone(),
+                doctor: doctor.clone(),
+                room: room.clone(),
+                staff: vec![],
+                devices: vec![],
+            }
+            .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    // Jane waits for an appointment with Dr. Ann on the day of the leave
+    database
+        .create_waitlist_entry(
+            WaitlistEntry {
+                patient_id: patient_ids[1].clone(),
+                appointment_type: AppointmentType::new("quick_checkup"),
+                doctor: Some(doctor.clone()),
+                start_date: monday,
+                end_date: monday,
+            },
+            Local::now().naive_local(),
+        )
+        .await
+        .unwrap();
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .configure(configure_error_handlers)
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(
+                web::scope("/api").service(
+                    web::resource("/appointment/mass_reschedule")
+                        .route(web::post().to(mass_reschedule_doctor)),
+                ),
+            ),
+    )
+    .await;
+
+    // Without an absence, Dr. Ann would be free during the leave, so the appointment isn't moved out of it
+    let body = serde_json::json!({
+        "doctor_id": doctor.as_thing().to_string(),
+        "start_date": monday,
+        "end_date": monday,
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/appointment/mass_reschedule")
+        .set_json(&body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 400);
+
+    absence(&database, &doctor, &monday.to_string(), &monday.to_string()).await;
+    let req = test::TestRequest::post()
+        .uri("/api/appointment/mass_reschedule")
+        .set_json(&body)
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["moved"].as_array().unwrap().len(), 1);
+
+    // The slot the appointment left is during Dr. Ann's absence, so it isn't offered to Jane
+    let offers = database.read_offers(&OfferFilter::default()).await.unwrap();
+    assert!(offers.is_empty());
+}
+
+#[actix_rt::test]
+async fn test_endpoint_clinic_calendar() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    let patient = database
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let patient_id = patient[0].id.to_string();
+    let doctor = common::create_doctor(&database, "Dr. Ann", true).await;
+    let room = common::create_room(&database, "Exam 1", RoomType::Examination)
+        .await
+        .as_thing()
+        .to_string();
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .configure(configure_error_handlers)
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(
+                web::scope("/api")
+                    .service(web::resource("/calendar/closure").route(web::get().to(read_closures)))
+                    .service(
+                        web::resource("/calendar/closure/import")
+                            .route(web::post().to(import_closures)),
+                    )
+                    .service(
+                        web::resource("/calendar/closure/{date}")
+                            .route(web::delete().to(delete_closure)),
+                    )
+                    .service(
+                        web::resource("/calendar/opening_override/{date}")
+                            .route(web::put().to(update_opening_override)),
+                    )
+                    .service(
+                        web::resource("/appointment").route(web::post().to(create_appointment)),
+                    )
+                    .service(
+                        web::resource("/appointment/mass_reschedule")
+                            .route(web::post().to(mass_reschedule_doctor)),
+                    ),
+            ),
+    )
+    .await;
+
+    let ical = "BEGIN:VCALENDAR\r\n\
+        BEGIN:VEVENT\r\n\
+        DTSTART;VALUE=DATE:20241224\r\n\
+        DTEND;VALUE=DATE:20241226\r\n\
+        SUMMARY:Christmas\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        DTSTART;VALUE=DATE:20241231\r\n\
+        SUMMARY:New Year's Eve\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        DTSTART:20241223T080000\r\n\
+        DTEND:20241223T120000\r\n\
+        SUMMARY:Team meeting\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+    let req = test::TestRequest::post()
+        .uri("/api/calendar/closure/import")
+        .insert_header(("Content-Type", "text/calendar"))
+        .set_payload(ical)
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"].as_array().unwrap().len(), 3);
+    // The meeting doesn't close the clinic for the whole day
+    assert_eq!(resp["skipped"].as_array().unwrap().len(), 1);
+
+    let req = test::TestRequest::delete()
+        .uri("/api/calendar/closure/2024-12-31")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri("/api/calendar/closure?from=2024-12-01")
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        resp["data"],
+        serde_json::json!([
+            { "date": "2024-12-24", "name": "Christmas" },
+            { "date": "2024-12-25", "name": "Christmas" },
+        ])
+    );
+
+    // The clinic closes early the day before
+    let req = test::TestRequest::put()
+        .uri("/api/calendar/opening_override/2024-12-23")
+        .set_json(serde_json::json!({ "opening_time": "08:00:00", "closing_time": "11:00:00" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let cases = [
+        ("2024-12-20T11:00:00", 200, None),
+        ("2024-12-23T10:00:00", 200, None),
+        ("2024-12-23T11:00:00", 422, Some("outside_opening_hours")),
+        ("2024-12-25T09:00:00", 422, Some("clinic_closed")),
+        // Sunday
+        ("2024-12-29T09:00:00", 422, Some("clinic_closed")),
+    ];
+    for (start_time, status, code) in cases {
+        let req = test::TestRequest::post()
+            .uri("/api/appointment")
+            .set_json(serde_json::json!({
+                "start_time": start_time,
+                "appointment_type": "quick_checkup",
+                "patient_id": patient_id,
+                "doctor": doctor.as_thing().to_string(),
+                "room": room,
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), status, "{}", start_time);
+
+        if let Some(code) = code {
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["error"]["code"], code, "{}", start_time);
+        }
+    }
+
+    // The appointments of the leave are moved past the weekend and the closures, keeping their time of day
+    absence(&database, &doctor, "2024-12-20", "2024-12-23").await;
+    let req = test::TestRequest::post()
+        .uri("/api/appointment/mass_reschedule")
+        .set_json(serde_json::json!({
+            "doctor_id": doctor.as_thing().to_string(),
+            "start_date": "2024-12-20",
+            "end_date": "2024-12-23"
+        }))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["unplaced"], serde_json::json!([]));
+
+    let rescheduled = database
+        .read_appointments(&AppointmentFilter {
+            day: Some("2024-12-26".parse().unwrap()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let start_times: Vec<String> = rescheduled
+        .iter()
+        .map(|appointment| appointment.start_time.to_string())
+        .collect();
+    assert_eq!(start_times, ["2024-12-26 10:00:00", "2024-12-26 11:00:00"]);
+}
+
+#[actix_rt::test]
+async fn test_endpoint_read_all_appointments() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(web::scope("/api").service(
+                web::resource("/appointment").route(web::get().to(read_all_appointments_handler)),
+            )),
+    )
+    .await;
+
+    // Create a test request to the /api/patient endpoint
+    let req = test::TestRequest::get()
+        .uri("/api/appointment")
+        .to_request();
+
+    // Call the service and get the response
+    let resp = test::call_service(&app, req).await;
+
+    // Assert that the response status is successful
+    assert!(resp.status().is_success());
+}
+
+#[actix_rt::test]
+async fn test_endpoint_read_appointments_combined_filters() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    let patient = database
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let patient_id = PatientRecordId::from(patient[0].id.clone());
+    let doctor = common::create_doctor(&database, "Dr. Ann", true).await;
+    let rooms = [
+        common::create_room(&database, "Exam 0", RoomType::Examination).await,
+        common::create_room(&database, "Exam 1", RoomType::Examination).await,
+    ];
+
+    for (start_time, room) in [
+        ("2024-03-04T10:00:00", &rooms[1]),
+        ("2024-03-05T10:00:00", &rooms[0]),
+        ("2024-04-04T10:00:00", &rooms[1]),
+    ] {
+        let appointment = Appointment {
+            start_time: start_time.to_string(),
+            appointment_type: AppointmentType::new("quick_checkup"),
+            patient_id: patient_id.clone(),
+            doctor: doctor.clone(),
+            room: room.clone(),
+            staff: vec![],
+            devices: vec![],
+        };
+        database
+            .create_appointment(
+                appointment
+                    .into_appointment_with_time(Duration::minutes(30), &Buffers::default())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(web::scope("/api").service(
+                web::resource("/appointment").route(web::get().to(read_all_appointments_handler)),
+            )),
+    )
+    .await;
+
+    let doctor_filter = format!("doctor={}", doctor.get_unique_id());
+    for (query, expected) in [
+        (
+            format!(
+                "{}&room={}&month=2024-03",
+                doctor_filter,
+                rooms[1].as_thing()
+            ),
+            1,
+        ),
+        (format!("{}&month=2024-03", doctor_filter), 2),
+        ("doctor=doesnt_exist".to_string(), 0),
+        (
+            "from=2024-03-05T00:00:00&appointment_type=quick_checkup".to_string(),
+            2,
+        ),
+        (
+            "from=2024-03-05T00:00:00&to=2024-04-01T00:00:00".to_string(),
+            1,
+        ),
+        // A range of days includes the day it ends on
+        ("from=2024-03-01&to=2024-03-31".to_string(), 2),
+        ("from=2024-03-05&to=2024-04-04".to_string(), 2),
+        // Types are configurable, so one that doesn't exist just matches nothing
+        ("appointment_type=nap".to_string(), 0),
+        (
+            format!("day=2024-04-04&room={}", rooms[0].get_unique_id()),
+            0,
+        ),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/api/appointment?patient_id={}&{}",
+                patient_id.as_thing(),
+                query
+            ))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["total"], expected, "{}", query);
+    }
+
+    // Assert that malformed criteria are rejected
+    for query in [
+        "month=2024-13",
+        "day=04.03.2024",
+        "doctor=patient:two",
+        "from=2024-03",
+    ] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/appointment?{}", query))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_client_error(), "{}", query);
+    }
+}
+
+#[actix_rt::test]
+async fn test_endpoint_read_appointments_malformed_patient_id() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(web::scope("/api").service(
+                web::resource("/appointment").route(web::get().to(read_all_appointments_handler)),
+            )),
+    )
+    .await;
+
+    // Filter by patient IDs that can't be parsed into a patient link
+    for value in ["room:1", "patient:", ":some_id"] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/appointment?patient_id={}", value))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+
+        // Assert that the request is rejected instead of crashing the server
+        assert!(resp.status().is_client_error());
+    }
+}
+
+#[actix_rt::test]
+async fn test_endpoint_read_appointment_by_id() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(web::scope("/api").service(
+                web::resource("/appointment/{id}").route(web::get().to(read_appointment)),
+            )),
+    )
+    .await;
+
+    // Create a test request to the /api/patient endpoint
+    let req = test::TestRequest::get()
+        .uri("/api/appointment/some_id")
+        .to_request();
+
+    // Call the service and get the response
+    let resp = test::call_service(&app, req).await;
+
+    // Assert that the response status is successful
+    assert!(resp.status().is_client_error());
+}
+
+#[actix_rt::test]
+async fn test_endpoint_update_appointment() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(web::scope("/api").service(
+                web::resource("/appointment/{id}").route(web::put().to(update_appointment)),
+            )),
+    )
+    .await;
+
+    // Create a test request to the /api/patient endpoint
+    let req = test::TestRequest::put()
+        .uri("/api/patient/some_id")
+        .set_json(serde_json::json!({
+            "doctor": "doctor:0",
+        }))
+        .to_request();
+
+    // Call the service and get the response
+    let resp = test::call_service(&app, req).await;
+
+    // Assert that the response status is successful
+    assert!(resp.status().is_client_error());
+}
+
+#[actix_rt::test]
+async fn test_endpoint_health() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .app_data(web::Data::new(ServerInfo {
+                started_at: Instant::now(),
+            }))
+            .service(
+                web::scope("/api")
+                    .service(web::resource("/health").route(web::get().to(get_health))),
+            ),
+    )
+    .await;
+
+    // Create a test request to the /api/health endpoint
+    let req = test::TestRequest::get().uri("/api/health").to_request();
+
+    // Call the service and get the response
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    // Assert that the database is reported as reachable
+    assert_eq!(resp["data"]["status"], "ok");
+    assert_eq!(resp["data"]["database"]["state"], "connected");
+    assert!(resp["data"]["database"]["latency_ms"].is_number());
+    assert!(resp["data"]["uptime_seconds"].is_number());
 }
@@ -0,0 +1,197 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use actix_web::{test, web, App};
+use backend::config::{AppConfig, AuthConfig, AuthMode};
+use backend::db::db::Database;
+use backend::db::types::{
+    Appointment, AppointmentType, Buffers, DatabaseError, Patient, PatientRecordId, RoomType,
+};
+use backend::patient_endpoints::{read_all_patients, read_patient};
+use common::{get_test_config, mock_db};
+use futures::future::join_all;
+
+const CONCURRENT_REQUESTS: usize = 50;
+const SLOW_QUERY: Duration = Duration::from_secs(1);
+
+#[actix_rt::test]
+async fn test_parallel_reads() {
+    // Initialize the configuration and database
+    let config = get_test_config().await;
+    let database = mock_db().await;
+
+    let patient = database
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let patient_id = patient[0].id.id.to_raw();
+
+    // Initialize the Actix web application
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(database.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .service(
+                web::scope("/api")
+                    .service(web::resource("/patient").route(web::get().to(read_all_patients)))
+                    .service(web::resource("/patient/{id}").route(web::get().to(read_patient))),
+            ),
+    )
+    .await;
+
+    // The embedded engine runs the queries of a connection one after another, so the round trips themselves only
+    // overlap on a server. What the shared handle has to guarantee is that nobody waits for it: while a slow query is
+    // in flight, every other caller still gets the connection right away and sends its query.
+    let slow_query = {
+        let database = database.clone();
+        tokio::spawn(async move {
+            let conn = database.get_connection().await.unwrap();
+            conn.query(format!("SLEEP {}ms", SLOW_QUERY.as_millis()))
+                .await
+                .unwrap();
+        })
+    };
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let started = Instant::now();
+    let connections = join_all((0..CONCURRENT_REQUESTS).map(|_| database.get_connection())).await;
+    assert!(connections.iter().all(Result::is_ok));
+    assert!(started.elapsed() < SLOW_QUERY / 10);
+    assert!(!slow_query.is_finished());
+
+    // Fire all reads at once, alternating between the list and the single patient endpoint. They're queued behind the
+    // slow query, so with a lock held across it they'd only be sent one after another once it's done.
+    let requests = (0..CONCURRENT_REQUESTS).map(|i| {
+        let uri = if i % 2 == 0 {
+            "/api/patient".to_string()
+        } else {
+            format!("/api/patient/{}", patient_id)
+        };
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        test::call_service(&app, req)
+    });
+    let responses = tokio::time::timeout(Duration::from_secs(30), join_all(requests))
+        .await
+        .expect("Parallel reads didn't finish in time");
+
+    assert!(responses.iter().all(|resp| resp.status().is_success()));
+    slow_query.await.unwrap();
+}
+
+// The tests below need a SurrealDB server, like one started with `surreal start --user root --pass root memory`:
+// TEST_DATABASE_URL=127.0.0.1:8000 cargo test --test load_tests -- --ignored
+async fn get_server_config(name: &str) -> AppConfig {
+    let mut config = get_test_config().await;
+    config.database_url = std::env::var("TEST_DATABASE_URL").unwrap();
+    config.database = format!("{}_{}", name, std::process::id());
+    config.auth = AuthConfig {
+        mode: AuthMode::Root,
+        username: Some(std::env::var("TEST_DATABASE_USER").unwrap_or("root".to_string())),
+        password: Some(std::env::var("TEST_DATABASE_PASS").unwrap_or("root".to_string())),
+        token: None,
+    };
+    config
+}
+
+// Unlike the embedded engine, a server answers the queries of one connection as they come, so slow reads on the
+// shared handle take about as long together as one of them alone
+#[actix_rt::test]
+#[ignore = "needs a SurrealDB server at TEST_DATABASE_URL"]
+async fn test_parallel_slow_reads_on_server() {
+    let database = Database::new();
+    database
+        .initiate_db(get_server_config("parallel_reads").await)
+        .await
+        .unwrap();
+
+    let slow_read = || async {
+        let conn = database.get_connection().await.unwrap();
+        conn.query(format!(
+            "SLEEP {}ms; SELECT * FROM patient;",
+            SLOW_QUERY.as_millis()
+        ))
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+    };
+
+    let started = Instant::now();
+    slow_read().await;
+    let single = started.elapsed();
+
+    let started = Instant::now();
+    join_all((0..CONCURRENT_REQUESTS).map(|_| slow_read())).await;
+    let parallel = started.elapsed();
+
+    // Run one after another they'd take CONCURRENT_REQUESTS times as long
+    assert!(
+        parallel < single * 3,
+        "{} reads took {:?}, one took {:?}",
+        CONCURRENT_REQUESTS,
+        parallel,
+        single
+    );
+}
+
+// Every connection to an embedded database starts its own one, so this needs a server as well
+#[actix_rt::test]
+#[ignore = "needs a SurrealDB server at TEST_DATABASE_URL"]
+async fn test_concurrent_bookings_on_separate_connections() {
+    let config = get_server_config("load_test").await;
+    let first = Database::new();
+    first.initiate_db(config.clone()).await.unwrap();
+    let second = Database::new();
+    second.initiate_db(config).await.unwrap();
+
+    let patient = first
+        .create_patient(Patient {
+            name: "John Doe".to_string(),
+            phone_number: "1234567890".to_string(),
+            insurance_number: None,
+        })
+        .await
+        .unwrap();
+    let doctor = common::create_doctor(&first, "Dr. Ann", true).await;
+    let room = common::create_room(&first, "Exam 1", RoomType::Examination).await;
+
+    // Both connections book the same doctor and room at the same time, on a new slot every round
+    for hour in 8..16 {
+        let booking = || {
+            Appointment {
+                start_time: format!("2023-10-02T{:02}:00:00", hour),
+                appointment_type: AppointmentType::new("quick_checkup"),
+                patient_id: PatientRecordId::from(patient[0].id.clone()),
+                doctor: doctor.clone(),
+                room: room.clone(),
+                staff: vec![],
+                devices: vec![],
+            }
+            .into_appointment_with_time(chrono::Duration::minutes(30), &Buffers::default())
+            .unwrap()
+        };
+        let (result1, result2) = tokio::join!(
+            first.create_appointment(booking()),
+            second.create_appointment(booking())
+        );
+
+        // Exactly one of them gets the slot, the other one is told it's taken
+        assert!(
+            result1.is_ok() != result2.is_ok(),
+            "{:?} {:?}",
+            result1,
+            result2
+        );
+        let failed = if result1.is_err() { result1 } else { result2 };
+        assert!(
+            matches!(failed, Err(DatabaseError::AppointmentConflict)),
+            "{:?}",
+            failed
+        );
+    }
+}
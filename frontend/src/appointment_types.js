@@ -0,0 +1,26 @@
+import { showAlert, readErrorMessage } from './utils.js';
+
+/**
+ * Populates the appointment type dropdown in a given select element with all appointment types.
+ * @param {HTMLSelectElement} selectElement - The select element to populate.
+ */
+export const populateAppointmentTypeDropdown = async (selectElement) => {
+    try {
+        const response = await fetch('http://127.0.0.1:8080/api/appointment_type');
+        if (!response.ok) {
+            throw new Error(await readErrorMessage(response));
+        }
+        const { data: appointmentTypes } = await response.json();
+        selectElement.innerHTML = '<option value="" disabled selected>Select Appointment Type</option>';
+        appointmentTypes.forEach(appointmentType => {
+            const option = document.createElement('option');
+            option.value = appointmentType.id.id.String;
+            option.textContent = `${appointmentType.name} (${appointmentType.duration_minutes} minutes)`;
+            selectElement.appendChild(option);
+        });
+        console.log('Appointment Type Dropdown Populated');
+    } catch (error) {
+        console.error(error);
+        showAlert(`Failed to load appointment types. ${error.message}`);
+    }
+};
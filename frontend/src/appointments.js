@@ -1,4 +1,4 @@
-import { showAlert, formatTime, calculateLength, capitalizeFirstLetter } from './utils.js';
+import { showAlert, formatTime, calculateLength, capitalizeFirstLetter, fetchAllPages, readErrorMessage } from './utils.js';
 
 /**
  * Clears the appointment form and resets validation.
@@ -18,49 +18,42 @@ function clearAppointmentForm() {
 export const fetchAndDisplayAppointments = async (date) => {
     console.log(`Fetching appointments for date: ${date}`);
     try {
-        const response = await fetch(`http://127.0.0.1:8080/api/appointment?filter=day&value=${date}`, {
-            method: 'GET',
-            headers: { 'Content-Type': 'application/json' }
-        });
-
         const appointmentsTableBody = document.getElementById('appointments-table-body');
         appointmentsTableBody.innerHTML = ''; // Clears the table before filling
 
-        if (!response.ok) {
-            console.error(`Error fetching appointments: ${response.statusText}`);
-            appointmentsTableBody.innerHTML = '<tr><td colspan="8" class="text-center">No appointments to show.</td></tr>';
-            document.getElementById('appointments-section').style.display = 'block';
-            return;
-        }
-
-        let data;
+        let appointments;
         try {
-            data = await response.json();
-        } catch (parseError) {
-            console.error('Invalid JSON format received from server.');
-            appointmentsTableBody.innerHTML = '<tr><td colspan="8" class="text-center">No appointments to show.</td></tr>';
+            appointments = await fetchAllPages(`http://127.0.0.1:8080/api/appointment?day=${date}&sort=start_time`);
+        } catch (fetchError) {
+            console.error(`Error fetching appointments: ${fetchError.message}`);
+            appointmentsTableBody.innerHTML = '<tr><td colspan="9" class="text-center">No appointments to show.</td></tr>';
             document.getElementById('appointments-section').style.display = 'block';
             return;
         }
 
-        const appointments = data.data;
         console.log(`Fetched Appointments:`, appointments);
 
         if (!appointments || appointments.length === 0) {
-            appointmentsTableBody.innerHTML = '<tr><td colspan="8" class="text-center">No appointments to show.</td></tr>';
+            appointmentsTableBody.innerHTML = '<tr><td colspan="9" class="text-center">No appointments to show.</td></tr>';
         } else {
             appointments.forEach(appointment => {
                 const row = document.createElement('tr');
+                // Only appointments that haven't started yet can be cancelled
+                const cancellable = ['scheduled', 'checked_in'].includes(appointment.status);
+                // The staff are listed after the doctor, and the devices after the room
+                const participants = [appointment.doctor, ...(appointment.staff || [])].map(doctor => doctor.name).join(', ');
+                const resources = [appointment.room, ...(appointment.devices || [])].map(resource => resource.name).join(', ');
 
                 row.innerHTML = `
-                    <td>Doctor ${appointment.doctor}</td>
+                    <td>${participants}</td>
                     <td>${appointment.patient.name}</td>
                     <td>${capitalizeFirstLetter(appointment.appointment_type.replace('_', ' '))}</td>
                     <td>${formatTime(appointment.start_time)}</td>
                     <td>${formatTime(appointment.end_time)}</td>
                     <td>${calculateLength(appointment.start_time, appointment.end_time)} minutes</td>
-                    <td>${appointment.room_nr}</td>
-                    <td><button class="btn btn-danger btn-sm cancel-appointment-btn" data-appointment-id="${appointment.id.id.String}">Cancel</button></td>
+                    <td>${resources}</td>
+                    <td>${capitalizeFirstLetter(appointment.status.replace('_', ' '))}</td>
+                    <td>${cancellable ? `<button class="btn btn-danger btn-sm cancel-appointment-btn" data-appointment-id="${appointment.id.id.String}" data-in-series="${Boolean(appointment.series)}">Cancel</button>` : ''}</td>
                 `;
                 appointmentsTableBody.appendChild(row);
             });
@@ -71,7 +64,7 @@ export const fetchAndDisplayAppointments = async (date) => {
     } catch (error) {
         console.error(error);
         const appointmentsTableBody = document.getElementById('appointments-table-body');
-        appointmentsTableBody.innerHTML = '<tr><td colspan="8" class="text-center">No appointments to show.</td></tr>';
+        appointmentsTableBody.innerHTML = '<tr><td colspan="9" class="text-center">No appointments to show.</td></tr>';
         document.getElementById('appointments-section').style.display = 'block';
     }
 };
@@ -92,7 +85,7 @@ export const createAppointment = async (payload, createAppointmentModal, selecte
         });
 
         if (!response.ok) {
-            const errorText = await response.text();
+            const errorText = await readErrorMessage(response);
             throw new Error(`Failed to create appointment: ${errorText}`);
         }
 
@@ -110,20 +103,61 @@ export const createAppointment = async (payload, createAppointmentModal, selecte
 };
 
 /**
- * Deletes an appointment.
+ * Creates a series of appointments that repeats by a rule. Occurrences that aren't free are left out and reported.
+ * @param {Object} payload - The data of the first appointment and the recurrence rule.
+ * @param {HTMLElement} createAppointmentModal - The modal element for appointment creation.
+ * @param {string} selectedDate - The currently selected date.
+ */
+export const createAppointmentSeries = async (payload, createAppointmentModal, selectedDate) => {
+    console.log('Creating appointment series with payload:', payload);
+    try {
+        const response = await fetch('http://127.0.0.1:8080/api/appointment_series', {
+            method: 'POST',
+            headers: { 'Content-Type': 'application/json' },
+            body: JSON.stringify(payload)
+        });
+
+        if (!response.ok) {
+            const errorText = await readErrorMessage(response);
+            throw new Error(`Failed to create appointment series: ${errorText}`);
+        }
+
+        const result = await response.json();
+        const skipped = result.conflicts
+            .map(conflict => `${conflict.start_time.replace('T', ' ')}: ${conflict.message}`)
+            .join('\n');
+        showAlert(skipped
+            ? `Booked ${result.occurrences.length} appointments. These were left out:\n${skipped}`
+            : `Booked ${result.occurrences.length} appointments.`);
+        clearAppointmentForm();
+        const bootstrapModal = bootstrap.Modal.getInstance(createAppointmentModal);
+        bootstrapModal.hide();
+
+        fetchAndDisplayAppointments(selectedDate);
+    } catch (error) {
+        console.error(error);
+        showAlert(`Error: ${error.message}`);
+    }
+};
+
+/**
+ * Cancels an appointment, which keeps it in the history.
  * @param {string} appointmentId - The ID of the appointment.
+ * @param {string} reason - Why the appointment is cancelled.
+ * @param {string} scope - Which appointments of its series are cancelled along with it: this, following or all.
  * @param {string} selectedDate - The currently selected date.
  */
-export const deleteAppointment = async (appointmentId, selectedDate) => {
+export const cancelAppointment = async (appointmentId, reason, scope, selectedDate) => {
     console.log(`Cancelling appointment with ID: ${appointmentId}`);
     try {
-        const response = await fetch(`http://127.0.0.1:8080/api/appointment/${appointmentId}`, {
-            method: 'DELETE',
-            headers: { 'Content-Type': 'application/json' }
+        const response = await fetch(`http://127.0.0.1:8080/api/appointment/${appointmentId}/cancel?scope=${scope}`, {
+            method: 'POST',
+            headers: { 'Content-Type': 'application/json' },
+            body: JSON.stringify({ reason })
         });
 
         if (!response.ok) {
-            const errorText = await response.text();
+            const errorText = await readErrorMessage(response);
             throw new Error(`Failed to cancel appointment: ${errorText}`);
         }
 
@@ -1,20 +1,19 @@
-import { showAlert } from './utils.js';
-import { fetchConfig } from './config.js';
+import { showAlert, readErrorMessage, fetchAllPages } from './utils.js';
 
 /**
- * Populates the doctor dropdown in a given select element.
+ * Populates the doctor dropdown in a given select element with all active doctors.
  * @param {HTMLSelectElement} selectElement - The select element to populate.
  */
 export const populateDoctorDropdown = async (selectElement) => {
     try {
-        const { doctorAmount } = await fetchConfig();
+        const doctors = await fetchAllPages('http://127.0.0.1:8080/api/doctor?active=true&sort=name');
         selectElement.innerHTML = '<option value="" disabled selected>Select a Doctor</option>';
-        for (let i = 0; i <= doctorAmount; i++) {
+        doctors.forEach(doctor => {
             const option = document.createElement('option');
-            option.value = i;
-            option.textContent = `Doctor ${i}`;
+            option.value = `doctor:${doctor.id.id.String}`;
+            option.textContent = `${doctor.name} (${doctor.specialty})`;
             selectElement.appendChild(option);
-        }
+        });
         console.log('Doctor Dropdown Populated');
     } catch (error) {
         console.error(error);
@@ -38,26 +37,71 @@ export const populateDoctorsForAppointment = async () => {
     await populateDoctorDropdown(doctorSelect);
 };
 
+/**
+ * Stores the absence of a doctor, so they can't be booked during it anymore.
+ * @param {Object} absence - The absence data (doctor, start_date, end_date, reason).
+ */
+export const createAbsence = async (absence) => {
+    const response = await fetch('http://127.0.0.1:8080/api/absence', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(absence)
+    });
+
+    if (!response.ok) {
+        const errorText = await readErrorMessage(response);
+        throw new Error(`Storing the absence failed: ${errorText}`);
+    }
+
+    return response.json();
+};
+
+const requestMassReschedule = async (payload) => {
+    const response = await fetch('http://127.0.0.1:8080/api/appointment/mass_reschedule', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(payload)
+    });
+
+    if (!response.ok) {
+        const errorText = await readErrorMessage(response);
+        console.log(JSON.stringify(payload));
+        throw new Error(`Mass Reschedule failed: ${errorText}`);
+    }
+
+    return response.json();
+};
+
 /**
  * Performs mass rescheduling of a doctor's appointments during sickness.
+ * The plan of a dry run is shown first, the appointments are only moved once it's confirmed.
  * @param {Object} payload - The reschedule data (doctor_id, start_date, end_date).
  */
 export const massRescheduleDoctor = async (payload) => {
     console.log('Mass Reschedule Doctor with payload:', payload);
     try {
-        const response = await fetch('http://127.0.0.1:8080/api/appointment/mass_reschedule', {
-            method: 'POST',
-            headers: { 'Content-Type': 'application/json' },
-            body: JSON.stringify(payload)
-        });
-
-        if (!response.ok) {
-            const errorText = await response.text();
-            console.log(JSON.stringify(payload));
-            throw new Error(`Mass Reschedule failed: ${errorText}`);
+        const plan = await requestMassReschedule({ ...payload, dry_run: true });
+        if (plan.moved.length === 0) {
+            showAlert(plan.unplaced.length > 0
+                ? `No free slot was found for any of the ${plan.unplaced.length} appointments.`
+                : 'The doctor has no appointments in that time.');
+            return;
         }
 
-        showAlert('Doctor\'s appointments have been mass rescheduled successfully.');
+        const moves = plan.moved
+            .map(({ from, to }) => `${from.start_time} -> ${to.start_time}`)
+            .join('\n');
+        const unplaced = plan.unplaced.length > 0
+            ? `\n\nNo free slot was found for ${plan.unplaced.length} appointments.`
+            : '';
+        if (!confirm(`The appointments will be moved like this:\n${moves}${unplaced}\n\nReschedule them?`)) return;
+
+        const { moved, unplaced: left } = await requestMassReschedule(payload);
+        if (left.length > 0) {
+            showAlert(`${moved.length} appointments have been rescheduled, but no free slot was found for ${left.length}. Please reschedule those manually.`);
+        } else {
+            showAlert('Doctor\'s appointments have been mass rescheduled successfully.');
+        }
     } catch (error) {
         console.error(error);
         showAlert(`Error: ${error.message}`);
@@ -1,8 +1,9 @@
-import { showAlert, handleFormValidation, populateDropdown, populateRoomDropdown, populateStartTimeDropdown } from './utils.js';
-import { fetchConfig } from './config.js';
+import { showAlert, handleFormValidation, populateDropdown, populateStartTimeDropdown } from './utils.js';
 import { fetchAndPopulatePatients, fetchPatientDetails, createPatient, updatePatient, deletePatient } from './patients.js';
-import { populateManageDoctorsDropdown, populateDoctorsForAppointment, massRescheduleDoctor } from './doctors.js';
-import { fetchAndDisplayAppointments, createAppointment, deleteAppointment } from './appointments.js';
+import { populateManageDoctorsDropdown, populateDoctorsForAppointment, massRescheduleDoctor, createAbsence } from './doctors.js';
+import { populateRoomDropdown } from './rooms.js';
+import { populateAppointmentTypeDropdown } from './appointment_types.js';
+import { fetchAndDisplayAppointments, createAppointment, createAppointmentSeries, cancelAppointment } from './appointments.js';
 
 /**
  * Initializes all event listeners and performs the initial setup.
@@ -34,6 +35,9 @@ document.addEventListener('DOMContentLoaded', () => {
 
     // Confirmation Modal Elements
     const confirmCancelBtn = document.getElementById('confirm-cancel-btn');
+    const cancelReasonInput = document.getElementById('cancel-reason');
+    const cancelScopeGroup = document.getElementById('cancel-scope-group');
+    const cancelScopeSelect = document.getElementById('cancel-scope');
 
     let appointmentIdToCancel = null;
 
@@ -63,12 +67,9 @@ document.addEventListener('DOMContentLoaded', () => {
     createAppointmentModal.addEventListener('show.bs.modal', async () => {
         await fetchAndPopulatePatients();
         await populateDoctorsForAppointment();
-        await fetchConfig().then(config => {
-            const roomSelect = document.getElementById('room-number-select');
-            populateRoomDropdown(roomSelect, config.roomAmount);
-            const startTimeSelect = document.getElementById('appointment-start-time');
-            populateStartTimeDropdown(startTimeSelect);
-        });
+        await populateRoomDropdown(document.getElementById('room-number-select'));
+        await populateAppointmentTypeDropdown(appointmentTypeSelect);
+        populateStartTimeDropdown(document.getElementById('appointment-start-time'));
     });
 
     // Opens and fills the "Manage Patients" Modal
@@ -145,16 +146,24 @@ document.addEventListener('DOMContentLoaded', () => {
         const appointmentType = document.getElementById('appointment-type').value;
         const patientId = document.getElementById('appointment-patient-id').value;
         const doctorId = document.getElementById('doctor-id').value;
-        const roomNumber = document.getElementById('room-number-select').value;
+        const roomId = document.getElementById('room-number-select').value;
 
         const payload = {
             start_time: startTime,
             appointment_type: appointmentType,
             patient_id: patientId,
-            doctor: parseInt(doctorId, 10),
-            room_nr: parseInt(roomNumber, 10)
+            doctor: doctorId,
+            room: roomId
         };
 
+        const frequency = document.getElementById('appointment-repeat').value;
+        if (frequency) {
+            const count = parseInt(document.getElementById('appointment-occurrences').value, 10);
+            payload.rule = { frequency, count };
+            await createAppointmentSeries(payload, createAppointmentModal, selectDayInput.value);
+            return;
+        }
+
         await createAppointment(payload, createAppointmentModal, selectDayInput.value);
     });
 
@@ -172,8 +181,22 @@ document.addEventListener('DOMContentLoaded', () => {
             return;
         }
 
+        // The absence is stored first, so no new appointments are booked into it while the old ones are moved
+        try {
+            await createAbsence({
+                doctor: doctorId,
+                start_date: startDate,
+                end_date: endDate,
+                reason: document.getElementById('absence-reason').value
+            });
+        } catch (error) {
+            console.error(error);
+            showAlert(`Error: ${error.message}`);
+            return;
+        }
+
         const payload = {
-            doctor_id: parseInt(doctorId, 10),
+            doctor_id: doctorId,
             start_date: startDate,
             end_date: endDate
         };
@@ -224,6 +247,9 @@ document.addEventListener('DOMContentLoaded', () => {
         if (e.target && e.target.matches('button.cancel-appointment-btn')) {
             appointmentIdToCancel = e.target.dataset.appointmentId;
             console.log(`Selected Appointment ID for Cancellation: ${appointmentIdToCancel}`);
+            cancelReasonInput.value = '';
+            cancelScopeSelect.value = 'this';
+            cancelScopeGroup.style.display = e.target.dataset.inSeries === 'true' ? 'block' : 'none';
             confirmCancelModal.show();
         }
     });
@@ -236,7 +262,14 @@ document.addEventListener('DOMContentLoaded', () => {
             return;
         }
 
-        await deleteAppointment(appointmentIdToCancel, selectDayInput.value);
+        const reason = cancelReasonInput.value.trim();
+        if (!reason) {
+            cancelReasonInput.classList.add('is-invalid');
+            return;
+        }
+        cancelReasonInput.classList.remove('is-invalid');
+
+        await cancelAppointment(appointmentIdToCancel, reason, cancelScopeSelect.value, selectDayInput.value);
         confirmCancelModal.hide();
         appointmentIdToCancel = null;
     });
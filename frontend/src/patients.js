@@ -1,29 +1,18 @@
-import { showAlert } from './utils.js';
-import { populateDropdown } from './utils.js';
+import { showAlert, readErrorMessage } from './utils.js';
+import { populateDropdown, fetchAllPages } from './utils.js';
 
 /**
  * Fetches all patients and populates the corresponding dropdowns.
  */
 export const fetchAndPopulatePatients = async () => {
     try {
-        const response = await fetch('http://127.0.0.1:8080/api/patient', {
-            method: 'GET',
-            headers: { 'Content-Type': 'application/json' }
-        });
-
-        if (!response.ok) {
-            const errorText = await response.text();
-            throw new Error(`Error fetching patients: ${errorText}`);
-        }
-
-        let data;
+        let patients;
         try {
-            data = await response.json();
-        } catch (parseError) {
-            throw new Error('Invalid JSON format received from server.');
+            patients = await fetchAllPages('http://127.0.0.1:8080/api/patient?sort=name');
+        } catch (fetchError) {
+            throw new Error(`Error fetching patients: ${fetchError.message}`);
         }
 
-        const patients = data.data;
         console.log('Fetched Patients:', patients);
 
         populateDropdown(document.getElementById('appointment-patient-id'), patients, 'Select a Patient', true);
@@ -48,7 +37,7 @@ export const fetchPatientDetails = async (patientId, updateForm) => {
         });
 
         if (!response.ok) {
-            const errorText = await response.text();
+            const errorText = await readErrorMessage(response);
             throw new Error(errorText || 'Patient not found.');
         }
 
@@ -89,7 +78,7 @@ export const createPatient = async (payload) => {
         });
 
         if (!response.ok) {
-            const errorText = await response.text();
+            const errorText = await readErrorMessage(response);
             throw new Error(`Failed to create patient: ${errorText}`);
         }
 
@@ -116,7 +105,7 @@ export const updatePatient = async (patientId, payload) => {
         });
 
         if (!response.ok) {
-            const errorText = await response.text();
+            const errorText = await readErrorMessage(response);
             throw new Error(`Failed to update patient: ${errorText}`);
         }
 
@@ -141,7 +130,7 @@ export const deletePatient = async (patientId) => {
         });
 
         if (!response.ok) {
-            const errorText = await response.text();
+            const errorText = await readErrorMessage(response);
             throw new Error(`Failed to delete patient: ${errorText}`);
         }
 
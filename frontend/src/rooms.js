@@ -0,0 +1,22 @@
+import { showAlert, fetchAllPages } from './utils.js';
+
+/**
+ * Populates the room dropdown in a given select element with all rooms.
+ * @param {HTMLSelectElement} selectElement - The select element to populate.
+ */
+export const populateRoomDropdown = async (selectElement) => {
+    try {
+        const rooms = await fetchAllPages('http://127.0.0.1:8080/api/room?sort=name');
+        selectElement.innerHTML = '<option value="" disabled selected>Select a Room</option>';
+        rooms.forEach(room => {
+            const option = document.createElement('option');
+            option.value = `room:${room.id.id.String}`;
+            option.textContent = `${room.name} (${room.room_type.replace('_', ' ')})`;
+            selectElement.appendChild(option);
+        });
+        console.log('Room Dropdown Populated');
+    } catch (error) {
+        console.error(error);
+        showAlert(`Failed to load rooms. ${error.message}`);
+    }
+};
@@ -56,6 +56,56 @@ export const handleFormValidation = (form) => {
     return true;
 };
 
+/**
+ * Reads the message of an error response. The backend answers with
+ * `{"error": {"code": ..., "message": ...}}`, anything else is returned as plain text.
+ * @param {Response} response - The failed response.
+ * @returns {Promise<string>} - The error message.
+ */
+export const readErrorMessage = async (response) => {
+    const text = await response.text();
+    try {
+        return JSON.parse(text).error.message || response.statusText;
+    } catch (parseError) {
+        return text || response.statusText;
+    }
+};
+
+/**
+ * Fetches every page of a list endpoint by following the cursor of each page.
+ * @param {string} url - The URL of the list endpoint, including any query parameters.
+ * @returns {Promise<Array>} - The entries of all pages.
+ */
+export const fetchAllPages = async (url) => {
+    const entries = [];
+    let cursor = null;
+
+    do {
+        const separator = url.includes('?') ? '&' : '?';
+        const pageUrl = cursor ? `${url}${separator}cursor=${encodeURIComponent(cursor)}` : url;
+        const response = await fetch(pageUrl, {
+            method: 'GET',
+            headers: { 'Content-Type': 'application/json' }
+        });
+
+        if (!response.ok) {
+            throw new Error(await readErrorMessage(response));
+        }
+
+        let data;
+        try {
+            data = await response.json();
+        } catch (parseError) {
+            throw new Error('Invalid JSON format received from server.');
+        }
+
+        entries.push(...data.data);
+        cursor = data.next_cursor;
+    } while (cursor);
+
+    return entries;
+};
+
 /**
  * Populates a dropdown menu with options.
  * @param {HTMLSelectElement} selectElement - The dropdown element to be populated.
@@ -73,22 +123,6 @@ export const populateDropdown = (selectElement, items, defaultOptionText, addPre
     });
 };
 
-/**
- * Populates the room dropdown based on the number of rooms available.
- * @param {HTMLSelectElement} selectElement - The dropdown element for rooms.
- * @param {number} roomAmount - The number of available rooms.
- */
-export const populateRoomDropdown = (selectElement, roomAmount) => {
-    selectElement.innerHTML = '<option value="" disabled selected>Select a Room</option>';
-    for (let i = 0; i <= roomAmount; i++) { // Start bei 0, um alle Räume abzudecken
-        const option = document.createElement('option');
-        option.value = i;
-        option.textContent = `Room ${i}`;
-        selectElement.appendChild(option);
-    }
-    console.log('Room Dropdown Populated');
-};
-
 /**
  * Populates the start time dropdown with predefined, hardcoded time options.
  * @param {HTMLSelectElement} selectElement - The dropdown element for start time.